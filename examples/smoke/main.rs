@@ -0,0 +1,272 @@
+// Headless end-to-end check of the crate against a real libcef, see
+// tests/smoke.rs. Also built as the `smoke` example binary, which runs the
+// same checks outside of `cargo test`.
+
+use cef::{
+    app::{App, AppCallbacks},
+    browser::{Browser, BrowserSettings},
+    browser_host::{BrowserHost, PaintElementType},
+    client::{
+        life_span_handler::{LifeSpanHandler, LifeSpanHandlerCallbacks},
+        render_handler::{CursorHandle, CursorType, RenderHandler, RenderHandlerCallbacks, ScreenInfo},
+        Client, ClientCallbacks,
+    },
+    command_line::CommandLine,
+    drag::DragOperation,
+    frame::Frame,
+    load_handler::{LoadHandler, LoadHandlerCallbacks},
+    settings::{LogSeverity, Settings},
+    task::{TaskRunner, ThreadId},
+    values::{Point, Rect, StoredValue},
+    window::WindowInfo,
+};
+use parking_lot::Mutex;
+use std::{ffi::c_void, path::PathBuf, sync::Arc};
+
+const WIDTH: i32 = 320;
+const HEIGHT: i32 = 240;
+/// The whole run is aborted after this long.
+const TIMEOUT_MS: i64 = 30_000;
+/// Time the page gets to paint after it finished loading.
+const PAINT_DELAY_MS: i64 = 500;
+const PAGE: &str = "data:text/html,<title>smoke</title>\
+                    <body style='margin:0;background:rgb(255,0,0)'></body>";
+
+/// Outcome of the steps, shared by all handlers.
+#[derive(Default)]
+struct Report {
+    passed: Vec<&'static str>,
+    failed: Vec<String>,
+    /// Last painted view, BGRA.
+    frame: Option<(i32, i32, Vec<u8>)>,
+    done: bool,
+}
+
+impl Report {
+    fn pass(&mut self, step: &'static str) {
+        println!("ok: {}", step);
+        self.passed.push(step);
+    }
+    fn fail(&mut self, step: &'static str, reason: impl std::fmt::Display) {
+        println!("FAILED: {}: {}", step, reason);
+        self.failed.push(format!("{}: {}", step, reason));
+    }
+}
+
+type SharedReport = Arc<Mutex<Report>>;
+
+fn finish(browser: &Browser, report: &SharedReport) {
+    let already_done = std::mem::replace(&mut report.lock().done, true);
+    if !already_done {
+        browser.get_host().close_browser(true);
+    }
+}
+
+struct AppCallbacksImpl;
+
+impl AppCallbacks for AppCallbacksImpl {
+    fn on_before_command_line_processing(&self, process_type: Option<&str>, command_line: CommandLine) {
+        if process_type == None {
+            command_line.append_switch("disable-gpu");
+            command_line.append_switch("disable-gpu-compositing");
+        }
+    }
+}
+
+struct ClientCallbacksImpl {
+    life_span_handler: LifeSpanHandler,
+    load_handler: LoadHandler,
+    render_handler: RenderHandler,
+}
+
+impl ClientCallbacks for ClientCallbacksImpl {
+    fn get_life_span_handler(&self) -> Option<LifeSpanHandler> {
+        Some(self.life_span_handler.clone())
+    }
+    fn get_load_handler(&self) -> Option<LoadHandler> {
+        Some(self.load_handler.clone())
+    }
+    fn get_render_handler(&self) -> Option<RenderHandler> {
+        Some(self.render_handler.clone())
+    }
+}
+
+struct LifeSpanHandlerImpl(SharedReport);
+
+impl LifeSpanHandlerCallbacks for LifeSpanHandlerImpl {
+    fn on_after_created(&self, _browser: Browser) {
+        self.0.lock().pass("browser creation");
+    }
+    fn on_before_close(&self, _browser: Browser) {
+        self.0.lock().pass("browser close");
+        cef::quit_message_loop().unwrap();
+    }
+}
+
+struct LoadHandlerImpl(SharedReport);
+
+impl LoadHandlerCallbacks for LoadHandlerImpl {
+    fn on_load_end(&self, browser: Browser, frame: Frame, _http_status_code: i32) {
+        if !frame.is_main() {
+            return;
+        }
+        self.0.lock().pass("navigation");
+        let report = self.0.clone();
+        frame.evaluate("document.title", move |result| {
+            match result {
+                Ok(StoredValue::String(ref title)) if title == "smoke" => report.lock().pass("javascript evaluation"),
+                Ok(value) => report.lock().fail("javascript evaluation", format!("unexpected result {:?}", value)),
+                Err(e) => report.lock().fail("javascript evaluation", format!("{:?}", e)),
+            }
+            TaskRunner::post_delayed_task_on(ThreadId::UI, move || {
+                check_frame(&report);
+                finish(&browser, &report);
+            }, PAINT_DELAY_MS);
+        });
+    }
+}
+
+/// Checks that the page's red background was painted.
+fn check_frame(report: &SharedReport) {
+    let mut report = report.lock();
+    let result = match &report.frame {
+        None => Err("nothing was painted".to_owned()),
+        Some((width, height, _)) if (*width, *height) != (WIDTH, HEIGHT) => {
+            Err(format!("painted {}x{}, expected {}x{}", width, height, WIDTH, HEIGHT))
+        }
+        Some((width, _, buffer)) => {
+            let offset = ((HEIGHT / 2 * width + WIDTH / 2) * 4) as usize;
+            match &buffer[offset..offset + 4] {
+                [0, 0, 255, 255] => Ok(()),
+                pixel => Err(format!("center pixel is {:?} (BGRA), expected red", pixel)),
+            }
+        }
+    };
+    match result {
+        Ok(()) => report.pass("screenshot"),
+        Err(e) => report.fail("screenshot", e),
+    }
+}
+
+struct RenderHandlerImpl(SharedReport);
+
+impl RenderHandlerCallbacks for RenderHandlerImpl {
+    fn get_view_rect(&self, _browser: Browser) -> Rect {
+        Rect { x: 0, y: 0, width: WIDTH, height: HEIGHT }
+    }
+    fn get_screen_point(&self, _browser: Browser, view_point: Point) -> Option<Point> {
+        Some(view_point)
+    }
+    fn get_screen_info(&self, _browser: Browser) -> Option<ScreenInfo> {
+        None
+    }
+    fn on_popup_show(&self, _browser: Browser, _show: bool) {}
+    fn on_popup_size(&self, _browser: Browser, _rect: Rect) {}
+    fn on_paint(
+        &self,
+        _browser: Browser,
+        type_: PaintElementType,
+        _dirty_rects: &[Rect],
+        buffer: &[u8],
+        width: i32,
+        height: i32,
+    ) {
+        if type_ == PaintElementType::View {
+            self.0.lock().frame = Some((width, height, buffer.to_vec()));
+        }
+    }
+    fn on_accelerated_paint(
+        &self,
+        _browser: Browser,
+        _type_: PaintElementType,
+        _dirty_rects: &[Rect],
+        _shared_handle: *mut c_void,
+    ) {
+    }
+    fn on_cursor_change(&self, _browser: Browser, _cursor: CursorHandle, _type_: CursorType<'_>) {}
+    fn update_drag_cursor(&self, _browser: Browser, _operation: DragOperation) {}
+}
+
+/// Directory holding CEF's resources, from the environment.
+const CEF_PATH: &str = "CEF_PATH";
+
+fn main() {
+    let cef_path = match std::env::var_os(CEF_PATH) {
+        Some(path) => PathBuf::from(path),
+        // CI has to download CEF with scripts/fetch-cef.sh, so a missing CEF
+        // there is a broken setup rather than a reason to skip.
+        None if std::env::var_os("CI").is_some() => {
+            println!("FAILED: {} is not set, run scripts/fetch-cef.sh first", CEF_PATH);
+            std::process::exit(1);
+        }
+        None => {
+            println!("smoke test ignored: set {} to run it against a real libcef", CEF_PATH);
+            return;
+        }
+    };
+
+    let app = App::new(AppCallbacksImpl);
+    let result = cef::execute_process(Some(app.clone()), None);
+    if result >= 0 {
+        std::process::exit(result);
+    }
+
+    #[allow(unused_mut)]
+    let mut settings = Settings::new()
+        .log_severity(LogSeverity::Warning)
+        .windowless_rendering_enabled(true)
+        .resources_dir_path(&cef_path)
+        .locales_dir_path(cef_path.join("locales"));
+    #[cfg(target_os = "macos")] {
+        settings.framework_dir_path = Some(cef::load_framework(None).unwrap());
+    }
+    let context = match cef::Context::initialize(settings, Some(app), None) {
+        Ok(context) => context,
+        Err(e) => {
+            println!("FAILED: initialization: {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("ok: initialization");
+
+    let report = SharedReport::default();
+    let client = Client::new(ClientCallbacksImpl {
+        life_span_handler: LifeSpanHandler::new(LifeSpanHandlerImpl(report.clone())),
+        load_handler: LoadHandler::new(LoadHandlerImpl(report.clone())),
+        render_handler: RenderHandler::new(RenderHandlerImpl(report.clone())),
+    });
+    let window_info = WindowInfo {
+        windowless_rendering_enabled: true,
+        width: WIDTH,
+        height: HEIGHT,
+        ..WindowInfo::new()
+    };
+    let browser = BrowserHost::create_browser_sync(
+        &window_info,
+        client,
+        PAGE,
+        &BrowserSettings::new(),
+        None,
+        None,
+    );
+
+    let watchdog_report = report.clone();
+    TaskRunner::post_delayed_task_on(ThreadId::UI, move || {
+        let timed_out = !watchdog_report.lock().done;
+        if timed_out {
+            watchdog_report.lock().fail("run", format!("timed out after {}ms", TIMEOUT_MS));
+            finish(&browser, &watchdog_report);
+        }
+    }, TIMEOUT_MS);
+
+    context.run_message_loop();
+    drop(context);
+    println!("ok: shutdown");
+
+    let report = report.lock();
+    let expected = 5;
+    if !report.failed.is_empty() || report.passed.len() < expected {
+        println!("{} of {} steps passed", report.passed.len(), expected);
+        std::process::exit(1);
+    }
+}
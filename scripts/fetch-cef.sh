@@ -0,0 +1,60 @@
+#!/bin/sh
+# Downloads the CEF distribution the smoke test runs against and prints the
+# environment it needs, for use with eval:
+#
+#     eval "$(scripts/fetch-cef.sh)"
+#     cargo test --test smoke
+#
+# The distribution is unpacked into the directory given as the first argument
+# (default: target/cef) and only downloaded once. CEF_VERSION selects another
+# build; it has to match the CEF headers cef-sys was generated from. On GitHub
+# Actions the environment is also added to $GITHUB_ENV for later steps.
+set -eu
+
+CEF_VERSION="${CEF_VERSION:-84.4.1+gfdc7504+chromium-84.0.4147.105}"
+CEF_CDN="${CEF_CDN:-https://cef-builds.spotifycdn.com}"
+dest="${1:-target/cef}"
+
+case "$(uname -s)" in
+    Linux) platform=linux64 ;;
+    Darwin) platform=macosx64 ;;
+    MINGW* | MSYS* | CYGWIN*) platform=windows64 ;;
+    *) echo "fetch-cef.sh: unsupported platform $(uname -s)" >&2; exit 1 ;;
+esac
+
+name="cef_binary_${CEF_VERSION}_${platform}_minimal"
+dir="$dest/$name"
+
+if [ ! -d "$dir" ]; then
+    mkdir -p "$dest"
+    # The version contains '+', which has to be escaped in the URL.
+    url="$CEF_CDN/$(printf '%s' "$name" | sed 's/+/%2B/g').tar.bz2"
+    echo "Downloading $url" >&2
+    curl --fail --location --retry 3 --output "$dest/$name.tar.bz2" "$url"
+    tar -xjf "$dest/$name.tar.bz2" -C "$dest"
+    rm "$dest/$name.tar.bz2"
+    # CEF looks for its resources next to the library unless told otherwise,
+    # so keep everything in one directory.
+    if [ -d "$dir/Resources" ]; then
+        cp -R "$dir/Resources/." "$dir/Release/"
+    fi
+fi
+
+release="$(cd "$dir/Release" && pwd)"
+if [ "$platform" = macosx64 ]; then
+    lib_var=DYLD_FRAMEWORK_PATH
+else
+    lib_var=LD_LIBRARY_PATH
+fi
+
+echo "export CEF_PATH='$release'"
+echo "export LIBRARY_PATH='$release'"
+echo "export $lib_var='$release'"
+
+if [ -n "${GITHUB_ENV:-}" ]; then
+    {
+        echo "CEF_PATH=$release"
+        echo "LIBRARY_PATH=$release"
+        echo "$lib_var=$release"
+    } >> "$GITHUB_ENV"
+fi
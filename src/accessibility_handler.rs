@@ -1,12 +1,14 @@
 use crate::{
+    devtools::{get_dictionary, get_dictionary_list, get_i32, get_list, get_string},
     refcounted::{RefCountedPtr, Wrapper},
     send_protector::SendProtector,
-    values::{StoredValue, Value},
+    values::{Rect, StoredValue, Value},
 };
 use cef_sys::{
     cef_accessibility_handler_t,
     cef_value_t
 };
+use std::collections::{HashMap, HashSet};
 
 ref_counted_ptr! {
     /// Implement this structure to receive accessibility notification when
@@ -21,17 +23,310 @@ impl AccessibilityHandler {
     }
 }
 
+/// Implement this trait to receive the accessibility tree of a windowless
+/// browser, after enabling accessibility with
+/// [BrowserHost::set_accessibility_state](crate::browser_host::BrowserHost::set_accessibility_state).
+/// [AccessibilityTree] keeps a copy of the tree up to date from these
+/// callbacks.
 pub trait AccessibilityHandlerCallbacks: 'static + Send {
     /// Called after renderer process sends accessibility tree changes to the
     /// browser process.
-    fn on_accessibility_tree_change(&self, value: StoredValue) {
+    fn on_accessibility_tree_change(&self, change: AccessibilityTreeChange) {
     }
     /// Called after renderer process sends accessibility location changes to the
     /// browser process.
-    fn on_accessibility_location_change(&self, value: StoredValue) {
+    fn on_accessibility_location_change(&self, changes: Vec<AccessibilityLocationChange>) {
     }
 }
 
+/// A node of the accessibility tree, corresponding to Chromium's `AXNodeData`.
+#[derive(Debug, Clone)]
+pub struct AccessibilityNode {
+    pub id: i32,
+    /// The role, like `button` or `staticText`.
+    pub role: String,
+    /// States like `focusable` or `invisible`.
+    pub state: Vec<String>,
+    /// Bounds relative to the node with id `offset_container_id`, or to the
+    /// view if there is none.
+    pub bounds: Option<Rect>,
+    pub offset_container_id: Option<i32>,
+    pub child_ids: Vec<i32>,
+    /// All other attributes, like `name` or `value`, by Chromium's names.
+    pub attributes: HashMap<String, StoredValue>,
+}
+
+impl AccessibilityNode {
+    fn from_dictionary(node: &HashMap<String, StoredValue>) -> AccessibilityNode {
+        let (bounds, offset_container_id) = parse_location(get_dictionary(node, "location"));
+        let state = match node.get("state") {
+            Some(StoredValue::List(state)) => string_list(state.clone().into()),
+            Some(StoredValue::Dictionary(state)) => {
+                let state: HashMap<String, StoredValue> = state.into();
+                state.into_iter().map(|(name, _)| name).collect()
+            }
+            _ => Vec::new(),
+        };
+        AccessibilityNode {
+            id: get_i32(node, "id"),
+            role: get_string(node, "role"),
+            state,
+            bounds,
+            offset_container_id: offset_container_id.or_else(|| node.get("offset_container_id").and_then(as_i32)),
+            child_ids: get_list(node, "child_ids").iter().filter_map(as_i32).collect(),
+            attributes: get_dictionary(node, "attributes").unwrap_or_default(),
+        }
+    }
+    /// The accessible name, e.g. the label of a button.
+    pub fn name(&self) -> Option<&str> {
+        self.string_attribute("name")
+    }
+    pub fn value(&self) -> Option<&str> {
+        self.string_attribute("value")
+    }
+    pub fn has_state(&self, state: &str) -> bool {
+        self.state.iter().any(|s| s == state)
+    }
+    fn string_attribute(&self, name: &str) -> Option<&str> {
+        match self.attributes.get(name) {
+            Some(StoredValue::String(s)) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// One update of an accessibility tree, corresponding to Chromium's
+/// `AXTreeUpdate`.
+#[derive(Debug, Clone, Default)]
+pub struct AccessibilityTreeUpdate {
+    /// The root node, if it changed.
+    pub root_id: Option<i32>,
+    /// The focused node, if the update includes the tree's data.
+    pub focused_id: Option<i32>,
+    /// A node whose descendants are removed before `nodes` are applied.
+    pub node_id_to_clear: Option<i32>,
+    /// New or changed nodes.
+    pub nodes: Vec<AccessibilityNode>,
+}
+
+/// An accessibility event, like `focus` or `valueChanged`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessibilityEvent {
+    pub node_id: i32,
+    pub event_type: String,
+}
+
+/// Changes to the accessibility tree of one frame.
+#[derive(Debug, Clone, Default)]
+pub struct AccessibilityTreeChange {
+    /// Identifies the tree, there is one per frame.
+    pub tree_id: String,
+    pub updates: Vec<AccessibilityTreeUpdate>,
+    pub events: Vec<AccessibilityEvent>,
+}
+
+impl AccessibilityTreeChange {
+    fn from_value(value: StoredValue) -> AccessibilityTreeChange {
+        let change: HashMap<String, StoredValue> = match value {
+            StoredValue::Dictionary(change) => change.into(),
+            _ => return AccessibilityTreeChange::default(),
+        };
+        let updates = get_dictionary_list(&change, "updates")
+            .iter()
+            .map(|update| {
+                let tree_data = get_dictionary(update, "tree_data");
+                AccessibilityTreeUpdate {
+                    root_id: update.get("root_id").and_then(as_i32),
+                    focused_id: tree_data.and_then(|data| data.get("focus_id").and_then(as_i32)),
+                    node_id_to_clear: update.get("node_id_to_clear").and_then(as_i32).filter(|&id| id != 0),
+                    nodes: get_dictionary_list(update, "nodes").iter().map(AccessibilityNode::from_dictionary).collect(),
+                }
+            })
+            .collect();
+        let events = get_dictionary_list(&change, "events")
+            .iter()
+            .map(|event| AccessibilityEvent {
+                node_id: get_i32(event, "id"),
+                event_type: get_string(event, "event_type"),
+            })
+            .collect();
+        AccessibilityTreeChange {
+            tree_id: get_string(&change, "ax_tree_id"),
+            updates,
+            events,
+        }
+    }
+}
+
+/// A node that moved without other changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessibilityLocationChange {
+    pub tree_id: String,
+    pub node_id: i32,
+    pub bounds: Rect,
+    pub offset_container_id: Option<i32>,
+}
+
+impl AccessibilityLocationChange {
+    fn from_value(value: StoredValue) -> Vec<AccessibilityLocationChange> {
+        let changes: Vec<StoredValue> = match value {
+            StoredValue::List(changes) => changes.into(),
+            _ => return Vec::new(),
+        };
+        changes
+            .into_iter()
+            .filter_map(|change| match change {
+                StoredValue::Dictionary(change) => {
+                    let change: HashMap<String, StoredValue> = change.into();
+                    let (bounds, offset_container_id) = parse_location(get_dictionary(&change, "new_location"));
+                    Some(AccessibilityLocationChange {
+                        tree_id: get_string(&change, "ax_tree_id"),
+                        node_id: get_i32(&change, "id"),
+                        bounds: bounds?,
+                        offset_container_id,
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// A copy of the accessibility trees of a browser's frames, updated from the
+/// changes received by an [AccessibilityHandler].
+#[derive(Debug, Clone, Default)]
+pub struct AccessibilityTree {
+    trees: HashMap<String, FrameTree>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct FrameTree {
+    root_id: Option<i32>,
+    focused_id: Option<i32>,
+    nodes: HashMap<i32, AccessibilityNode>,
+}
+
+impl AccessibilityTree {
+    pub fn new() -> AccessibilityTree {
+        AccessibilityTree::default()
+    }
+    pub fn apply_change(&mut self, change: &AccessibilityTreeChange) {
+        let tree = self.trees.entry(change.tree_id.clone()).or_default();
+        for update in &change.updates {
+            if let Some(id) = update.node_id_to_clear {
+                let children = tree.nodes.get(&id).map(|node| node.child_ids.clone()).unwrap_or_default();
+                for child in children {
+                    remove_subtree(&mut tree.nodes, child, &HashSet::new());
+                }
+            }
+            if update.root_id.is_some() {
+                tree.root_id = update.root_id;
+            }
+            if update.focused_id.is_some() {
+                tree.focused_id = update.focused_id;
+            }
+            // Children dropped by their parent are removed with their
+            // subtrees, unless another node of the update adopted them.
+            let mut dropped = Vec::new();
+            for node in &update.nodes {
+                if let Some(old) = tree.nodes.insert(node.id, node.clone()) {
+                    dropped.extend(old.child_ids.into_iter().filter(|id| !node.child_ids.contains(id)));
+                }
+            }
+            let adopted: HashSet<i32> = update.nodes.iter().flat_map(|node| node.child_ids.iter().copied()).collect();
+            for id in dropped {
+                if !adopted.contains(&id) {
+                    remove_subtree(&mut tree.nodes, id, &adopted);
+                }
+            }
+        }
+    }
+    pub fn apply_location_changes(&mut self, changes: &[AccessibilityLocationChange]) {
+        for change in changes {
+            let node = self.trees.get_mut(&change.tree_id).and_then(|tree| tree.nodes.get_mut(&change.node_id));
+            if let Some(node) = node {
+                node.bounds = Some(change.bounds);
+                node.offset_container_id = change.offset_container_id;
+            }
+        }
+    }
+    /// Returns the identifiers of the trees, one per frame.
+    pub fn tree_ids(&self) -> Vec<&str> {
+        self.trees.keys().map(|id| &**id).collect()
+    }
+    pub fn root(&self, tree_id: &str) -> Option<&AccessibilityNode> {
+        let tree = self.trees.get(tree_id)?;
+        tree.nodes.get(&tree.root_id?)
+    }
+    pub fn focused(&self, tree_id: &str) -> Option<&AccessibilityNode> {
+        let tree = self.trees.get(tree_id)?;
+        tree.nodes.get(&tree.focused_id?)
+    }
+    pub fn node(&self, tree_id: &str, id: i32) -> Option<&AccessibilityNode> {
+        self.trees.get(tree_id)?.nodes.get(&id)
+    }
+    pub fn children<'a>(&'a self, tree_id: &str, node: &'a AccessibilityNode) -> impl Iterator<Item = &'a AccessibilityNode> {
+        let nodes = self.trees.get(tree_id).map(|tree| &tree.nodes);
+        node.child_ids.iter().filter_map(move |id| nodes?.get(id))
+    }
+    /// Forget the tree of a frame, e.g. after it was detached.
+    pub fn remove_tree(&mut self, tree_id: &str) {
+        self.trees.remove(tree_id);
+    }
+}
+
+/// Remove the node `id` and its descendants, except for the nodes in `keep`
+/// and their descendants.
+fn remove_subtree(nodes: &mut HashMap<i32, AccessibilityNode>, id: i32, keep: &HashSet<i32>) {
+    // Trees can be deep enough to overflow the stack when recursing.
+    let mut pending = vec![id];
+    while let Some(id) = pending.pop() {
+        if let Some(node) = nodes.remove(&id) {
+            pending.extend(node.child_ids.into_iter().filter(|child| !keep.contains(child)));
+        }
+    }
+}
+
+fn as_i32(value: &StoredValue) -> Option<i32> {
+    match value {
+        StoredValue::Int(i) => Some(*i),
+        StoredValue::Double(d) => Some(*d as i32),
+        _ => None,
+    }
+}
+
+fn string_list(values: Vec<StoredValue>) -> Vec<String> {
+    values
+        .into_iter()
+        .filter_map(|value| match value {
+            StoredValue::String(s) => Some(s),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Node locations are either a rectangle or relative bounds with the rectangle
+/// under `bounds`.
+fn parse_location(location: Option<HashMap<String, StoredValue>>) -> (Option<Rect>, Option<i32>) {
+    let location = match location {
+        Some(location) => location,
+        None => return (None, None),
+    };
+    let offset_container_id = location.get("offset_container_id").and_then(as_i32).filter(|&id| id != -1);
+    let rect = get_dictionary(&location, "bounds").unwrap_or_else(|| location.clone());
+    let bounds = if rect.contains_key("width") {
+        Some(Rect {
+            x: get_i32(&rect, "x"),
+            y: get_i32(&rect, "y"),
+            width: get_i32(&rect, "width"),
+            height: get_i32(&rect, "height"),
+        })
+    } else {
+        None
+    };
+    (bounds, offset_container_id)
+}
+
 struct AccessibilityHandlerWrapper {
     delegate: SendProtector<Box<dyn AccessibilityHandlerCallbacks>>,
 }
@@ -64,13 +359,136 @@ cef_callback_impl!{
             &self,
             value: Value: *mut cef_value_t,
         ) {
-            unsafe{ self.delegate.get() }.on_accessibility_tree_change(value.into())
+            unsafe{ self.delegate.get() }.on_accessibility_tree_change(AccessibilityTreeChange::from_value(value.into()))
         }
         fn on_accessibility_location_change(
             &self,
             value: Value: *mut cef_value_t,
         ) {
-            unsafe{ self.delegate.get() }.on_accessibility_location_change(value.into())
+            unsafe{ self.delegate.get() }.on_accessibility_location_change(AccessibilityLocationChange::from_value(value.into()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TREE: &str = "frame";
+
+    fn node(id: i32, child_ids: &[i32]) -> AccessibilityNode {
+        AccessibilityNode {
+            id,
+            role: "genericContainer".to_owned(),
+            state: Vec::new(),
+            bounds: None,
+            offset_container_id: None,
+            child_ids: child_ids.to_vec(),
+            attributes: HashMap::new(),
+        }
+    }
+
+    fn change(update: AccessibilityTreeUpdate) -> AccessibilityTreeChange {
+        AccessibilityTreeChange {
+            tree_id: TREE.to_owned(),
+            updates: vec![update],
+            events: Vec::new(),
         }
     }
+
+    fn nodes(nodes: Vec<AccessibilityNode>) -> AccessibilityTreeUpdate {
+        AccessibilityTreeUpdate {
+            nodes,
+            ..AccessibilityTreeUpdate::default()
+        }
+    }
+
+    /// 1 -> (2 -> (4, 5), 3)
+    fn tree() -> AccessibilityTree {
+        let mut tree = AccessibilityTree::new();
+        tree.apply_change(&change(AccessibilityTreeUpdate {
+            root_id: Some(1),
+            focused_id: Some(4),
+            node_id_to_clear: None,
+            nodes: vec![node(1, &[2, 3]), node(2, &[4, 5]), node(3, &[]), node(4, &[]), node(5, &[])],
+        }));
+        tree
+    }
+
+    fn ids(tree: &AccessibilityTree) -> Vec<i32> {
+        let mut ids = (0..10).filter(|&id| tree.node(TREE, id).is_some()).collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn root_focus_and_children_are_tracked() {
+        let tree = tree();
+        let root = tree.root(TREE).unwrap();
+        assert_eq!(root.id, 1);
+        assert_eq!(tree.children(TREE, root).map(|node| node.id).collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(tree.focused(TREE).map(|node| node.id), Some(4));
+        assert_eq!(tree.tree_ids(), [TREE]);
+    }
+
+    #[test]
+    fn clearing_a_node_removes_its_descendants() {
+        let mut tree = tree();
+        tree.apply_change(&change(AccessibilityTreeUpdate {
+            node_id_to_clear: Some(1),
+            ..nodes(vec![node(1, &[3]), node(3, &[])])
+        }));
+        assert_eq!(ids(&tree), [1, 3]);
+    }
+
+    #[test]
+    fn dropped_children_are_removed_with_their_subtrees() {
+        let mut tree = tree();
+        tree.apply_change(&change(nodes(vec![node(1, &[3])])));
+        assert_eq!(ids(&tree), [1, 3]);
+    }
+
+    #[test]
+    fn adopted_children_are_kept() {
+        let mut tree = tree();
+        // 4 moves from 2 to 3, 2 and 5 are dropped.
+        tree.apply_change(&change(nodes(vec![node(1, &[3]), node(3, &[4])])));
+        assert_eq!(ids(&tree), [1, 3, 4]);
+    }
+
+    #[test]
+    fn deep_trees_are_removed_without_recursion() {
+        const DEPTH: i32 = 100_000;
+        let mut tree = AccessibilityTree::new();
+        let mut update = nodes((1..DEPTH).map(|id| node(id, &[id + 1])).collect());
+        update.nodes.push(node(DEPTH, &[]));
+        update.nodes.push(node(0, &[1]));
+        update.root_id = Some(0);
+        tree.apply_change(&change(update));
+        tree.apply_change(&change(nodes(vec![node(0, &[])])));
+        assert_eq!(ids(&tree), [0]);
+        assert!(tree.node(TREE, DEPTH).is_none());
+    }
+
+    #[test]
+    fn location_changes_move_nodes() {
+        let mut tree = tree();
+        let bounds = Rect { x: 1, y: 2, width: 3, height: 4 };
+        tree.apply_location_changes(&[AccessibilityLocationChange {
+            tree_id: TREE.to_owned(),
+            node_id: 3,
+            bounds,
+            offset_container_id: Some(1),
+        }]);
+        let node = tree.node(TREE, 3).unwrap();
+        assert_eq!((node.bounds, node.offset_container_id), (Some(bounds), Some(1)));
+    }
+
+    #[test]
+    fn removed_trees_are_forgotten() {
+        let mut tree = tree();
+        tree.remove_tree(TREE);
+        assert!(tree.root(TREE).is_none());
+        assert!(tree.tree_ids().is_empty());
+    }
 }
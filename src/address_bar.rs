@@ -0,0 +1,441 @@
+//! Rendering URLs and titles safely in application UI.
+//!
+//! Pages control their URL and title, and can use both to impersonate other
+//! sites: `https://bank.com@evil.com/` is a page on `evil.com`, `xn--80ak6aa92e.com`
+//! renders as a Cyrillic lookalike of `apple.com`, and a title can contain
+//! bidirectional overrides that reorder the text around it. The functions in
+//! this module produce strings that can be shown to the user, and
+//! [AddressBarModel] keeps them up to date for a browser.
+
+use crate::{
+    browser::Browser,
+    client::display_handler::{DisplayHandler, DisplayHandlerCallbacks, DisplayHandlerDecorator},
+    frame::Frame,
+    string::CefString,
+};
+use cef_sys::cef_format_url_for_security_display;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// Format `url` for display in a security context, e.g. the origin of a
+/// permission prompt. Shows the scheme (unless it's http or https), host and
+/// port, and leaves out the path and credentials.
+pub fn format_url_for_security_display(url: &str) -> String {
+    unsafe {
+        CefString::from_userfree(cef_format_url_for_security_display(CefString::new(url).as_ptr()))
+            .map(String::from)
+            .unwrap_or_default()
+    }
+}
+
+/// Returns `url` as it should be shown in an address bar: without user name
+/// and password, and with internationalized host names in Unicode only if
+/// they can't be confused with other host names (see [display_host]).
+pub fn display_url(url: &str) -> String {
+    let authority_start = match url.find("://") {
+        Some(i) => i + 3,
+        None => return url.to_owned(),
+    };
+    let rest = &url[authority_start..];
+    let authority_end = rest.find(|c| c == '/' || c == '?' || c == '#').unwrap_or(rest.len());
+    let authority = &rest[..authority_end];
+    let host_and_port = authority.rsplit('@').next().unwrap_or(authority);
+    let (host, port) = match host_and_port.rfind(':') {
+        // IPv6 addresses contain colons, but are enclosed in brackets.
+        Some(i) if !host_and_port[i..].contains(']') => host_and_port.split_at(i),
+        _ => (host_and_port, ""),
+    };
+    format!("{}{}{}{}", &url[..authority_start], display_host(host), port, &rest[authority_end..])
+}
+
+/// Returns `host` lowercased, with punycode labels (`xn--...`) decoded if the
+/// result is safe to show. Labels stay encoded if they contain invisible
+/// characters, consist only of Greek or Cyrillic letters that look like Latin
+/// ones, or mix scripts. Like in Chromium, Latin may only be mixed with the
+/// scripts of Japanese (Han, Hiragana and Katakana), Korean (Han and Hangul)
+/// or Chinese (Han and Bopomofo).
+///
+/// This is a conservative subset of Chromium's spoof checks: some legitimate
+/// names are shown encoded, but no lookalike of an ASCII name is decoded.
+pub fn display_host(host: &str) -> String {
+    host.split('.')
+        .map(|label| {
+            let label = label.to_lowercase();
+            if !label.starts_with("xn--") {
+                return label;
+            }
+            match punycode_decode(&label[4..]) {
+                Some(decoded) if is_safe_label(&decoded) => decoded,
+                _ => label,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Returns `title` with whitespace collapsed and invisible and control
+/// characters removed, shortened to at most `max_chars` characters with an
+/// ellipsis.
+pub fn elide_title(title: &str, max_chars: usize) -> String {
+    let cleaned = title
+        .chars()
+        .filter(|&c| !is_invisible(c) && (!c.is_control() || c.is_whitespace()))
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if cleaned.chars().count() <= max_chars {
+        return cleaned;
+    }
+    let mut elided = cleaned.chars().take(max_chars.saturating_sub(1)).collect::<String>();
+    elided.truncate(elided.trim_end().len());
+    elided.push('…');
+    elided
+}
+
+fn is_invisible(c: char) -> bool {
+    match c {
+        '\u{00AD}' | '\u{034F}' | '\u{061C}' | '\u{115F}' | '\u{1160}' | '\u{180E}' | '\u{3164}'
+        | '\u{FEFF}' | '\u{FFA0}' => true,
+        '\u{200B}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2060}'..='\u{2069}' => true,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    /// Digits and hyphens, which go with every script.
+    Common,
+    Latin,
+    Greek,
+    Cyrillic,
+    Han,
+    Hiragana,
+    Katakana,
+    Hangul,
+    Bopomofo,
+    /// Everything else, which can't be mixed with any other script.
+    Other,
+}
+
+fn script_of(c: char) -> Script {
+    match c {
+        '0'..='9' | '-' => Script::Common,
+        'a'..='z' | '\u{00C0}'..='\u{024F}' | '\u{1E00}'..='\u{1EFF}' => Script::Latin,
+        '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}' => Script::Greek,
+        '\u{0400}'..='\u{052F}' => Script::Cyrillic,
+        '\u{2E80}'..='\u{2FDF}'
+        | '\u{3005}'
+        | '\u{3007}'
+        | '\u{3021}'..='\u{3029}'
+        | '\u{3038}'..='\u{303B}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2FA1F}' => Script::Han,
+        '\u{3041}'..='\u{309F}' => Script::Hiragana,
+        '\u{30A0}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}' => Script::Katakana,
+        '\u{1100}'..='\u{11FF}'
+        | '\u{3130}'..='\u{318F}'
+        | '\u{A960}'..='\u{A97F}'
+        | '\u{AC00}'..='\u{D7FF}' => Script::Hangul,
+        '\u{3100}'..='\u{312F}' | '\u{31A0}'..='\u{31BF}' => Script::Bopomofo,
+        _ => Script::Other,
+    }
+}
+
+/// Scripts that may be mixed in a label, see Chromium's IDN spoof checker.
+const SAFE_SCRIPT_COMBINATIONS: &[&[Script]] = &[
+    &[Script::Latin, Script::Han, Script::Hiragana, Script::Katakana],
+    &[Script::Latin, Script::Han, Script::Hangul],
+    &[Script::Latin, Script::Han, Script::Bopomofo],
+];
+
+/// Cyrillic and Greek letters that look like Latin letters. Labels made only
+/// of these can spell a Latin name.
+const LATIN_LOOKALIKES: &str = "аеорсухіјѕԁһӏԛԝαικνορτυχ";
+
+fn is_safe_label(label: &str) -> bool {
+    if label.is_ascii() || label.chars().any(|c| is_invisible(c) || c.is_control()) {
+        return false;
+    }
+    let scripts = label
+        .chars()
+        .map(script_of)
+        .filter(|&script| script != Script::Common)
+        .fold(Vec::new(), |mut scripts, script| {
+            if !scripts.contains(&script) {
+                scripts.push(script);
+            }
+            scripts
+        });
+    match &scripts[..] {
+        [Script::Greek] | [Script::Cyrillic] => !label
+            .chars()
+            .filter(|&c| script_of(c) != Script::Common)
+            .all(|c| LATIN_LOOKALIKES.contains(c)),
+        [_] => true,
+        _ => SAFE_SCRIPT_COMBINATIONS
+            .iter()
+            .any(|combination| scripts.iter().all(|script| combination.contains(script))),
+    }
+}
+
+/// Decodes a punycode string (RFC 3492) without the `xn--` prefix.
+fn punycode_decode(input: &str) -> Option<String> {
+    const BASE: u32 = 36;
+    const T_MIN: u32 = 1;
+    const T_MAX: u32 = 26;
+    const INITIAL_BIAS: u32 = 72;
+    const INITIAL_N: u32 = 128;
+
+    fn adapt(delta: u32, num_points: u32, first_time: bool) -> u32 {
+        const SKEW: u32 = 38;
+        const DAMP: u32 = 700;
+        let mut delta = delta / if first_time { DAMP } else { 2 };
+        delta += delta / num_points;
+        let mut k = 0;
+        while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+            delta /= BASE - T_MIN;
+            k += BASE;
+        }
+        k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+    }
+
+    let (basic, extended) = match input.rfind('-') {
+        Some(i) => (&input[..i], &input[i + 1..]),
+        None => ("", input),
+    };
+    if !basic.is_ascii() {
+        return None;
+    }
+    let mut output: Vec<char> = basic.chars().collect();
+    let mut n = INITIAL_N;
+    let mut i: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut digits = extended.bytes().peekable();
+    while digits.peek().is_some() {
+        let old_i = i;
+        let mut w: u32 = 1;
+        let mut k = BASE;
+        loop {
+            let digit = u32::from(match digits.next()? {
+                byte @ b'a'..=b'z' => byte - b'a',
+                byte @ b'A'..=b'Z' => byte - b'A',
+                byte @ b'0'..=b'9' => byte - b'0' + 26,
+                _ => return None,
+            });
+            i = i.checked_add(digit.checked_mul(w)?)?;
+            let t = if k <= bias {
+                T_MIN
+            } else if k >= bias + T_MAX {
+                T_MAX
+            } else {
+                k - bias
+            };
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(BASE - t)?;
+            k += BASE;
+        }
+        let len = output.len() as u32 + 1;
+        bias = adapt(i - old_i, len, old_i == 0);
+        n = n.checked_add(i / len)?;
+        i %= len;
+        output.insert(i as usize, std::char::from_u32(n)?);
+        i += 1;
+    }
+    Some(output.into_iter().collect())
+}
+
+/// The URL and title of a browser, as reported by CEF.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AddressBarState {
+    pub url: String,
+    pub title: String,
+}
+
+impl AddressBarState {
+    /// The URL to show in the address bar, see [display_url].
+    pub fn display_url(&self) -> String {
+        display_url(&self.url)
+    }
+    /// The origin to show in security UI, see [format_url_for_security_display].
+    pub fn security_origin(&self) -> String {
+        format_url_for_security_display(&self.url)
+    }
+    /// The title to show in a tab or window caption, see [elide_title]. Falls
+    /// back to the URL for pages without a title.
+    pub fn display_title(&self, max_chars: usize) -> String {
+        let title = elide_title(&self.title, max_chars);
+        if title.is_empty() {
+            elide_title(&self.display_url(), max_chars)
+        } else {
+            title
+        }
+    }
+}
+
+/// Called with the new state whenever the URL or title changed.
+pub trait AddressBarListener = 'static + Send + FnMut(&AddressBarState);
+
+/// Tracks the URL and title of a browser for the application's address bar
+/// and window caption. Either wrap the browser's display handler with
+/// [AddressBarModel::display_handler], or call [AddressBarModel::set_url] and
+/// [AddressBarModel::set_title] from it.
+#[derive(Clone, Default)]
+pub struct AddressBarModel {
+    state: Arc<Mutex<AddressBarState>>,
+    listener: Arc<Mutex<Option<Box<dyn AddressBarListener>>>>,
+}
+
+impl AddressBarModel {
+    pub fn new() -> AddressBarModel {
+        Self::default()
+    }
+    /// Call `listener` on every change, replacing the previous listener.
+    pub fn on_change(&self, listener: impl AddressBarListener) {
+        *self.listener.lock() = Some(Box::new(listener));
+    }
+    pub fn state(&self) -> AddressBarState {
+        self.state.lock().clone()
+    }
+    /// Update the URL, from [DisplayHandlerCallbacks::on_address_change] of
+    /// the main frame.
+    pub fn set_url(&self, url: &str) {
+        self.update(|state| state.url = url.to_owned());
+    }
+    /// Update the title, from [DisplayHandlerCallbacks::on_title_change].
+    pub fn set_title(&self, title: &str) {
+        self.update(|state| state.title = title.to_owned());
+    }
+    /// Create a display handler that updates this model before calling into
+    /// `callbacks`.
+    pub fn display_handler<C: DisplayHandlerCallbacks>(&self, callbacks: C) -> DisplayHandler {
+        DisplayHandler::new(AddressBarDisplayHandler {
+            model: self.clone(),
+            inner: callbacks,
+        })
+    }
+    fn update(&self, f: impl FnOnce(&mut AddressBarState)) {
+        let state = {
+            let mut state = self.state.lock();
+            let old = state.clone();
+            f(&mut state);
+            if *state == old {
+                return;
+            }
+            state.clone()
+        };
+        if let Some(listener) = &mut *self.listener.lock() {
+            listener(&state);
+        }
+    }
+}
+
+struct AddressBarDisplayHandler<C: DisplayHandlerCallbacks> {
+    model: AddressBarModel,
+    inner: C,
+}
+
+impl<C: DisplayHandlerCallbacks> DisplayHandlerDecorator for AddressBarDisplayHandler<C> {
+    type Inner = C;
+    fn inner(&mut self) -> &mut C {
+        &mut self.inner
+    }
+    fn on_address_change(&mut self, browser: Browser, frame: Frame, url: &str) {
+        if frame.is_main() {
+            self.model.set_url(url);
+        }
+        self.inner.on_address_change(browser, frame, url)
+    }
+    fn on_title_change(&mut self, browser: Browser, title: &str) {
+        self.model.set_title(title);
+        self.inner.on_title_change(browser, title)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn punycode_decodes_rfc_3492_labels() {
+        assert_eq!(punycode_decode("mnchen-3ya").as_deref(), Some("münchen"));
+        assert_eq!(punycode_decode("wgv71a119e").as_deref(), Some("日本語"));
+        assert_eq!(punycode_decode("3e0b707e").as_deref(), Some("한국"));
+        assert_eq!(punycode_decode("ab-ry2cs33g").as_deref(), Some("ab中文"));
+    }
+
+    #[test]
+    fn punycode_rejects_invalid_input() {
+        assert_eq!(punycode_decode("mnchen-3y!"), None);
+        assert_eq!(punycode_decode("mnchen-3y"), None);
+        assert_eq!(punycode_decode("ü-3ya"), None);
+        assert_eq!(punycode_decode("99999999999"), None);
+    }
+
+    #[test]
+    fn single_scripts_are_safe() {
+        assert!(is_safe_label("münchen"));
+        assert!(is_safe_label("日本語"));
+        assert!(is_safe_label("한국"));
+        assert!(is_safe_label("αβγ"));
+        assert!(is_safe_label("ㄅㄆ"));
+    }
+
+    #[test]
+    fn ascii_and_invisible_characters_are_unsafe() {
+        assert!(!is_safe_label("example"));
+        assert!(!is_safe_label("exa\u{200B}mplé"));
+        assert!(!is_safe_label("mün\u{00AD}chen"));
+    }
+
+    #[test]
+    fn latin_lookalikes_are_unsafe() {
+        assert!(!is_safe_label("аррӏе"));
+        assert!(!is_safe_label("ορα"));
+    }
+
+    #[test]
+    fn cjk_combinations_are_safe() {
+        assert!(is_safe_label("ab中文"));
+        assert!(is_safe_label("abテスト"));
+        assert!(is_safe_label("ab한국"));
+        assert!(is_safe_label("abㄅ中"));
+        assert!(is_safe_label("日本のテスト"));
+    }
+
+    #[test]
+    fn other_combinations_are_unsafe() {
+        assert!(!is_safe_label("abαβ"));
+        assert!(!is_safe_label("abԁ"));
+        assert!(!is_safe_label("abաբ"));
+        assert!(!is_safe_label("ab한국テスト"));
+        assert!(!is_safe_label("ab中ㄅ한"));
+    }
+
+    #[test]
+    fn display_host_decodes_safe_labels_only() {
+        assert_eq!(display_host("XN--MNCHEN-3YA.de"), "münchen.de");
+        assert_eq!(display_host("xn--80ak6aa92e.com"), "xn--80ak6aa92e.com");
+        assert_eq!(display_host("xn--ab-ecde.com"), "xn--ab-ecde.com");
+        assert_eq!(display_host("xn--ab-hi4a2bl.jp"), "abテスト.jp");
+    }
+
+    #[test]
+    fn display_url_drops_credentials() {
+        assert_eq!(display_url("https://bank.com@evil.com/login"), "https://evil.com/login");
+        assert_eq!(display_url("https://user:pw@xn--mnchen-3ya.de:8080/?q"), "https://münchen.de:8080/?q");
+        assert_eq!(display_url("http://[::1]:80/"), "http://[::1]:80/");
+        assert_eq!(display_url("about:blank"), "about:blank");
+    }
+
+    #[test]
+    fn elide_title_removes_invisible_characters() {
+        assert_eq!(elide_title("  a\u{202E}b \n\t c ", 10), "ab c");
+        assert_eq!(elide_title("hello world", 7), "hello…");
+        assert_eq!(elide_title("hello", 5), "hello");
+    }
+}
@@ -0,0 +1,243 @@
+//! Reloading browsers when the files of a web front-end change, for
+//! development.
+//!
+//! An [AssetWatcher] polls the directories an application serves its
+//! front-end from, e.g. through a [SchemeHandlerFactory](crate::scheme::SchemeHandlerFactory),
+//! and when files in them change, reloads browsers bypassing the cache or
+//! dispatches an event to pages that update themselves:
+//!
+//! ```rust,ignore
+//! let _watch = AssetWatcher::new()
+//!     .watch("frontend/dist")
+//!     .reload(browser.clone())
+//!     .start();
+//! ```
+//!
+//! Polling avoids a dependency on platform file notification APIs, at the
+//! cost of walking the directories once per interval, so only watch
+//! directories of a front-end's size. This module is only available with the
+//! `asset_watch` feature.
+
+use crate::{
+    browser::Browser,
+    string::quote_string,
+    task::{TaskRunner, ThreadId},
+};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime},
+};
+
+/// Called on the browser process UI thread with the paths of the changed,
+/// added and removed files.
+pub trait AssetChangeHook = 'static + Send + Sync + Fn(&[PathBuf]);
+
+enum Target {
+    Reload(Browser),
+    Event { browser: Browser, event: String },
+}
+
+/// The shortest interval directories are checked at. Shorter ones, including
+/// zero, would keep a background thread busy walking the directories.
+pub const MIN_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Watches directories for changes. Configure it, then call
+/// [AssetWatcher::start].
+pub struct AssetWatcher {
+    directories: Vec<PathBuf>,
+    interval: Duration,
+    targets: Vec<Target>,
+    hooks: Vec<Arc<dyn AssetChangeHook>>,
+}
+
+impl AssetWatcher {
+    pub fn new() -> AssetWatcher {
+        AssetWatcher {
+            directories: Vec::new(),
+            interval: Duration::from_millis(500),
+            targets: Vec::new(),
+            hooks: Vec::new(),
+        }
+    }
+    /// Watch the files in `directory` and its subdirectories. Files and
+    /// directories whose names start with a dot, like editor swap files and
+    /// `.git`, are ignored.
+    pub fn watch(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.directories.push(directory.into());
+        self
+    }
+    /// How often the directories are checked. Defaults to 500 ms. Changes
+    /// within one interval, like a build writing many files, cause a single
+    /// reload. Intervals shorter than [MIN_INTERVAL] are raised to it.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval.max(MIN_INTERVAL);
+        self
+    }
+    /// Reload all frames of `browser`, ignoring the cache, when files change.
+    pub fn reload(mut self, browser: Browser) -> Self {
+        self.targets.push(Target::Reload(browser));
+        self
+    }
+    /// Dispatch a `CustomEvent` named `event` on the `window` of the main frame
+    /// of `browser` when files change, instead of reloading it. The event's
+    /// `detail.paths` lists the changed paths, for pages that swap styles or
+    /// modules in place.
+    pub fn notify(mut self, browser: Browser, event: &str) -> Self {
+        self.targets.push(Target::Event {
+            browser,
+            event: event.to_owned(),
+        });
+        self
+    }
+    /// Call `hook` when files change, in addition to the configured reloads
+    /// and events.
+    pub fn on_change(mut self, hook: impl AssetChangeHook) -> Self {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+    /// Start polling on CEF's background file thread. Watching stops when
+    /// the returned [AssetWatch] is dropped.
+    pub fn start(self) -> AssetWatch {
+        let running = Arc::new(AtomicBool::new(true));
+        let snapshot = snapshot(&self.directories);
+        let watcher = Arc::new(self);
+        schedule(watcher, snapshot, running.clone());
+        AssetWatch { running }
+    }
+}
+
+impl Default for AssetWatcher {
+    fn default() -> AssetWatcher {
+        AssetWatcher::new()
+    }
+}
+
+/// A running [AssetWatcher]. Stops watching when dropped.
+pub struct AssetWatch {
+    running: Arc<AtomicBool>,
+}
+
+impl AssetWatch {
+    /// Stop watching. Same as dropping the watch.
+    pub fn stop(self) {}
+}
+
+impl Drop for AssetWatch {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+type Snapshot = HashMap<PathBuf, (SystemTime, u64)>;
+
+fn schedule(watcher: Arc<AssetWatcher>, previous: Snapshot, running: Arc<AtomicBool>) {
+    let delay_ms = watcher.interval.as_millis() as i64;
+    TaskRunner::post_delayed_task_on(
+        ThreadId::FileBackground,
+        move || {
+            if !running.load(Ordering::Relaxed) {
+                return;
+            }
+            let current = snapshot(&watcher.directories);
+            let changed = changed_paths(&previous, &current);
+            if !changed.is_empty() {
+                let watcher = watcher.clone();
+                TaskRunner::post_task_on(ThreadId::UI, move || watcher.apply(&changed));
+            }
+            schedule(watcher, current, running);
+        },
+        delay_ms,
+    );
+}
+
+impl AssetWatcher {
+    fn apply(&self, changed: &[PathBuf]) {
+        log::debug!("{} watched files changed", changed.len());
+        for target in &self.targets {
+            match target {
+                Target::Reload(browser) => {
+                    if browser.is_valid() {
+                        browser.reload(true);
+                    }
+                }
+                Target::Event { browser, event } => {
+                    if !browser.is_valid() {
+                        continue;
+                    }
+                    let paths = changed
+                        .iter()
+                        .map(|path| quote_string(&path.to_string_lossy()))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let code = format!(
+                        "window.dispatchEvent(new CustomEvent({}, {{ detail: {{ paths: [{}] }} }}));",
+                        quote_string(event),
+                        paths,
+                    );
+                    browser.get_main_frame().execute_java_script(&code, "", 0);
+                }
+            }
+        }
+        for hook in &self.hooks {
+            hook(changed);
+        }
+    }
+}
+
+fn snapshot(directories: &[PathBuf]) -> Snapshot {
+    let mut files = HashMap::new();
+    for directory in directories {
+        walk(directory, &mut files);
+    }
+    files
+}
+
+fn walk(directory: &Path, files: &mut Snapshot) {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.filter_map(Result::ok) {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if metadata.is_dir() {
+            walk(&entry.path(), files);
+        } else {
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            files.insert(entry.path(), (modified, metadata.len()));
+        }
+    }
+}
+
+fn changed_paths(previous: &Snapshot, current: &Snapshot) -> Vec<PathBuf> {
+    let mut changed = current
+        .iter()
+        .filter(|(path, state)| previous.get(*path) != Some(*state))
+        .map(|(path, _)| path.clone())
+        .chain(previous.keys().filter(|path| !current.contains_key(*path)).cloned())
+        .collect::<Vec<_>>();
+    changed.sort();
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_intervals_are_raised_to_the_minimum() {
+        assert_eq!(AssetWatcher::new().with_interval(Duration::from_secs(0)).interval, MIN_INTERVAL);
+        assert_eq!(AssetWatcher::new().with_interval(Duration::from_secs(2)).interval, Duration::from_secs(2));
+    }
+}
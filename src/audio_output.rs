@@ -0,0 +1,150 @@
+//! Routing the audio of a browser to a specific output device.
+//!
+//! Chromium has no per-browser audio output setting; pages pick the device of
+//! each media element with `HTMLMediaElement.setSinkId`. The functions in this
+//! module inject a script that does this for every `<audio>` and `<video>`
+//! element of every document loaded in the browser, so the application doesn't
+//! need the pages' cooperation. Web Audio (`AudioContext`) output can't be
+//! redirected this way and keeps using the default device.
+//!
+//! Device labels are only exposed to pages that were granted media access.
+//! CEF has no handler for media access requests, so it can only be granted to
+//! all pages with the `enable-media-stream` or `use-fake-ui-for-media-stream`
+//! switch; otherwise devices can only be selected by id.
+
+use crate::{
+    browser_host::BrowserHost,
+    devtools::{DevToolsError, get_string, page::UserScriptSlot},
+    string::quote_string,
+    values::StoredValue,
+};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{collections::HashMap, sync::Arc};
+
+/// An audio output device as seen by the pages in a browser.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AudioOutputDevice {
+    /// Device id. Ids are different for every origin, so an id is only valid
+    /// for pages of the origin it was queried from.
+    pub device_id: String,
+    /// Human-readable name of the device, which is the same for all origins.
+    /// Empty if the page wasn't granted media access.
+    pub label: String,
+    /// Devices belonging to the same physical device, e.g. the speaker and
+    /// microphone of a headset, have the same group id.
+    pub group_id: String,
+}
+
+lazy_static! {
+    /// The script of each browser that [BrowserHost::set_audio_output_device]
+    /// routed to a device, by browser identifier, until the browser closes.
+    static ref OUTPUT_SCRIPTS: Mutex<HashMap<i32, Arc<UserScriptSlot>>> = Mutex::new(HashMap::new());
+}
+
+/// Drop the script of a closed browser.
+pub(crate) fn forget_browser(browser_id: i32) {
+    OUTPUT_SCRIPTS.lock().remove(&browser_id);
+}
+
+/// Returns a script routing the media elements of a document to the device with
+/// the given id or label, or to the default device for an empty string. Running
+/// it again in the same document changes the device.
+fn output_device_script(device: &str) -> String {
+    format!(
+        "(function() {{\n\
+         var state = window.__cefAudioOutput;\n\
+         if (!state) {{\n\
+         state = window.__cefAudioOutput = {{ sinkId: '' }};\n\
+         var apply = function(element) {{\n\
+         if (element.setSinkId && element.sinkId !== state.sinkId) element.setSinkId(state.sinkId).catch(function() {{}});\n\
+         }};\n\
+         var applyTree = function(node) {{\n\
+         if (node instanceof HTMLMediaElement) apply(node);\n\
+         if (node.querySelectorAll) node.querySelectorAll('audio, video').forEach(apply);\n\
+         }};\n\
+         state.applyAll = function() {{ applyTree(document); }};\n\
+         var play = HTMLMediaElement.prototype.play;\n\
+         HTMLMediaElement.prototype.play = function() {{ apply(this); return play.apply(this, arguments); }};\n\
+         new MutationObserver(function(mutations) {{\n\
+         mutations.forEach(function(mutation) {{ mutation.addedNodes.forEach(applyTree); }});\n\
+         }}).observe(document, {{ childList: true, subtree: true }});\n\
+         }}\n\
+         var wanted = {};\n\
+         if (!wanted || !navigator.mediaDevices) {{\n\
+         state.sinkId = '';\n\
+         state.applyAll();\n\
+         return;\n\
+         }}\n\
+         navigator.mediaDevices.enumerateDevices().then(function(devices) {{\n\
+         var device = devices.find(function(device) {{\n\
+         return device.kind === 'audiooutput' && (device.deviceId === wanted || device.label === wanted);\n\
+         }});\n\
+         state.sinkId = device ? device.deviceId : '';\n\
+         state.applyAll();\n\
+         }});\n\
+         }})();",
+        quote_string(device)
+    )
+}
+
+impl BrowserHost {
+    /// List the audio output devices, as seen by the document in the main
+    /// frame.
+    ///
+    /// `callback` will be called on the browser process UI thread.
+    pub fn get_audio_output_devices(
+        &self,
+        callback: impl 'static + Send + FnOnce(Result<Vec<AudioOutputDevice>, DevToolsError>),
+    ) {
+        let expression = "navigator.mediaDevices.enumerateDevices().then(function(devices) {\n\
+                          return devices.filter(function(device) { return device.kind === 'audiooutput'; })\n\
+                          .map(function(device) {\n\
+                          return { deviceId: device.deviceId, label: device.label, groupId: device.groupId };\n\
+                          });\n\
+                          })";
+        self.get_browser().get_main_frame().evaluate(expression, move |result| {
+            callback(result.and_then(|value| match value {
+                StoredValue::List(devices) => {
+                    let devices: Vec<StoredValue> = devices.into();
+                    Ok(devices
+                        .into_iter()
+                        .filter_map(|device| match device {
+                            StoredValue::Dictionary(device) => {
+                                let device: HashMap<String, StoredValue> = device.into();
+                                Some(AudioOutputDevice {
+                                    device_id: get_string(&device, "deviceId"),
+                                    label: get_string(&device, "label"),
+                                    group_id: get_string(&device, "groupId"),
+                                })
+                            }
+                            _ => None,
+                        })
+                        .collect())
+                }
+                _ => Err(DevToolsError::InvalidResult),
+            }))
+        });
+    }
+    /// Play the audio of this browser on the output device with the given id or
+    /// label, or on the system default device if `device` is `None` or doesn't
+    /// match any device. Selecting by label works for pages of every origin,
+    /// while ids are only valid for one origin (see
+    /// [AudioOutputDevice::device_id]).
+    ///
+    /// The device is switched in the documents currently loaded and used for
+    /// every document loaded afterwards, until the browser is closed. Calls
+    /// are applied in order, even before earlier ones completed.
+    /// `callback` will be called on the browser process UI thread.
+    pub fn set_audio_output_device(
+        &self,
+        device: Option<&str>,
+        callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+    ) {
+        let browser_id = self.get_browser().get_identifier();
+        let script = output_device_script(device.unwrap_or(""));
+        self.execute_in_all_frames(&script);
+        let slot = OUTPUT_SCRIPTS.lock().entry(browser_id).or_default().clone();
+        slot.set(self, device.map(|_| script), callback);
+    }
+}
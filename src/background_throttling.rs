@@ -0,0 +1,162 @@
+//! Controlling how Chromium throttles browsers that aren't visible.
+//!
+//! Hidden pages get a lower renderer priority, their timers are limited to
+//! about one wake-up per second (or per minute after a while), and windows
+//! covered by other windows are treated as hidden. That saves power, but stalls
+//! dashboards that have to keep updating in the background.
+//!
+//! [BackgroundThrottlingSettings] turns these optimizations off for the whole
+//! application through switches of the browser process. For windowless
+//! browsers, throttling can instead be disabled per browser with
+//! [BrowserHost::set_background_throttling]: such a browser is never reported
+//! as hidden when the application calls [BrowserHost::set_hidden] instead of
+//! [BrowserHost::was_hidden], so it keeps running at full speed.
+
+use crate::{browser_host::BrowserHost, command_line::CommandLine};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+
+/// Background throttling settings applied as command-line switches. Every
+/// optimization is enabled by default, as in Chromium.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BackgroundThrottlingSettings {
+    /// Lower the priority of renderer processes that only have hidden pages.
+    pub renderer_backgrounding: bool,
+    /// Limit the rate of timers in hidden pages.
+    pub timer_throttling: bool,
+    /// Treat windows that are fully covered by other windows as hidden.
+    pub occlusion_detection: bool,
+}
+
+impl Default for BackgroundThrottlingSettings {
+    fn default() -> Self {
+        BackgroundThrottlingSettings {
+            renderer_backgrounding: true,
+            timer_throttling: true,
+            occlusion_detection: true,
+        }
+    }
+}
+
+impl BackgroundThrottlingSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Settings with every optimization disabled.
+    pub fn disabled() -> Self {
+        BackgroundThrottlingSettings {
+            renderer_backgrounding: false,
+            timer_throttling: false,
+            occlusion_detection: false,
+        }
+    }
+    pub fn renderer_backgrounding(mut self, renderer_backgrounding: bool) -> Self {
+        self.renderer_backgrounding = renderer_backgrounding;
+        self
+    }
+    pub fn timer_throttling(mut self, timer_throttling: bool) -> Self {
+        self.timer_throttling = timer_throttling;
+        self
+    }
+    pub fn occlusion_detection(mut self, occlusion_detection: bool) -> Self {
+        self.occlusion_detection = occlusion_detection;
+        self
+    }
+    /// Add the switches for these settings to `command_line`, which has to be
+    /// the command line of the browser process.
+    pub fn apply(&self, command_line: &CommandLine) {
+        let mut switches = Vec::new();
+        if !self.renderer_backgrounding {
+            switches.push("disable-renderer-backgrounding");
+        }
+        if !self.timer_throttling {
+            switches.push("disable-background-timer-throttling");
+        }
+        if !self.occlusion_detection {
+            switches.push("disable-backgrounding-occluded-windows");
+        }
+        for switch in switches {
+            if !command_line.has_switch(switch) {
+                command_line.append_switch(switch);
+            }
+        }
+    }
+}
+
+/// What [BrowserHost::set_background_throttling] and [BrowserHost::set_hidden]
+/// were told about a windowless browser. Browsers that are shown and
+/// throttled, as they start out, have no state.
+struct ThrottlingState {
+    throttling: bool,
+    /// Whether the application wants the browser to be hidden.
+    hidden: bool,
+}
+
+impl Default for ThrottlingState {
+    fn default() -> Self {
+        ThrottlingState {
+            throttling: true,
+            hidden: false,
+        }
+    }
+}
+
+lazy_static! {
+    static ref THROTTLING: Mutex<HashMap<i32, ThrottlingState>> = Mutex::new(HashMap::new());
+}
+
+fn update_throttling_state<R>(browser_id: i32, f: impl FnOnce(&mut ThrottlingState) -> R) -> R {
+    let mut states = THROTTLING.lock();
+    let state = states.entry(browser_id).or_default();
+    let result = f(state);
+    if state.throttling && !state.hidden {
+        states.remove(&browser_id);
+    }
+    result
+}
+
+/// Drop the state of a closed browser.
+pub(crate) fn forget_browser(browser_id: i32) {
+    THROTTLING.lock().remove(&browser_id);
+}
+
+impl BrowserHost {
+    /// Enable or disable background throttling of this browser. Only has an
+    /// effect for windowless browsers that are hidden and shown with
+    /// [BrowserHost::set_hidden]; windowed browsers are controlled by
+    /// [BackgroundThrottlingSettings]. Enabled by default.
+    ///
+    /// Disabling throttling of a hidden browser shows it to Chromium again;
+    /// enabling it hides it.
+    pub fn set_background_throttling(&self, enabled: bool) {
+        let hidden = update_throttling_state(self.get_browser().get_identifier(), |state| {
+            state.throttling = enabled;
+            state.hidden
+        });
+        if hidden {
+            self.was_hidden(enabled);
+        }
+    }
+    /// Returns false if background throttling was disabled with
+    /// [BrowserHost::set_background_throttling].
+    pub fn is_background_throttling_enabled(&self) -> bool {
+        THROTTLING
+            .lock()
+            .get(&self.get_browser().get_identifier())
+            .map(|state| state.throttling)
+            .unwrap_or(true)
+    }
+    /// Hide or show a windowless browser. Unlike [BrowserHost::was_hidden] this
+    /// respects [BrowserHost::set_background_throttling]: a browser with
+    /// throttling disabled keeps painting and running timers while hidden.
+    pub fn set_hidden(&self, hidden: bool) {
+        let throttling = update_throttling_state(self.get_browser().get_identifier(), |state| {
+            state.hidden = hidden;
+            state.throttling
+        });
+        if throttling || !hidden {
+            self.was_hidden(hidden);
+        }
+    }
+}
@@ -3,8 +3,10 @@ use cef_sys::{cef_browser_settings_t, cef_browser_t, cef_state_t};
 use crate::{
     browser_host::BrowserHost,
     color::Color,
-    frame::Frame,
+    frame::{Frame, FrameTree},
+    helper_traits::IsValid,
     string::{CefString, CefStringList},
+    CefError,
 };
 
 ref_counted_ptr! {
@@ -16,11 +18,25 @@ ref_counted_ptr! {
 }
 
 impl Browser {
+    /// Returns false once [LifeSpanHandlerCallbacks::on_before_close] was
+    /// called for this browser. Other functions panic when called on a browser
+    /// that isn't valid anymore.
+    ///
+    /// [LifeSpanHandlerCallbacks::on_before_close]: crate::client::life_span_handler::LifeSpanHandlerCallbacks::on_before_close
+    pub fn is_valid(&self) -> bool {
+        !self.0.check_poisoned()
+    }
     /// Returns the browser host object. This function can only be called in the
     /// browser process.
     pub fn get_host(&self) -> BrowserHost {
         unsafe { BrowserHost::from_ptr_unchecked((self.0.get_host.unwrap())(self.0.as_ptr())) }
     }
+    /// Like [Browser::get_host], but fails instead of panicking if this browser
+    /// was closed.
+    pub fn try_get_host(&self) -> Result<BrowserHost, CefError> {
+        self.ensure_valid()?;
+        unsafe { BrowserHost::from_ptr((self.0.get_host.unwrap())(self.0.as_ptr())) }.ok_or(CefError::ObjectInvalid)
+    }
     /// Returns true if the browser can navigate backwards.
     pub fn can_go_back(&self) -> bool {
         unsafe { (self.0.can_go_back.unwrap())(self.0.as_ptr()) != 0 }
@@ -49,11 +65,11 @@ impl Browser {
     pub fn reload(&self, ignore_cache: bool) {
         if ignore_cache {
             unsafe {
-                (self.0.reload.unwrap())(self.0.as_ptr());
+                (self.0.reload_ignore_cache.unwrap())(self.0.as_ptr());
             }
         } else {
             unsafe {
-                (self.0.reload_ignore_cache.unwrap())(self.0.as_ptr());
+                (self.0.reload.unwrap())(self.0.as_ptr());
             }
         }
     }
@@ -80,10 +96,22 @@ impl Browser {
     pub fn get_main_frame(&self) -> Frame {
         unsafe { Frame::from_ptr_unchecked((self.0.get_main_frame.unwrap())(self.0.as_ptr())) }
     }
+    /// Like [Browser::get_main_frame], but fails if this browser was closed or
+    /// is being closed and has no main frame anymore.
+    pub fn try_get_main_frame(&self) -> Result<Frame, CefError> {
+        self.ensure_valid()?;
+        unsafe { Frame::from_ptr((self.0.get_main_frame.unwrap())(self.0.as_ptr())) }.ok_or(CefError::ObjectInvalid)
+    }
     /// Returns the focused frame for the browser window.
     pub fn get_focused_frame(&self) -> Option<Frame> {
         unsafe { Frame::from_ptr((self.0.get_focused_frame.unwrap())(self.0.as_ptr())) }
     }
+    /// Open the HTML source of the focused frame, or of the main frame if no
+    /// frame is focused, in the default text viewing application, like Chrome's
+    /// "View page source" command.
+    pub fn view_source(&self) {
+        self.get_focused_frame().unwrap_or_else(|| self.get_main_frame()).view_source();
+    }
     /// Returns the frame with the specified identifier, or None if not found.
     pub fn get_frame_byident(&self, identifier: i64) -> Option<Frame> {
         unsafe {
@@ -127,6 +155,16 @@ impl Browser {
         }
         Vec::from(list)
     }
+    /// Returns a snapshot of the frame hierarchy, including the identifier,
+    /// name, URL and parent of every frame. The returned tree implements
+    /// [std::fmt::Display], which prints one frame per line indented by depth.
+    pub fn frame_tree(&self) -> FrameTree {
+        FrameTree::new(
+            self.get_frame_identifiers()
+                .into_iter()
+                .filter_map(|identifier| self.get_frame_byident(identifier))
+        )
+    }
 }
 
 /// Represents the state of a setting.
@@ -167,7 +205,7 @@ impl Default for State {
 /// default values. The consequences of using custom values may not be well
 /// tested. Many of these and other settings can also configured using command-
 /// line switches.
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct BrowserSettings {
     pub windowless_frame_rate: i32,
     pub standard_font_family: String,
@@ -280,3 +318,9 @@ impl BrowserSettings {
         }
     }
 }
+
+impl IsValid for Browser {
+    fn is_valid(&self) -> bool {
+        Browser::is_valid(self)
+    }
+}
@@ -7,29 +7,35 @@ use crate::{
     drag::{DragData, DragOperation},
     events::{KeyEvent, MouseButtonType, MouseEvent, TouchEvent},
     extension::Extension,
-    file_dialog::{FileDialogMode, RunFileDialogCallbackWrapper},
+    file_dialog::{AcceptFilter, FileDialogMode, InvalidAcceptFilter, RunFileDialogCallbackWrapper},
+    helper_traits::IsValid,
     image::Image,
     ime::CompositionUnderline,
-    navigation::NavigationEntry,
-    printing::PDFPrintSettings,
+    navigation::{HistoryModel, NavigationEntry, NavigationHistoryEntry},
+    printing::{check_pdf_output_path, PDFPrintSettings},
     refcounted::{RefCountedPtr, Wrapper},
     request_context::RequestContext,
     send_protector::SendProtectorMut,
     string::{CefString, CefStringList},
+    task::{TaskRunner, ThreadId},
     values::{DictionaryValue, Point, Range, Size, StoredValue},
     window::{RawWindow, WindowInfo},
+    CefError,
 };
 use cef_sys::{
-    cef_browser_host_create_browser_sync, cef_browser_host_t,
+    cef_browser_host_create_browser_sync, cef_browser_host_t, cef_composition_underline_t,
     cef_download_image_callback_t, cef_image_t, cef_navigation_entry_t,
     cef_navigation_entry_visitor_t, cef_paint_element_type_t, cef_pdf_print_callback_t,
+    cef_pdf_print_settings_t, cef_point_t,
     cef_string_t,
 };
 use parking_lot::Mutex;
 use std::{
     collections::HashMap,
     iter::FromIterator,
+    path::Path,
     ptr::{null, null_mut},
+    sync::Arc,
 };
 
 /// Paint element types.
@@ -123,6 +129,19 @@ impl BrowserHost {
     pub fn get_browser(&self) -> Browser {
         unsafe { Browser::from_ptr_unchecked(self.0.get_browser.unwrap()(self.0.as_ptr())) }
     }
+    /// Returns false once the hosted browser was closed, see
+    /// [Browser::is_valid]. CEF ignores most calls on the host of a closed
+    /// browser instead of failing.
+    pub fn is_valid(&self) -> bool {
+        unsafe { Browser::from_ptr(self.0.get_browser.unwrap()(self.0.as_ptr())) }.map_or(false, |browser| browser.is_valid())
+    }
+    /// Like [BrowserHost::get_browser], but fails if the browser was closed.
+    pub fn try_get_browser(&self) -> Result<Browser, CefError> {
+        match unsafe { Browser::from_ptr(self.0.get_browser.unwrap()(self.0.as_ptr())) } {
+            Some(browser) if browser.is_valid() => Ok(browser),
+            _ => Err(CefError::ObjectInvalid),
+        }
+    }
     /// Request that the browser close. The JavaScript 'onbeforeunload' event will
     /// be fired. If `force_close` is false the event handler, if any, will be
     /// allowed to prompt the user and the user can optionally cancel the close. If
@@ -182,7 +201,8 @@ impl BrowserHost {
             .map(|has_view| unsafe { has_view(self.0.as_ptr()) != 0 })
             .unwrap_or(false)
     }
-    /// Returns the client for this browser, None if the type is not correct.
+    /// Returns the client for this browser. Use [Client::get_callbacks] to get
+    /// the callbacks it was created with.
     pub fn get_client(&self) -> Option<Client> {
         let get_client = self.0.get_client.unwrap();
         unsafe{ Client::from_ptr(get_client(self.0.as_ptr())) }
@@ -196,6 +216,21 @@ impl BrowserHost {
             })
             .unwrap()
     }
+    /// Set the languages spell checking uses, like `["en-US", "de-DE"]`.
+    /// Spell checking is configured per request context, so this affects all
+    /// browsers sharing this browser's [RequestContext]; give a browser its
+    /// own context to check it in a different language. This function must be
+    /// called on the browser process UI thread.
+    pub fn set_spellcheck_languages(&self, languages: &[&str]) -> Result<(), String> {
+        self.get_request_context().set_spellcheck_languages(languages)
+    }
+    /// Enable or disable spell checking. Like
+    /// [BrowserHost::set_spellcheck_languages], this affects all browsers
+    /// sharing this browser's [RequestContext]. This function must be called
+    /// on the browser process UI thread.
+    pub fn set_spellcheck_enabled(&self, enabled: bool) -> Result<(), String> {
+        self.get_request_context().set_spellcheck_enabled(enabled)
+    }
     /// Get the current zoom level. The default zoom level is 0.0. This function
     /// can only be called on the UI thread.
     pub fn get_zoom_level(&self) -> f64 {
@@ -220,45 +255,55 @@ impl BrowserHost {
     /// default title ("Open" or "Save" depending on the mode). `default_file_path`
     /// is the path with optional directory and/or file name component that will be
     /// initially selected in the dialog. `accept_filters` are used to restrict the
-    /// selectable file types and may any combination of (a) valid lower-cased MIME
-    /// types (e.g. "text/*" or "image/\*"), (b) individual file extensions (e.g.
-    /// ".txt" or ".png"), or (c) combined description and file extension delimited
-    /// using "|" and ";" (e.g. "Image Types|.png;.gif;.jpg").
-    /// `selected_accept_filter` is the 0-based index of the filter that will be
-    /// selected by default. `callback` will be executed after the dialog is
-    /// dismissed or immediately if another dialog is already pending. The dialog
-    /// will be initiated asynchronously on the UI thread.
+    /// selectable file types. `selected_accept_filter` is the 0-based index of
+    /// the filter that will be selected by default. `callback` will be executed
+    /// after the dialog is dismissed or immediately if another dialog is already
+    /// pending. The dialog will be initiated asynchronously on the UI thread.
     ///
     /// On the `callback`, the first parameter is the 0-based index of the value
     /// selected from `accept_filters`. The second parameter will be a single value
     /// or a list of values depending on the dialog mode. If the selection was
     /// cancelled it will be None.
+    ///
+    /// Returns an error without opening the dialog if one of the filters is
+    /// malformed, which CEF would otherwise silently ignore.
     pub fn run_file_dialog(
         &self,
         mode: FileDialogMode,
         title: Option<&str>,
         default_file_path: Option<&str>,
-        accept_filters: &[&str],
-        selected_accept_filter: i32,
+        accept_filters: &[AcceptFilter],
+        selected_accept_filter: usize,
         callback: impl Send + FnOnce(usize, Option<Vec<String>>) + 'static,
-    ) {
+    ) -> Result<(), InvalidAcceptFilter> {
+        for (index, filter) in accept_filters.iter().enumerate() {
+            filter.validate().map_err(|reason| InvalidAcceptFilter {
+                index,
+                filter: filter.clone(),
+                reason,
+            })?;
+        }
         if let Some(run_file_dialog) = self.0.run_file_dialog {
             let title = title.map(CefString::new);
             let default_file_path = default_file_path.map(CefString::new);
+            let filters = accept_filters.iter().map(ToString::to_string).collect::<Vec<_>>();
+            // CEF copies the list.
+            let mut filters = CefStringList::from_iter(filters.iter().map(|filter| &**filter));
             unsafe {
                 run_file_dialog(
                     self.0.as_ptr(),
                     mode.into(),
-                    title.map(|s| s.as_ptr()).unwrap_or_else(null),
-                    default_file_path.map(|s| s.as_ptr()).unwrap_or_else(null),
-                    CefStringList::from_iter(accept_filters.iter().cloned()).into_raw(),
-                    selected_accept_filter,
+                    title.as_ref().map(CefString::as_ptr).unwrap_or_else(null),
+                    default_file_path.as_ref().map(CefString::as_ptr).unwrap_or_else(null),
+                    filters.as_mut_ptr(),
+                    selected_accept_filter as i32,
                     RunFileDialogCallbackWrapper::new(callback)
                         .wrap()
                         .into_raw(),
                 );
             }
         }
+        Ok(())
     }
     /// Download the file at `url` using [DownloadHandler].
     pub fn start_download(&self, url: &str) {
@@ -319,6 +364,9 @@ impl BrowserHost {
     /// `path` when done. For PDF printing to work on Linux you must implement the
     /// [PrintHandler::GetPdfPaperSize] function.
     ///
+    /// `path` must be absolute, and its directory must exist. Otherwise nothing
+    /// is printed and `callback` is told that printing failed.
+    ///
     /// On the callback, the first parameter is the output path. The second parameter
     /// will be true if the printing completed successfully or false otherwise. It
     /// will be called on the browser process UI thread.
@@ -328,15 +376,25 @@ impl BrowserHost {
         settings: &PDFPrintSettings,
         callback: impl Send + FnOnce(&str, bool) + 'static,
     ) {
-        if let Some(print_to_pdf) = self.0.print_to_pdf {
-            unsafe {
-                print_to_pdf(
-                    self.0.as_ptr(),
-                    CefString::new(path).as_ptr(),
-                    &settings.into(),
-                    PDFPrintCallbackWrapper::new(callback).wrap().into_raw(),
-                );
-            }
+        let print_to_pdf = match self.0.print_to_pdf {
+            Some(print_to_pdf) => print_to_pdf,
+            None => return fail_pdf_print(path, callback),
+        };
+        if let Err(e) = check_pdf_output_path(Path::new(path)) {
+            log::error!("Cannot print to {}: {}", path, e);
+            return fail_pdf_print(path, callback);
+        }
+        let settings: cef_pdf_print_settings_t = settings.into();
+        unsafe {
+            print_to_pdf(
+                self.0.as_ptr(),
+                CefString::new(path).as_ptr(),
+                &settings,
+                PDFPrintCallbackWrapper::new(callback).wrap().into_raw(),
+            );
+            // CEF copies the settings.
+            CefString::from_raw(settings.header_footer_title);
+            CefString::from_raw(settings.header_footer_url);
         }
     }
     /// Search for `searchText`. `identifier` must be a unique ID and these IDs
@@ -383,26 +441,31 @@ impl BrowserHost {
     /// `client` and `settings` parameters will be ignored. If `inspect_element_at`
     /// is non-None then the element at the specified (x,y) location will be
     /// inspected. The `window_info` parameter will be ignored if this browser is
-    /// wrapped in a [BrowserView].
+    /// wrapped in a [BrowserView]. If `client` is None, the client returned by
+    /// [BrowserProcessHandlerCallbacks::get_default_client] is used.
+    ///
+    /// [BrowserProcessHandlerCallbacks::get_default_client]: crate::browser_process_handler::BrowserProcessHandlerCallbacks::get_default_client
     pub fn show_dev_tools(
         &self,
         window_info: &WindowInfo,
         client: Option<Client>,
         settings: Option<BrowserSettings>,
-        inspect_element_at: Point,
+        inspect_element_at: Option<Point>,
     ) {
         if let Some(show_dev_tools) = self.0.show_dev_tools {
             let client = client
+                .or_else(crate::browser_process_handler::default_client)
                 .map(Client::into_raw)
                 .unwrap_or_else(null_mut);
             let settings = settings.map(|s| s.into_raw());
+            let inspect_element_at = inspect_element_at.map(cef_point_t::from);
             unsafe {
                 show_dev_tools(
                     self.0.as_ptr(),
                     &window_info.into_raw(),
                     client,
-                    settings.map(|s| &s as *const _).unwrap_or_else(null),
-                    &inspect_element_at.into(),
+                    settings.as_ref().map(|s| s as *const _).unwrap_or_else(null),
+                    inspect_element_at.as_ref().map(|p| p as *const _).unwrap_or_else(null),
                 );
             }
         }
@@ -476,20 +539,27 @@ impl BrowserHost {
     /// return the assigned message ID if called on the UI thread and the message
     /// was successfully submitted for validation, otherwise 0. See the
     /// `send_dev_tools_message` documentation for additional usage information.
+    ///
+    /// Results and events are delivered to the observers added with
+    /// [BrowserHost::add_dev_tools_message_observer]; register one before
+    /// calling this, or use [BrowserHost::call_dev_tools_method] which does so.
     pub fn execute_dev_tools_method(
         &self,
         message_id: i32,
         method: &str,
         params: Option<DictionaryValue>,
-    ) -> bool {
-        unsafe {
-            self.0.execute_dev_tools_method.unwrap()(
-                self.as_ptr(),
-                message_id,
-                CefString::from(method).as_ptr(),
-                params.map(|p| p.into_raw()).unwrap_or_else(null_mut),
-            ) != 0
-        }
+    ) -> i32 {
+        self.0
+            .execute_dev_tools_method
+            .map(|execute_dev_tools_method| unsafe {
+                execute_dev_tools_method(
+                    self.as_ptr(),
+                    message_id,
+                    CefString::from(method).as_ptr(),
+                    params.map(|p| p.into_raw()).unwrap_or_else(null_mut),
+                )
+            })
+            .unwrap_or(0)
     }
     /// Add an observer for DevTools protocol messages (function results and
     /// events). The observer will remain registered until the returned
@@ -508,6 +578,29 @@ impl BrowserHost {
             )
         }
     }
+    /// Returns copies of all navigation entries, oldest first. This function
+    /// can only be called on the browser process UI thread, where
+    /// [BrowserHost::get_navigation_entries] visits the entries before it
+    /// returns.
+    pub fn navigation_history(&self) -> Vec<NavigationHistoryEntry> {
+        let entries = Arc::new(Mutex::new(Vec::new()));
+        let visited = entries.clone();
+        self.get_navigation_entries(
+            NavigationEntryVisitor::new(move |visit: NavigationEntryVisit| {
+                visited.lock().push(NavigationHistoryEntry::new(&visit.entry, visit.current));
+                true
+            }),
+            false,
+        );
+        let entries = std::mem::take(&mut *entries.lock());
+        entries
+    }
+    /// Returns the back/forward list as a [HistoryModel]. Like
+    /// [BrowserHost::navigation_history] this can only be called on the browser
+    /// process UI thread.
+    pub fn history_model(&self) -> HistoryModel {
+        HistoryModel::new(self.navigation_history())
+    }
     /// Retrieve a snapshot of current navigation entries as values sent to the
     /// specified visitor. If `current_only` is true only the current
     /// navigation entry will be sent, otherwise all navigation entries will be
@@ -573,9 +666,16 @@ impl BrowserHost {
             .unwrap_or(false)
     }
     /// Notify the browser that the widget has been resized. The browser will first
-    /// call [RenderHandler::get_view_rect] to get the new size and then call
-    /// [RenderHandler::on_paint] asynchronously with the updated regions. This
-    /// function is only used when window rendering is disabled.
+    /// call [RenderHandlerCallbacks::get_view_rect] to get the new size and then
+    /// call [RenderHandlerCallbacks::on_paint] asynchronously with the updated
+    /// regions. This function is only used when window rendering is disabled.
+    ///
+    /// Update the size returned by `get_view_rect` before calling this. Frames
+    /// of the old size may still be painted until the new size has been laid
+    /// out, so check the `width` and `height` passed to `on_paint`.
+    ///
+    /// [RenderHandlerCallbacks::get_view_rect]: crate::client::render_handler::RenderHandlerCallbacks::get_view_rect
+    /// [RenderHandlerCallbacks::on_paint]: crate::client::render_handler::RenderHandlerCallbacks::on_paint
     pub fn was_resized(&self) {
         if let Some(was_resized) = self.0.was_resized {
             unsafe {
@@ -584,8 +684,11 @@ impl BrowserHost {
         }
     }
     /// Notify the browser that it has been hidden or shown. Layouting and
-    /// [RenderHandler::on_paint] notification will stop when the browser is
-    /// hidden. This function is only used when window rendering is disabled.
+    /// [RenderHandlerCallbacks::on_paint] notification will stop when the
+    /// browser is hidden. This function is only used when window rendering is
+    /// disabled.
+    ///
+    /// [RenderHandlerCallbacks::on_paint]: crate::client::render_handler::RenderHandlerCallbacks::on_paint
     pub fn was_hidden(&self, hidden: bool) {
         if let Some(was_hidden) = self.0.was_hidden {
             unsafe {
@@ -594,11 +697,13 @@ impl BrowserHost {
         }
     }
     /// Send a notification to the browser that the screen info has changed. The
-    /// browser will then call [RenderHandler::get_screen_info] to update the
-    /// screen information with the new values. This simulates moving the webview
-    /// window from one display to another, or changing the properties of the
-    /// current display. This function is only used when window rendering is
-    /// disabled.
+    /// browser will then call [RenderHandlerCallbacks::get_screen_info] to
+    /// update the screen information with the new values. This simulates moving
+    /// the webview window from one display to another, or changing the
+    /// properties of the current display. This function is only used when window
+    /// rendering is disabled.
+    ///
+    /// [RenderHandlerCallbacks::get_screen_info]: crate::client::render_handler::RenderHandlerCallbacks::get_screen_info
     pub fn notify_screen_info_changed(&self) {
         if let Some(notify_screen_info_changed) = self.0.notify_screen_info_changed {
             unsafe {
@@ -606,9 +711,11 @@ impl BrowserHost {
             }
         }
     }
-    /// Invalidate the view. The browser will call [RenderHandler::on_paint]
-    /// asynchronously. This function is only used when window rendering is
-    /// disabled.
+    /// Invalidate the view. The browser will call
+    /// [RenderHandlerCallbacks::on_paint] asynchronously. This function is only
+    /// used when window rendering is disabled.
+    ///
+    /// [RenderHandlerCallbacks::on_paint]: crate::client::render_handler::RenderHandlerCallbacks::on_paint
     pub fn invalidate(&self, element_type: PaintElementType) {
         if let Some(invalidate) = self.0.invalidate {
             unsafe {
@@ -618,6 +725,12 @@ impl BrowserHost {
     }
     /// Issue a BeginFrame request to Chromium.  Only valid when
     /// [WindowInfo::external_begin_frame_enabled] is set to true.
+    ///
+    /// Each call lets Chromium produce at most one frame, which is passed to
+    /// the render handler if anything changed, so call this once per frame of
+    /// the application's render loop. The rate of these calls replaces the
+    /// windowless frame rate; paints still arrive on the UI thread. This
+    /// function can be called on any thread.
     pub fn send_external_begin_frame(&self) {
         if let Some(send_external_begin_frame) = self.0.send_external_begin_frame {
             unsafe {
@@ -628,8 +741,10 @@ impl BrowserHost {
     /// Send a key event to the browser.
     pub fn send_key_event(&self, event: KeyEvent) {
         if let Some(send_key_event) = self.0.send_key_event {
-            unsafe {
-                send_key_event(self.0.as_ptr(), &event.as_cef());
+            for event in event.to_cef_events() {
+                unsafe {
+                    send_key_event(self.0.as_ptr(), &event);
+                }
             }
         }
     }
@@ -667,7 +782,9 @@ impl BrowserHost {
     /// relative to the upper-left corner of the view. The `deltaX` and `deltaY`
     /// values represent the movement delta in the X and Y directions respectively.
     /// In order to scroll inside select popups with window rendering disabled
-    /// [RenderHandler::get_screen_point] should be implemented properly.
+    /// [RenderHandlerCallbacks::get_screen_point] should be implemented properly.
+    ///
+    /// [RenderHandlerCallbacks::get_screen_point]: crate::client::render_handler::RenderHandlerCallbacks::get_screen_point
     pub fn send_mouse_wheel_event(&self, event: &MouseEvent, delta_x: i32, delta_y: i32) {
         if let Some(send_mouse_wheel_event) = self.0.send_mouse_wheel_event {
             unsafe {
@@ -675,6 +792,28 @@ impl BrowserHost {
             }
         }
     }
+    /// Scroll the main frame's document to `x` and `y`, in CSS pixels. The new
+    /// offset is reported to [RenderHandlerCallbacks::on_scroll_offset_changed]
+    /// in windowless mode, like scrolling by the user. Unlike wheel events
+    /// this doesn't depend on the position of the mouse, so the page scrolls
+    /// even if a nested scrollable element is under the pointer.
+    ///
+    /// [RenderHandlerCallbacks::on_scroll_offset_changed]: crate::client::render_handler::RenderHandlerCallbacks::on_scroll_offset_changed
+    pub fn scroll_to(&self, x: f64, y: f64) {
+        self.scroll("scrollTo", x, y);
+    }
+    /// Scroll the main frame's document by `delta_x` and `delta_y`, in CSS
+    /// pixels. See [BrowserHost::scroll_to].
+    pub fn scroll_by(&self, delta_x: f64, delta_y: f64) {
+        self.scroll("scrollBy", delta_x, delta_y);
+    }
+    fn scroll(&self, function: &str, x: f64, y: f64) {
+        if !x.is_finite() || !y.is_finite() {
+            return;
+        }
+        let code = format!("window.{}({}, {});", function, x, y);
+        self.get_browser().get_main_frame().execute_java_script(&code, "", 0);
+    }
     /// Send a touch event to the browser for a windowless browser.
     pub fn send_touch_event(&self, event: &TouchEvent) {
         if let Some(send_touch_event) = self.0.send_touch_event {
@@ -709,25 +848,36 @@ impl BrowserHost {
         }
     }
     /// Returns the maximum rate in frames per second (fps) that
-    /// [RenderHandler::on_paint] will be called for a windowless browser. The
-    /// actual fps may be lower if the browser cannot generate frames at the
-    /// requested rate. The minimum value is 1 and the maximum value is 60 (default
-    /// 30). This function can only be called on the UI thread.
+    /// [RenderHandlerCallbacks::on_paint] will be called for a windowless
+    /// browser. The actual fps may be lower if the browser cannot generate
+    /// frames at the requested rate. The minimum value is 1 and the maximum
+    /// value is 60 (default 30). This function can only be called on the UI
+    /// thread, elsewhere CEF returns 0.
+    ///
+    /// [RenderHandlerCallbacks::on_paint]: crate::client::render_handler::RenderHandlerCallbacks::on_paint
     pub fn get_windowless_frame_rate(&self) -> i32 {
         self.0
             .get_windowless_frame_rate
             .map(|get_windowless_frame_rate| unsafe { get_windowless_frame_rate(self.0.as_ptr()) })
             .unwrap_or(30)
     }
-    /// Set the maximum rate in frames per second (fps) that [RenderHandler::on_paint]
-    /// will be called for a windowless browser. The actual fps may be
+    /// Set the maximum rate in frames per second (fps) that
+    /// [RenderHandlerCallbacks::on_paint] will be called for a windowless
+    /// browser. The actual fps may be
     /// lower if the browser cannot generate frames at the requested rate. The
-    /// minimum value is 1 and the maximum value is 60 (default 30). Can also be
-    /// set at browser creation via [BrowserSettings::windowless_frame_rate].
+    /// minimum value is 1 and the maximum value is 60 (default 30), values
+    /// outside that range are clamped. Can also be set at browser creation via
+    /// [BrowserSettings::windowless_frame_rate].
+    ///
+    /// With [WindowInfo::external_begin_frame_enabled] frames are produced by
+    /// [BrowserHost::send_external_begin_frame] instead, and this rate has no
+    /// effect on how often they are painted.
+    ///
+    /// [RenderHandlerCallbacks::on_paint]: crate::client::render_handler::RenderHandlerCallbacks::on_paint
     pub fn set_windowless_frame_rate(&self, frame_rate: i32) {
         if let Some(set_windowless_frame_rate) = self.0.set_windowless_frame_rate {
             unsafe {
-                set_windowless_frame_rate(self.0.as_ptr(), frame_rate);
+                set_windowless_frame_rate(self.0.as_ptr(), frame_rate.max(1).min(60));
             }
         }
     }
@@ -755,18 +905,21 @@ impl BrowserHost {
     pub fn ime_set_composition(
         &self,
         text: &str,
-        underlines_count: usize,
-        underlines: &CompositionUnderline,
-        replacement_range: &Range,
-        selection_range: &Range,
+        underlines: &[CompositionUnderline],
+        replacement_range: Option<Range>,
+        selection_range: Option<Range>,
     ) {
         if let Some(ime_set_composition) = self.0.ime_set_composition {
+            let underlines = underlines.iter().map(cef_composition_underline_t::from).collect::<Vec<_>>();
+            // CEF ignores the call if a range is null, an invalid range means none.
+            let replacement_range = replacement_range.unwrap_or(Range::INVALID);
+            let selection_range = selection_range.unwrap_or(Range::INVALID);
             unsafe {
                 ime_set_composition(
                     self.0.as_ptr(),
                     CefString::new(text).as_ptr(),
-                    underlines_count,
-                    &underlines.into(),
+                    underlines.len(),
+                    underlines.as_ptr(),
                     replacement_range.as_ptr(),
                     selection_range.as_ptr(),
                 );
@@ -784,16 +937,17 @@ impl BrowserHost {
     pub fn ime_commit_text(
         &self,
         text: Option<&str>,
-        replacement_range: Option<&Range>,
+        replacement_range: Option<Range>,
         relative_cursor_pos: i32,
     ) {
         if let Some(ime_commit_text) = self.0.ime_commit_text {
-            let text = text.map(|text| CefString::new(text));
+            let text = text.map(CefString::new);
+            let replacement_range = replacement_range.unwrap_or(Range::INVALID);
             unsafe {
                 ime_commit_text(
                     self.0.as_ptr(),
-                    text.map(|s| s.as_ptr()).unwrap_or_else(null),
-                    replacement_range.map(Range::as_ptr).unwrap_or_else(null),
+                    text.as_ref().map(CefString::as_ptr).unwrap_or_else(null),
+                    replacement_range.as_ptr(),
                     relative_cursor_pos,
                 );
             }
@@ -811,7 +965,7 @@ impl BrowserHost {
         }
     }
     /// Cancels the existing composition and discards the composition node contents
-    /// without applying them. See comments on ImeSetComposition for usage. This
+    /// without applying them. See comments on [BrowserHost::ime_set_composition] for usage. This
     /// function is only used when window rendering is disabled.
     pub fn ime_cancel_composition(&self) {
         if let Some(ime_cancel_composition) = self.0.ime_cancel_composition {
@@ -821,12 +975,15 @@ impl BrowserHost {
         }
     }
     /// Call this function when the user drags the mouse into the web view (before
-    /// calling [BrowserHost::drag_target_drag_over]/[BrowserHost::drag_target_leave]/[BrowserHost::drag_target_drop]). `drag_data`
-    /// should not contain file contents as this type of data is not allowed to be
-    /// dragged into the web view. File contents can be removed using
-    /// [DragData::reset_file_contents] (for example, if `drag_data` comes from
-    /// [RenderHandler::start_dragging]). This function is only used when
-    /// window rendering is disabled.
+    /// calling [BrowserHost::drag_target_drag_over]/
+    /// [BrowserHost::drag_target_drag_leave]/[BrowserHost::drag_target_drop]).
+    /// `drag_data` should not contain file contents as this type of data is not
+    /// allowed to be dragged into the web view. File contents can be removed
+    /// using [DragData::reset_file_contents] (for example, if `drag_data` comes
+    /// from [RenderHandlerCallbacks::start_dragging]). This function is only
+    /// used when window rendering is disabled.
+    ///
+    /// [RenderHandlerCallbacks::start_dragging]: crate::client::render_handler::RenderHandlerCallbacks::start_dragging
     pub fn drag_target_drag_enter(
         &self,
         drag_data: DragData,
@@ -882,12 +1039,14 @@ impl BrowserHost {
         }
     }
     /// Call this function when the drag operation started by a
-    /// [RenderHandler::start_dragging] call has ended either in a drop or by
-    /// being cancelled. `x` and `y` are mouse coordinates relative to the upper-
-    /// left corner of the view. If the web view is both the drag source and the
-    /// drag target then all drag_target_* functions should be called before
-    /// drag_source_* methods. This function is only used when window rendering is
-    /// disabled.
+    /// [RenderHandlerCallbacks::start_dragging] call has ended either in a drop
+    /// or by being cancelled. `x` and `y` are mouse coordinates relative to the
+    /// upper-left corner of the view. If the web view is both the drag source
+    /// and the drag target then all drag_target_* functions should be called
+    /// before drag_source_* methods. This function is only used when window
+    /// rendering is disabled.
+    ///
+    /// [RenderHandlerCallbacks::start_dragging]: crate::client::render_handler::RenderHandlerCallbacks::start_dragging
     pub fn drag_source_ended_at(&self, x: i32, y: i32, op: DragOperation) {
         if let Some(drag_source_ended_at) = self.0.drag_source_ended_at {
             unsafe {
@@ -896,11 +1055,14 @@ impl BrowserHost {
         }
     }
     /// Call this function when the drag operation started by a
-    /// [RenderHandler::start_dragging] call has completed. This function may
-    /// be called immediately without first calling [BrowserHost::drag_source_ended_at] to cancel a
-    /// drag operation. If the web view is both the drag source and the drag target
-    /// then all drag_target_* functions should be called before drag_source_* methods.
+    /// [RenderHandlerCallbacks::start_dragging] call has completed. This
+    /// function may be called immediately without first calling
+    /// [BrowserHost::drag_source_ended_at] to cancel a drag operation. If the
+    /// web view is both the drag source and the drag target then all
+    /// drag_target_* functions should be called before drag_source_* methods.
     /// This function is only used when window rendering is disabled.
+    ///
+    /// [RenderHandlerCallbacks::start_dragging]: crate::client::render_handler::RenderHandlerCallbacks::start_dragging
     pub fn drag_source_system_drag_ended(&self) {
         if let Some(drag_source_system_drag_ended) = self.0.drag_source_system_drag_ended {
             unsafe {
@@ -908,12 +1070,13 @@ impl BrowserHost {
             }
         }
     }
-    /// Returns the current visible navigation entry for this browser. This
-    /// function can only be called on the UI thread.
-    pub fn get_visible_navigation_entry(&self) -> NavigationEntry {
-        let get_visible_navigation_entry = self.0.get_visible_navigation_entry.unwrap();
+    /// Returns the current visible navigation entry for this browser, or `None`
+    /// if nothing was loaded yet. This function can only be called on the UI
+    /// thread.
+    pub fn get_visible_navigation_entry(&self) -> Option<NavigationEntry> {
+        let get_visible_navigation_entry = self.0.get_visible_navigation_entry?;
         unsafe {
-            NavigationEntry::from_ptr_unchecked(get_visible_navigation_entry(self.0.as_ptr()))
+            NavigationEntry::from_ptr(get_visible_navigation_entry(self.0.as_ptr()))
         }
     }
     /// Set accessibility state for all frames. If `accessibility_state` is [State::Default]
@@ -935,9 +1098,11 @@ impl BrowserHost {
     /// For windowless browsers accessibility will be enabled in TreeOnly mode
     /// (which corresponds to `kAccessibilityModeWebContentsOnly` in Chromium). In
     /// this mode renderer accessibility is enabled, the full tree is computed, and
-    /// events are passed to [AccessibiltyHandler], but platform accessibility
-    /// objects are not created. The client may implement platform accessibility
-    /// objects using [AccessibiltyHandler] callbacks if desired.
+    /// events are passed to [AccessibilityHandler](crate::accessibility_handler::AccessibilityHandler),
+    /// but platform accessibility objects are not created. The client may
+    /// implement platform accessibility objects using
+    /// [AccessibilityHandlerCallbacks](crate::accessibility_handler::AccessibilityHandlerCallbacks)
+    /// if desired.
     pub fn set_accessibility_state(&self, accessibility_state: State) {
         if let Some(set_accessibility_state) = self.0.set_accessibility_state {
             unsafe {
@@ -946,9 +1111,17 @@ impl BrowserHost {
         }
     }
     /// Enable notifications of auto resize via
-    /// [DisplayHandler::on_auto_resize]. Notifications are disabled by default.
-    /// `min_size` and `max_size` define the range of allowed sizes.
+    /// [DisplayHandlerCallbacks::on_auto_resize](crate::client::display_handler::DisplayHandlerCallbacks::on_auto_resize).
+    /// Notifications are disabled by default. `min_size` and `max_size` define
+    /// the range of allowed sizes in view coordinates; a `max_size` smaller
+    /// than `min_size` is raised to it.
+    ///
+    /// While enabled, the page is laid out at its preferred size within that
+    /// range, so an embedded widget can size itself to the content. Unless
+    /// `on_auto_resize` returns true, windowed browsers resize their window
+    /// themselves.
     pub fn set_auto_resize_enabled(&self, enabled: bool, min_size: &Size, max_size: &Size) {
+        let max_size = auto_resize_max_size(min_size, max_size);
         if let Some(set_auto_resize_enabled) = self.0.set_auto_resize_enabled {
             unsafe {
                 set_auto_resize_enabled(
@@ -960,8 +1133,13 @@ impl BrowserHost {
             }
         }
     }
+    /// Stop sizing the page to its content, see
+    /// [BrowserHost::set_auto_resize_enabled].
+    pub fn disable_auto_resize(&self) {
+        self.set_auto_resize_enabled(false, &Size::new(), &Size::new());
+    }
     /// Returns the extension hosted in this browser or None if no extension is
-    /// hosted. See [RequestContest::load_extension] for details.
+    /// hosted. See [RequestContext::load_extension] for details.
     pub fn get_extension(&self) -> Option<Extension> {
         self.0.get_extension.and_then(|get_extension| unsafe {
             Extension::from_ptr(get_extension(self.0.as_ptr()))
@@ -976,7 +1154,7 @@ impl BrowserHost {
             .map(|is_background_host| unsafe { is_background_host(self.0.as_ptr()) != 0 })
             .unwrap_or(false)
     }
-    ///  Set whether the browser's audio is muted.
+    /// Set whether the browser's audio is muted.
     pub fn set_audio_muted(&self, mute: bool) {
         if let Some(set_audio_muted) = self.0.set_audio_muted {
             unsafe {
@@ -994,6 +1172,12 @@ impl BrowserHost {
     }
 }
 
+impl IsValid for BrowserHost {
+    fn is_valid(&self) -> bool {
+        BrowserHost::is_valid(self)
+    }
+}
+
 pub(crate) struct DownloadImageCallbackWrapper {
     callback: Mutex<Option<Box<dyn Send + FnOnce(&str, u16, Option<Image>)>>>,
 }
@@ -1065,6 +1249,16 @@ impl PDFPrintCallbackWrapper {
     }
 }
 
+/// Report a PDF print failure on the UI thread, like CEF reports the result.
+fn fail_pdf_print(path: &str, callback: impl Send + FnOnce(&str, bool) + 'static) {
+    let path = path.to_owned();
+    if TaskRunner::currently_on(ThreadId::UI) {
+        callback(&path, false);
+    } else {
+        TaskRunner::post_task_on(ThreadId::UI, move || callback(&path, false));
+    }
+}
+
 cef_callback_impl! {
     impl for PDFPrintCallbackWrapper: cef_pdf_print_callback_t {
         fn pdf_print_finished(
@@ -1151,3 +1345,50 @@ cef_callback_impl! {
         }
     }
 }
+
+/// Returns `max_size` raised to `min_size` where it's smaller, so CEF never
+/// gets an inverted auto-resize range.
+fn auto_resize_max_size(min_size: &Size, max_size: &Size) -> Size {
+    Size {
+        width: max_size.width.max(min_size.width),
+        height: max_size.height.max(min_size.height),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cef_sys::cef_size_t;
+
+    fn size(width: i32, height: i32) -> Size {
+        Size { width, height }
+    }
+
+    #[test]
+    fn auto_resize_max_size_is_raised_to_min_size() {
+        assert_eq!(auto_resize_max_size(&size(200, 100), &size(100, 50)), size(200, 100));
+        assert_eq!(auto_resize_max_size(&size(200, 100), &size(300, 50)), size(300, 100));
+        assert_eq!(auto_resize_max_size(&size(200, 100), &size(100, 150)), size(200, 150));
+    }
+
+    #[test]
+    fn auto_resize_max_size_keeps_valid_ranges() {
+        assert_eq!(auto_resize_max_size(&size(200, 100), &size(800, 600)), size(800, 600));
+        assert_eq!(auto_resize_max_size(&size(200, 100), &size(200, 100)), size(200, 100));
+    }
+
+    #[test]
+    fn disabling_auto_resize_passes_an_empty_range() {
+        // disable_auto_resize passes empty sizes, which must stay empty.
+        assert_eq!(auto_resize_max_size(&Size::new(), &Size::new()), Size::new());
+    }
+
+    #[test]
+    fn size_round_trips_through_cef() {
+        let max_size = auto_resize_max_size(&size(200, 100), &size(1920, 1080));
+        let cef_size = unsafe { &*max_size.as_ptr() };
+        assert_eq!((cef_size.width, cef_size.height), (1920, 1080));
+        assert_eq!(Size::from(cef_size), max_size);
+        assert_eq!(Size::from(cef_size_t { width: 1920, height: 1080 }), max_size);
+    }
+}
@@ -1,11 +1,15 @@
 use cef_sys::{cef_browser_process_handler_t, cef_command_line_t, cef_list_value_t};
 
 use crate::{
+    client::Client,
     command_line::CommandLine,
     refcounted::{RefCountedPtr, Wrapper},
     values::ListValue,
     // print_handler::PrintHandler,
 };
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::sync::Arc;
 
 ref_counted_ptr!{
     pub struct BrowserProcessHandler(*mut cef_browser_process_handler_t);
@@ -51,10 +55,34 @@ pub trait BrowserProcessHandlerCallbacks: 'static + Sync + Send {
     /// specified delay and any currently pending scheduled call should be
     /// cancelled.
     fn on_schedule_message_pump_work(&self, delay_ms: i64) {}
+    /// Return the client for browsers that are created without one, e.g. a
+    /// DevTools browser opened with [BrowserHost::show_dev_tools] and no
+    /// client. Without a default client such browsers get no handlers at all.
+    ///
+    /// This version of CEF doesn't ask for a default client itself, so this is
+    /// called by the crate for the browsers it creates, on the browser process
+    /// UI thread, once the context has been initialized.
+    ///
+    /// [BrowserHost::show_dev_tools]: crate::browser_host::BrowserHost::show_dev_tools
+    fn get_default_client(&self) -> Option<Client> {
+        None
+    }
+}
+
+lazy_static! {
+    /// The handler of the browser process, registered once the context is
+    /// initialized.
+    static ref BROWSER_PROCESS_HANDLER: Mutex<Option<Arc<dyn BrowserProcessHandlerCallbacks>>> = Mutex::new(None);
+}
+
+/// Returns the client from [BrowserProcessHandlerCallbacks::get_default_client].
+pub(crate) fn default_client() -> Option<Client> {
+    let handler = BROWSER_PROCESS_HANDLER.lock().clone();
+    handler.and_then(|handler| handler.get_default_client())
 }
 
 pub(crate) struct BrowserProcessHandlerWrapper {
-    delegate: Box<dyn BrowserProcessHandlerCallbacks>,
+    delegate: Arc<dyn BrowserProcessHandlerCallbacks>,
 }
 
 impl Wrapper for BrowserProcessHandlerWrapper {
@@ -80,7 +108,7 @@ impl Wrapper for BrowserProcessHandlerWrapper {
 impl BrowserProcessHandlerWrapper {
     pub(crate) fn new(delegate: Box<dyn BrowserProcessHandlerCallbacks>) -> BrowserProcessHandlerWrapper {
         Self {
-            delegate,
+            delegate: delegate.into(),
         }
     }
 }
@@ -88,6 +116,7 @@ impl BrowserProcessHandlerWrapper {
 cef_callback_impl! {
     impl for BrowserProcessHandlerWrapper: cef_browser_process_handler_t {
         fn context_initialized(&self) {
+            *BROWSER_PROCESS_HANDLER.lock() = Some(self.delegate.clone());
             self.delegate.on_context_initialized();
         }
         fn before_child_process_launch(
@@ -0,0 +1,143 @@
+//! Reusable configurations for creating similar browsers.
+//!
+//! Applications that open many browsers of a few kinds, like tabs and tool
+//! windows, describe each kind once as a [BrowserTemplate] and create browsers
+//! with [BrowserTemplate::spawn]. Templates can be derived from each other, so
+//! a tool window template can start from the tab template and only change the
+//! window size:
+//!
+//! ```rust,ignore
+//! let tab = BrowserTemplate::new(|| Client::new(TabClient::default()))
+//!     .with_settings(settings)
+//!     .with_extra_info("kind", StoredValue::String("tab".into()));
+//! let tool_window = tab
+//!     .derive()
+//!     .configure_window(|info| { info.width = 400; info.height = 300; })
+//!     .with_extra_info("kind", StoredValue::String("tool".into()));
+//! let browser = tool_window.spawn("https://example.com");
+//! ```
+
+use crate::{
+    browser::{Browser, BrowserSettings},
+    browser_host::BrowserHost,
+    client::Client,
+    renderer_bootstrap::RendererBootstrap,
+    request_context::RequestContext,
+    values::StoredValue,
+    window::WindowInfo,
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// Creates the [Client] of each browser spawned from a template. Return clones
+/// of the same client to share it between browsers.
+pub trait ClientFactory = 'static + Send + Sync + Fn() -> Client;
+
+/// Everything needed to create a browser except its URL.
+#[derive(Clone)]
+pub struct BrowserTemplate {
+    window_info: WindowInfo,
+    settings: BrowserSettings,
+    client: Arc<dyn ClientFactory>,
+    request_context: Option<RequestContext>,
+    extra_info: HashMap<String, StoredValue>,
+}
+
+impl BrowserTemplate {
+    /// Create a template with default window info and settings, whose
+    /// browsers get their client from `client`.
+    pub fn new(client: impl ClientFactory) -> BrowserTemplate {
+        BrowserTemplate {
+            window_info: WindowInfo::new(),
+            settings: BrowserSettings::new(),
+            client: Arc::new(client),
+            request_context: None,
+            extra_info: HashMap::new(),
+        }
+    }
+    /// Returns a copy of this template to be changed independently of it.
+    pub fn derive(&self) -> BrowserTemplate {
+        self.clone()
+    }
+    pub fn with_window_info(mut self, window_info: WindowInfo) -> Self {
+        self.window_info = window_info;
+        self
+    }
+    /// Change some fields of the window info, keeping the others.
+    pub fn configure_window(mut self, configure: impl FnOnce(&mut WindowInfo)) -> Self {
+        configure(&mut self.window_info);
+        self
+    }
+    pub fn with_settings(mut self, settings: BrowserSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+    /// Change some of the browser settings, keeping the others.
+    pub fn configure_settings(mut self, configure: impl FnOnce(&mut BrowserSettings)) -> Self {
+        configure(&mut self.settings);
+        self
+    }
+    pub fn with_client(mut self, client: impl ClientFactory) -> Self {
+        self.client = Arc::new(client);
+        self
+    }
+    /// Use `request_context` instead of the global request context.
+    pub fn with_request_context(mut self, request_context: RequestContext) -> Self {
+        self.request_context = Some(request_context);
+        self
+    }
+    /// Use the global request context, e.g. in a template derived from one
+    /// with its own request context.
+    pub fn with_global_request_context(mut self) -> Self {
+        self.request_context = None;
+        self
+    }
+    /// Add an entry to the extra info passed to
+    /// [RenderProcessHandlerCallbacks::on_browser_created](crate::render_process_handler::RenderProcessHandlerCallbacks::on_browser_created),
+    /// replacing an inherited entry with the same key.
+    pub fn with_extra_info(mut self, key: &str, value: StoredValue) -> Self {
+        self.extra_info.insert(key.to_owned(), value);
+        self
+    }
+    /// Pass `bootstrap` to the render process of each browser, replacing
+    /// inherited data of the same type.
+    pub fn with_bootstrap<B: RendererBootstrap>(mut self, bootstrap: &B) -> Self {
+        bootstrap.write_extra_info(&mut self.extra_info);
+        self
+    }
+    pub fn window_info(&self) -> &WindowInfo {
+        &self.window_info
+    }
+    pub fn settings(&self) -> &BrowserSettings {
+        &self.settings
+    }
+    pub fn request_context(&self) -> Option<&RequestContext> {
+        self.request_context.as_ref()
+    }
+    pub fn extra_info(&self) -> &HashMap<String, StoredValue> {
+        &self.extra_info
+    }
+    /// Create a browser loading `url` with [BrowserHost::create_browser_sync].
+    /// This function can only be called on the browser process UI thread.
+    pub fn spawn(&self, url: &str) -> Browser {
+        self.spawn_with(url, |_| ())
+    }
+    /// Like [BrowserTemplate::spawn], but lets `configure` change the window
+    /// info of this browser only, e.g. to set its parent window.
+    pub fn spawn_with(&self, url: &str, configure: impl FnOnce(&mut WindowInfo)) -> Browser {
+        let mut window_info = self.window_info.clone();
+        configure(&mut window_info);
+        let extra_info = if self.extra_info.is_empty() {
+            None
+        } else {
+            Some(&self.extra_info)
+        };
+        BrowserHost::create_browser_sync(
+            &window_info,
+            (self.client)(),
+            url,
+            &self.settings,
+            extra_info,
+            self.request_context.clone(),
+        )
+    }
+}
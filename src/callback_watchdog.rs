@@ -0,0 +1,111 @@
+//! Warnings about handler callbacks that block CEF's threads.
+//!
+//! CEF calls handlers on its own threads and waits for them to return. A
+//! callback that takes a few hundred milliseconds on the UI thread stops
+//! painting and input for that long. Once enabled, every callback that takes
+//! longer than its budget is logged, or passed to a hook set with
+//! [set_hook]. Callbacks are measured when they return, so a callback that
+//! never returns isn't reported.
+
+use crate::task::{TaskRunner, ThreadId};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref BUDGETS: Mutex<Budgets> = Mutex::new(Budgets {
+        ui: Duration::from_millis(50),
+        other: Duration::from_millis(200),
+    });
+    /// The hook is called without holding this lock, so it can replace
+    /// itself.
+    static ref HOOK: Mutex<Option<Arc<Mutex<dyn SlowCallbackHook>>>> = Mutex::new(None);
+}
+
+struct Budgets {
+    ui: Duration,
+    other: Duration,
+}
+
+/// A callback that exceeded its budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowCallback {
+    /// Name of the callback, like `cef_load_handler_t::on_load_end`.
+    pub name: &'static str,
+    /// Identifier of the browser passed to the callback, if any.
+    pub browser_id: Option<i32>,
+    pub duration: Duration,
+    pub on_ui_thread: bool,
+}
+
+/// Called with every callback that exceeded its budget, on the thread the
+/// callback ran on. It may call [set_hook] or [clear_hook]; the change applies
+/// to the next slow callback.
+pub trait SlowCallbackHook = 'static + Send + FnMut(&SlowCallback);
+
+/// Start reporting callbacks that take longer than `ui_budget` on the browser
+/// process UI thread or longer than `other_budget` on other threads.
+pub fn enable(ui_budget: Duration, other_budget: Duration) {
+    *BUDGETS.lock() = Budgets {
+        ui: ui_budget,
+        other: other_budget,
+    };
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Pass slow callbacks to `hook` instead of logging a warning.
+pub fn set_hook(hook: impl SlowCallbackHook) {
+    *HOOK.lock() = Some(Arc::new(Mutex::new(hook)));
+}
+
+/// Log slow callbacks again.
+pub fn clear_hook() {
+    *HOOK.lock() = None;
+}
+
+pub(crate) fn check(name: &'static str, browser_id: Option<i32>, duration: Duration) {
+    if !is_enabled() {
+        return;
+    }
+    let on_ui_thread = TaskRunner::currently_on(ThreadId::UI);
+    let budget = {
+        let budgets = BUDGETS.lock();
+        if on_ui_thread { budgets.ui } else { budgets.other }
+    };
+    if duration <= budget {
+        return;
+    }
+    let slow = SlowCallback {
+        name,
+        browser_id,
+        duration,
+        on_ui_thread,
+    };
+    let hook = HOOK.lock().clone();
+    match hook {
+        Some(hook) => (&mut *hook.lock())(&slow),
+        None => log::warn!(
+            "{} took {} ms on {} (budget {} ms)",
+            slow.name,
+            slow.duration.as_millis(),
+            if on_ui_thread { "the UI thread" } else { "a background thread" },
+            budget.as_millis(),
+        ),
+    }
+}
@@ -0,0 +1,85 @@
+//! Chrome's built-in browser commands.
+//!
+//! CEF builds with the Chrome runtime can run Chrome's own implementation of
+//! commands like zooming or printing by their `IDC_*` id. This version of CEF
+//! has no Chrome runtime, so [BrowserHost::execute_chrome_command] implements
+//! the commands listed in this module on top of the regular browser API, with
+//! the same behavior as in Chrome where possible. Zooming, for example, steps
+//! through Chrome's preset zoom factors instead of changing the zoom level by
+//! a fixed amount.
+
+use crate::{
+    browser_host::BrowserHost,
+    client::request_handler::WindowOpenDisposition,
+};
+
+pub const IDC_BACK: i32 = 33000;
+pub const IDC_FORWARD: i32 = 33001;
+pub const IDC_RELOAD: i32 = 33002;
+pub const IDC_STOP: i32 = 33006;
+pub const IDC_RELOAD_BYPASSING_CACHE: i32 = 33007;
+pub const IDC_VIEW_SOURCE: i32 = 35002;
+pub const IDC_PRINT: i32 = 35003;
+pub const IDC_CUT: i32 = 36000;
+pub const IDC_COPY: i32 = 36001;
+pub const IDC_PASTE: i32 = 36003;
+pub const IDC_ZOOM_PLUS: i32 = 38001;
+pub const IDC_ZOOM_NORMAL: i32 = 38002;
+pub const IDC_ZOOM_MINUS: i32 = 38003;
+
+const SUPPORTED_COMMANDS: &[i32] = &[
+    IDC_BACK,
+    IDC_FORWARD,
+    IDC_RELOAD,
+    IDC_STOP,
+    IDC_RELOAD_BYPASSING_CACHE,
+    IDC_VIEW_SOURCE,
+    IDC_PRINT,
+    IDC_CUT,
+    IDC_COPY,
+    IDC_PASTE,
+    IDC_ZOOM_PLUS,
+    IDC_ZOOM_NORMAL,
+    IDC_ZOOM_MINUS,
+];
+
+impl BrowserHost {
+    /// Returns true if `command_id` is one of the commands supported by
+    /// [BrowserHost::execute_chrome_command].
+    pub fn can_execute_chrome_command(&self, command_id: i32) -> bool {
+        SUPPORTED_COMMANDS.contains(&command_id)
+    }
+    /// Execute the Chrome command with the given id, e.g. [IDC_ZOOM_PLUS]. Only
+    /// [WindowOpenDisposition::CurrentTab] and [WindowOpenDisposition::Unknown]
+    /// are supported as `disposition`. Returns false if the command or
+    /// disposition isn't supported.
+    ///
+    /// Commands can be executed from any thread. Zoom commands depend on the
+    /// current zoom level, so they are executed asynchronously on the browser
+    /// process UI thread unless called there.
+    pub fn execute_chrome_command(&self, command_id: i32, disposition: WindowOpenDisposition) -> bool {
+        match disposition {
+            WindowOpenDisposition::CurrentTab | WindowOpenDisposition::Unknown => (),
+            _ => return false,
+        }
+        let browser = self.get_browser();
+        let focused_frame = || browser.get_focused_frame().unwrap_or_else(|| browser.get_main_frame());
+        match command_id {
+            IDC_BACK => browser.go_back(),
+            IDC_FORWARD => browser.go_forward(),
+            IDC_RELOAD => browser.reload(false),
+            IDC_RELOAD_BYPASSING_CACHE => browser.reload(true),
+            IDC_STOP => browser.stop_load(),
+            IDC_VIEW_SOURCE => browser.view_source(),
+            IDC_PRINT => self.print(),
+            IDC_CUT => focused_frame().cut(),
+            IDC_COPY => focused_frame().copy(),
+            IDC_PASTE => focused_frame().paste(),
+            IDC_ZOOM_NORMAL => self.reset_zoom(),
+            IDC_ZOOM_PLUS => self.zoom_in(),
+            IDC_ZOOM_MINUS => self.zoom_out(),
+            _ => return false,
+        }
+        true
+    }
+}
@@ -49,7 +49,7 @@ use crate::{
     frame::Frame,
     load_handler::LoadHandler,
     process::{ProcessId, ProcessMessage},
-    refcounted::{RefCountedPtr, Wrapper},
+    refcounted::{RefCounted, RefCountedPtr, Wrapper},
 };
 
 ref_counted_ptr!{
@@ -60,6 +60,16 @@ impl Client {
     pub fn new<C: ClientCallbacks>(callbacks: C) -> Client {
         unsafe{ Client::from_ptr_unchecked(ClientWrapper::new(Box::new(callbacks)).wrap().into_raw()) }
     }
+    /// Returns the callbacks this client was created with, if they are of
+    /// type `C`. Returns `None` for clients that weren't created with
+    /// [Client::new]. Use this to get back to the application's own client state
+    /// from a browser, e.g. with
+    /// `browser.get_host().get_client()?.get_callbacks::<MyClient>()`.
+    pub fn get_callbacks<C: ClientCallbacks>(&self) -> Option<&C> {
+        // Clients created by `Client::new` wrap a `ClientWrapper`, but a
+        // client passed in from C code may not.
+        unsafe { RefCounted::<ClientWrapper>::try_wrapper(self.as_ptr()) }?.get_client()
+    }
 }
 
 /// Implement this trait to provide handler implementations.
@@ -209,3 +219,38 @@ cef_callback_impl! {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cef_sys::cef_base_ref_counted_t;
+    use std::os::raw::c_int;
+
+    struct First;
+    impl ClientCallbacks for First {}
+
+    struct Second;
+    impl ClientCallbacks for Second {}
+
+    extern "C" fn add_ref(_: *mut cef_base_ref_counted_t) {}
+    extern "C" fn release(_: *mut cef_base_ref_counted_t) -> c_int {
+        0
+    }
+
+    #[test]
+    fn callbacks_are_returned_if_the_type_matches() {
+        let client = Client::new(First);
+        assert!(client.get_callbacks::<First>().is_some());
+        assert!(client.get_callbacks::<Second>().is_none());
+    }
+
+    #[test]
+    fn foreign_clients_have_no_callbacks() {
+        let mut cef: Box<cef_client_t> = Box::new(unsafe { std::mem::zeroed() });
+        cef.base.size = std::mem::size_of::<cef_client_t>();
+        cef.base.add_ref = Some(add_ref);
+        cef.base.release = Some(release);
+        let client = unsafe { Client::from_ptr_unchecked(&mut *cef) };
+        assert!(client.get_callbacks::<First>().is_none());
+    }
+}
@@ -158,7 +158,7 @@ impl AudioParameters {
 }
 
 ref_counted_ptr!{
-    /// Instantiate this structure to handle events related to browser display state.
+    /// Instantiate this structure to capture the audio of a browser.
     pub struct AudioHandler(*mut cef_audio_handler_t);
 }
 
@@ -166,12 +166,13 @@ impl AudioHandler {
     pub fn new<C: AudioHandlerCallbacks>(callbacks: C) -> AudioHandler {
         unsafe{ AudioHandler::from_ptr_unchecked(AudioHandlerWrapper {
             c: Box::new(callbacks),
-            floats_per_frame: AtomicUsize::new(0),
+            channels: AtomicUsize::new(0),
         }.wrap().into_raw()) }
     }
 }
 
-/// Implement this trait to handle events related to browser display state.
+/// Implement this trait to capture the audio of a browser. Captured audio is
+/// no longer played on the default output device.
 pub trait AudioHandlerCallbacks: 'static + Send + Sync {
     /// Called on the UI thread to allow configuration of audio stream parameters.
     /// Return `true` to proceed with audio stream capture, or `false` to
@@ -194,17 +195,16 @@ pub trait AudioHandlerCallbacks: 'static + Send + Sync {
         channels: usize,
     );
     /// Called on the audio stream thread when a PCM packet is received for the
-    /// stream. `data` is an array representing the raw PCM data as a floating
-    /// point type, i.e. 4-byte value(s). `frames` is the number of frames in the
-    /// PCM packet. `pts` is the presentation timestamp (in milliseconds since the
-    /// Unix Epoch) and represents the time at which the decompressed packet should
+    /// stream. `data` holds the raw PCM samples of each channel, planar and as
+    /// 32-bit floats, so every slice has one sample per frame of the packet.
+    /// `pts` is the presentation timestamp (in milliseconds since the Unix
+    /// Epoch) and represents the time at which the decompressed packet should
     /// be presented to the user.
     fn on_audio_stream_packet(
         &self,
         browser: Browser,
-        data: &[&f32],
-        frames: usize,
-        pts: i64, // TODO MAKE INSTANT?
+        data: &[&[f32]],
+        pts: i64,
     );
     /// Called on the UI thread when the stream has stopped. `on_audio_stream_stopped`
     /// will always be called after `on_audio_stream_started`; both functions may be
@@ -226,7 +226,8 @@ pub trait AudioHandlerCallbacks: 'static + Send + Sync {
 
 struct AudioHandlerWrapper {
     c: Box<dyn AudioHandlerCallbacks>,
-    floats_per_frame: AtomicUsize,
+    /// Channel count of the current stream.
+    channels: AtomicUsize,
 }
 
 impl Wrapper for AudioHandlerWrapper {
@@ -268,7 +269,8 @@ cef_callback_impl!{
             channels: i32: i32,
         ) {
             let params = unsafe{ AudioParameters::from_raw(params) };
-            self.floats_per_frame.store(params.channel_layout.num_channels(), Ordering::SeqCst);
+            // The layout doesn't determine the count for e.g. discrete channels.
+            self.channels.store(channels.max(0) as usize, Ordering::SeqCst);
             self.c.on_audio_stream_started(
                 browser,
                 &params,
@@ -282,13 +284,17 @@ cef_callback_impl!{
             frames: i32: c_int,
             pts: i64: i64,
         ) {
-            assert!(frames > 0);
-            let data_len = self.floats_per_frame.load(Ordering::SeqCst);
-            let data = unsafe{ slice::from_raw_parts(data as *const &f32, data_len) };
+            let channels = self.channels.load(Ordering::SeqCst);
+            if data.is_null() || frames <= 0 || channels == 0 {
+                return;
+            }
+            let data = unsafe{ slice::from_raw_parts(data as *const *const f32, channels) }
+                .iter()
+                .map(|&channel| unsafe{ slice::from_raw_parts(channel, frames as usize) })
+                .collect::<Vec<_>>();
             self.c.on_audio_stream_packet(
                 browser,
-                data,
-                frames as usize,
+                &data,
                 pts,
             );
         }
@@ -65,6 +65,21 @@ macro_rules! id {
 id!(pub struct CommandId);
 id!(pub struct GroupId);
 
+/// Commands with a default implementation in CEF.
+impl CommandId {
+    /// Replaces the misspelled word with the first to fifth suggestion.
+    pub const SPELLCHECK_SUGGESTIONS: [CommandId; 5] =
+        [CommandId(200), CommandId(201), CommandId(202), CommandId(203), CommandId(204)];
+    /// Placeholder shown if there are no suggestions. Does nothing.
+    pub const NO_SPELLING_SUGGESTIONS: CommandId = CommandId(205);
+    /// Adds the misspelled word to the custom dictionary.
+    pub const ADD_TO_DICTIONARY: CommandId = CommandId(206);
+    /// The first id for commands defined by the application.
+    pub const USER_FIRST: CommandId = CommandId(26500);
+    /// The last id for commands defined by the application.
+    pub const USER_LAST: CommandId = CommandId(28500);
+}
+
 /// Supported color types for menu items.
 #[repr(C)]
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
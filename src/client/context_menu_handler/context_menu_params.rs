@@ -1,11 +1,12 @@
 use crate::{
+    browser_host::BrowserHost,
     string::{CefString, CefStringList},
 };
 use cef_sys::{
     _cef_context_menu_params_t,
 };
 
-use super::{ContextMenuTypeFlags, ContextMenuMediaStateFlags, ContextMenuEditStateFlags};
+use super::{CommandId, ContextMenuTypeFlags, ContextMenuMediaStateFlags, ContextMenuEditStateFlags, MenuModel};
 
 ref_counted_ptr!{
     /// Provides information about the context menu state. The ethods of this
@@ -130,6 +131,18 @@ impl ContextMenuParams {
             }
         }
     }
+    /// Returns the misspelled word the context menu was invoked on together
+    /// with the spell checker's suggestions, or `None` if there is none.
+    pub fn get_spelling_correction(&self) -> Option<SpellingCorrection> {
+        let misspelled_word = self.get_misspelled_word();
+        if misspelled_word.is_empty() {
+            return None;
+        }
+        Some(SpellingCorrection {
+            misspelled_word,
+            suggestions: self.get_dictionary_suggestions().unwrap_or_default(),
+        })
+    }
     /// Returns `true` if the context menu was invoked on an editable node.
     pub fn is_editable(&self) -> bool {
         unsafe {
@@ -165,3 +178,45 @@ impl ContextMenuParams {
         }
     }
 }
+
+/// A misspelled word and the suggestions for replacing it, for building a
+/// corrections menu.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpellingCorrection {
+    pub misspelled_word: String,
+    /// At most five suggestions, best first.
+    pub suggestions: Vec<String>,
+}
+
+impl SpellingCorrection {
+    /// Add the suggestions and an "Add to dictionary" item labeled
+    /// `add_to_dictionary_label` to `model`, using the command ids CEF handles
+    /// by default, so no handling in
+    /// [ContextMenuHandlerCallbacks::on_context_menu_command](super::ContextMenuHandlerCallbacks::on_context_menu_command)
+    /// is needed.
+    pub fn add_to_menu(&self, model: &MenuModel, add_to_dictionary_label: &str) {
+        for (suggestion, &command_id) in self.suggestions.iter().zip(CommandId::SPELLCHECK_SUGGESTIONS.iter()) {
+            model.add_item(command_id, suggestion);
+        }
+        model.add_item(CommandId::ADD_TO_DICTIONARY, add_to_dictionary_label);
+    }
+    /// Execute a command added by [SpellingCorrection::add_to_menu], for menus
+    /// shown by the application itself. Returns false if `command_id` isn't
+    /// one of them.
+    pub fn execute(&self, host: &BrowserHost, command_id: CommandId) -> bool {
+        if command_id == CommandId::ADD_TO_DICTIONARY {
+            host.add_word_to_dictionary(&self.misspelled_word);
+            return true;
+        }
+        match CommandId::SPELLCHECK_SUGGESTIONS.iter().position(|&id| id == command_id) {
+            Some(index) => match self.suggestions.get(index) {
+                Some(suggestion) => {
+                    host.replace_misspelling(suggestion);
+                    true
+                }
+                None => false,
+            },
+            None => false,
+        }
+    }
+}
@@ -61,7 +61,7 @@ impl MenuModel {
     /// Returns the number of items in this menu.
     pub fn get_count(&self) -> usize {
         unsafe {
-            c(self.0.clear.unwrap()(self.as_ptr()))
+            c(self.0.get_count.unwrap()(self.as_ptr()))
         }
     }
     /// Add a separator to the menu. Returns `true` on success.
@@ -257,22 +257,17 @@ impl MenuModel {
         }
     }
 
-    /// Returns the command id at the specified `index` or -1 if not found due to
-    /// invalid range or the index being a separator.
+    /// Returns the command id at the specified `index` or `None` if not found
+    /// due to invalid range or the index being a separator.
     pub fn get_command_id_at(
         &self,
         index: usize,
-    ) -> Option<usize> {
+    ) -> Option<CommandId> {
         unsafe {
-            let index = self.0.get_command_id_at.unwrap()(
+            CommandId::new(self.0.get_command_id_at.unwrap()(
                 self.as_ptr(),
                 c(index),
-            );
-            if index >= 0 {
-                Some(c(index))
-            } else {
-                None
-            }
+            ))
         }
     }
     /// Sets the command id at the specified `index`. Returns `true` on success.
@@ -289,23 +284,23 @@ impl MenuModel {
             ) != 0
         }
     }
-    /// Returns the label for the specified `command_id` or NULL if not found.
+    /// Returns the label for the specified `command_id` or `None` if not found.
     pub fn get_label(
         &self,
         command_id: CommandId,
-    ) -> String {
+    ) -> Option<String> {
         unsafe {
-            CefString::from_userfree_unchecked(self.0.get_label.unwrap()(self.as_ptr(), command_id.get())).into()
+            CefString::from_userfree(self.0.get_label.unwrap()(self.as_ptr(), command_id.get())).map(String::from)
         }
     }
-    /// Returns the label at the specified `index` or NULL if not found due to
+    /// Returns the label at the specified `index` or `None` if not found due to
     /// invalid range or the index being a separator.
     pub fn get_label_at(
         &self,
         index: usize,
-    ) -> String {
+    ) -> Option<String> {
         unsafe {
-            CefString::from_userfree_unchecked(self.0.get_label_at.unwrap()(self.as_ptr(), c(index))).into()
+            CefString::from_userfree(self.0.get_label_at.unwrap()(self.as_ptr(), c(index))).map(String::from)
         }
     }
     /// Sets the label for the specified `command_id`. Returns `true` on success.
@@ -354,7 +349,7 @@ impl MenuModel {
         index: usize,
     ) -> MenuItemType {
         unsafe {
-            MenuItemType::from_unchecked(self.0.get_type.unwrap()(
+            MenuItemType::from_unchecked(self.0.get_type_at.unwrap()(
                 self.as_ptr(),
                 c(index),
             ))
@@ -413,25 +408,25 @@ impl MenuModel {
             ) != 0
         }
     }
-    /// Returns the submenu for the specified `command_id` or NULL if invalid.
+    /// Returns the submenu for the specified `command_id` or `None` if invalid.
     pub fn get_sub_menu(
         &self,
         command_id: CommandId,
-    ) -> MenuModel {
+    ) -> Option<MenuModel> {
         unsafe {
-            MenuModel::from_ptr_unchecked(self.0.get_sub_menu.unwrap()(
+            MenuModel::from_ptr(self.0.get_sub_menu.unwrap()(
                 self.as_ptr(),
                 command_id.get(),
             ))
         }
     }
-    /// Returns the submenu at the specified `index` or NULL if invalid.
+    /// Returns the submenu at the specified `index` or `None` if invalid.
     pub fn get_sub_menu_at(
         &self,
         index: usize,
-    ) -> MenuModel {
+    ) -> Option<MenuModel> {
         unsafe {
-            MenuModel::from_ptr_unchecked(self.0.get_sub_menu_at.unwrap()(
+            MenuModel::from_ptr(self.0.get_sub_menu_at.unwrap()(
                 self.as_ptr(),
                 c(index),
             ))
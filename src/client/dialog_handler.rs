@@ -15,6 +15,8 @@ use cef_sys::{
     cef_file_dialog_mode_t,
 };
 use std::os::raw::{c_int};
+use std::path::PathBuf;
+use std::fmt;
 use parking_lot::Mutex;
 
 ref_counted_ptr!{
@@ -39,12 +41,71 @@ impl FileDialogCallback {
     pub fn new<C: FileDialogCallbacks>(callbacks: C) -> FileDialogCallback {
         unsafe{ FileDialogCallback::from_ptr_unchecked(FileDialogCallbackWrapper(Box::new(callbacks)).wrap().into_raw()) }
     }
+    /// Continue the file selection with the files the user picked.
+    /// `selected_accept_filter` should be the 0-based index of the value
+    /// selected from the accept filters passed to
+    /// [DialogHandlerCallbacks::on_file_dialog]. `file_paths` should be a single
+    /// value or a list of values depending on the dialog mode. An empty
+    /// `file_paths` is treated the same as calling [FileDialogCallback::cancel].
+    ///
+    /// CEF only takes paths that are valid Unicode. If one isn't, nothing is
+    /// continued and the error names the path, so the selection can be
+    /// canceled or continued without it.
+    pub fn cont(&self, selected_accept_filter: usize, file_paths: &[PathBuf]) -> Result<(), NonUnicodePath> {
+        let mut list = CefStringList::new();
+        for path in unicode_paths(file_paths)? {
+            list.push(&CefString::new(path));
+        }
+        unsafe {
+            self.0.cont.unwrap()(self.as_ptr(), selected_accept_filter as c_int, list.as_mut_ptr());
+        }
+        Ok(())
+    }
+    /// Cancel the file selection, as if the user closed the dialog.
+    pub fn cancel(&self) {
+        unsafe {
+            self.0.cancel.unwrap()(self.as_ptr());
+        }
+    }
+}
+
+/// A path passed to [FileDialogCallback::cont] that isn't valid Unicode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonUnicodePath(pub PathBuf);
+
+impl fmt::Display for NonUnicodePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is not valid Unicode", self.0.display())
+    }
+}
+
+impl std::error::Error for NonUnicodePath {}
+
+fn unicode_paths(file_paths: &[PathBuf]) -> Result<Vec<&str>, NonUnicodePath> {
+    file_paths
+        .iter()
+        .map(|path| path.to_str().ok_or_else(|| NonUnicodePath(path.clone())))
+        .collect()
 }
 
 /// Implement this trait to handle dialog events.
 ///
 /// The functions of this structure will be called on the browser process UI thread.
 pub trait DialogHandlerCallbacks: 'static + Send {
+    /// Called to run a file chooser dialog, e.g. for `<input type=file>`.
+    /// `title` is the title to be used for the dialog, or `None` to show the
+    /// default title ("Open" or "Save" depending on the mode).
+    /// `default_file_path` is the path with optional directory and/or file name
+    /// component that should be initially selected in the dialog.
+    /// `accept_filters` are used to restrict the selectable file types and may
+    /// be any combination of valid lower-cased MIME types (e.g. "text/*" or
+    /// "image/*"), individual file extensions (e.g. ".txt" or ".png"), or
+    /// combined description and file extension delimited using "|" and ";"
+    /// (e.g. "Image Types|.png;.gif;.jpg"); see [AcceptFilter](crate::file_dialog::AcceptFilter).
+    /// `selected_accept_filter` is the 0-based index of the filter that should
+    /// be selected by default. To display a custom dialog return `true` and
+    /// call [FileDialogCallback::cont] or [FileDialogCallback::cancel] either
+    /// inline or at a later time. To display the default dialog return `false`.
     fn on_file_dialog(
         &self,
         browser: Browser,
@@ -68,7 +129,7 @@ pub trait FileDialogCallbacks: 'static + Send + Sync {
     fn cont(
         &self,
         selected_accept_filter: usize,
-        file_paths: &[String],
+        file_paths: &[PathBuf],
     );
     /// Cancel the file selection.
     fn cancel(&self);
@@ -140,7 +201,7 @@ cef_callback_impl!{
             if let Some(file_paths) = file_paths {
                 self.0.cont(
                     selected_accept_filter as usize,
-                    &(&*file_paths).into_iter().map(String::from).collect::<Vec<_>>()
+                    &(&*file_paths).into_iter().map(|path| PathBuf::from(String::from(path))).collect::<Vec<_>>()
                 );
             } else {
                 self.0.cancel();
@@ -151,3 +212,24 @@ cef_callback_impl!{
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unicode_paths_are_passed_unchanged() {
+        let paths = vec![PathBuf::from("/tmp/a.txt"), PathBuf::from("/tmp/ü b.png")];
+        assert_eq!(unicode_paths(&paths), Ok(vec!["/tmp/a.txt", "/tmp/ü b.png"]));
+        assert_eq!(unicode_paths(&[]), Ok(vec![]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_unicode_paths_are_rejected() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+        let invalid = PathBuf::from(OsStr::from_bytes(b"/tmp/\xff.txt"));
+        let paths = vec![PathBuf::from("/tmp/a.txt"), invalid.clone()];
+        assert_eq!(unicode_paths(&paths), Err(NonUnicodePath(invalid)));
+    }
+}
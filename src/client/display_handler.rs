@@ -87,8 +87,12 @@ pub trait DisplayHandlerCallbacks: 'static + Send {
         value: &str,
     ) {
     }
-    /// Called to display a console message. Return `true` to stop the message
-    /// from being output to the console.
+    /// Called to display a console message. `level` is [LogSeverity::Verbose]
+    /// for `console.debug`, [LogSeverity::Info] for `console.log` and
+    /// `console.info`, [LogSeverity::Warning] for `console.warn` and
+    /// [LogSeverity::Error] for `console.error` and uncaught exceptions; use
+    /// [LogSeverity::to_log_level] to forward messages to the `log` crate.
+    /// Return `true` to stop the message from being output to the console.
     fn on_console_message(
         &mut self,
         browser: Browser,
@@ -100,9 +104,10 @@ pub trait DisplayHandlerCallbacks: 'static + Send {
         false
     }
     /// Called when auto-resize is enabled via
-    /// cef_browser_host_t::SetAutoResizeEnabled and the contents have auto-
-    /// resized. `new_size` will be the desired size in view coordinates. Return
-    /// `true` if the resize was handled or `false` for default handling.
+    /// [BrowserHost::set_auto_resize_enabled](crate::browser_host::BrowserHost::set_auto_resize_enabled)
+    /// and the contents have auto-resized. `new_size` will be the desired size
+    /// in view coordinates. Return `true` if the resize was handled or `false`
+    /// for default handling, which resizes the window of windowed browsers.
     fn on_auto_resize(
         &mut self,
         browser: Browser,
@@ -120,6 +125,85 @@ pub trait DisplayHandlerCallbacks: 'static + Send {
     }
 }
 
+/// Base for [DisplayHandlerCallbacks] implementations that wrap another one, see
+/// [ResourceRequestHandlerDecorator](crate::resource_request_handler::ResourceRequestHandlerDecorator).
+pub trait DisplayHandlerDecorator: 'static + Send {
+    type Inner: DisplayHandlerCallbacks;
+    /// Returns the wrapped callbacks.
+    fn inner(&mut self) -> &mut Self::Inner;
+    fn on_address_change(&mut self, browser: Browser, frame: Frame, url: &str) {
+        self.inner().on_address_change(browser, frame, url)
+    }
+    fn on_title_change(&mut self, browser: Browser, title: &str) {
+        self.inner().on_title_change(browser, title)
+    }
+    fn on_favicon_url_change(&mut self, browser: Browser, icon_urls: &[String]) {
+        self.inner().on_favicon_url_change(browser, icon_urls)
+    }
+    fn on_fullscreen_mode_change(&mut self, browser: Browser, fullscreen: bool) {
+        self.inner().on_fullscreen_mode_change(browser, fullscreen)
+    }
+    fn on_tooltip(&mut self, browser: Browser, text: &mut String) -> bool {
+        self.inner().on_tooltip(browser, text)
+    }
+    fn on_status_message(&mut self, browser: Browser, value: &str) {
+        self.inner().on_status_message(browser, value)
+    }
+    fn on_console_message(
+        &mut self,
+        browser: Browser,
+        level: LogSeverity,
+        message: &str,
+        source: &str,
+        line: usize,
+    ) -> bool {
+        self.inner().on_console_message(browser, level, message, source, line)
+    }
+    fn on_auto_resize(&mut self, browser: Browser, new_size: Size) -> bool {
+        self.inner().on_auto_resize(browser, new_size)
+    }
+    fn on_loading_progress_change(&mut self, browser: Browser, progress: f64) {
+        self.inner().on_loading_progress_change(browser, progress)
+    }
+}
+
+impl<D: DisplayHandlerDecorator> DisplayHandlerCallbacks for D {
+    fn on_address_change(&mut self, browser: Browser, frame: Frame, url: &str) {
+        DisplayHandlerDecorator::on_address_change(self, browser, frame, url)
+    }
+    fn on_title_change(&mut self, browser: Browser, title: &str) {
+        DisplayHandlerDecorator::on_title_change(self, browser, title)
+    }
+    fn on_favicon_url_change(&mut self, browser: Browser, icon_urls: &[String]) {
+        DisplayHandlerDecorator::on_favicon_url_change(self, browser, icon_urls)
+    }
+    fn on_fullscreen_mode_change(&mut self, browser: Browser, fullscreen: bool) {
+        DisplayHandlerDecorator::on_fullscreen_mode_change(self, browser, fullscreen)
+    }
+    fn on_tooltip(&mut self, browser: Browser, text: &mut String) -> bool {
+        DisplayHandlerDecorator::on_tooltip(self, browser, text)
+    }
+    fn on_status_message(&mut self, browser: Browser, value: &str) {
+        DisplayHandlerDecorator::on_status_message(self, browser, value)
+    }
+    fn on_console_message(
+        &mut self,
+        browser: Browser,
+        level: LogSeverity,
+        message: &str,
+        source: &str,
+        line: usize,
+    ) -> bool {
+        DisplayHandlerDecorator::on_console_message(self, browser, level, message, source, line)
+    }
+    fn on_auto_resize(&mut self, browser: Browser, new_size: Size) -> bool {
+        DisplayHandlerDecorator::on_auto_resize(self, browser, new_size)
+    }
+    fn on_loading_progress_change(&mut self, browser: Browser, progress: f64) {
+        DisplayHandlerDecorator::on_loading_progress_change(self, browser, progress)
+    }
+}
+
 struct DisplayHandlerWrapper(Mutex<Box<dyn DisplayHandlerCallbacks>>);
 
 impl Wrapper for DisplayHandlerWrapper {
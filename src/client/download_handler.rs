@@ -5,6 +5,7 @@ use crate::{
 };
 use cef_sys::{
     cef_string_t,
+    cef_string_userfree_t,
     cef_browser_t,
     cef_download_handler_t,
     cef_download_item_callback_t,
@@ -124,9 +125,10 @@ impl DownloadItem {
     pub fn get_end_time(&self) -> DateTime<Utc> {
         crate::values::cef_time_to_date_time(unsafe{ self.0.get_end_time.unwrap()(self.as_ptr()) })
     }
-    /// Returns the full path to the downloaded or downloading file.
+    /// Returns the full path to the downloaded or downloading file, or an
+    /// empty string before the path was chosen.
     pub fn get_full_path(&self) -> String {
-        String::from(unsafe{ CefString::from_userfree_unchecked(self.0.get_full_path.unwrap()(self.as_ptr())) })
+        unsafe{ userfree_string(self.0.get_full_path.unwrap()(self.as_ptr())) }
     }
     /// Returns the unique identifier for this download.
     pub fn get_id(&self) -> u32 {
@@ -134,26 +136,31 @@ impl DownloadItem {
     }
     /// Returns the URL.
     pub fn get_url(&self) -> String {
-        String::from(unsafe{ CefString::from_userfree_unchecked(self.0.get_url.unwrap()(self.as_ptr())) })
+        unsafe{ userfree_string(self.0.get_url.unwrap()(self.as_ptr())) }
     }
     /// Returns the original URL before any redirections.
     pub fn get_original_url(&self) -> String {
-        String::from(unsafe{ CefString::from_userfree_unchecked(self.0.get_original_url.unwrap()(self.as_ptr())) })
+        unsafe{ userfree_string(self.0.get_original_url.unwrap()(self.as_ptr())) }
     }
     /// Returns the suggested file name.
     pub fn get_suggested_file_name(&self) -> String {
-        String::from(unsafe{ CefString::from_userfree_unchecked(self.0.get_suggested_file_name.unwrap()(self.as_ptr())) })
+        unsafe{ userfree_string(self.0.get_suggested_file_name.unwrap()(self.as_ptr())) }
     }
     /// Returns the content disposition.
     pub fn get_content_disposition(&self) -> String {
-        String::from(unsafe{ CefString::from_userfree_unchecked(self.0.get_content_disposition.unwrap()(self.as_ptr())) })
+        unsafe{ userfree_string(self.0.get_content_disposition.unwrap()(self.as_ptr())) }
     }
     /// Returns the mime type.
     pub fn get_mime_type(&self) -> String {
-        String::from(unsafe{ CefString::from_userfree_unchecked(self.0.get_mime_type.unwrap()(self.as_ptr())) })
+        unsafe{ userfree_string(self.0.get_mime_type.unwrap()(self.as_ptr())) }
     }
 }
 
+/// Converts a string returned by CEF, which returns null for empty strings.
+unsafe fn userfree_string(raw: cef_string_userfree_t) -> String {
+    CefString::from_userfree(raw).map(String::from).unwrap_or_default()
+}
+
 /// Trait used to handle file downloads. The functions of this structure will
 /// called on the browser process UI thread.
 pub trait DownloadHandlerCallbacks: 'static + Send {
@@ -2,6 +2,7 @@ use crate::values::Rect;
 use cef_sys::cef_rect_t;
 use crate::{
     browser::{Browser},
+    browser_host::BrowserHost,
     refcounted::{RefCountedPtr, Wrapper},
 };
 use cef_sys::{
@@ -10,6 +11,7 @@ use cef_sys::{
 };
 use std::os::raw::{c_int};
 use parking_lot::Mutex;
+use std::sync::Arc;
 
 ref_counted_ptr!{
     /// Instantiate this structure to handle events related to find results. The
@@ -80,3 +82,112 @@ cef_callback_impl!{
         }
     }
 }
+
+/// A result reported to [FindHandlerCallbacks::on_find_result].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FindResult {
+    pub identifier: i32,
+    /// The number of matches found so far.
+    pub count: usize,
+    /// The location of the active match, in window coordinates.
+    pub selection_rect: Rect,
+    /// The 1-based position of the active match, 0 if there is none.
+    pub active_match_ordinal: usize,
+    /// True if this is the last result of the search.
+    pub final_update: bool,
+}
+
+/// Called with the results of the current search of a [FindSession].
+pub trait FindResultListener = 'static + Send + FnMut(&FindResult);
+
+#[derive(Default)]
+struct FindState {
+    text: String,
+    match_case: bool,
+    identifier: i32,
+}
+
+/// Drives the in-page search of a find bar. Repeated calls to
+/// [FindSession::find] with the same text move between the matches, and
+/// results of earlier searches are filtered out, which [BrowserHost::find]
+/// leaves to the application.
+#[derive(Clone)]
+pub struct FindSession {
+    host: BrowserHost,
+    state: Arc<Mutex<FindState>>,
+}
+
+impl FindSession {
+    pub fn new(host: BrowserHost) -> FindSession {
+        FindSession {
+            host,
+            state: Arc::new(Mutex::new(FindState::default())),
+        }
+    }
+    /// Create a find handler that calls `listener` with the results of the
+    /// current search. Return it from
+    /// [ClientCallbacks::get_find_handler](crate::client::ClientCallbacks::get_find_handler).
+    pub fn find_handler(&self, listener: impl FindResultListener) -> FindHandler {
+        FindHandler::new(FindSessionHandler {
+            state: self.state.clone(),
+            listener: Box::new(listener),
+        })
+    }
+    /// Search for `text`, or move to the next match in the direction given by
+    /// `forward` if `text` was already searched for. An empty `text` stops the
+    /// search.
+    pub fn find(&self, text: &str, forward: bool, match_case: bool) {
+        if text.is_empty() {
+            return self.stop(true);
+        }
+        let (identifier, find_next) = {
+            let mut state = self.state.lock();
+            let find_next = state.identifier != 0 && state.text == text && state.match_case == match_case;
+            if !find_next {
+                state.identifier += 1;
+                state.text = text.to_owned();
+                state.match_case = match_case;
+            }
+            (state.identifier, find_next)
+        };
+        self.host.find(identifier, text, forward, match_case, find_next);
+    }
+    /// Stop searching. Results still arriving for the stopped search are
+    /// dropped.
+    pub fn stop(&self, clear_selection: bool) {
+        {
+            let mut state = self.state.lock();
+            state.identifier += 1;
+            state.text.clear();
+        }
+        self.host.stop_finding(clear_selection);
+    }
+}
+
+struct FindSessionHandler {
+    state: Arc<Mutex<FindState>>,
+    listener: Box<dyn FindResultListener>,
+}
+
+impl FindHandlerCallbacks for FindSessionHandler {
+    fn on_find_result(
+        &mut self,
+        browser: Browser,
+        identifier: i32,
+        count: usize,
+        selection_rect: Rect,
+        active_match_ordinal: usize,
+        final_update: bool
+    ) {
+        if identifier != self.state.lock().identifier {
+            return;
+        }
+        (self.listener)(&FindResult {
+            identifier,
+            count,
+            selection_rect,
+            active_match_ordinal,
+            final_update,
+        });
+    }
+}
@@ -2,6 +2,7 @@ use crate::string::CefString;
 use cef_sys::cef_string_t;
 use crate::{
     browser::{Browser},
+    browser_host::BrowserHost,
     refcounted::{RefCountedPtr, Wrapper},
 };
 use cef_sys::{
@@ -10,8 +11,12 @@ use cef_sys::{
     cef_jsdialog_callback_t,
     cef_jsdialog_type_t,
 };
-use std::os::raw::{c_int};
+use std::{
+    collections::HashMap,
+    os::raw::{c_int},
+};
 use parking_lot::Mutex;
+use lazy_static::lazy_static;
 
 ref_counted_ptr!{
     /// Instantiate this structure to handle events related to JavaScript dialogs. The
@@ -125,6 +130,75 @@ pub trait JsDialogHandlerCallbacks: 'static + Send {
     }
 }
 
+/// How `beforeunload` dialogs of a browser are answered. Browsers without a
+/// state ask the user as usual.
+#[derive(Default)]
+struct BeforeUnloadState {
+    /// Set by [BrowserHost::set_ignore_beforeunload].
+    ignore: bool,
+    /// The dialog currently shown, for [BrowserHost::answer_beforeunload].
+    pending: Option<JsDialogCallback>,
+}
+
+lazy_static!{
+    static ref BEFORE_UNLOAD: Mutex<HashMap<i32, BeforeUnloadState>> = Mutex::new(HashMap::new());
+}
+
+fn update_before_unload_state(browser_id: i32, f: impl FnOnce(&mut BeforeUnloadState)) {
+    let mut states = BEFORE_UNLOAD.lock();
+    let state = states.entry(browser_id).or_default();
+    f(state);
+    if !state.ignore && state.pending.is_none() {
+        states.remove(&browser_id);
+    }
+}
+
+/// Drop the state of a closed browser, including a dialog that is still shown.
+pub(crate) fn forget_browser(browser_id: i32) {
+    BEFORE_UNLOAD.lock().remove(&browser_id);
+}
+
+impl BrowserHost {
+    /// If `ignore` is true, pages in this browser can no longer block
+    /// navigation or closing with a `beforeunload` handler: leaving the page
+    /// is confirmed without asking the user, including for a dialog that is
+    /// currently shown. Used to force navigation, e.g. on logout.
+    ///
+    /// This only has an effect if the browser's client has a [JsDialogHandler].
+    pub fn set_ignore_beforeunload(&self, ignore: bool) {
+        let browser_id = self.get_browser().get_identifier();
+        let mut pending = None;
+        update_before_unload_state(browser_id, |state| {
+            state.ignore = ignore;
+            if ignore {
+                pending = state.pending.take();
+            }
+        });
+        if let Some(callback) = pending {
+            callback.cont(true, "");
+        }
+    }
+    /// Answer the `beforeunload` dialog currently shown for this browser, as if
+    /// the user chose to leave the page if `proceed` is true or to stay
+    /// otherwise. Returns false if no dialog is pending.
+    ///
+    /// Only dialogs shown by the application, i.e. for which
+    /// [JsDialogHandlerCallbacks::on_before_unload_dialog] returned true, can
+    /// be answered.
+    pub fn answer_beforeunload(&self, proceed: bool) -> bool {
+        let browser_id = self.get_browser().get_identifier();
+        let mut pending = None;
+        update_before_unload_state(browser_id, |state| pending = state.pending.take());
+        match pending {
+            Some(callback) => {
+                callback.cont(proceed, "");
+                true
+            }
+            None => false,
+        }
+    }
+}
+
 struct JsDialogHandlerWrapper(Mutex<Box<dyn JsDialogHandlerCallbacks>>);
 impl Wrapper for JsDialogHandlerWrapper {
     type Cef = cef_jsdialog_handler_t;
@@ -174,23 +248,42 @@ cef_callback_impl!{
             is_reload: c_int: c_int,
             callback: JsDialogCallback: *mut cef_jsdialog_callback_t
         ) -> c_int {
-            self.0.lock().on_before_unload_dialog(
+            let browser_id = browser.get_identifier();
+            let mut ignore = false;
+            update_before_unload_state(browser_id, |state| {
+                ignore = state.ignore;
+                if !ignore {
+                    state.pending = Some(callback.clone());
+                }
+            });
+            if ignore {
+                callback.cont(true, "");
+                return 1;
+            }
+            let handled = self.0.lock().on_before_unload_dialog(
                 browser,
                 &String::from(message_text),
                 is_reload != 0,
                 callback,
-            ) as c_int
+            );
+            if !handled {
+                // The default dialog uses its own callback.
+                update_before_unload_state(browser_id, |state| state.pending = None);
+            }
+            handled as c_int
         }
         fn on_reset_dialog_state(
             &self,
             browser: Browser: *mut cef_browser_t
         ) {
+            update_before_unload_state(browser.get_identifier(), |state| state.pending = None);
             self.0.lock().on_reset_dialog_state(browser);
         }
         fn on_dialog_closed(
             &self,
             browser: Browser: *mut cef_browser_t
         ) {
+            update_before_unload_state(browser.get_identifier(), |state| state.pending = None);
             self.0.lock().on_dialog_closed(browser);
         }
     }
@@ -51,6 +51,39 @@ pub trait KeyboardHandlerCallbacks: 'static + Send {
     }
 }
 
+/// Base for [KeyboardHandlerCallbacks] implementations that wrap another one, see
+/// [ResourceRequestHandlerDecorator](crate::resource_request_handler::ResourceRequestHandlerDecorator).
+pub trait KeyboardHandlerDecorator: 'static + Send {
+    type Inner: KeyboardHandlerCallbacks;
+    /// Returns the wrapped callbacks.
+    fn inner(&mut self) -> &mut Self::Inner;
+    fn on_pre_key_event(
+        &mut self,
+        browser: Browser,
+        event: KeyEvent,
+        is_keyboard_shortcut: &mut bool,
+    ) -> bool {
+        self.inner().on_pre_key_event(browser, event, is_keyboard_shortcut)
+    }
+    fn on_key_event(&mut self, browser: Browser, event: KeyEvent) -> bool {
+        self.inner().on_key_event(browser, event)
+    }
+}
+
+impl<D: KeyboardHandlerDecorator> KeyboardHandlerCallbacks for D {
+    fn on_pre_key_event(
+        &mut self,
+        browser: Browser,
+        event: KeyEvent,
+        is_keyboard_shortcut: &mut bool,
+    ) -> bool {
+        KeyboardHandlerDecorator::on_pre_key_event(self, browser, event, is_keyboard_shortcut)
+    }
+    fn on_key_event(&mut self, browser: Browser, event: KeyEvent) -> bool {
+        KeyboardHandlerDecorator::on_key_event(self, browser, event)
+    }
+}
+
 struct KeyboardHandlerWrapper(Mutex<Box<dyn KeyboardHandlerCallbacks>>);
 
 impl Wrapper for KeyboardHandlerWrapper {
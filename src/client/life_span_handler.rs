@@ -1,10 +1,14 @@
 use crate::{
+    audio_output,
+    background_throttling,
     browser::{Browser, BrowserSettings},
     client::{
         Client,
+        js_dialog_handler,
         request_handler::WindowOpenDisposition,
     },
     frame::Frame,
+    fullscreen,
     refcounted::{RefCountedPtr, Wrapper},
     values::DictionaryValue,
     window::WindowInfo,
@@ -41,20 +45,24 @@ pub trait LifeSpanHandlerCallbacks: 'static + Send + Sync {
     /// - `target_disposition`: where the user intended to open the popup (e.g. current tab, new tab, etc).
     /// - `user_gesture` will be `true` if the popup was opened via explicit user gesture (e.g. clicking a link) or `false` if the popup opened automatically (e.g. via the DomContentLoaded event).
     /// - `popup_features`: contains additional information about the requested popup window.
-    /// To allow creation
-    /// of the popup browser optionally modify `window_info`, `client`, `settings`
-    /// and `no_javascript_access` and return `false`. To cancel creation of the
-    /// popup browser return `true`. The `client` and `settings` values will
-    /// default to the source browser's values. If the `no_javascript_access` value
-    /// is set to `false` the new browser will not be scriptable and may not be
-    /// hosted in the same renderer process as the source browser. Any
-    /// modifications to `window_info` will be ignored if the parent browser is
-    /// wrapped in a cef_browser_view_t. Popup browser creation will be canceled if
-    /// the parent browser is destroyed before the popup browser creation completes
-    /// (indicated by a call to `on_after_created` for the popup browser). The
-    /// `extra_info` parameter provides an opportunity to specify extra information
-    /// specific to the created popup browser that will be passed to
-    /// cef_render_process_handler_t::on_browser_created() in the render process.
+    ///
+    /// To allow creation of the popup browser optionally modify `window_info`,
+    /// `client`, `settings` and `no_javascript_access` and return `false`. To
+    /// cancel creation of the popup browser return `true`; to take over, e.g. to
+    /// open the URL in a new tab of the application's own window, create a
+    /// browser for `target_url` with
+    /// [BrowserHost::create_browser_sync](crate::browser_host::BrowserHost::create_browser_sync)
+    /// and return `true`. The `client` and `settings` values will default to the
+    /// source browser's values. If `no_javascript_access` is set to `true` the
+    /// new browser will not be scriptable and may not be hosted in the same
+    /// renderer process as the source browser. Any modifications to
+    /// `window_info` will be ignored if the parent browser is wrapped in a
+    /// BrowserView. Popup browser creation will be canceled if the parent
+    /// browser is destroyed before the popup browser creation completes
+    /// (indicated by a call to `on_after_created` for the popup browser).
+    /// `extra_info` is `None` unless set here, and will be passed to
+    /// [RenderProcessHandlerCallbacks::on_browser_created](crate::render_process_handler::RenderProcessHandlerCallbacks::on_browser_created)
+    /// in the render process.
     fn on_before_popup(
         &self,
         browser: Browser,
@@ -63,14 +71,13 @@ pub trait LifeSpanHandlerCallbacks: 'static + Send + Sync {
         target_frame_name: Option<&str>,
         target_disposition: WindowOpenDisposition,
         user_gesture: bool,
-        popup_features: PopupFeatures, //*const _cef_popup_features_t,
-        window_info: &mut WindowInfo, //*mut _cef_window_info_t,
-        client: &mut Client, // *mut *mut _cef_client_t,
-        settings: &mut BrowserSettings, // *mut _cef_browser_settings_t,
-        extra_info: &mut DictionaryValue, // *mut *mut _cef_dictionary_value_t,
-        no_javascript_access: &mut bool // *mut c_int
+        popup_features: PopupFeatures,
+        window_info: &mut WindowInfo,
+        client: &mut Client,
+        settings: &mut BrowserSettings,
+        extra_info: &mut Option<DictionaryValue>,
+        no_javascript_access: &mut bool,
     ) -> bool {
-        println!("popup window");
         false
     }
     /// Called after a new browser is created. This callback will be the first
@@ -252,7 +259,7 @@ cef_callback_impl!{
             window_info: *mut cef_window_info_t: *mut cef_window_info_t,
             client: &mut Client: *mut *mut cef_client_t,
             settings: *mut cef_browser_settings_t: *mut cef_browser_settings_t,
-            extra_info: &mut DictionaryValue: *mut *mut cef_dictionary_value_t,
+            extra_info: *mut *mut cef_dictionary_value_t: *mut *mut cef_dictionary_value_t,
             no_javascript_access: &mut std::os::raw::c_int: *mut std::os::raw::c_int
         ) -> std::os::raw::c_int {
             let window_info_ref = unsafe{ &mut *window_info };
@@ -260,6 +267,9 @@ cef_callback_impl!{
             let settings_ref = unsafe{ &mut *settings };
             let mut settings_rust = unsafe{ BrowserSettings::from_raw(&*settings) };
             let mut no_javascript_access_rust = *no_javascript_access != 0;
+            // CEF passes a null dictionary unless one is set, which the
+            // non-null `DictionaryValue` can't represent.
+            let extra_info = unsafe { &mut *(extra_info as *mut Option<DictionaryValue>) };
             let ret = self.0.on_before_popup(
                 browser,
                 frame,
@@ -293,6 +303,11 @@ cef_callback_impl!{
         }
         fn on_before_close(&self, browser: Browser: *mut cef_browser_t) {
             self.0.on_before_close(browser.clone());
+            let browser_id = browser.get_identifier();
+            js_dialog_handler::forget_browser(browser_id);
+            background_throttling::forget_browser(browser_id);
+            audio_output::forget_browser(browser_id);
+            fullscreen::forget_browser(browser_id);
             unsafe{ browser.poison(); }
         }
     }
@@ -28,17 +28,28 @@ use libc::c_int;
 use std::os::raw::c_void;
 use std::ptr;
 
+/// The kind of on-screen keyboard an editable element asks for, from its
+/// `inputmode` attribute or input type.
 #[repr(C)]
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum TextInputMode {
+    /// A standard keyboard for the platform's locale.
     Default = cef_text_input_mode_t::CEF_TEXT_INPUT_MODE_DEFAULT as isize,
+    /// No keyboard; hide any keyboard that is shown.
     None = cef_text_input_mode_t::CEF_TEXT_INPUT_MODE_NONE as isize,
+    /// A standard text keyboard.
     Text = cef_text_input_mode_t::CEF_TEXT_INPUT_MODE_TEXT as isize,
+    /// A telephone keypad.
     Tel = cef_text_input_mode_t::CEF_TEXT_INPUT_MODE_TEL as isize,
+    /// A keyboard for entering URLs, e.g. with a `/` key.
     URL = cef_text_input_mode_t::CEF_TEXT_INPUT_MODE_URL as isize,
+    /// A keyboard for entering email addresses, e.g. with an `@` key.
     Email = cef_text_input_mode_t::CEF_TEXT_INPUT_MODE_EMAIL as isize,
+    /// Digits only.
     Numeric = cef_text_input_mode_t::CEF_TEXT_INPUT_MODE_NUMERIC as isize,
+    /// Digits and the locale's decimal separator.
     Decimal = cef_text_input_mode_t::CEF_TEXT_INPUT_MODE_DECIMAL as isize,
+    /// A text keyboard with a search or go key.
     Search = cef_text_input_mode_t::CEF_TEXT_INPUT_MODE_SEARCH as isize,
 }
 
@@ -46,6 +57,11 @@ impl TextInputMode {
     pub unsafe fn from_unchecked(c: crate::CEnumType) -> Self {
         std::mem::transmute(c)
     }
+    /// Returns true if a keyboard should be shown for this mode, and false for
+    /// [TextInputMode::None].
+    pub fn shows_keyboard(self) -> bool {
+        self != TextInputMode::None
+    }
 }
 
 pub type CursorHandle = cef_sys::cef_cursor_handle_t;
@@ -161,12 +177,17 @@ impl<'a> CursorType<'a> {
             cef_cursor_type_t::CT_GRABBING => Self::Grabbing,
             cef_cursor_type_t::CT_MIDDLE_PANNING_VERTICAL => Self::MiddlePanningVertical,
             cef_cursor_type_t::CT_MIDDLE_PANNING_HORIZONTAL => Self::MiddlePanningHorizontal,
-            cef_cursor_type_t::CT_CUSTOM => Self::Custom({
+            cef_cursor_type_t::CT_CUSTOM if !custom_cursor_info.is_null() => Self::Custom({
                 let cci = &*custom_cursor_info;
+                let buffer = if cci.buffer.is_null() || cci.size.width <= 0 || cci.size.height <= 0 {
+                    &[][..]
+                } else {
+                    std::slice::from_raw_parts(cci.buffer as *const u8, (4 * cci.size.width * cci.size.height) as usize)
+                };
                 CustomCursorInfo {
                     hotspot: Point::from(&cci.hotspot),
                     image_scale_factor: cci.image_scale_factor,
-                    buffer: std::slice::from_raw_parts(cci.buffer as *const u8, (4 * cci.size.width * cci.size.height) as usize),
+                    buffer,
                     size: Size::from(&cci.size),
                 }
             }),
@@ -174,7 +195,12 @@ impl<'a> CursorType<'a> {
             cef_cursor_type_t::CT_DND_MOVE => Self::DndMove,
             cef_cursor_type_t::CT_DND_COPY => Self::DndCopy,
             cef_cursor_type_t::CT_DND_LINK => Self::DndLink,
-            _ => panic!("bad custom cursor value"),
+            // Panicking here would unwind into CEF, so fall back to the
+            // default cursor for types added by newer versions.
+            _ => {
+                log::warn!("Unknown cursor type {}", cursor_type);
+                Self::Pointer
+            }
         }
     }
 }
@@ -246,7 +272,7 @@ impl From<&'_ cef_screen_info_t> for ScreenInfo {
         ScreenInfo {
             device_scale_factor: info.device_scale_factor,
             depth: info.depth as u32,
-            depth_per_component: info.depth as u32,
+            depth_per_component: info.depth_per_component as u32,
             is_monochrome: info.is_monochrome != 0,
             rect: Rect::from(&info.rect),
             available_rect: Rect::from(&info.available_rect),
@@ -264,6 +290,10 @@ impl RenderHandler {
     }
 }
 
+/// Implement this trait to render a windowless browser. Only
+/// [RenderHandlerCallbacks::get_view_rect] and [RenderHandlerCallbacks::on_paint]
+/// are required; return the handler from
+/// [ClientCallbacks::get_render_handler](crate::client::ClientCallbacks::get_render_handler).
 pub trait RenderHandlerCallbacks: 'static + Send + Sync {
     /// Return the handler for accessibility notifications. If no handler is
     /// provided the default implementation will be used.
@@ -290,7 +320,9 @@ pub trait RenderHandlerCallbacks: 'static + Send + Sync {
         &self,
         browser: Browser,
         view_point: Point,
-    ) -> Option<Point>;
+    ) -> Option<Point> {
+        None
+    }
     /// Called to allow the client to fill in the CefScreenInfo object with
     /// appropriate values.
     ///
@@ -300,21 +332,25 @@ pub trait RenderHandlerCallbacks: 'static + Send + Sync {
     fn get_screen_info(
         &self,
         browser: Browser,
-    ) -> Option<ScreenInfo>;
+    ) -> Option<ScreenInfo> {
+        None
+    }
     /// Called when the browser wants to show or hide the popup widget. The popup
     /// should be shown if `show` is `true` and hidden if `show` is `false`.
     fn on_popup_show(
         &self,
         browser: Browser,
         show: bool,
-    );
+    ) {
+    }
     /// Called when the browser wants to move or resize the popup widget. `rect`
     /// contains the new location and size in view coordinates.
     fn on_popup_size(
         &self,
         browser: Browser,
         rect: Rect,
-    );
+    ) {
+    }
     /// Called when an element should be painted. Pixel values passed to this
     /// function are scaled relative to view coordinates based on the value of
     /// CefScreenInfo.device_scale_factor returned from GetScreenInfo. `type`
@@ -346,15 +382,21 @@ pub trait RenderHandlerCallbacks: 'static + Send + Sync {
         type_: PaintElementType,
         dirty_rects: &[Rect],
         shared_handle: *mut c_void,
-    );
-    /// Called when the browser's cursor has changed. If `type` is CT_CUSTOM then
-    /// `custom_cursor_info` will be populated with the custom cursor information.
+    ) {
+    }
+    /// Called when the browser's cursor has changed. `type_` is
+    /// [CursorType::Custom] with the cursor image for cursors set by the page
+    /// with CSS `cursor: url(...)`. `cursor` is the platform cursor CEF would
+    /// show for windowed browsers. Not called while cursor changes are
+    /// disabled with
+    /// [BrowserHost::set_mouse_cursor_change_disabled](crate::browser_host::BrowserHost::set_mouse_cursor_change_disabled).
     fn on_cursor_change(
         &self,
         browser: Browser,
         cursor: CursorHandle,
         type_: CursorType<'_>,
-    );
+    ) {
+    }
     /// Called when the user starts dragging content in the web view. Contextual
     /// information about the dragged content is supplied by `drag_data`. `drag_start`
     /// is the drag start location in screen coordinates. OS APIs that run a
@@ -383,8 +425,12 @@ pub trait RenderHandlerCallbacks: 'static + Send + Sync {
         &self,
         browser: Browser,
         operation: DragOperation,
-    );
-    /// Called when the scroll offset has changed.
+    ) {
+    }
+    /// Called when the scroll offset of the main frame's document has changed,
+    /// with the new offset in CSS pixels. Use
+    /// [BrowserHost::get_layout_metrics](crate::browser_host::BrowserHost::get_layout_metrics)
+    /// for the size of the document.
     fn on_scroll_offset_changed(
         &self,
         browser: Browser,
@@ -394,18 +440,23 @@ pub trait RenderHandlerCallbacks: 'static + Send + Sync {
     }
     /// Called when the IME composition range has changed. `selected_range` is the
     /// range of characters that have been selected. `character_bounds` is the
-    /// bounds of each character in view coordinates.
+    /// bounds of each character of the composition in view coordinates, and is
+    /// empty if there's no composition.
+    ///
+    /// In windowless mode the OS doesn't know where the caret is, so position
+    /// the IME candidate window here, e.g. below
+    /// [composition_bounds](crate::ime::composition_bounds) converted with
+    /// [RenderHandlerCallbacks::get_screen_point].
     fn on_ime_composition_range_changed(
         &self,
         browser: Browser,
         selected_range: Range,
-        character_bounds_count: usize,
-        character_bounds: Rect,
+        character_bounds: &[Rect],
     ) {
     }
     /// Called when text selection has changed for the specified `browser`.
     /// `selected_text` is the currently selected text and `selected_range` is the
-    /// character range.
+    /// character range. An empty `selected_range` is the position of the caret.
     fn on_text_selection_changed(
         &self,
         browser: Browser,
@@ -414,9 +465,10 @@ pub trait RenderHandlerCallbacks: 'static + Send + Sync {
     ) {
     }
     /// Called when an on-screen keyboard should be shown or hidden for the
-    /// specified `browser`. `input_mode` specifies what kind of keyboard should be
-    /// opened. If `input_mode` is CEF_TEXT_INPUT_MODE_NONE, any existing keyboard
-    /// for this browser should be hidden.
+    /// specified `browser`, e.g. when an input field gains or loses focus.
+    /// `input_mode` specifies what kind of keyboard should be opened. If
+    /// `input_mode` is [TextInputMode::None], any existing keyboard for this
+    /// browser should be hidden; see [TextInputMode::shows_keyboard].
     fn on_virtual_keyboard_requested(
         &self,
         browser: Browser,
@@ -425,6 +477,173 @@ pub trait RenderHandlerCallbacks: 'static + Send + Sync {
     }
 }
 
+/// Base for [RenderHandlerCallbacks] implementations that wrap another one, see
+/// [ResourceRequestHandlerDecorator](crate::resource_request_handler::ResourceRequestHandlerDecorator).
+pub trait RenderHandlerDecorator: 'static + Send + Sync {
+    type Inner: RenderHandlerCallbacks;
+    /// Returns the wrapped callbacks.
+    fn inner(&self) -> &Self::Inner;
+    fn get_accessibility_handler(&self) -> Option<AccessibilityHandler> {
+        self.inner().get_accessibility_handler()
+    }
+    fn get_root_screen_rect(&self, browser: Browser) -> Option<Rect> {
+        self.inner().get_root_screen_rect(browser)
+    }
+    fn get_view_rect(&self, browser: Browser) -> Rect {
+        self.inner().get_view_rect(browser)
+    }
+    fn get_screen_point(&self, browser: Browser, view_point: Point) -> Option<Point> {
+        self.inner().get_screen_point(browser, view_point)
+    }
+    fn get_screen_info(&self, browser: Browser) -> Option<ScreenInfo> {
+        self.inner().get_screen_info(browser)
+    }
+    fn on_popup_show(&self, browser: Browser, show: bool) {
+        self.inner().on_popup_show(browser, show)
+    }
+    fn on_popup_size(&self, browser: Browser, rect: Rect) {
+        self.inner().on_popup_size(browser, rect)
+    }
+    fn on_paint(
+        &self,
+        browser: Browser,
+        type_: PaintElementType,
+        dirty_rects: &[Rect],
+        buffer: &[u8],
+        width: i32,
+        height: i32,
+    ) {
+        self.inner().on_paint(browser, type_, dirty_rects, buffer, width, height)
+    }
+    fn on_accelerated_paint(
+        &self,
+        browser: Browser,
+        type_: PaintElementType,
+        dirty_rects: &[Rect],
+        shared_handle: *mut c_void,
+    ) {
+        self.inner().on_accelerated_paint(browser, type_, dirty_rects, shared_handle)
+    }
+    fn on_cursor_change(&self, browser: Browser, cursor: CursorHandle, type_: CursorType<'_>) {
+        self.inner().on_cursor_change(browser, cursor, type_)
+    }
+    fn start_dragging(
+        &self,
+        browser: Browser,
+        drag_data: DragData,
+        allowed_ops: DragOperation,
+        drag_start: Point,
+    ) -> bool {
+        self.inner().start_dragging(browser, drag_data, allowed_ops, drag_start)
+    }
+    fn update_drag_cursor(&self, browser: Browser, operation: DragOperation) {
+        self.inner().update_drag_cursor(browser, operation)
+    }
+    fn on_scroll_offset_changed(&self, browser: Browser, x: f64, y: f64) {
+        self.inner().on_scroll_offset_changed(browser, x, y)
+    }
+    fn on_ime_composition_range_changed(
+        &self,
+        browser: Browser,
+        selected_range: Range,
+        character_bounds: &[Rect],
+    ) {
+        self.inner().on_ime_composition_range_changed(browser, selected_range, character_bounds)
+    }
+    fn on_text_selection_changed(
+        &self,
+        browser: Browser,
+        selected_text: Option<&str>,
+        selected_range: Range,
+    ) {
+        self.inner().on_text_selection_changed(browser, selected_text, selected_range)
+    }
+    fn on_virtual_keyboard_requested(&self, browser: Browser, input_mode: TextInputMode) {
+        self.inner().on_virtual_keyboard_requested(browser, input_mode)
+    }
+}
+
+impl<D: RenderHandlerDecorator> RenderHandlerCallbacks for D {
+    fn get_accessibility_handler(&self) -> Option<AccessibilityHandler> {
+        RenderHandlerDecorator::get_accessibility_handler(self)
+    }
+    fn get_root_screen_rect(&self, browser: Browser) -> Option<Rect> {
+        RenderHandlerDecorator::get_root_screen_rect(self, browser)
+    }
+    fn get_view_rect(&self, browser: Browser) -> Rect {
+        RenderHandlerDecorator::get_view_rect(self, browser)
+    }
+    fn get_screen_point(&self, browser: Browser, view_point: Point) -> Option<Point> {
+        RenderHandlerDecorator::get_screen_point(self, browser, view_point)
+    }
+    fn get_screen_info(&self, browser: Browser) -> Option<ScreenInfo> {
+        RenderHandlerDecorator::get_screen_info(self, browser)
+    }
+    fn on_popup_show(&self, browser: Browser, show: bool) {
+        RenderHandlerDecorator::on_popup_show(self, browser, show)
+    }
+    fn on_popup_size(&self, browser: Browser, rect: Rect) {
+        RenderHandlerDecorator::on_popup_size(self, browser, rect)
+    }
+    fn on_paint(
+        &self,
+        browser: Browser,
+        type_: PaintElementType,
+        dirty_rects: &[Rect],
+        buffer: &[u8],
+        width: i32,
+        height: i32,
+    ) {
+        RenderHandlerDecorator::on_paint(self, browser, type_, dirty_rects, buffer, width, height)
+    }
+    fn on_accelerated_paint(
+        &self,
+        browser: Browser,
+        type_: PaintElementType,
+        dirty_rects: &[Rect],
+        shared_handle: *mut c_void,
+    ) {
+        RenderHandlerDecorator::on_accelerated_paint(self, browser, type_, dirty_rects, shared_handle)
+    }
+    fn on_cursor_change(&self, browser: Browser, cursor: CursorHandle, type_: CursorType<'_>) {
+        RenderHandlerDecorator::on_cursor_change(self, browser, cursor, type_)
+    }
+    fn start_dragging(
+        &self,
+        browser: Browser,
+        drag_data: DragData,
+        allowed_ops: DragOperation,
+        drag_start: Point,
+    ) -> bool {
+        RenderHandlerDecorator::start_dragging(self, browser, drag_data, allowed_ops, drag_start)
+    }
+    fn update_drag_cursor(&self, browser: Browser, operation: DragOperation) {
+        RenderHandlerDecorator::update_drag_cursor(self, browser, operation)
+    }
+    fn on_scroll_offset_changed(&self, browser: Browser, x: f64, y: f64) {
+        RenderHandlerDecorator::on_scroll_offset_changed(self, browser, x, y)
+    }
+    fn on_ime_composition_range_changed(
+        &self,
+        browser: Browser,
+        selected_range: Range,
+        character_bounds: &[Rect],
+    ) {
+        RenderHandlerDecorator::on_ime_composition_range_changed(self, browser, selected_range, character_bounds)
+    }
+    fn on_text_selection_changed(
+        &self,
+        browser: Browser,
+        selected_text: Option<&str>,
+        selected_range: Range,
+    ) {
+        RenderHandlerDecorator::on_text_selection_changed(self, browser, selected_text, selected_range)
+    }
+    fn on_virtual_keyboard_requested(&self, browser: Browser, input_mode: TextInputMode) {
+        RenderHandlerDecorator::on_virtual_keyboard_requested(self, browser, input_mode)
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct PopupFeatures {
     pub x: Option<i32>,
@@ -567,18 +786,8 @@ cef_callback_impl!{
             width: i32: c_int,
             height: i32: c_int,
         ) {
-            let dirty_rects = unsafe {
-                std::slice::from_raw_parts(
-                    dirty_rects as *const Rect,
-                    dirty_rects_count,
-                )
-            };
-            let buffer = unsafe {
-                std::slice::from_raw_parts(
-                    buffer as *const u8,
-                    width as usize * height as usize * 4
-                )
-            };
+            let dirty_rects = unsafe { rect_slice(dirty_rects, dirty_rects_count) };
+            let buffer = unsafe { paint_buffer(buffer, width, height) };
             self.0.on_paint(browser, type_, dirty_rects, buffer, width, height);
         }
         fn on_accelerated_paint(
@@ -589,12 +798,7 @@ cef_callback_impl!{
             dirty_rects: *const cef_rect_t: *const cef_rect_t,
             shared_handle: *mut c_void: *mut c_void,
         ) {
-            let dirty_rects = unsafe {
-                std::slice::from_raw_parts(
-                    dirty_rects as *const Rect,
-                    dirty_rects_count,
-                )
-            };
+            let dirty_rects = unsafe { rect_slice(dirty_rects, dirty_rects_count) };
             self.0.on_accelerated_paint(browser, type_, dirty_rects, shared_handle);
         }
         fn on_cursor_change(
@@ -637,9 +841,10 @@ cef_callback_impl!{
             browser: Browser: *mut cef_browser_t,
             selected_range: &Range: *const cef_range_t,
             character_bounds_count: usize: usize,
-            character_bounds: &Rect: *const cef_rect_t,
+            character_bounds: *const cef_rect_t: *const cef_rect_t,
         ) {
-            self.0.on_ime_composition_range_changed(browser, *selected_range, character_bounds_count, *character_bounds);
+            let character_bounds = unsafe { rect_slice(character_bounds, character_bounds_count) };
+            self.0.on_ime_composition_range_changed(browser, *selected_range, character_bounds);
         }
         fn on_text_selection_changed(
             &self,
@@ -665,3 +870,73 @@ cef_callback_impl!{
         }
     }
 }
+
+unsafe fn rect_slice<'a>(rects: *const cef_rect_t, count: usize) -> &'a [Rect] {
+    if rects.is_null() || count == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(rects as *const Rect, count)
+    }
+}
+
+/// Returns the BGRA pixels of a `width` x `height` paint. A view resized to
+/// zero width or height is painted without a buffer.
+unsafe fn paint_buffer<'a>(buffer: *const c_void, width: i32, height: i32) -> &'a [u8] {
+    if buffer.is_null() || width <= 0 || height <= 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(buffer as *const u8, width as usize * height as usize * 4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rect_slice_of_null_is_empty() {
+        assert!(unsafe { rect_slice(ptr::null(), 3) }.is_empty());
+    }
+
+    #[test]
+    fn rect_slice_of_zero_rects_is_empty() {
+        let rect = cef_rect_t { x: 1, y: 2, width: 3, height: 4 };
+        assert!(unsafe { rect_slice(&rect, 0) }.is_empty());
+    }
+
+    #[test]
+    fn rect_slice_converts_all_rects() {
+        let rects = [
+            cef_rect_t { x: 1, y: 2, width: 3, height: 4 },
+            cef_rect_t { x: 5, y: 6, width: 7, height: 8 },
+        ];
+        let slice = unsafe { rect_slice(rects.as_ptr(), rects.len()) };
+        assert_eq!(slice, &[
+            Rect { x: 1, y: 2, width: 3, height: 4 },
+            Rect { x: 5, y: 6, width: 7, height: 8 },
+        ]);
+    }
+
+    #[test]
+    fn paint_without_buffer_is_empty() {
+        assert!(unsafe { paint_buffer(ptr::null(), 10, 10) }.is_empty());
+    }
+
+    #[test]
+    fn paint_of_empty_view_is_empty() {
+        let pixels = [0u8; 16];
+        let buffer = pixels.as_ptr() as *const c_void;
+        assert!(unsafe { paint_buffer(buffer, 0, 2) }.is_empty());
+        assert!(unsafe { paint_buffer(buffer, 2, 0) }.is_empty());
+        assert!(unsafe { paint_buffer(buffer, -1, 2) }.is_empty());
+    }
+
+    #[test]
+    fn paint_buffer_follows_the_painted_size() {
+        // After a resize, paints of the old and the new size may arrive.
+        let pixels = [0u8; 6 * 4 * 4];
+        let buffer = pixels.as_ptr() as *const c_void;
+        assert_eq!(unsafe { paint_buffer(buffer, 6, 4) }.len(), 6 * 4 * 4);
+        assert_eq!(unsafe { paint_buffer(buffer, 3, 2) }.len(), 3 * 2 * 4);
+    }
+}
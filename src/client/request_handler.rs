@@ -78,7 +78,7 @@ impl RequestHandler {
 pub trait RequestHandlerCallbacks: Sync + Send + 'static {
     /// Called on the UI thread before browser navigation. Return true to
     /// cancel the navigation or false (0) to allow the navigation to proceed.
-    /// The `request` object canoot be modified in this callback.
+    /// The `request` object cannot be modified in this callback.
     /// [LoadHandlerCallbacks::on_loading_state_change] will be called twice in all cases.
     /// If the navigation is allowed [LoadHandlerCallbacks::on_load_start] and
     /// [LoadHandlerCallbacks::on_load_end] will be called. If the navigation is canceled
@@ -189,8 +189,9 @@ pub trait RequestHandlerCallbacks: Sync + Send + 'static {
     /// certificate. Return true and call [RequestCallback::cont] either
     /// in this function or at a later time to continue or cancel the request.
     /// Return false to cancel the request immediately. If
-    /// [CefSettings::ignore_certificate_errors] is set all invalid certificates will
-    /// be accepted without calling this function.
+    /// [RequestContextBuilder::ignore_certificate_errors](crate::request_context::RequestContextBuilder::ignore_certificate_errors)
+    /// is set all invalid certificates will be accepted without calling this
+    /// function.
     fn on_certificate_error(
         &self,
         browser: Browser,
@@ -372,7 +373,11 @@ cef_callback_impl!{
             certificates: *const *mut cef_x509certificate_t: *const *mut cef_x509certificate_t,
             callback: SelectClientCertificateCallback: *mut cef_select_client_certificate_callback_t
         ) -> c_int {
-            let certificates = unsafe{ std::slice::from_raw_parts(certificates as *const X509Certificate, certificates_count) };
+            let certificates = if certificates.is_null() || certificates_count == 0 {
+                &[][..]
+            } else {
+                unsafe{ std::slice::from_raw_parts(certificates as *const X509Certificate, certificates_count) }
+            };
             self.0.on_select_client_certificate(browser, is_proxy, &host, port as _, certificates, callback) as c_int
         }
         fn on_plugin_crashed(
@@ -416,7 +421,7 @@ impl SelectClientCertificateCallback {
         unsafe {
             self.0.select.unwrap()(
                 self.0.as_ptr(),
-                cert.map(|cert| cert.as_ptr()).unwrap_or_else(null_mut),
+                cert.map(|cert| cert.into_raw()).unwrap_or_else(null_mut),
             );
         }
     }
@@ -0,0 +1,416 @@
+//! Extension-style content scripts without loading an extension.
+//!
+//! A [ContentScript] bundles scripts and style sheets with the match patterns
+//! of the pages they apply to, like the "content_scripts" key of an extension
+//! manifest. [BrowserHost::add_content_script] registers it as a user script
+//! that checks the URL of every new document and injects the payloads at the
+//! requested time.
+//!
+//! Unlike the scripts of real extensions, content scripts run in the page's
+//! own JavaScript world, so they share globals with the page and have no
+//! access to extension APIs.
+
+use crate::{
+    browser_host::BrowserHost,
+    devtools::{page::{ScriptInjectionTime, UserScriptId}, DevToolsError},
+    extension::{ContentScriptRunAt, ManifestContentScript},
+    frame::insert_css_script,
+    string::quote_string,
+};
+use std::{fs, io, path::Path};
+
+/// Schemes matched by a `*` scheme.
+const WILDCARD_SCHEMES: &[&str] = &["http", "https"];
+/// Schemes matched by `<all_urls>`.
+const ALL_URLS_SCHEMES: &[&str] = &["http", "https", "ws", "wss", "ftp", "file"];
+
+/// A match pattern like `https://*.example.com/*`, see
+/// https://developer.chrome.com/extensions/match_patterns for details.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchPattern {
+    pattern: String,
+    /// Empty for `<all_urls>`.
+    schemes: Vec<String>,
+    /// `None` if any host matches.
+    host: Option<String>,
+    match_subdomains: bool,
+    path: String,
+}
+
+impl MatchPattern {
+    /// Parse `pattern`. Returns `None` if it's not a valid match pattern.
+    pub fn parse(pattern: &str) -> Option<MatchPattern> {
+        if pattern == "<all_urls>" {
+            return Some(MatchPattern {
+                pattern: pattern.to_owned(),
+                schemes: ALL_URLS_SCHEMES.iter().map(|&s| s.to_owned()).collect(),
+                host: None,
+                match_subdomains: false,
+                path: "/*".to_owned(),
+            });
+        }
+        let separator = pattern.find("://")?;
+        let scheme = pattern[..separator].to_lowercase();
+        let schemes = match &*scheme {
+            "*" => WILDCARD_SCHEMES.iter().map(|&s| s.to_owned()).collect(),
+            scheme if ALL_URLS_SCHEMES.contains(&scheme) => vec![scheme.to_owned()],
+            _ => return None,
+        };
+        let rest = &pattern[separator + 3..];
+        let path_start = rest.find('/')?;
+        let host = rest[..path_start].to_lowercase();
+        let path = rest[path_start..].to_owned();
+        let (host, match_subdomains) = if host == "*" {
+            (None, false)
+        } else if host.starts_with("*.") {
+            (Some(host[2..].to_owned()), true)
+        } else if host.contains('*') || (host.is_empty() && scheme != "file") {
+            return None;
+        } else {
+            (Some(host), false)
+        };
+        Some(MatchPattern {
+            pattern: pattern.to_owned(),
+            schemes,
+            host,
+            match_subdomains,
+            path,
+        })
+    }
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+    /// Returns true if `url` matches this pattern. The fragment of `url` is
+    /// ignored.
+    pub fn matches(&self, url: &str) -> bool {
+        let url = url.split('#').next().unwrap_or(url);
+        let separator = match url.find("://") {
+            Some(separator) => separator,
+            None => return false,
+        };
+        if !self.schemes.iter().any(|scheme| scheme.eq_ignore_ascii_case(&url[..separator])) {
+            return false;
+        }
+        let rest = &url[separator + 3..];
+        let path_start = rest.find('/').unwrap_or(rest.len());
+        let authority = &rest[..path_start];
+        let host_and_port = authority.rsplit('@').next().unwrap_or(authority);
+        let host = match host_and_port.rfind(':') {
+            Some(i) if !host_and_port[i..].contains(']') => &host_and_port[..i],
+            _ => host_and_port,
+        }
+        .to_lowercase();
+        let host_matches = match &self.host {
+            None => true,
+            Some(pattern_host) if self.match_subdomains => {
+                host == *pattern_host || host.ends_with(&format!(".{}", pattern_host))
+            }
+            Some(pattern_host) => host == *pattern_host,
+        };
+        let path = if path_start < rest.len() { &rest[path_start..] } else { "/" };
+        host_matches && glob_matches(&self.path, path)
+    }
+    /// Returns a JavaScript regular expression source matching the same URLs
+    /// as [MatchPattern::matches], without fragment.
+    fn to_regex(&self) -> String {
+        let host = match &self.host {
+            None => "[^/]*".to_owned(),
+            Some(host) if self.match_subdomains => format!("([^/@]*@)?([^/:]*\\.)?{}(:\\d+)?", escape_regex(host)),
+            Some(host) => format!("([^/@]*@)?{}(:\\d+)?", escape_regex(host)),
+        };
+        let path = self
+            .path
+            .split('*')
+            .map(escape_regex)
+            .collect::<Vec<_>>()
+            .join(".*");
+        format!("^({})://{}{}$", self.schemes.join("|"), host, path)
+    }
+}
+
+/// Matches `text` against `pattern`, in which `*` matches any sequence of
+/// characters.
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    if !text.starts_with(first) {
+        return false;
+    }
+    let mut rest = &text[first.len()..];
+    let parts = parts.collect::<Vec<_>>();
+    let last = match parts.split_last() {
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(i) => rest = &rest[i + part.len()..],
+                    None => return false,
+                }
+            }
+            last
+        }
+        None => return rest.is_empty(),
+    };
+    rest.ends_with(last)
+}
+
+fn escape_regex(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if "\\^$.|?*+()[]{}/".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Scripts and style sheets injected into the pages matching a set of
+/// patterns.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentScript {
+    matches: Vec<MatchPattern>,
+    exclude_matches: Vec<MatchPattern>,
+    js: Vec<String>,
+    css: Vec<String>,
+    run_at: ContentScriptRunAt,
+    all_frames: bool,
+}
+
+impl ContentScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Load the content script of an extension manifest, reading the files
+    /// from `extension_dir`. Invalid match patterns are skipped.
+    pub fn from_manifest(script: &ManifestContentScript, extension_dir: &Path) -> io::Result<Self> {
+        let patterns = |patterns: &[String]| {
+            patterns
+                .iter()
+                .filter_map(|pattern| {
+                    let parsed = MatchPattern::parse(pattern);
+                    if parsed.is_none() {
+                        log::warn!("Skipping invalid match pattern {}", pattern);
+                    }
+                    parsed
+                })
+                .collect()
+        };
+        let read = |paths: &[String]| {
+            paths
+                .iter()
+                .map(|path| fs::read_to_string(extension_dir.join(path)))
+                .collect::<io::Result<Vec<_>>>()
+        };
+        Ok(ContentScript {
+            matches: patterns(&script.matches),
+            exclude_matches: patterns(&script.exclude_matches),
+            js: read(&script.js)?,
+            css: read(&script.css)?,
+            run_at: script.run_at,
+            all_frames: script.all_frames,
+        })
+    }
+    /// Inject into pages matching `pattern`.
+    pub fn matches(mut self, pattern: MatchPattern) -> Self {
+        self.matches.push(pattern);
+        self
+    }
+    /// Don't inject into pages matching `pattern`, even if they match one of
+    /// the patterns passed to [ContentScript::matches].
+    pub fn exclude_matches(mut self, pattern: MatchPattern) -> Self {
+        self.exclude_matches.push(pattern);
+        self
+    }
+    /// Add a script. Scripts run in the order they were added, each in its own
+    /// function scope.
+    pub fn js(mut self, code: &str) -> Self {
+        self.js.push(code.to_owned());
+        self
+    }
+    /// Add a style sheet. Style sheets are always inserted at document start.
+    pub fn css(mut self, css: &str) -> Self {
+        self.css.push(css.to_owned());
+        self
+    }
+    pub fn run_at(mut self, run_at: ContentScriptRunAt) -> Self {
+        self.run_at = run_at;
+        self
+    }
+    /// Inject into child frames too, not only into the main frame.
+    pub fn all_frames(mut self, all_frames: bool) -> Self {
+        self.all_frames = all_frames;
+        self
+    }
+    /// Returns true if this script applies to a document loaded from `url`.
+    pub fn applies_to(&self, url: &str) -> bool {
+        self.matches.iter().any(|pattern| pattern.matches(url))
+            && !self.exclude_matches.iter().any(|pattern| pattern.matches(url))
+    }
+
+    fn script(&self) -> String {
+        let regexes = |patterns: &[MatchPattern]| {
+            patterns
+                .iter()
+                .map(|pattern| format!("new RegExp({})", quote_string(&pattern.to_regex())))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let css = self
+            .css
+            .iter()
+            .map(|css| insert_css_script(css, None))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let js = self
+            .js
+            .iter()
+            .map(|code| format!("try {{ (function() {{\n{}\n}})(); }} catch (e) {{ console.error(e); }}", code))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let run = match self.run_at {
+            ContentScriptRunAt::DocumentStart => "run();",
+            ContentScriptRunAt::DocumentEnd => {
+                "if (document.readyState === 'loading') {\n\
+                 document.addEventListener('DOMContentLoaded', run, { once: true });\n\
+                 } else {\n\
+                 run();\n\
+                 }"
+            }
+            // Like Chrome, run once the page had a chance to finish its own
+            // DOMContentLoaded work, at the latest on load.
+            ContentScriptRunAt::DocumentIdle => {
+                "var ran = false;\n\
+                 var idle = function() { if (!ran) { ran = true; run(); } };\n\
+                 if (document.readyState === 'complete') {\n\
+                 idle();\n\
+                 } else {\n\
+                 document.addEventListener('DOMContentLoaded', function() { setTimeout(idle, 0); }, { once: true });\n\
+                 window.addEventListener('load', idle, { once: true });\n\
+                 }"
+            }
+        };
+        format!(
+            "(function() {{\n\
+             var href = location.href.split('#')[0];\n\
+             var test = function(regex) {{ return regex.test(href); }};\n\
+             if (![{}].some(test) || [{}].some(test)) return;\n\
+             {}\
+             {}\n\
+             var run = function() {{\n{}\n}};\n\
+             {}\n\
+             }})();",
+            regexes(&self.matches),
+            regexes(&self.exclude_matches),
+            if self.all_frames { "" } else { "if (window !== window.top) return;\n" },
+            css,
+            js,
+            run,
+        )
+    }
+}
+
+impl BrowserHost {
+    /// Inject `script` into every matching document subsequently loaded in
+    /// this browser. Like [BrowserHost::add_user_script], it doesn't run in the
+    /// currently loaded documents, and the returned identifier can be passed
+    /// to [BrowserHost::remove_user_script].
+    ///
+    /// `callback` will be called on the browser process UI thread.
+    pub fn add_content_script(
+        &self,
+        script: &ContentScript,
+        callback: impl 'static + Send + FnOnce(Result<UserScriptId, DevToolsError>),
+    ) {
+        self.add_user_script(&script.script(), ScriptInjectionTime::DocumentStart, callback);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(pattern: &str) -> MatchPattern {
+        MatchPattern::parse(pattern).unwrap()
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        for invalid in &[
+            "example.com/*",
+            "https://example.com",
+            "chrome://settings/*",
+            "https://www.*.com/*",
+            "https://ex*ample.com/*",
+            "https:///*",
+        ] {
+            assert_eq!(MatchPattern::parse(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn file_patterns_have_no_host() {
+        assert!(pattern("file:///home/*").matches("file:///home/user/index.html"));
+        assert!(!pattern("file:///home/*").matches("file:///etc/passwd"));
+    }
+
+    #[test]
+    fn wildcard_scheme_matches_http_and_https_only() {
+        let pattern = pattern("*://example.com/*");
+        assert!(pattern.matches("http://example.com/"));
+        assert!(pattern.matches("HTTPS://example.com/page"));
+        assert!(!pattern.matches("ftp://example.com/"));
+        assert!(!pattern.matches("file:///example.com/"));
+    }
+
+    #[test]
+    fn subdomain_patterns_match_the_domain_itself() {
+        let pattern = pattern("https://*.example.com/*");
+        assert!(pattern.matches("https://example.com/"));
+        assert!(pattern.matches("https://a.b.example.com/x"));
+        assert!(!pattern.matches("https://badexample.com/"));
+        assert!(!pattern.matches("https://example.com.evil.org/"));
+    }
+
+    #[test]
+    fn credentials_port_and_fragment_are_ignored() {
+        let pattern = pattern("https://example.com/app/*");
+        assert!(pattern.matches("https://user:pw@example.com:8443/app/index.html#top"));
+        assert!(!pattern.matches("https://example.com@evil.org/app/"));
+        assert!(!pattern.matches("https://example.com/other#/app/"));
+    }
+
+    #[test]
+    fn urls_without_path_match_the_root() {
+        assert!(pattern("https://example.com/*").matches("https://example.com"));
+        assert!(pattern("https://example.com/").matches("https://example.com"));
+        assert!(!pattern("https://example.com/a").matches("https://example.com"));
+    }
+
+    #[test]
+    fn all_urls_matches_web_and_file_schemes() {
+        let pattern = pattern("<all_urls>");
+        assert!(pattern.matches("wss://example.com/socket"));
+        assert!(pattern.matches("file:///tmp/a.html"));
+        assert!(!pattern.matches("chrome://settings/"));
+        assert!(!pattern.matches("about:blank"));
+    }
+
+    #[test]
+    fn glob_matches_wildcards_anywhere() {
+        assert!(glob_matches("/*", "/"));
+        assert!(glob_matches("/a*c", "/abbc"));
+        assert!(glob_matches("/a*b*c", "/abc"));
+        assert!(!glob_matches("/a*b*c", "/acb"));
+        assert!(!glob_matches("/ab", "/abc"));
+        assert!(!glob_matches("/a*a", "/a"));
+    }
+
+    #[test]
+    fn regex_escapes_the_host_and_path() {
+        assert_eq!(
+            pattern("https://*.example.com/a.b/*").to_regex(),
+            "^(https)://([^/@]*@)?([^/:]*\\.)?example\\.com(:\\d+)?\\/a\\.b\\/.*$"
+        );
+        assert_eq!(pattern("*://*/*").to_regex(), "^(http|https)://[^/]*\\/.*$");
+    }
+}
@@ -0,0 +1,296 @@
+//! Keeping the objects of different request contexts apart.
+//!
+//! Applications with several profiles create a [RequestContext] for each of
+//! them, and nothing in CEF stops code from using e.g. the cookie manager of
+//! one profile for a browser of another. Registering the contexts gives them a
+//! [ContextId], and a [ContextBound] value remembers the context it belongs to
+//! and checks the browsers it's used for in debug builds:
+//!
+//! ```rust,ignore
+//! let work = context_isolation::register(&work_context, "work");
+//! let cookies = ContextBound::new(work, work_context.get_cookie_manager(|| ()));
+//! // Panics in debug builds if `browser` doesn't use the work profile.
+//! cookies.get_for(&browser).visit_all_cookies(visitor);
+//! ```
+//!
+//! Contexts sharing their storage, see [RequestContext::new_shared], count as
+//! the same context. Registered contexts are kept alive until they are
+//! unregistered.
+//!
+//! The checks are opt-in: only values wrapped in a [ContextBound] and taken
+//! out with [ContextBound::get_for] or [ContextBound::try_get_for] are
+//! checked. Nothing stops other code from mixing the objects of different
+//! contexts.
+
+use crate::{browser::Browser, request_context::RequestContext};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::fmt;
+
+/// Identifier of a registered [RequestContext], unique for the lifetime of the
+/// process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ContextId(u64);
+
+/// A context registered with [register].
+#[derive(Clone)]
+pub struct RegisteredContext {
+    pub id: ContextId,
+    /// Name given by the application, e.g. the name of the profile.
+    pub name: String,
+    pub context: RequestContext,
+}
+
+/// Why a value can't be used with a browser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextCheckError {
+    /// The value's context was unregistered, so it can't be checked anymore.
+    Unregistered(ContextId),
+    /// The browser uses another request context than the value.
+    OtherContext(ContextId),
+}
+
+impl fmt::Display for ContextCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContextCheckError::Unregistered(id) => write!(f, "{:?} is no longer registered", id),
+            ContextCheckError::OtherContext(id) => write!(f, "the browser doesn't use {:?}", id),
+        }
+    }
+}
+
+impl std::error::Error for ContextCheckError {}
+
+/// The comparisons the registry needs, so it can be tested without CEF.
+trait Context: Clone {
+    fn is_same(&self, other: &Self) -> bool;
+    fn is_sharing_with(&self, other: &Self) -> bool;
+}
+
+impl Context for RequestContext {
+    fn is_same(&self, other: &Self) -> bool {
+        RequestContext::is_same(self, other.clone())
+    }
+    fn is_sharing_with(&self, other: &Self) -> bool {
+        RequestContext::is_sharing_with(self, other.clone())
+    }
+}
+
+struct Registry<C> {
+    next_id: u64,
+    contexts: Vec<(ContextId, String, C)>,
+}
+
+impl<C: Context> Registry<C> {
+    fn new() -> Self {
+        Registry {
+            next_id: 1,
+            contexts: Vec::new(),
+        }
+    }
+    fn register(&mut self, context: &C, name: &str) -> ContextId {
+        if let Some((id, ..)) = self.contexts.iter().find(|(_, _, registered)| registered.is_same(context)) {
+            return *id;
+        }
+        let id = ContextId(self.next_id);
+        self.next_id += 1;
+        self.contexts.push((id, name.to_owned(), context.clone()));
+        id
+    }
+    fn unregister(&mut self, id: ContextId) -> bool {
+        let len = self.contexts.len();
+        self.contexts.retain(|(registered, ..)| *registered != id);
+        self.contexts.len() != len
+    }
+    fn get(&self, id: ContextId) -> Option<&C> {
+        self.contexts
+            .iter()
+            .find(|(registered, ..)| *registered == id)
+            .map(|(_, _, context)| context)
+    }
+    fn context_id(&self, context: &C) -> Option<ContextId> {
+        self.contexts
+            .iter()
+            .find(|(_, _, registered)| registered.is_same(context))
+            .or_else(|| self.contexts.iter().find(|(_, _, registered)| registered.is_sharing_with(context)))
+            .map(|(id, ..)| *id)
+    }
+    fn check(&self, id: ContextId, context: &C) -> Result<(), ContextCheckError> {
+        match self.get(id) {
+            Some(registered) if registered.is_sharing_with(context) => Ok(()),
+            Some(_) => Err(ContextCheckError::OtherContext(id)),
+            None => Err(ContextCheckError::Unregistered(id)),
+        }
+    }
+}
+
+lazy_static! {
+    static ref REGISTRY: Mutex<Registry<RequestContext>> = Mutex::new(Registry::new());
+}
+
+/// Register `context` under `name` and return its id. If `context` is already
+/// registered, its existing id is returned and the name is left unchanged.
+pub fn register(context: &RequestContext, name: &str) -> ContextId {
+    REGISTRY.lock().register(context, name)
+}
+
+/// Forget the context with the given id. Returns false if it wasn't registered.
+pub fn unregister(id: ContextId) -> bool {
+    REGISTRY.lock().unregister(id)
+}
+
+/// Returns the registered contexts, in the order they were registered.
+pub fn live_contexts() -> Vec<RegisteredContext> {
+    REGISTRY
+        .lock()
+        .contexts
+        .iter()
+        .map(|(id, name, context)| RegisteredContext {
+            id: *id,
+            name: name.clone(),
+            context: context.clone(),
+        })
+        .collect()
+}
+
+/// Returns the registered context with the given id.
+pub fn get(id: ContextId) -> Option<RegisteredContext> {
+    live_contexts().into_iter().find(|registered| registered.id == id)
+}
+
+/// Returns the id of `context`, or of the registered context it shares its
+/// storage with. Returns `None` if neither is registered.
+pub fn context_id(context: &RequestContext) -> Option<ContextId> {
+    REGISTRY.lock().context_id(context)
+}
+
+/// Returns the id of the context `browser` uses, see [context_id].
+pub fn browser_context_id(browser: &Browser) -> Option<ContextId> {
+    context_id(&browser.get_host().get_request_context())
+}
+
+/// Check that `browser` uses the context with the given id, or one sharing
+/// its storage.
+pub fn check_browser(browser: &Browser, id: ContextId) -> Result<(), ContextCheckError> {
+    REGISTRY.lock().check(id, &browser.get_host().get_request_context())
+}
+
+/// Returns true if `browser` uses the context with the given id, or one
+/// sharing its storage. Returns false if the context isn't registered.
+pub fn is_browser_in(browser: &Browser, id: ContextId) -> bool {
+    check_browser(browser, id).is_ok()
+}
+
+/// Panics in debug builds if `a` and `b` don't share their storage.
+pub fn debug_assert_same_context(a: &RequestContext, b: &RequestContext) {
+    debug_assert!(a.is_sharing_with(b.clone()), "objects of different request contexts are mixed");
+}
+
+/// A value that belongs to the request context with the given id, like its
+/// cookie manager or a per-profile cache of the application.
+#[derive(Debug, Clone)]
+pub struct ContextBound<T> {
+    context_id: ContextId,
+    value: T,
+}
+
+impl<T> ContextBound<T> {
+    pub fn new(context_id: ContextId, value: T) -> Self {
+        ContextBound { context_id, value }
+    }
+    pub fn context_id(&self) -> ContextId {
+        self.context_id
+    }
+    /// Returns the value for use with `browser`. Panics in debug builds if
+    /// `browser` uses another context. Once the value's context was
+    /// unregistered it can't be checked, and the value is returned.
+    pub fn get_for(&self, browser: &Browser) -> &T {
+        if cfg!(debug_assertions) {
+            if let Err(ContextCheckError::OtherContext(id)) = check_browser(browser, self.context_id) {
+                panic!(
+                    "value of {:?} used for browser {} of another request context",
+                    id,
+                    browser.get_identifier(),
+                );
+            }
+        }
+        &self.value
+    }
+    /// Returns the value for use with `browser`, or why it can't be used.
+    pub fn try_get_for(&self, browser: &Browser) -> Result<&T, ContextCheckError> {
+        check_browser(browser, self.context_id)?;
+        Ok(&self.value)
+    }
+    /// Returns the value without checking which context it's used with.
+    pub fn get_unchecked(&self) -> &T {
+        &self.value
+    }
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A context with its own identity and the storage it uses.
+    #[derive(Debug, Clone)]
+    struct FakeContext {
+        id: u32,
+        storage: u32,
+    }
+
+    impl Context for FakeContext {
+        fn is_same(&self, other: &Self) -> bool {
+            self.id == other.id
+        }
+        fn is_sharing_with(&self, other: &Self) -> bool {
+            self.storage == other.storage
+        }
+    }
+
+    fn context(id: u32, storage: u32) -> FakeContext {
+        FakeContext { id, storage }
+    }
+
+    #[test]
+    fn registering_twice_returns_the_same_id() {
+        let mut registry = Registry::new();
+        let work = registry.register(&context(1, 1), "work");
+        let home = registry.register(&context(2, 2), "home");
+        assert_ne!(work, home);
+        assert_eq!(registry.register(&context(1, 1), "renamed"), work);
+        assert_eq!(registry.contexts[0].1, "work");
+    }
+
+    #[test]
+    fn shared_contexts_map_to_the_registered_one() {
+        let mut registry = Registry::new();
+        let work = registry.register(&context(1, 1), "work");
+        assert_eq!(registry.context_id(&context(1, 1)), Some(work));
+        assert_eq!(registry.context_id(&context(3, 1)), Some(work));
+        assert_eq!(registry.context_id(&context(4, 2)), None);
+    }
+
+    #[test]
+    fn check_tells_other_contexts_from_unregistered_ones() {
+        let mut registry = Registry::new();
+        let work = registry.register(&context(1, 1), "work");
+        registry.register(&context(2, 2), "home");
+        assert_eq!(registry.check(work, &context(1, 1)), Ok(()));
+        assert_eq!(registry.check(work, &context(3, 1)), Ok(()));
+        assert_eq!(registry.check(work, &context(2, 2)), Err(ContextCheckError::OtherContext(work)));
+        assert!(registry.unregister(work));
+        assert!(!registry.unregister(work));
+        assert_eq!(registry.check(work, &context(1, 1)), Err(ContextCheckError::Unregistered(work)));
+    }
+
+    #[test]
+    fn ids_are_not_reused() {
+        let mut registry = Registry::new();
+        let work = registry.register(&context(1, 1), "work");
+        registry.unregister(work);
+        assert_ne!(registry.register(&context(1, 1), "work"), work);
+    }
+}
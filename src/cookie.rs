@@ -7,7 +7,13 @@ use cef_sys::cef_cookie_visitor_t;
 use cef_sys::cef_set_cookie_callback_t;
 use cef_sys::cef_delete_cookies_callback_t;
 use cef_sys::{cef_cookie_t, cef_cookie_manager_t, cef_cookie_priority_t, cef_cookie_same_site_t};
-use chrono::{DateTime, Utc};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+    time::SystemTime,
+};
 
 use crate::{
     callback::CompletionCallback,
@@ -65,12 +71,12 @@ pub struct Cookie {
     pub httponly: bool,
     /// The cookie creation date. This is automatically populated by the system on
     /// cookie creation.
-    pub creation: DateTime<Utc>,
+    pub creation: SystemTime,
     /// The cookie last access date. This is automatically populated by the system
     /// on access.
-    pub last_access: DateTime<Utc>,
-    /// The cookie expiration date.
-    pub expires: Option<DateTime<Utc>>,
+    pub last_access: SystemTime,
+    /// The cookie expiration date, or `None` for a session cookie.
+    pub expires: Option<SystemTime>,
     /// Same site.
     pub same_site: CookieSameSite,
     /// Priority.
@@ -93,36 +99,51 @@ pub struct CookieVisit<'a> {
 pub trait CookieVisitorFn = 'static + Send + for<'a> FnMut(CookieVisit<'a>) -> bool;
 
 impl Cookie {
-    pub(crate) unsafe fn new(cookie: *const cef_cookie_t) -> Self {
-        let cookie = cookie.as_ref().unwrap();
-        let name = CefString::from_ptr(&cookie.name)
-            .map(String::from)
-            .unwrap_or_default();
-        let value = CefString::from_ptr(&cookie.value)
-            .map(String::from)
-            .unwrap_or_default();
-        let domain = CefString::from_ptr(&cookie.domain)
-            .map(String::from)
-            .unwrap_or_default();
-        let path = CefString::from_ptr(&cookie.path)
+    /// Create a session cookie that is sent to the host it was set for only,
+    /// with the default attributes. Set the public fields to change them.
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        let now = SystemTime::now();
+        Self {
+            name: name.into(),
+            value: value.into(),
+            domain: String::new(),
+            path: String::new(),
+            secure: false,
+            httponly: false,
+            creation: now,
+            last_access: now,
+            expires: None,
+            same_site: CookieSameSite::Unspecified,
+            priority: CookiePriority::Medium,
+        }
+    }
+    pub(crate) unsafe fn from_raw(cookie: *const cef_cookie_t) -> Self {
+        Self::from(cookie.as_ref().unwrap())
+    }
+}
+
+impl From<&'_ cef_cookie_t> for Cookie {
+    fn from(cookie: &cef_cookie_t) -> Cookie {
+        let string = |s: &cef_sys::cef_string_t| unsafe { CefString::from_ptr(s) }
             .map(String::from)
             .unwrap_or_default();
+        let time = |t: cef_sys::cef_time_t| crate::values::cef_time_to_system_time(t).unwrap_or(SystemTime::UNIX_EPOCH);
         Self {
-            name,
-            value,
-            domain,
-            path,
+            name: string(&cookie.name),
+            value: string(&cookie.value),
+            domain: string(&cookie.domain),
+            path: string(&cookie.path),
             secure: cookie.secure != 0,
             httponly: cookie.httponly != 0,
-            creation: crate::values::cef_time_to_date_time(cookie.creation),
-            last_access: crate::values::cef_time_to_date_time(cookie.last_access),
+            creation: time(cookie.creation),
+            last_access: time(cookie.last_access),
             expires: if cookie.has_expires != 0 {
-                Some(crate::values::cef_time_to_date_time(cookie.expires))
+                crate::values::cef_time_to_system_time(cookie.expires)
             } else {
                 None
             },
-            same_site: CookieSameSite::from_unchecked(cookie.same_site),
-            priority: CookiePriority::from_unchecked(cookie.priority),
+            same_site: unsafe { CookieSameSite::from_unchecked(cookie.same_site) },
+            priority: unsafe { CookiePriority::from_unchecked(cookie.priority) },
         }
     }
 }
@@ -136,10 +157,10 @@ impl From<&'_ Cookie> for cef_cookie_t {
             path: CefString::new(&cookie.path).into_raw(),
             secure: cookie.secure as c_int,
             httponly: cookie.httponly as c_int,
-            creation: crate::values::date_time_to_cef_time(cookie.creation),
-            last_access: crate::values::date_time_to_cef_time(cookie.last_access),
+            creation: crate::values::system_time_to_cef_time(cookie.creation),
+            last_access: crate::values::system_time_to_cef_time(cookie.last_access),
             has_expires: cookie.expires.is_some() as c_int,
-            expires: cookie.expires.map(crate::values::date_time_to_cef_time).unwrap_or(unsafe{ std::mem::zeroed() }),
+            expires: cookie.expires.map(crate::values::system_time_to_cef_time).unwrap_or(unsafe{ std::mem::zeroed() }),
             same_site: cookie.same_site as _,
             priority: cookie.priority as _,
         }
@@ -162,6 +183,44 @@ ref_counted_ptr!{
     struct DeleteCookiesCallback(*mut cef_delete_cookies_callback_t);
 }
 
+/// Schemes whose origins can store cookies, in addition to or instead of the
+/// default ones ("http", "https", "ws" and "wss"). Custom schemes need to be
+/// listed here for `document.cookie` and `Set-Cookie` to work on their pages,
+/// e.g. for login flows on `app://` origins.
+///
+/// Used by [Settings::cookieable_schemes](crate::settings::Settings::cookieable_schemes)
+/// and [RequestContextBuilder::cookieable_schemes](crate::request_context::RequestContextBuilder::cookieable_schemes).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CookieableSchemes {
+    /// Schemes to support, without the `:` or `://` suffix.
+    pub schemes: Vec<String>,
+    /// If true, cookies are only supported for `schemes`, not for the default
+    /// schemes. With an empty `schemes` this disables cookies entirely.
+    pub exclude_defaults: bool,
+}
+
+impl CookieableSchemes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn scheme<T: Into<String>>(mut self, scheme: T) -> Self {
+        self.schemes.push(scheme.into());
+        self
+    }
+    pub fn exclude_defaults(mut self, exclude_defaults: bool) -> Self {
+        self.exclude_defaults = exclude_defaults;
+        self
+    }
+    /// Apply these schemes to `manager`. See [CookieManager::set_supported_schemes].
+    pub fn apply(&self, manager: &CookieManager, on_completion: impl 'static + Send + FnOnce()) {
+        manager.set_supported_schemes(
+            self.schemes.iter().map(String::as_str),
+            !self.exclude_defaults,
+            on_completion,
+        );
+    }
+}
+
 impl CookieManager {
     /// Set the schemes supported by this manager. If `include_defaults` is `true`
     /// the default schemes ("http", "https", "ws" and "wss") will also be
@@ -282,6 +341,146 @@ impl CookieManager {
     }
 }
 
+impl CookieManager {
+    /// Read all cookies of this manager, e.g. to move a session to another
+    /// request context or to an HTTP client outside of CEF. The returned future
+    /// resolves to `None` if cookies cannot be accessed.
+    pub fn export_cookies(&self) -> CookieTransfer<Option<Vec<Cookie>>> {
+        let state = Arc::new(Mutex::new(TransferState::default()));
+        let mut export = CookieExport {
+            state: state.clone(),
+            cookies: Vec::new(),
+        };
+        let visiting = self.visit_all_cookies(move |visit| {
+            export.cookies.push(visit.cookie);
+            true
+        });
+        if !visiting {
+            state.lock().finish(None);
+        }
+        CookieTransfer(state)
+    }
+    /// Set all of `cookies`, e.g. from [CookieManager::export_cookies] of
+    /// another manager. Host cookies stay host cookies, and cookies keep their
+    /// dates. The returned future resolves to the number of cookies that were
+    /// set successfully.
+    pub fn import_cookies(&self, cookies: &[Cookie]) -> CookieTransfer<usize> {
+        let state = Arc::new(Mutex::new(TransferState::default()));
+        let import = Arc::new(Mutex::new(CookieImport {
+            state: state.clone(),
+            pending: cookies.len(),
+            imported: 0,
+        }));
+        if cookies.is_empty() {
+            state.lock().finish(0);
+        }
+        for cookie in cookies {
+            let (url, cookie) = cookie_for_import(cookie);
+            let import_cb = import.clone();
+            let set = self.set_cookie(&url, &cookie, move |success| {
+                import_cb.lock().complete(success);
+            });
+            if !set {
+                import.lock().complete(false);
+            }
+        }
+        CookieTransfer(state)
+    }
+}
+
+/// Returns a URL for `set_cookie` matching the cookie's domain, path and
+/// security, along with the cookie to set. CEF reports host cookies with the
+/// host as domain, and setting a domain would turn them into domain cookies.
+pub(crate) fn cookie_for_import(cookie: &Cookie) -> (String, Cookie) {
+    let mut cookie = cookie.clone();
+    let host = cookie.domain.trim_start_matches('.').to_owned();
+    if !cookie.domain.starts_with('.') {
+        cookie.domain.clear();
+    }
+    let path = if cookie.path.starts_with('/') { cookie.path.as_str() } else { "/" };
+    let scheme = if cookie.secure { "https" } else { "http" };
+    (format!("{}://{}{}", scheme, host, path), cookie)
+}
+
+struct TransferState<T> {
+    result: Option<T>,
+    wakers: Vec<Waker>,
+}
+
+impl<T> Default for TransferState<T> {
+    fn default() -> Self {
+        TransferState {
+            result: None,
+            wakers: Vec::new(),
+        }
+    }
+}
+
+impl<T> TransferState<T> {
+    fn finish(&mut self, result: T) {
+        self.result = Some(result);
+        for waker in self.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// Collects the visited cookies. CEF releases the visitor after the last
+/// cookie, or right away if there are none, which completes the export.
+struct CookieExport {
+    state: Arc<Mutex<TransferState<Option<Vec<Cookie>>>>>,
+    cookies: Vec<Cookie>,
+}
+
+impl Drop for CookieExport {
+    fn drop(&mut self) {
+        let mut state = self.state.lock();
+        if state.result.is_none() {
+            let cookies = std::mem::replace(&mut self.cookies, Vec::new());
+            state.finish(Some(cookies));
+        }
+    }
+}
+
+struct CookieImport {
+    state: Arc<Mutex<TransferState<usize>>>,
+    pending: usize,
+    imported: usize,
+}
+
+impl CookieImport {
+    fn complete(&mut self, success: bool) {
+        self.pending -= 1;
+        if success {
+            self.imported += 1;
+        }
+        if self.pending == 0 {
+            self.state.lock().finish(self.imported);
+        }
+    }
+}
+
+/// Future returned by [CookieManager::export_cookies] and
+/// [CookieManager::import_cookies]. It doesn't depend on any executor; it's
+/// woken from the browser process UI thread.
+pub struct CookieTransfer<T>(Arc<Mutex<TransferState<T>>>);
+
+impl<T> Future for CookieTransfer<T> {
+    type Output = T;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<T> {
+        let mut state = self.0.lock();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                    state.wakers.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
 impl CookieVisitor {
     pub fn new(f: impl CookieVisitorFn) -> CookieVisitor {
         unsafe{ CookieVisitor::from_ptr_unchecked(CookieVisitorWrapper(Mutex::new(Some(Box::new(f)))).wrap().into_raw()) }
@@ -312,9 +511,9 @@ cef_callback_impl!{
             total: c_int: c_int,
             delete_cookie: &mut c_int: *mut c_int,
         ) -> c_int {
-            let cookie = unsafe{ Cookie::new(cookie) };
+            let cookie = unsafe{ Cookie::from_raw(cookie) };
             let mut delete_cookie_rs = *delete_cookie != 0;
-            let ret = self.0.lock().take().unwrap()(CookieVisit {
+            let ret = self.0.lock().as_mut().unwrap()(CookieVisit {
                 cookie,
                 index: count as usize,
                 len: total as usize,
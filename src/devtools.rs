@@ -0,0 +1,233 @@
+//! Typed helpers built on top of the DevTools protocol.
+//!
+//! [BrowserHost::call_dev_tools_method] executes a single protocol method and
+//! hands the parsed result to a callback, which takes care of registering a
+//! [DevToolsMessageObserver] and matching the result to the originating call.
+//! The submodules use it to expose specific protocol domains.
+
+use crate::{
+    browser::Browser,
+    browser_host::BrowserHost,
+    devtools_message_observer::{DevToolsMessageObserver, DevToolsMessageObserverCallbacks},
+    registration::Registration,
+    task::{TaskRunner, ThreadId},
+    values::{DictionaryValue, StoredValue},
+};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+};
+
+pub mod emulation;
+pub mod memory;
+pub mod page;
+pub mod profiler;
+pub mod runtime;
+pub mod system_info;
+
+/// Error returned by DevTools protocol method calls.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DevToolsError {
+    /// The method call could not be submitted. This happens if the browser is
+    /// being closed or if the method name is malformed.
+    SubmitFailed,
+    /// The method was executed but returned an error.
+    Method {
+        code: i32,
+        message: String,
+    },
+    /// The DevTools agent detached before the result was delivered.
+    AgentDetached,
+    /// The result could not be parsed or did not have the expected shape.
+    InvalidResult,
+    /// The frame the method was targeted at isn't known to the DevTools agent,
+    /// e.g. because it was detached.
+    FrameNotFound,
+    /// Evaluated script threw an exception.
+    ScriptException {
+        message: String,
+    },
+}
+
+/// Callback receiving the `result` dictionary of a DevTools protocol method.
+pub trait DevToolsMethodCallback = 'static + Send + FnOnce(Result<HashMap<String, StoredValue>, DevToolsError>);
+
+impl BrowserHost {
+    /// Execute a DevTools protocol method and pass its `result` dictionary to
+    /// `callback` once it is received.
+    ///
+    /// Unlike [BrowserHost::execute_dev_tools_method] this function may be called
+    /// on any thread, and no [DevToolsMessageObserver] needs to be registered.
+    /// `callback` will be called on the browser process UI thread.
+    pub fn call_dev_tools_method(
+        &self,
+        method: &str,
+        params: Option<HashMap<String, StoredValue>>,
+        callback: impl DevToolsMethodCallback,
+    ) {
+        if !TaskRunner::currently_on(ThreadId::UI) {
+            let host = self.clone();
+            let method = method.to_owned();
+            TaskRunner::post_task_on(ThreadId::UI, move || host.call_dev_tools_method(&method, params, callback));
+            return;
+        }
+
+        let call = Arc::new(Mutex::new(PendingCall {
+            message_id: None,
+            callback: Some(Box::new(callback)),
+            registration: None,
+        }));
+        let registration = self.add_dev_tools_message_observer(
+            DevToolsMessageObserver::new(PendingCallObserver(call.clone()))
+        );
+        let message_id = self.execute_dev_tools_method(
+            0,
+            method,
+            params.as_ref().map(DictionaryValue::from),
+        );
+
+        let mut call = call.lock();
+        if message_id == 0 {
+            if let Some(callback) = call.callback.take() {
+                callback(Err(DevToolsError::SubmitFailed));
+            }
+        } else {
+            call.message_id = Some(message_id);
+            call.registration = Some(registration);
+        }
+    }
+
+    /// Same as [BrowserHost::call_dev_tools_method], but discards the result
+    /// and only reports whether the method succeeded.
+    pub(crate) fn call_dev_tools_method_unit(
+        &self,
+        method: &str,
+        params: Option<HashMap<String, StoredValue>>,
+        callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+    ) {
+        self.call_dev_tools_method(method, params, move |result| callback(result.map(|_| ())));
+    }
+}
+
+struct PendingCall {
+    message_id: Option<i32>,
+    callback: Option<Box<dyn Send + FnOnce(Result<HashMap<String, StoredValue>, DevToolsError>)>>,
+    registration: Option<Registration>,
+}
+
+impl PendingCall {
+    fn complete(&mut self, result: Result<HashMap<String, StoredValue>, DevToolsError>) {
+        if let Some(callback) = self.callback.take() {
+            callback(result);
+        }
+        // The observer can't be removed from within one of its own callbacks.
+        if let Some(registration) = self.registration.take() {
+            TaskRunner::post_task_on(ThreadId::UI, move || drop(registration));
+        }
+    }
+}
+
+struct PendingCallObserver(Arc<Mutex<PendingCall>>);
+
+impl DevToolsMessageObserverCallbacks for PendingCallObserver {
+    fn on_dev_tools_method_result(
+        &mut self,
+        browser: Browser,
+        message_id: i32,
+        success: bool,
+        result: &[u8],
+    ) {
+        let mut call = self.0.lock();
+        if call.message_id != Some(message_id) {
+            return;
+        }
+        let result = parse_json_dictionary(result);
+        call.complete(match (success, result) {
+            (true, Some(result)) => Ok(result),
+            (true, None) => Err(DevToolsError::InvalidResult),
+            (false, error) => {
+                let error = error.unwrap_or_default();
+                Err(DevToolsError::Method {
+                    code: get_i32(&error, "code"),
+                    message: get_string(&error, "message"),
+                })
+            }
+        });
+    }
+    fn on_dev_tools_agent_detached(&mut self, browser: Browser) {
+        let mut call = self.0.lock();
+        if call.message_id.is_some() {
+            call.complete(Err(DevToolsError::AgentDetached));
+        }
+    }
+}
+
+/// Parses a UTF8-encoded JSON dictionary as delivered to
+/// [DevToolsMessageObserverCallbacks]. An empty message is an empty dictionary.
+pub(crate) fn parse_json_dictionary(json: &[u8]) -> Option<HashMap<String, StoredValue>> {
+    if json.is_empty() {
+        return Some(HashMap::new());
+    }
+    match StoredValue::from_json(std::str::from_utf8(json).ok()?)? {
+        StoredValue::Dictionary(dictionary) => Some(dictionary.into()),
+        _ => None,
+    }
+}
+
+pub(crate) fn get_string(map: &HashMap<String, StoredValue>, key: &str) -> String {
+    match map.get(key) {
+        Some(StoredValue::String(s)) => s.clone(),
+        _ => String::new(),
+    }
+}
+
+pub(crate) fn get_bool(map: &HashMap<String, StoredValue>, key: &str) -> bool {
+    match map.get(key) {
+        Some(StoredValue::Bool(b)) => *b,
+        _ => false,
+    }
+}
+
+/// JSON numbers are parsed as doubles if they don't fit into an `i32`, so
+/// accept both.
+pub(crate) fn get_i32(map: &HashMap<String, StoredValue>, key: &str) -> i32 {
+    match map.get(key) {
+        Some(StoredValue::Int(i)) => *i,
+        Some(StoredValue::Double(d)) => *d as i32,
+        _ => 0,
+    }
+}
+
+pub(crate) fn get_f64(map: &HashMap<String, StoredValue>, key: &str) -> f64 {
+    match map.get(key) {
+        Some(StoredValue::Int(i)) => *i as f64,
+        Some(StoredValue::Double(d)) => *d,
+        _ => 0.0,
+    }
+}
+
+pub(crate) fn get_dictionary(map: &HashMap<String, StoredValue>, key: &str) -> Option<HashMap<String, StoredValue>> {
+    match map.get(key) {
+        Some(StoredValue::Dictionary(d)) => Some(d.into()),
+        _ => None,
+    }
+}
+
+pub(crate) fn get_list(map: &HashMap<String, StoredValue>, key: &str) -> Vec<StoredValue> {
+    match map.get(key) {
+        Some(StoredValue::List(l)) => l.clone().into(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the dictionaries contained in the list stored under `key`.
+pub(crate) fn get_dictionary_list(map: &HashMap<String, StoredValue>, key: &str) -> Vec<HashMap<String, StoredValue>> {
+    get_list(map, key)
+        .into_iter()
+        .filter_map(|value| match value {
+            StoredValue::Dictionary(d) => Some(d.into()),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,235 @@
+//! Helpers built on the DevTools `Emulation` domain. See
+//! https://chromedevtools.github.io/devtools-protocol/tot/Emulation/ for
+//! details.
+
+use super::DevToolsError;
+use crate::{
+    browser_host::BrowserHost,
+    values::{DictionaryValue, ListValue, StoredValue},
+};
+use std::collections::HashMap;
+
+/// Value reported for the `prefers-color-scheme` media feature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+impl ColorScheme {
+    fn as_str(self) -> &'static str {
+        match self {
+            ColorScheme::Light => "light",
+            ColorScheme::Dark => "dark",
+        }
+    }
+}
+
+/// Screen and input properties of a device, for [BrowserHost::emulate_device].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DevicePreset {
+    /// Viewport width in CSS pixels.
+    pub width: u32,
+    /// Viewport height in CSS pixels.
+    pub height: u32,
+    /// Device pixel ratio reported to pages.
+    pub device_scale_factor: f64,
+    /// Emulate a mobile device: the viewport meta tag is honored, overlay
+    /// scrollbars are used and text is autosized.
+    pub mobile: bool,
+    /// Number of touch points, or 0 to disable touch emulation. With touch
+    /// emulation, mouse input is delivered to pages as touch events.
+    pub max_touch_points: u32,
+    /// User agent string to send, or `None` to keep the browser's.
+    pub user_agent: Option<String>,
+}
+
+impl DevicePreset {
+    /// iPhone 11 Pro in portrait orientation.
+    pub fn iphone() -> DevicePreset {
+        DevicePreset {
+            width: 375,
+            height: 812,
+            device_scale_factor: 3.0,
+            mobile: true,
+            max_touch_points: 5,
+            user_agent: Some(
+                "Mozilla/5.0 (iPhone; CPU iPhone OS 13_5 like Mac OS X) AppleWebKit/605.1.15 \
+                 (KHTML, like Gecko) Version/13.1.1 Mobile/15E148 Safari/604.1"
+                    .to_owned(),
+            ),
+        }
+    }
+    /// iPad (7th generation) in portrait orientation.
+    pub fn ipad() -> DevicePreset {
+        DevicePreset {
+            width: 810,
+            height: 1080,
+            device_scale_factor: 2.0,
+            mobile: true,
+            max_touch_points: 5,
+            user_agent: Some(
+                "Mozilla/5.0 (iPad; CPU OS 13_5 like Mac OS X) AppleWebKit/605.1.15 \
+                 (KHTML, like Gecko) Version/13.1.1 Mobile/15E148 Safari/604.1"
+                    .to_owned(),
+            ),
+        }
+    }
+    /// Full HD landscape touch screen, as used by kiosks.
+    pub fn kiosk_1080p() -> DevicePreset {
+        DevicePreset {
+            width: 1920,
+            height: 1080,
+            device_scale_factor: 1.0,
+            mobile: false,
+            max_touch_points: 10,
+            user_agent: None,
+        }
+    }
+    /// Returns the preset rotated by 90 degrees.
+    pub fn landscape(mut self) -> DevicePreset {
+        std::mem::swap(&mut self.width, &mut self.height);
+        self
+    }
+}
+
+/// Call the given methods one after another, stopping at the first error.
+fn call_in_sequence(
+    host: BrowserHost,
+    mut calls: Vec<(&'static str, HashMap<String, StoredValue>)>,
+    callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+) {
+    if calls.is_empty() {
+        return callback(Ok(()));
+    }
+    let (method, params) = calls.remove(0);
+    let next = host.clone();
+    host.call_dev_tools_method_unit(method, Some(params), move |result| match result {
+        Ok(()) => call_in_sequence(next, calls, callback),
+        Err(e) => callback(Err(e)),
+    });
+}
+
+impl BrowserHost {
+    /// Force the `prefers-color-scheme` media feature for the pages in this
+    /// browser, or pass `None` to follow the system setting again. Pages with
+    /// `(prefers-color-scheme)` media queries or `matchMedia` listeners are
+    /// updated immediately, without a reload.
+    ///
+    /// The override lasts until the browser is closed and applies to every
+    /// document it loads. To change the default for all browsers at startup,
+    /// pass the `force-dark-mode` switch on the command line instead.
+    ///
+    /// `callback` will be called on the browser process UI thread.
+    pub fn set_preferred_color_scheme(
+        &self,
+        scheme: Option<ColorScheme>,
+        callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+    ) {
+        // An empty value removes the override.
+        let value = scheme.map(ColorScheme::as_str).unwrap_or("");
+        let mut feature = HashMap::new();
+        feature.insert("name".to_owned(), StoredValue::String("prefers-color-scheme".to_owned()));
+        feature.insert("value".to_owned(), StoredValue::String(value.to_owned()));
+        let features = ListValue::new();
+        features.set_dictionary(0, DictionaryValue::from(&feature));
+        let mut params = HashMap::new();
+        params.insert("features".to_owned(), StoredValue::List(features));
+        self.call_dev_tools_method_unit("Emulation.setEmulatedMedia", Some(params), callback);
+    }
+    /// Make pages in this browser use the IANA time zone `timezone_id` (e.g.
+    /// "Europe/Berlin") instead of the system time zone, or pass `None` to
+    /// follow the system again. This affects `Date` and `Intl` in documents
+    /// loaded in this browser, including the current ones.
+    ///
+    /// `callback` will be called on the browser process UI thread. It receives
+    /// an error if the time zone id isn't known.
+    pub fn set_timezone_override(
+        &self,
+        timezone_id: Option<&str>,
+        callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+    ) {
+        // An empty id disables the override.
+        let mut params = HashMap::new();
+        params.insert("timezoneId".to_owned(), StoredValue::String(timezone_id.unwrap_or("").to_owned()));
+        self.call_dev_tools_method_unit("Emulation.setTimezoneOverride", Some(params), callback);
+    }
+    /// Make pages in this browser use the ICU locale `locale` (e.g. "de_DE")
+    /// for `Intl` formatting and `toLocaleString`, or pass `None` to use the
+    /// application's locale again. This doesn't change `navigator.language` or
+    /// the `Accept-Language` header; see
+    /// [RequestContext::set_accept_language_list](crate::request_context::RequestContext::set_accept_language_list)
+    /// for those.
+    ///
+    /// `callback` will be called on the browser process UI thread.
+    pub fn set_locale_override(
+        &self,
+        locale: Option<&str>,
+        callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+    ) {
+        // Omitting the locale disables the override.
+        let params = locale.map(|locale| {
+            let mut params = HashMap::new();
+            params.insert("locale".to_owned(), StoredValue::String(locale.to_owned()));
+            params
+        });
+        self.call_dev_tools_method_unit("Emulation.setLocaleOverride", params, callback);
+    }
+    /// Make pages in this browser see the screen size, pixel ratio, touch
+    /// support and user agent of `device`. The viewport is resized to the
+    /// device's and scaled into the view if it doesn't fit. Lasts until
+    /// [BrowserHost::clear_device_emulation] is called or the browser is
+    /// closed. Pages have to be reloaded to pick up the user agent.
+    ///
+    /// `callback` will be called on the browser process UI thread.
+    pub fn emulate_device(
+        &self,
+        device: &DevicePreset,
+        callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+    ) {
+        let mut metrics = HashMap::new();
+        metrics.insert("width".to_owned(), StoredValue::Int(device.width as i32));
+        metrics.insert("height".to_owned(), StoredValue::Int(device.height as i32));
+        metrics.insert("deviceScaleFactor".to_owned(), StoredValue::Double(device.device_scale_factor));
+        metrics.insert("mobile".to_owned(), StoredValue::Bool(device.mobile));
+        let mut calls = vec![("Emulation.setDeviceMetricsOverride", metrics)];
+        calls.extend(touch_emulation_calls(device.max_touch_points));
+        calls.push(user_agent_call(device.user_agent.as_deref().unwrap_or("")));
+        call_in_sequence(self.clone(), calls, callback);
+    }
+    /// Stop emulating the device set with [BrowserHost::emulate_device].
+    ///
+    /// `callback` will be called on the browser process UI thread.
+    pub fn clear_device_emulation(
+        &self,
+        callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+    ) {
+        let mut calls = vec![("Emulation.clearDeviceMetricsOverride", HashMap::new())];
+        calls.extend(touch_emulation_calls(0));
+        calls.push(user_agent_call(""));
+        call_in_sequence(self.clone(), calls, callback);
+    }
+}
+
+fn touch_emulation_calls(max_touch_points: u32) -> Vec<(&'static str, HashMap<String, StoredValue>)> {
+    let enabled = max_touch_points > 0;
+    let mut touch = HashMap::new();
+    touch.insert("enabled".to_owned(), StoredValue::Bool(enabled));
+    if enabled {
+        touch.insert("maxTouchPoints".to_owned(), StoredValue::Int(max_touch_points as i32));
+    }
+    let mut mouse = HashMap::new();
+    mouse.insert("enabled".to_owned(), StoredValue::Bool(enabled));
+    mouse.insert("configuration".to_owned(), StoredValue::String("mobile".to_owned()));
+    vec![
+        ("Emulation.setTouchEmulationEnabled", touch),
+        ("Emulation.setEmitTouchEventsForMouse", mouse),
+    ]
+}
+
+/// An empty user agent removes the override.
+fn user_agent_call(user_agent: &str) -> (&'static str, HashMap<String, StoredValue>) {
+    let mut params = HashMap::new();
+    params.insert("userAgent".to_owned(), StoredValue::String(user_agent.to_owned()));
+    ("Emulation.setUserAgentOverride", params)
+}
@@ -0,0 +1,53 @@
+//! Reclaiming browser memory through the DevTools `Memory` and `HeapProfiler`
+//! domains. See https://chromedevtools.github.io/devtools-protocol/tot/Memory/
+//! for details.
+
+use super::DevToolsError;
+use crate::{
+    browser_host::BrowserHost,
+    values::StoredValue,
+};
+use std::collections::HashMap;
+
+/// Severity of a memory pressure notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemoryPressureLevel {
+    /// Caches are trimmed and unused memory is released where it's cheap.
+    Moderate,
+    /// Everything that can be released is released, at the cost of having to
+    /// recreate it later.
+    Critical,
+}
+
+impl BrowserHost {
+    /// Send a memory pressure notification to all CEF processes, as the OS
+    /// would when running low on memory. Chromium reacts by purging caches,
+    /// discarding decoded images and running V8 garbage collection, so this
+    /// can be used when the host application needs memory back. The
+    /// notification isn't limited to this browser.
+    ///
+    /// `callback` will be called on the browser process UI thread once the
+    /// notification has been sent.
+    pub fn send_memory_pressure_notification(
+        &self,
+        level: MemoryPressureLevel,
+        callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+    ) {
+        let level = match level {
+            MemoryPressureLevel::Moderate => "moderate",
+            MemoryPressureLevel::Critical => "critical",
+        };
+        let mut params = HashMap::new();
+        params.insert("level".to_owned(), StoredValue::String(level.to_owned()));
+        self.call_dev_tools_method_unit("Memory.simulatePressureNotification", Some(params), callback);
+    }
+    /// Run a full V8 garbage collection in the renderer process of this
+    /// browser's main frame. `callback` will be called on the browser process
+    /// UI thread once the collection has finished.
+    pub fn collect_garbage(
+        &self,
+        callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+    ) {
+        self.call_dev_tools_method_unit("HeapProfiler.collectGarbage", None, callback);
+    }
+}
@@ -0,0 +1,482 @@
+//! Helpers built on the DevTools `Page` domain. See
+//! https://chromedevtools.github.io/devtools-protocol/tot/Page/ for details.
+
+use super::{DevToolsError, get_dictionary, get_f64, get_string, parse_json_dictionary};
+use crate::{
+    browser::Browser,
+    browser_host::BrowserHost,
+    devtools_message_observer::{DevToolsMessageObserver, DevToolsMessageObserverCallbacks},
+    frame::insert_css_script,
+    registration::Registration,
+    string::Utf16String,
+    values::StoredValue,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// When a user script added with [BrowserHost::add_user_script] runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScriptInjectionTime {
+    /// Run after the document object is created, before any of the page's own
+    /// scripts, in every frame.
+    DocumentStart,
+    /// Run once the document has been parsed (on `DOMContentLoaded`), in every
+    /// frame. The script is wrapped in a function, so top-level `var` and
+    /// function declarations don't become globals.
+    DocumentEnd,
+}
+
+/// Identifier of a script added with [BrowserHost::add_user_script].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UserScriptId(String);
+
+/// Identifier of a stylesheet added with [BrowserHost::add_stylesheet].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StylesheetId {
+    id: u64,
+    script: UserScriptId,
+}
+
+static NEXT_STYLESHEET_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Load milestone of a document.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LifecycleEventKind {
+    /// A new document started loading.
+    Init,
+    /// The document was parsed; `DOMContentLoaded` fired.
+    DomContentLoaded,
+    /// The document and all its subresources finished loading; `load` fired.
+    Load,
+    FirstPaint,
+    FirstContentfulPaint,
+    FirstMeaningfulPaint,
+    /// There were no more than 2 network connections for at least 500ms.
+    NetworkAlmostIdle,
+    /// There were no network connections for at least 500ms.
+    NetworkIdle,
+    /// Milestone not known to this crate.
+    Other(String),
+}
+
+impl LifecycleEventKind {
+    fn from_name(name: &str) -> LifecycleEventKind {
+        match name {
+            "init" => LifecycleEventKind::Init,
+            "DOMContentLoaded" => LifecycleEventKind::DomContentLoaded,
+            "load" => LifecycleEventKind::Load,
+            "firstPaint" => LifecycleEventKind::FirstPaint,
+            "firstContentfulPaint" => LifecycleEventKind::FirstContentfulPaint,
+            "firstMeaningfulPaint" => LifecycleEventKind::FirstMeaningfulPaint,
+            "networkAlmostIdle" => LifecycleEventKind::NetworkAlmostIdle,
+            "networkIdle" => LifecycleEventKind::NetworkIdle,
+            name => LifecycleEventKind::Other(name.to_owned()),
+        }
+    }
+}
+
+/// Event passed to the callback of [BrowserHost::add_lifecycle_observer].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LifecycleEvent {
+    /// DevTools id of the frame the event belongs to.
+    pub frame_id: String,
+    /// Id of the document load the event belongs to. Events of a previous
+    /// navigation can be told apart by a different loader id.
+    pub loader_id: String,
+    pub kind: LifecycleEventKind,
+    /// Monotonic time of the event, in seconds.
+    pub timestamp: f64,
+}
+
+/// Image format of [BrowserHost::capture_screenshot].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenshotFormat {
+    Png,
+    /// `quality` ranges from 0 to 100.
+    Jpeg { quality: u8 },
+}
+
+/// Scroll position and sizes of the main frame's document, in CSS pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LayoutMetrics {
+    /// Horizontal scroll offset.
+    pub scroll_x: f64,
+    /// Vertical scroll offset.
+    pub scroll_y: f64,
+    /// Width of the visible part of the document.
+    pub viewport_width: f64,
+    /// Height of the visible part of the document.
+    pub viewport_height: f64,
+    /// Width of the whole document.
+    pub content_width: f64,
+    /// Height of the whole document.
+    pub content_height: f64,
+}
+
+/// Callback receiving the load milestones of a browser.
+pub trait LifecycleEventCallback = 'static + Send + FnMut(Browser, LifecycleEvent);
+
+impl BrowserHost {
+    /// Inject `code` into every document subsequently loaded in this browser,
+    /// including documents loaded by cross-site navigations and in child frames.
+    /// The script stays registered until it's removed with
+    /// [BrowserHost::remove_user_script] or the browser is closed. It doesn't run
+    /// in the currently loaded document.
+    ///
+    /// `callback` will be called on the browser process UI thread with the
+    /// identifier of the registered script.
+    pub fn add_user_script(
+        &self,
+        code: &str,
+        injection_time: ScriptInjectionTime,
+        callback: impl 'static + Send + FnOnce(Result<UserScriptId, DevToolsError>),
+    ) {
+        let source = match injection_time {
+            ScriptInjectionTime::DocumentStart => code.to_owned(),
+            ScriptInjectionTime::DocumentEnd => format!(
+                "(function() {{\n\
+                 var run = function() {{\n{}\n}};\n\
+                 if (document.readyState === 'loading') {{\n\
+                 document.addEventListener('DOMContentLoaded', run, {{ once: true }});\n\
+                 }} else {{\n\
+                 run();\n\
+                 }}\n\
+                 }})();",
+                code
+            ),
+        };
+        let mut params = HashMap::new();
+        params.insert("source".to_owned(), StoredValue::String(source));
+        self.call_dev_tools_method("Page.addScriptToEvaluateOnNewDocument", Some(params), move |result| {
+            callback(result.and_then(|result| {
+                match get_string(&result, "identifier") {
+                    identifier if identifier.is_empty() => Err(DevToolsError::InvalidResult),
+                    identifier => Ok(UserScriptId(identifier)),
+                }
+            }))
+        });
+    }
+    /// Remove a script added with [BrowserHost::add_user_script]. Documents that
+    /// already ran the script aren't affected.
+    pub fn remove_user_script(
+        &self,
+        id: &UserScriptId,
+        callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+    ) {
+        let mut params = HashMap::new();
+        params.insert("identifier".to_owned(), StoredValue::String(id.0.clone()));
+        self.call_dev_tools_method_unit("Page.removeScriptToEvaluateOnNewDocument", Some(params), callback);
+    }
+    /// Apply `css` to the documents currently loaded in this browser and to every
+    /// document loaded afterwards, in all frames. This can be used to force a
+    /// dark theme or brand styling onto pages the application doesn't control.
+    ///
+    /// `callback` will be called on the browser process UI thread with the
+    /// identifier of the stylesheet, which can be passed to
+    /// [BrowserHost::remove_stylesheet].
+    pub fn add_stylesheet(
+        &self,
+        css: &str,
+        callback: impl 'static + Send + FnOnce(Result<StylesheetId, DevToolsError>),
+    ) {
+        let id = NEXT_STYLESHEET_ID.fetch_add(1, Ordering::SeqCst);
+        let script = insert_css_script(css, Some(id));
+        self.execute_in_all_frames(&script);
+        self.add_user_script(&script, ScriptInjectionTime::DocumentStart, move |result| {
+            callback(result.map(|script| StylesheetId { id, script }))
+        });
+    }
+    /// Remove a stylesheet added with [BrowserHost::add_stylesheet] from the
+    /// currently loaded documents and stop applying it to new ones.
+    pub fn remove_stylesheet(
+        &self,
+        id: &StylesheetId,
+        callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+    ) {
+        self.execute_in_all_frames(&format!(
+            "document.querySelectorAll('style[data-cef-stylesheet=\"{}\"]').forEach(function(style) {{ style.remove(); }});",
+            id.id
+        ));
+        self.remove_user_script(&id.script, callback);
+    }
+    /// Capture the visible part of the page and pass the encoded image to
+    /// `callback`, which will be called on the browser process UI thread.
+    /// Unlike the paint callbacks of windowless rendering this also works for
+    /// windowed browsers, but the page must be visible to be painted.
+    pub fn capture_screenshot(
+        &self,
+        format: ScreenshotFormat,
+        callback: impl 'static + Send + FnOnce(Result<Vec<u8>, DevToolsError>),
+    ) {
+        let mut params = HashMap::new();
+        match format {
+            ScreenshotFormat::Png => {
+                params.insert("format".to_owned(), StoredValue::String("png".to_owned()));
+            }
+            ScreenshotFormat::Jpeg { quality } => {
+                params.insert("format".to_owned(), StoredValue::String("jpeg".to_owned()));
+                params.insert("quality".to_owned(), StoredValue::Int(i32::from(quality.min(100))));
+            }
+        }
+        self.call_dev_tools_method("Page.captureScreenshot", Some(params), move |result| {
+            callback(result.and_then(|result| {
+                decode_base64(&get_string(&result, "data")).ok_or(DevToolsError::InvalidResult)
+            }))
+        });
+    }
+    /// Pass the scroll offset, viewport size and document size of the main
+    /// frame to `callback`, which will be called on the browser process UI
+    /// thread. All of them are in CSS pixels. Together with
+    /// [RenderHandlerCallbacks::on_scroll_offset_changed](crate::client::render_handler::RenderHandlerCallbacks::on_scroll_offset_changed)
+    /// and [BrowserHost::scroll_to] this is enough to keep a minimap in sync
+    /// with the page.
+    pub fn get_layout_metrics(
+        &self,
+        callback: impl 'static + Send + FnOnce(Result<LayoutMetrics, DevToolsError>),
+    ) {
+        // `Page.getLayoutMetrics` reports the content size in device pixels,
+        // so it's scaled by the page's device pixel ratio, which includes the
+        // zoom level.
+        let host = self.clone();
+        self.get_browser().get_main_frame().evaluate("window.devicePixelRatio", move |ratio| {
+            let device_pixel_ratio = match ratio {
+                Ok(StoredValue::Int(ratio)) => f64::from(ratio),
+                Ok(StoredValue::Double(ratio)) => ratio,
+                Ok(_) => return callback(Err(DevToolsError::InvalidResult)),
+                Err(error) => return callback(Err(error)),
+            };
+            host.call_dev_tools_method("Page.getLayoutMetrics", None, move |result| {
+                callback(result.and_then(|result| {
+                    let viewport = get_dictionary(&result, "layoutViewport").ok_or(DevToolsError::InvalidResult)?;
+                    let content = get_dictionary(&result, "contentSize").ok_or(DevToolsError::InvalidResult)?;
+                    Ok(layout_metrics(&viewport, &content, device_pixel_ratio))
+                }))
+            });
+        });
+    }
+
+    pub(crate) fn execute_in_all_frames(&self, code: &str) {
+        let code = Utf16String::new(code);
+        let browser = self.get_browser();
+        for identifier in browser.get_frame_identifiers() {
+            if let Some(frame) = browser.get_frame_byident(identifier) {
+                frame.execute_java_script_utf16(&code, "", 0);
+            }
+        }
+    }
+
+    /// Call `callback` with the load milestones of every document loaded in
+    /// this browser, including child frames, until the returned [Registration]
+    /// is dropped. Unlike [LoadHandlerCallbacks](crate::load_handler::LoadHandlerCallbacks)
+    /// this also reports paint and network-idle milestones, which makes it
+    /// possible to tell when a page that keeps loading content from script is
+    /// actually ready.
+    ///
+    /// `callback` will be called on the browser process UI thread. Events are
+    /// only reported once lifecycle events have been enabled, which happens
+    /// asynchronously.
+    pub fn add_lifecycle_observer(&self, callback: impl LifecycleEventCallback) -> Registration {
+        let registration = self.add_dev_tools_message_observer(
+            DevToolsMessageObserver::new(LifecycleObserver(callback))
+        );
+        let host = self.clone();
+        self.call_dev_tools_method_unit("Page.enable", None, move |result| {
+            if result.is_ok() {
+                let mut params = HashMap::new();
+                params.insert("enabled".to_owned(), StoredValue::Bool(true));
+                host.call_dev_tools_method_unit("Page.setLifecycleEventsEnabled", Some(params), |_| ());
+            }
+        });
+        registration
+    }
+}
+
+struct LifecycleObserver<F: LifecycleEventCallback>(F);
+
+impl<F: LifecycleEventCallback> DevToolsMessageObserverCallbacks for LifecycleObserver<F> {
+    fn on_dev_tools_event(&mut self, browser: Browser, method: &str, params: &[u8]) {
+        if method != "Page.lifecycleEvent" {
+            return;
+        }
+        if let Some(params) = parse_json_dictionary(params) {
+            (self.0)(browser, LifecycleEvent {
+                frame_id: get_string(&params, "frameId"),
+                loader_id: get_string(&params, "loaderId"),
+                kind: LifecycleEventKind::from_name(&get_string(&params, "name")),
+                timestamp: get_f64(&params, "timestamp"),
+            });
+        }
+    }
+}
+
+type SlotCallback = Box<dyn 'static + Send + FnOnce(Result<(), DevToolsError>)>;
+
+/// A user script that is replaced as a whole whenever it changes, e.g. to
+/// update a policy. Replacing a script has to wait for the identifier of the
+/// previous one, so updates are queued and applied one after another.
+#[derive(Default)]
+pub(crate) struct UserScriptSlot(Mutex<SlotState>);
+
+#[derive(Default)]
+struct SlotState {
+    script: Option<UserScriptId>,
+    /// True while an update is being applied.
+    busy: bool,
+    /// Updates waiting for the current one, with None removing the script.
+    queue: VecDeque<(Option<String>, SlotCallback)>,
+}
+
+impl UserScriptSlot {
+    /// Replace the script with `code`, injected at document start, or remove
+    /// it if `code` is None, once the earlier updates are done. `callback` is
+    /// called on the browser process UI thread.
+    pub(crate) fn set(
+        self: &Arc<Self>,
+        host: &BrowserHost,
+        code: Option<String>,
+        callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+    ) {
+        let mut state = self.0.lock();
+        state.queue.push_back((code, Box::new(callback)));
+        if !state.busy {
+            state.busy = true;
+            drop(state);
+            self.clone().apply_next(host.clone());
+        }
+    }
+    fn apply_next(self: Arc<Self>, host: BrowserHost) {
+        let (code, callback, previous) = {
+            let mut state = self.0.lock();
+            match state.queue.pop_front() {
+                Some((code, callback)) => (code, callback, state.script.take()),
+                None => {
+                    state.busy = false;
+                    return;
+                }
+            }
+        };
+        let add_host = host.clone();
+        let add = move |removed: Result<(), DevToolsError>| match code {
+            Some(code) => {
+                let next_host = add_host.clone();
+                add_host.add_user_script(&code, ScriptInjectionTime::DocumentStart, move |result| {
+                    callback(result.map(|id| {
+                        self.0.lock().script = Some(id);
+                    }));
+                    self.apply_next(next_host);
+                });
+            }
+            None => {
+                callback(removed);
+                self.apply_next(add_host);
+            }
+        };
+        match previous {
+            // Scripts run in the order they were added, so should the removal
+            // fail, the new script still overrides the old one.
+            Some(previous) => host.remove_user_script(&previous, add),
+            None => add(Ok(())),
+        }
+    }
+}
+
+/// Decodes standard base64, which the protocol uses for binary data.
+fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    fn value(c: u8) -> Option<u32> {
+        Some(u32::from(match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        }))
+    }
+    let data = data.trim_end_matches('=').as_bytes();
+    let mut decoded = Vec::with_capacity(data.len() * 3 / 4);
+    for chunk in data.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let mut bits = 0;
+        for (i, &c) in chunk.iter().enumerate() {
+            bits |= value(c)? << (18 - 6 * i);
+        }
+        let bytes = [(bits >> 16) as u8, (bits >> 8) as u8, bits as u8];
+        decoded.extend_from_slice(&bytes[..chunk.len() - 1]);
+    }
+    Some(decoded)
+}
+
+/// Combines the `layoutViewport` and the `contentSize` reported by
+/// `Page.getLayoutMetrics`, converting the latter to CSS pixels.
+fn layout_metrics(
+    viewport: &HashMap<String, StoredValue>,
+    content: &HashMap<String, StoredValue>,
+    device_pixel_ratio: f64,
+) -> LayoutMetrics {
+    let device_pixel_ratio = if device_pixel_ratio > 0.0 { device_pixel_ratio } else { 1.0 };
+    LayoutMetrics {
+        scroll_x: get_f64(viewport, "pageX"),
+        scroll_y: get_f64(viewport, "pageY"),
+        viewport_width: get_f64(viewport, "clientWidth"),
+        viewport_height: get_f64(viewport, "clientHeight"),
+        content_width: get_f64(content, "width") / device_pixel_ratio,
+        content_height: get_f64(content, "height") / device_pixel_ratio,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dictionary(entries: &[(&str, StoredValue)]) -> HashMap<String, StoredValue> {
+        entries.iter().map(|(key, value)| (key.to_string(), value.clone())).collect()
+    }
+
+    #[test]
+    fn content_size_is_converted_to_css_pixels() {
+        let viewport = dictionary(&[
+            ("pageX", StoredValue::Int(0)),
+            ("pageY", StoredValue::Double(120.5)),
+            ("clientWidth", StoredValue::Int(800)),
+            ("clientHeight", StoredValue::Int(600)),
+        ]);
+        let content = dictionary(&[("width", StoredValue::Int(1600)), ("height", StoredValue::Int(5000))]);
+        let metrics = layout_metrics(&viewport, &content, 2.0);
+        assert_eq!(metrics.scroll_y, 120.5);
+        assert_eq!(metrics.viewport_width, 800.0);
+        assert_eq!(metrics.content_width, 800.0);
+        assert_eq!(metrics.content_height, 2500.0);
+    }
+
+    #[test]
+    fn invalid_ratios_leave_the_content_size_alone() {
+        let content = dictionary(&[("width", StoredValue::Int(1600)), ("height", StoredValue::Int(5000))]);
+        let metrics = layout_metrics(&HashMap::new(), &content, 0.0);
+        assert_eq!(metrics.content_width, 1600.0);
+    }
+
+    #[test]
+    fn base64_decodes_padded_and_unpadded_data() {
+        assert_eq!(decode_base64("").unwrap(), b"");
+        assert_eq!(decode_base64("Zg==").unwrap(), b"f");
+        assert_eq!(decode_base64("Zm8=").unwrap(), b"fo");
+        assert_eq!(decode_base64("Zm9v").unwrap(), b"foo");
+        assert_eq!(decode_base64("Zm9vYmE").unwrap(), b"fooba");
+        assert_eq!(decode_base64("+/+/").unwrap(), [0xfb, 0xff, 0xbf]);
+    }
+
+    #[test]
+    fn base64_rejects_invalid_data() {
+        assert_eq!(decode_base64("Zm9v!"), None);
+        assert_eq!(decode_base64("Zm9vY"), None);
+        assert_eq!(decode_base64("Zm-_"), None);
+    }
+}
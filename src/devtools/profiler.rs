@@ -0,0 +1,251 @@
+//! JavaScript CPU profiling and code coverage through the DevTools `Profiler`
+//! domain. See https://chromedevtools.github.io/devtools-protocol/tot/Profiler/
+//! for details on the returned data.
+
+use super::{
+    DevToolsError, get_bool, get_dictionary, get_dictionary_list, get_f64, get_i32, get_list,
+    get_string,
+};
+use crate::{
+    browser_host::BrowserHost,
+    values::StoredValue,
+};
+use std::collections::HashMap;
+
+/// Stack entry for runtime errors and assertions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallFrame {
+    /// JavaScript function name.
+    pub function_name: String,
+    /// JavaScript script id.
+    pub script_id: String,
+    /// JavaScript script name or url.
+    pub url: String,
+    /// JavaScript script line number (0-based).
+    pub line_number: i32,
+    /// JavaScript script column number (0-based).
+    pub column_number: i32,
+}
+
+/// Profile node. Holds callsite information, execution statistics and child
+/// nodes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileNode {
+    /// Unique id of the node.
+    pub id: i32,
+    /// Function location.
+    pub call_frame: CallFrame,
+    /// Number of samples where this node was on top of the call stack.
+    pub hit_count: i32,
+    /// Child node ids.
+    pub children: Vec<i32>,
+}
+
+/// Profile returned by [BrowserHost::stop_js_profile].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CpuProfile {
+    /// The list of profile nodes. First item is the root node.
+    pub nodes: Vec<ProfileNode>,
+    /// Profiling start timestamp in microseconds.
+    pub start_time: f64,
+    /// Profiling end timestamp in microseconds.
+    pub end_time: f64,
+    /// Ids of samples top nodes.
+    pub samples: Vec<i32>,
+    /// Time intervals between adjacent samples in microseconds. The first delta
+    /// is relative to the profile `start_time`.
+    pub time_deltas: Vec<i32>,
+}
+
+/// Coverage data for a source range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoverageRange {
+    /// JavaScript script source offset for the range start.
+    pub start_offset: i32,
+    /// JavaScript script source offset for the range end.
+    pub end_offset: i32,
+    /// Collected execution count of the source range.
+    pub count: i32,
+}
+
+/// Coverage data for a JavaScript function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionCoverage {
+    /// JavaScript function name.
+    pub function_name: String,
+    /// Source ranges inside the function with coverage data.
+    pub ranges: Vec<CoverageRange>,
+    /// Whether coverage data for this function has block granularity.
+    pub is_block_coverage: bool,
+}
+
+/// Coverage data for a JavaScript script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptCoverage {
+    /// JavaScript script id.
+    pub script_id: String,
+    /// JavaScript script name or url.
+    pub url: String,
+    /// Functions contained in the script that has coverage data.
+    pub functions: Vec<FunctionCoverage>,
+}
+
+impl BrowserHost {
+    /// Enable the profiler and start collecting a JavaScript CPU profile.
+    /// `sampling_interval_us` changes the sampling interval, in microseconds,
+    /// if specified. `callback` will be called on the browser process UI
+    /// thread once profiling has started.
+    pub fn start_js_profile(
+        &self,
+        sampling_interval_us: Option<i32>,
+        callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+    ) {
+        let host = self.clone();
+        self.call_dev_tools_method_unit("Profiler.enable", None, move |result| {
+            if let Err(e) = result {
+                return callback(Err(e));
+            }
+            let host_start = host.clone();
+            let start = move || host_start.call_dev_tools_method_unit("Profiler.start", None, callback);
+            match sampling_interval_us {
+                Some(interval) => {
+                    let mut params = HashMap::new();
+                    params.insert("interval".to_owned(), StoredValue::Int(interval));
+                    host.call_dev_tools_method_unit("Profiler.setSamplingInterval", Some(params), move |result| {
+                        // The interval can't be changed while a profile is being
+                        // recorded, which isn't a reason to fail.
+                        start()
+                    });
+                }
+                None => start(),
+            }
+        });
+    }
+    /// Stop the profile started by [BrowserHost::start_js_profile] and pass the
+    /// recorded profile to `callback`, which will be called on the browser
+    /// process UI thread.
+    pub fn stop_js_profile(
+        &self,
+        callback: impl 'static + Send + FnOnce(Result<CpuProfile, DevToolsError>),
+    ) {
+        self.call_dev_tools_method("Profiler.stop", None, move |result| {
+            callback(result.and_then(|result| {
+                get_dictionary(&result, "profile")
+                    .map(|profile| cpu_profile(&profile))
+                    .ok_or(DevToolsError::InvalidResult)
+            }))
+        });
+    }
+    /// Enable precise code coverage collection. Coverage is only collected for
+    /// code that runs after this call, so it should be made before the page is
+    /// loaded. If `detailed` is true coverage is collected with block
+    /// granularity, otherwise only function-level coverage is collected.
+    pub fn start_js_coverage(
+        &self,
+        detailed: bool,
+        callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+    ) {
+        let host = self.clone();
+        self.call_dev_tools_method_unit("Profiler.enable", None, move |result| {
+            if let Err(e) = result {
+                return callback(Err(e));
+            }
+            let mut params = HashMap::new();
+            params.insert("callCount".to_owned(), StoredValue::Bool(true));
+            params.insert("detailed".to_owned(), StoredValue::Bool(detailed));
+            host.call_dev_tools_method_unit("Profiler.startPreciseCoverage", Some(params), callback);
+        });
+    }
+    /// Collect the code coverage gathered since [BrowserHost::start_js_coverage]
+    /// was called and stop collecting. If precise coverage wasn't started, the
+    /// best-effort coverage maintained by V8 is returned instead, which may be
+    /// incomplete. `callback` will be called on the browser process UI thread.
+    pub fn collect_js_coverage(
+        &self,
+        callback: impl 'static + Send + FnOnce(Result<Vec<ScriptCoverage>, DevToolsError>),
+    ) {
+        let host = self.clone();
+        self.call_dev_tools_method("Profiler.takePreciseCoverage", None, move |result| {
+            match result {
+                Ok(result) => {
+                    let coverage = script_coverage_list(&result);
+                    host.call_dev_tools_method_unit("Profiler.stopPreciseCoverage", None, move |_| callback(Ok(coverage)));
+                }
+                Err(DevToolsError::Method { .. }) => {
+                    host.call_dev_tools_method("Profiler.getBestEffortCoverage", None, move |result| {
+                        callback(result.map(|result| script_coverage_list(&result)))
+                    });
+                }
+                Err(e) => callback(Err(e)),
+            }
+        });
+    }
+}
+
+fn cpu_profile(profile: &HashMap<String, StoredValue>) -> CpuProfile {
+    CpuProfile {
+        nodes: get_dictionary_list(profile, "nodes")
+            .iter()
+            .map(|node| ProfileNode {
+                id: get_i32(node, "id"),
+                call_frame: get_dictionary(node, "callFrame")
+                    .map(|frame| CallFrame {
+                        function_name: get_string(&frame, "functionName"),
+                        script_id: get_string(&frame, "scriptId"),
+                        url: get_string(&frame, "url"),
+                        line_number: get_i32(&frame, "lineNumber"),
+                        column_number: get_i32(&frame, "columnNumber"),
+                    })
+                    .unwrap_or_else(|| CallFrame {
+                        function_name: String::new(),
+                        script_id: String::new(),
+                        url: String::new(),
+                        line_number: 0,
+                        column_number: 0,
+                    }),
+                hit_count: get_i32(node, "hitCount"),
+                children: int_list(node, "children"),
+            })
+            .collect(),
+        start_time: get_f64(profile, "startTime"),
+        end_time: get_f64(profile, "endTime"),
+        samples: int_list(profile, "samples"),
+        time_deltas: int_list(profile, "timeDeltas"),
+    }
+}
+
+fn script_coverage_list(result: &HashMap<String, StoredValue>) -> Vec<ScriptCoverage> {
+    get_dictionary_list(result, "result")
+        .iter()
+        .map(|script| ScriptCoverage {
+            script_id: get_string(script, "scriptId"),
+            url: get_string(script, "url"),
+            functions: get_dictionary_list(script, "functions")
+                .iter()
+                .map(|function| FunctionCoverage {
+                    function_name: get_string(function, "functionName"),
+                    ranges: get_dictionary_list(function, "ranges")
+                        .iter()
+                        .map(|range| CoverageRange {
+                            start_offset: get_i32(range, "startOffset"),
+                            end_offset: get_i32(range, "endOffset"),
+                            count: get_i32(range, "count"),
+                        })
+                        .collect(),
+                    is_block_coverage: get_bool(function, "isBlockCoverage"),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+fn int_list(map: &HashMap<String, StoredValue>, key: &str) -> Vec<i32> {
+    get_list(map, key)
+        .into_iter()
+        .filter_map(|value| match value {
+            StoredValue::Int(i) => Some(i),
+            StoredValue::Double(d) => Some(d as i32),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,291 @@
+//! Evaluating JavaScript with a result from the browser process, through the
+//! DevTools `Runtime` domain. See
+//! https://chromedevtools.github.io/devtools-protocol/tot/Runtime/ for details.
+//!
+//! Scripts are evaluated in an isolated world of the target frame, so they
+//! share the DOM with the page but not its JavaScript globals, and page scripts
+//! can't interfere with them.
+
+use super::{DevToolsError, DevToolsMethodCallback, get_dictionary, get_dictionary_list, get_f64, get_i32, get_string};
+use crate::{
+    browser_host::BrowserHost,
+    frame::Frame,
+    string::quote_string,
+    values::{Rect, StoredValue},
+};
+use parking_lot::Mutex;
+use std::{collections::HashMap, sync::Arc};
+use uuid::Uuid;
+
+const WORLD_NAME: &str = "cef-rs";
+
+impl Frame {
+    /// Evaluate `expression` in this frame and pass its value to `callback`,
+    /// which will be called on the browser process UI thread. The value is
+    /// converted the same way as by `JSON.stringify`; `undefined` and `null`
+    /// both become [StoredValue::Null]. If the expression evaluates to a
+    /// promise, the result it resolves to is passed instead.
+    pub fn evaluate(
+        &self,
+        expression: &str,
+        callback: impl 'static + Send + FnOnce(Result<StoredValue, DevToolsError>),
+    ) {
+        let host = self.get_browser().get_host();
+        let frame = self.clone();
+        let expression = expression.to_owned();
+        let evaluate_host = host.clone();
+        let evaluate = move |context_id: Result<i32, DevToolsError>| {
+            let context_id = match context_id {
+                Ok(context_id) => context_id,
+                Err(e) => return callback(Err(e)),
+            };
+            evaluate_in_context(&evaluate_host, context_id, expression, move |result| {
+                callback(result.and_then(|result| evaluate_result(&result)))
+            });
+        };
+        let host_world = host.clone();
+        host.call_dev_tools_method("Page.getFrameTree", None, move |result| {
+            let tree = match result {
+                Ok(tree) => tree,
+                Err(e) => return evaluate(Err(e)),
+            };
+            if frame.is_main() {
+                match main_frame_id(&tree) {
+                    Some(frame_id) => create_isolated_world(&host_world, frame_id, evaluate),
+                    None => evaluate(Err(DevToolsError::FrameNotFound)),
+                }
+            } else {
+                identify_frame(&host_world, &frame, subframe_ids(&tree), evaluate);
+            }
+        });
+    }
+    /// Find the first element matching the CSS `selector` in this frame and
+    /// pass its bounding box to `callback`, or `None` if no element matches.
+    ///
+    /// The bounds are in CSS pixels relative to the top-left corner of the
+    /// view, which are view coordinates as long as the page isn't zoomed, so
+    /// they can be passed to [BrowserHost::click_at](crate::browser_host::BrowserHost::click_at).
+    /// For elements in a cross-origin frame the bounds are relative to that
+    /// frame instead, as the frame's position can't be determined from inside
+    /// it.
+    pub fn query_selector_bounds(
+        &self,
+        selector: &str,
+        callback: impl 'static + Send + FnOnce(Result<Option<Rect>, DevToolsError>),
+    ) {
+        let expression = format!(
+            "(function() {{\n\
+             var element = document.querySelector({});\n\
+             if (!element) return null;\n\
+             var rect = element.getBoundingClientRect();\n\
+             var x = rect.left, y = rect.top;\n\
+             for (var w = window; w.frameElement; w = w.parent) {{\n\
+             var frameRect = w.frameElement.getBoundingClientRect();\n\
+             x += frameRect.left + w.frameElement.clientLeft;\n\
+             y += frameRect.top + w.frameElement.clientTop;\n\
+             }}\n\
+             return {{ x: x, y: y, width: rect.width, height: rect.height }};\n\
+             }})()",
+            quote_string(selector)
+        );
+        self.evaluate(&expression, move |result| {
+            callback(result.and_then(|value| match value {
+                StoredValue::Null => Ok(None),
+                StoredValue::Dictionary(rect) => {
+                    let rect: HashMap<String, StoredValue> = rect.into();
+                    Ok(Some(Rect {
+                        x: get_f64(&rect, "x").round() as i32,
+                        y: get_f64(&rect, "y").round() as i32,
+                        width: get_f64(&rect, "width").round() as i32,
+                        height: get_f64(&rect, "height").round() as i32,
+                    }))
+                }
+                _ => Err(DevToolsError::InvalidResult),
+            }))
+        });
+    }
+    /// Scroll the first element matching the CSS `selector` in this frame into
+    /// the center of the view. `callback` receives `false` if no element
+    /// matches.
+    pub fn scroll_to(
+        &self,
+        selector: &str,
+        callback: impl 'static + Send + FnOnce(Result<bool, DevToolsError>),
+    ) {
+        let expression = format!(
+            "(function() {{\n\
+             var element = document.querySelector({});\n\
+             if (!element) return false;\n\
+             element.scrollIntoView({{ block: 'center', inline: 'center' }});\n\
+             return true;\n\
+             }})()",
+            quote_string(selector)
+        );
+        self.evaluate(&expression, move |result| {
+            callback(result.and_then(|value| match value {
+                StoredValue::Bool(found) => Ok(found),
+                _ => Err(DevToolsError::InvalidResult),
+            }))
+        });
+    }
+}
+
+/// Returns the DevTools id of the main frame from the result of
+/// `Page.getFrameTree`.
+fn main_frame_id(tree: &HashMap<String, StoredValue>) -> Option<String> {
+    let root = get_dictionary(tree, "frameTree")?;
+    get_dictionary(&root, "frame").map(|frame| get_string(&frame, "id"))
+}
+
+/// Returns the DevTools ids of all subframes from the result of
+/// `Page.getFrameTree`.
+fn subframe_ids(tree: &HashMap<String, StoredValue>) -> Vec<String> {
+    let mut ids = Vec::new();
+    let mut pending = get_dictionary(tree, "frameTree")
+        .map(|root| get_dictionary_list(&root, "childFrames"))
+        .unwrap_or_default();
+    while let Some(node) = pending.pop() {
+        if let Some(frame) = get_dictionary(&node, "frame") {
+            ids.push(get_string(&frame, "id"));
+        }
+        pending.extend(get_dictionary_list(&node, "childFrames"));
+    }
+    ids
+}
+
+/// Create an isolated world in the frame with the given DevTools id and pass
+/// the id of its execution context to `callback`.
+fn create_isolated_world(
+    host: &BrowserHost,
+    frame_id: String,
+    callback: impl 'static + Send + FnOnce(Result<i32, DevToolsError>),
+) {
+    let mut params = HashMap::new();
+    params.insert("frameId".to_owned(), StoredValue::String(frame_id));
+    params.insert("worldName".to_owned(), StoredValue::String(WORLD_NAME.to_owned()));
+    host.call_dev_tools_method("Page.createIsolatedWorld", Some(params), move |result| {
+        callback(result.map(|result| get_i32(&result, "executionContextId")))
+    });
+}
+
+fn evaluate_in_context(
+    host: &BrowserHost,
+    context_id: i32,
+    expression: String,
+    callback: impl DevToolsMethodCallback,
+) {
+    let mut params = HashMap::new();
+    params.insert("expression".to_owned(), StoredValue::String(expression));
+    params.insert("contextId".to_owned(), StoredValue::Int(context_id));
+    params.insert("returnByValue".to_owned(), StoredValue::Bool(true));
+    params.insert("awaitPromise".to_owned(), StoredValue::Bool(true));
+    host.call_dev_tools_method("Runtime.evaluate", Some(params), callback);
+}
+
+/// Progress of [identify_frame].
+struct FrameProbe<F> {
+    /// Candidates whose listener wasn't requested yet.
+    installing: usize,
+    /// Candidates that haven't answered yet.
+    answering: usize,
+    callback: Option<F>,
+}
+
+impl<F: FnOnce(Result<i32, DevToolsError>)> FrameProbe<F> {
+    /// Record the answer of a candidate, with the context id of its isolated
+    /// world if it's the frame.
+    fn answer(probe: &Mutex<Self>, context_id: Option<i32>) {
+        let mut state = probe.lock();
+        state.answering -= 1;
+        let result = match context_id {
+            Some(context_id) => Ok(context_id),
+            None if state.answering == 0 => Err(DevToolsError::FrameNotFound),
+            None => return,
+        };
+        if let Some(callback) = state.callback.take() {
+            drop(state);
+            callback(result);
+        }
+    }
+}
+
+/// Find which of the frames with the DevTools ids in `candidates` is `frame`,
+/// and pass the execution context id of an isolated world in it to
+/// `callback`. DevTools frame ids aren't related to CEF's frame identifiers,
+/// so the frame is told apart by an event with a random type, which is fired
+/// in `frame` through CEF and awaited in the isolated world of every
+/// candidate. Events fired in one world reach the listeners of all worlds.
+fn identify_frame(
+    host: &BrowserHost,
+    frame: &Frame,
+    candidates: Vec<String>,
+    callback: impl 'static + Send + FnOnce(Result<i32, DevToolsError>),
+) {
+    if candidates.is_empty() {
+        return callback(Err(DevToolsError::FrameNotFound));
+    }
+    let event_type = format!("cef-frame-probe-{}", Uuid::new_v4().to_simple());
+    let listen = format!(
+        "new Promise(function(resolve) {{\n\
+         var timeout = setTimeout(function() {{ resolve(false); }}, 1000);\n\
+         document.addEventListener({}, function() {{ clearTimeout(timeout); resolve(true); }}, {{ once: true }});\n\
+         }})",
+        quote_string(&event_type)
+    );
+    // The listeners are installed through DevTools and the event is fired
+    // through CEF, which don't keep their order, so it's fired a few times.
+    let fire = format!(
+        "(function() {{\n\
+         var count = 0;\n\
+         (function fire() {{\n\
+         document.dispatchEvent(new Event({}));\n\
+         if (++count < 10) setTimeout(fire, 50);\n\
+         }})();\n\
+         }})();",
+        quote_string(&event_type)
+    );
+    let probe = Arc::new(Mutex::new(FrameProbe {
+        installing: candidates.len(),
+        answering: candidates.len(),
+        callback: Some(callback),
+    }));
+    for frame_id in candidates {
+        let probe = probe.clone();
+        let evaluate_host = host.clone();
+        let listen = listen.clone();
+        let frame = frame.clone();
+        let fire = fire.clone();
+        create_isolated_world(host, frame_id, move |context_id| {
+            if let Ok(context_id) = context_id {
+                let answer_probe = probe.clone();
+                evaluate_in_context(&evaluate_host, context_id, listen, move |result| {
+                    match result.and_then(|result| evaluate_result(&result)) {
+                        Ok(StoredValue::Bool(true)) => FrameProbe::answer(&answer_probe, Some(context_id)),
+                        _ => FrameProbe::answer(&answer_probe, None),
+                    }
+                });
+            } else {
+                FrameProbe::answer(&probe, None);
+            }
+            let mut state = probe.lock();
+            state.installing -= 1;
+            if state.installing == 0 {
+                drop(state);
+                frame.execute_java_script(&fire, "", 0);
+            }
+        });
+    }
+}
+
+fn evaluate_result(result: &HashMap<String, StoredValue>) -> Result<StoredValue, DevToolsError> {
+    if let Some(details) = get_dictionary(result, "exceptionDetails") {
+        let message = get_dictionary(&details, "exception")
+            .map(|exception| get_string(&exception, "description"))
+            .filter(|description| !description.is_empty())
+            .unwrap_or_else(|| get_string(&details, "text"));
+        return Err(DevToolsError::ScriptException { message });
+    }
+    Ok(get_dictionary(result, "result")
+        .and_then(|object| object.get("value").cloned())
+        .unwrap_or(StoredValue::Null))
+}
@@ -0,0 +1,106 @@
+//! Helpers built on the DevTools `SystemInfo` domain. See
+//! https://chromedevtools.github.io/devtools-protocol/tot/SystemInfo/ for
+//! details.
+
+use super::{DevToolsError, get_dictionary, get_dictionary_list, get_f64, get_list, get_string};
+use crate::{browser_host::BrowserHost, values::StoredValue};
+use std::collections::HashMap;
+
+/// A graphics adapter seen by the GPU process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GpuDevice {
+    /// PCI vendor id, or 0 if unknown.
+    pub vendor_id: u32,
+    /// PCI device id, or 0 if unknown.
+    pub device_id: u32,
+    /// Vendor name on platforms without PCI ids (e.g. Android).
+    pub vendor_string: String,
+    /// Device name on platforms without PCI ids (e.g. Android).
+    pub device_string: String,
+    pub driver_vendor: String,
+    pub driver_version: String,
+}
+
+/// GPU state as reported by the GPU process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GpuInfo {
+    /// The graphics adapters. The first one is the one in use.
+    pub devices: Vec<GpuDevice>,
+    /// Status of each GPU feature, as shown on chrome://gpu. Keys are e.g.
+    /// "gpu_compositing", "rasterization" or "webgl", values e.g. "enabled",
+    /// "disabled_software" or "unavailable_off".
+    pub feature_status: HashMap<String, String>,
+    /// Names of the driver bug workarounds in effect.
+    pub driver_bug_workarounds: Vec<String>,
+    /// Machine model name, e.g. "MacBookPro". Empty if unknown.
+    pub model_name: String,
+    /// Machine model version, e.g. "10.1". Empty if unknown.
+    pub model_version: String,
+    /// Command line of the browser process.
+    pub command_line: String,
+}
+
+impl GpuInfo {
+    /// Returns the status of the named feature.
+    pub fn feature_status(&self, feature: &str) -> Option<&str> {
+        self.feature_status.get(feature).map(String::as_str)
+    }
+    /// Returns true if compositing runs on the GPU. This is false if the GPU
+    /// process failed to initialize and Chromium fell back to software
+    /// rendering, or if the GPU was disabled.
+    pub fn is_hardware_accelerated(&self) -> bool {
+        self.feature_status("gpu_compositing")
+            .map(|status| status.starts_with("enabled"))
+            .unwrap_or(false)
+    }
+
+    fn from_result(result: &HashMap<String, StoredValue>) -> Option<GpuInfo> {
+        let gpu = get_dictionary(result, "gpu")?;
+        let devices = get_dictionary_list(&gpu, "devices")
+            .iter()
+            .map(|device| GpuDevice {
+                vendor_id: get_f64(device, "vendorId") as u32,
+                device_id: get_f64(device, "deviceId") as u32,
+                vendor_string: get_string(device, "vendorString"),
+                device_string: get_string(device, "deviceString"),
+                driver_vendor: get_string(device, "driverVendor"),
+                driver_version: get_string(device, "driverVersion"),
+            })
+            .collect();
+        let feature_status = get_dictionary(&gpu, "featureStatus")
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(feature, status)| match status {
+                StoredValue::String(status) => Some((feature, status)),
+                _ => None,
+            })
+            .collect();
+        let driver_bug_workarounds = get_list(&gpu, "driverBugWorkarounds")
+            .into_iter()
+            .filter_map(|workaround| match workaround {
+                StoredValue::String(workaround) => Some(workaround),
+                _ => None,
+            })
+            .collect();
+        Some(GpuInfo {
+            devices,
+            feature_status,
+            driver_bug_workarounds,
+            model_name: get_string(result, "modelName"),
+            model_version: get_string(result, "modelVersion"),
+            command_line: get_string(result, "commandLine"),
+        })
+    }
+}
+
+impl BrowserHost {
+    /// Query the state of the GPU process. The information is global to the
+    /// application; the browser is only used to reach the DevTools agent.
+    ///
+    /// `callback` will be called on the browser process UI thread.
+    pub fn get_gpu_info(&self, callback: impl 'static + Send + FnOnce(Result<GpuInfo, DevToolsError>)) {
+        self.call_dev_tools_method("SystemInfo.getInfo", None, move |result| {
+            callback(result.and_then(|result| GpuInfo::from_result(&result).ok_or(DevToolsError::InvalidResult)))
+        });
+    }
+}
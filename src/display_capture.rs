@@ -0,0 +1,285 @@
+//! Screen sharing with `getDisplayMedia`.
+//!
+//! Chromium asks the user which screen or window to share with a picker,
+//! which CEF doesn't have: with the `enable-media-stream` switch CEF shares
+//! the entire screen without asking, and without it every request fails.
+//! After [BrowserHost::enable_display_capture], `getDisplayMedia` calls of
+//! pages are forwarded to a [DisplayCaptureHandlerCallbacks] implementation,
+//! which picks the [DisplayCaptureSource] to share, e.g. the application's own
+//! window, or denies the request.
+//!
+//! The shared source is captured with Chromium's desktop capturer, so the
+//! `enable-media-stream` switch is still required. The switch grants all
+//! media requests, including those of pages asking for a desktop source
+//! directly, so the handler is a policy for the pages the application loads,
+//! not a security boundary. Only video is shared.
+//!
+//! Messages from pages arrive through a DevTools binding. The origin of a
+//! request is taken from the DevTools execution context it was sent from, not
+//! from the page.
+
+use crate::{
+    browser::Browser,
+    browser_host::BrowserHost,
+    devtools::{get_bool, get_dictionary, get_i32, get_string, page::UserScriptSlot, parse_json_dictionary},
+    devtools_message_observer::{DevToolsMessageObserver, DevToolsMessageObserverCallbacks},
+    registration::Registration,
+    string::quote_string,
+    values::StoredValue,
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// Name of the DevTools binding pages send their requests through.
+const BINDING: &str = "__cefRequestDisplayCapture";
+
+/// Replaces `navigator.mediaDevices.getDisplayMedia`. Running it again in the
+/// same document only enables the replacement again after
+/// [DISABLE_SCRIPT].
+const DISPLAY_CAPTURE_SCRIPT: &str = r#"(function() {
+if (window.__cefDisplayCapture) {
+  window.__cefDisplayCapture.disabled = false;
+  return;
+}
+if (!navigator.mediaDevices) return;
+var state = window.__cefDisplayCapture = { pending: {}, next: 1, disabled: false };
+var getUserMedia = navigator.mediaDevices.getUserMedia.bind(navigator.mediaDevices);
+var denied = function() { return new DOMException('Permission denied', 'NotAllowedError'); };
+navigator.mediaDevices.getDisplayMedia = function(constraints) {
+  constraints = constraints || {};
+  if (state.disabled) return Promise.reject(denied());
+  return new Promise(function(resolve, reject) {
+    var id = String(state.next++);
+    state.pending[id] = { resolve: resolve, reject: reject, video: constraints.video };
+    window.__cefRequestDisplayCapture(JSON.stringify({ id: id, audio: !!constraints.audio }));
+  });
+};
+state.resolve = function(id, sourceId) {
+  var request = state.pending[id];
+  delete state.pending[id];
+  if (!request) return;
+  if (!sourceId) {
+    request.reject(denied());
+    return;
+  }
+  var mandatory = { chromeMediaSource: 'desktop', chromeMediaSourceId: sourceId };
+  var video = typeof request.video === 'object' ? request.video : {};
+  var max = function(value) { return typeof value === 'object' ? value.max || value.ideal : value; };
+  if (max(video.width)) mandatory.maxWidth = max(video.width);
+  if (max(video.height)) mandatory.maxHeight = max(video.height);
+  if (max(video.frameRate)) mandatory.maxFrameRate = max(video.frameRate);
+  getUserMedia({ audio: false, video: { mandatory: mandatory } }).then(request.resolve, request.reject);
+};
+state.disable = function() {
+  state.disabled = true;
+  Object.keys(state.pending).forEach(function(id) { state.resolve(id, null); });
+};
+})();"#;
+
+/// Denies the pending and future requests of a document once the
+/// [Registration] was dropped.
+const DISABLE_SCRIPT: &str = "window.__cefDisplayCapture && window.__cefDisplayCapture.disable();";
+
+/// What a page can share.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DisplayCaptureSource {
+    /// The screen with the given platform id; see
+    /// [DisplayCaptureSource::ENTIRE_SCREEN].
+    Screen(i64),
+    /// The top-level window with the given platform handle, e.g. an `HWND` on
+    /// Windows, an X11 window id on Linux or a `CGWindowID` on macOS.
+    Window(i64),
+}
+
+impl DisplayCaptureSource {
+    /// All screens together.
+    pub const ENTIRE_SCREEN: DisplayCaptureSource = DisplayCaptureSource::Screen(-1);
+
+    /// Returns the id of the source in Chromium's desktop media id format.
+    pub fn media_id(&self) -> String {
+        match self {
+            DisplayCaptureSource::Screen(id) => format!("screen:{}:0", id),
+            DisplayCaptureSource::Window(id) => format!("window:{}:0", id),
+        }
+    }
+}
+
+/// Implement this trait to decide what pages can share.
+pub trait DisplayCaptureHandlerCallbacks: 'static + Send {
+    /// Called when a page of `origin` calls `getDisplayMedia`. `audio` is true
+    /// if the page asked for audio as well, which isn't shared. Show a picker
+    /// and call [DisplayCaptureCallback::cont] with the user's choice, now or
+    /// later. Denies the request by default.
+    fn on_display_capture_request(
+        &mut self,
+        browser: &Browser,
+        origin: &str,
+        audio: bool,
+        callback: DisplayCaptureCallback,
+    ) {
+        callback.cont(None);
+    }
+}
+
+/// Evaluate `expression` in the execution context with the given id.
+fn evaluate_in_context(host: &BrowserHost, context_id: i32, expression: String) {
+    let mut params = HashMap::new();
+    params.insert("expression".to_owned(), StoredValue::String(expression));
+    params.insert("contextId".to_owned(), StoredValue::Int(context_id));
+    host.call_dev_tools_method_unit("Runtime.evaluate", Some(params), |_| ());
+}
+
+struct PendingRequest {
+    host: BrowserHost,
+    request_id: String,
+    context_id: i32,
+}
+
+impl PendingRequest {
+    fn resolve(self, source: Option<DisplayCaptureSource>) {
+        let source_id = match source {
+            Some(source) => quote_string(&source.media_id()),
+            None => "null".to_owned(),
+        };
+        evaluate_in_context(
+            &self.host,
+            self.context_id,
+            format!(
+                "window.__cefDisplayCapture && window.__cefDisplayCapture.resolve({}, {})",
+                quote_string(&self.request_id),
+                source_id
+            ),
+        );
+    }
+}
+
+/// Answers a `getDisplayMedia` request. Dropping it without calling
+/// [DisplayCaptureCallback::cont] denies the request.
+pub struct DisplayCaptureCallback {
+    origin: String,
+    request: Option<PendingRequest>,
+}
+
+impl DisplayCaptureCallback {
+    /// The origin asking to capture.
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+    /// Share `source` with the page, or deny the request with `None`.
+    pub fn cont(mut self, source: Option<DisplayCaptureSource>) {
+        self.request.take().unwrap().resolve(source);
+    }
+}
+
+impl Drop for DisplayCaptureCallback {
+    fn drop(&mut self) {
+        if let Some(request) = self.request.take() {
+            request.resolve(None);
+        }
+    }
+}
+
+impl BrowserHost {
+    /// Forward the `getDisplayMedia` calls of pages in this browser to
+    /// `callbacks` until the returned [Registration] is dropped. Afterwards,
+    /// pending and future calls of pages loaded before are denied, and new
+    /// documents get Chromium's `getDisplayMedia` back.
+    ///
+    /// `callbacks` will be called on the browser process UI thread.
+    pub fn enable_display_capture(&self, callbacks: impl DisplayCaptureHandlerCallbacks) -> Registration {
+        let user_script = Arc::new(UserScriptSlot::default());
+        let disabled = Arc::new(AtomicBool::new(false));
+        let registration = self.add_dev_tools_message_observer(DevToolsMessageObserver::new(DisplayCaptureObserver {
+            host: self.clone(),
+            callbacks,
+            contexts: HashMap::new(),
+            user_script: user_script.clone(),
+            disabled: disabled.clone(),
+        }));
+        let mut params = HashMap::new();
+        params.insert("name".to_owned(), StoredValue::String(BINDING.to_owned()));
+        let host = self.clone();
+        self.call_dev_tools_method_unit("Runtime.addBinding", Some(params), move |result| {
+            if result.is_err() || disabled.load(Ordering::SeqCst) {
+                return;
+            }
+            // Reports the existing execution contexts, whose origins are
+            // needed to attribute requests.
+            host.call_dev_tools_method_unit("Runtime.enable", None, |_| ());
+            host.execute_in_all_frames(DISPLAY_CAPTURE_SCRIPT);
+            user_script.set(&host, Some(DISPLAY_CAPTURE_SCRIPT.to_owned()), |_| ());
+        });
+        registration
+    }
+}
+
+struct DisplayCaptureObserver<C: DisplayCaptureHandlerCallbacks> {
+    host: BrowserHost,
+    callbacks: C,
+    /// Origins of the execution contexts, keyed by context id.
+    contexts: HashMap<i32, String>,
+    user_script: Arc<UserScriptSlot>,
+    /// Set once the registration was dropped, so a late `Runtime.addBinding`
+    /// result doesn't install the script anymore.
+    disabled: Arc<AtomicBool>,
+}
+
+/// CEF releases the observer when the [Registration] returned by
+/// [BrowserHost::enable_display_capture] is dropped.
+impl<C: DisplayCaptureHandlerCallbacks> Drop for DisplayCaptureObserver<C> {
+    fn drop(&mut self) {
+        self.disabled.store(true, Ordering::SeqCst);
+        self.user_script.set(&self.host, None, |_| ());
+        self.host.execute_in_all_frames(DISABLE_SCRIPT);
+        let mut params = HashMap::new();
+        params.insert("name".to_owned(), StoredValue::String(BINDING.to_owned()));
+        self.host.call_dev_tools_method_unit("Runtime.removeBinding", Some(params), |_| ());
+    }
+}
+
+impl<C: DisplayCaptureHandlerCallbacks> DevToolsMessageObserverCallbacks for DisplayCaptureObserver<C> {
+    fn on_dev_tools_event(&mut self, browser: Browser, method: &str, params: &[u8]) {
+        let params = match parse_json_dictionary(params) {
+            Some(params) => params,
+            None => return,
+        };
+        match method {
+            "Runtime.executionContextCreated" => {
+                if let Some(context) = get_dictionary(&params, "context") {
+                    self.contexts.insert(get_i32(&context, "id"), get_string(&context, "origin"));
+                }
+            }
+            "Runtime.executionContextDestroyed" => {
+                self.contexts.remove(&get_i32(&params, "executionContextId"));
+            }
+            "Runtime.executionContextsCleared" => self.contexts.clear(),
+            "Runtime.bindingCalled" if get_string(&params, "name") == BINDING => {
+                let context_id = get_i32(&params, "executionContextId");
+                let origin = match self.contexts.get(&context_id) {
+                    Some(origin) => origin.clone(),
+                    None => return,
+                };
+                let message = match parse_json_dictionary(get_string(&params, "payload").as_bytes()) {
+                    Some(message) => message,
+                    None => return,
+                };
+                let callback = DisplayCaptureCallback {
+                    origin: origin.clone(),
+                    request: Some(PendingRequest {
+                        host: self.host.clone(),
+                        request_id: get_string(&message, "id"),
+                        context_id,
+                    }),
+                };
+                self.callbacks
+                    .on_display_capture_request(&browser, &origin, get_bool(&message, "audio"), callback);
+            }
+            _ => (),
+        }
+    }
+}
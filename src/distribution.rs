@@ -0,0 +1,292 @@
+//! Checking that the CEF binary distribution is laid out as CEF expects.
+//!
+//! Chromium aborts with little explanation when a file it needs is missing,
+//! often in a subprocess, long after startup. [check] looks for the files
+//! CEF loads with the given [Settings] before CEF is initialized, so a broken
+//! installation can be reported with the path that's missing and what to do
+//! about it:
+//!
+//! ```rust,ignore
+//! let report = distribution::check(&settings);
+//! for problem in &report.warnings {
+//!     log::warn!("{}", problem);
+//! }
+//! report.into_result()?;
+//! let context = Context::initialize(settings, Some(app), None)?;
+//! ```
+//!
+//! Once CEF is loaded, [get_path] returns the directories CEF itself
+//! resolves, like [PathKey::Resources].
+
+use crate::{
+    get_path,
+    i18n::{self, DEFAULT_LOCALE},
+    settings::Settings,
+    PathKey,
+};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// Returns the directory CEF will load pack files from with `settings`,
+/// without needing CEF to be loaded. Use [get_path] with
+/// [PathKey::Resources] afterwards.
+pub fn resources_dir(settings: &Settings) -> Option<PathBuf> {
+    if cfg!(target_os = "macos") {
+        Some(framework_dir(settings)?.join("Resources"))
+    } else {
+        match &settings.resources_dir_path {
+            Some(resources_dir) => Some(resources_dir.clone()),
+            None => module_dir(),
+        }
+    }
+}
+
+/// Resolves `path` against the resources directory like CEF does, unless it's
+/// absolute.
+pub fn resolve_resource_path(settings: &Settings, path: impl AsRef<Path>) -> Option<PathBuf> {
+    let path = path.as_ref();
+    if path.is_absolute() {
+        Some(path.to_owned())
+    } else {
+        Some(resources_dir(settings)?.join(path))
+    }
+}
+
+fn exe_dir() -> Option<PathBuf> {
+    Some(std::env::current_exe().ok()?.parent()?.to_owned())
+}
+
+/// Returns the directory containing the CEF library, which CEF loads its data
+/// files from on Windows and Linux. The library is linked into the process
+/// there, so CEF can resolve it before being initialized.
+pub(crate) fn module_dir() -> Option<PathBuf> {
+    get_path(PathKey::ModuleDir).or_else(exe_dir)
+}
+
+fn framework_dir(settings: &Settings) -> Option<PathBuf> {
+    match &settings.framework_dir_path {
+        Some(framework_dir) => Some(framework_dir.clone()),
+        None => Some(exe_dir()?.join("../Frameworks/Chromium Embedded Framework.framework")),
+    }
+}
+
+/// A missing or misplaced part of the distribution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistributionProblem {
+    /// The path that was expected to exist.
+    pub path: PathBuf,
+    /// What's wrong, and how to fix it.
+    pub message: String,
+}
+
+impl fmt::Display for DistributionProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+/// The result of [check].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DistributionReport {
+    /// Problems that will make CEF abort or fail to show pages.
+    pub errors: Vec<DistributionProblem>,
+    /// Problems that disable optional features, like DevTools or a locale.
+    pub warnings: Vec<DistributionProblem>,
+}
+
+impl DistributionReport {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+    /// Returns the errors, if any, as an error that can be returned from
+    /// `main`.
+    pub fn into_result(self) -> Result<(), DistributionError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(DistributionError { problems: self.errors })
+        }
+    }
+    fn error(&mut self, path: PathBuf, message: impl Into<String>) {
+        self.errors.push(DistributionProblem { path, message: message.into() });
+    }
+    fn warning(&mut self, path: PathBuf, message: impl Into<String>) {
+        self.warnings.push(DistributionProblem { path, message: message.into() });
+    }
+}
+
+/// The CEF distribution is incomplete.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistributionError {
+    pub problems: Vec<DistributionProblem>,
+}
+
+impl fmt::Display for DistributionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the CEF distribution is incomplete")?;
+        for problem in &self.problems {
+            write!(f, "\n  {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DistributionError {}
+
+/// Check the files CEF will load with `settings`. Can be called before CEF
+/// is loaded.
+pub fn check(settings: &Settings) -> DistributionReport {
+    let mut report = DistributionReport::default();
+    let resources_dir = match resources_dir(settings) {
+        Some(resources_dir) => resources_dir,
+        None => {
+            report.error(PathBuf::new(), "cannot determine the CEF library's directory to find the resources in");
+            return report;
+        }
+    };
+
+    if cfg!(target_os = "macos") {
+        let framework_dir = resources_dir.parent().map(Path::to_owned).unwrap_or_default();
+        if !framework_dir.join("Chromium Embedded Framework").is_file() {
+            report.error(
+                framework_dir.clone(),
+                "the CEF framework is missing; copy \"Chromium Embedded Framework.framework\" into the \
+                 bundle's Frameworks directory or set Settings::framework_dir_path",
+            );
+            return report;
+        }
+    } else if settings.resources_dir_path.is_some() && !resources_dir.is_dir() {
+        report.error(resources_dir, "Settings::resources_dir_path doesn't exist");
+        return report;
+    }
+
+    // The V8 snapshots and ICU data are loaded from the library's directory
+    // on Windows and Linux, regardless of the resources directory.
+    let data_dir = if cfg!(target_os = "macos") {
+        Some(resources_dir.clone())
+    } else {
+        module_dir()
+    };
+    if let Some(data_dir) = data_dir {
+        for file in &["icudtl.dat", "snapshot_blob.bin", "v8_context_snapshot.bin"] {
+            let path = data_dir.join(file);
+            if !path.is_file() {
+                report.error(
+                    path,
+                    "required by V8 and ICU; copy it from the Release directory of the CEF distribution \
+                     next to the CEF library",
+                );
+            }
+        }
+    }
+
+    if !settings.pack_loading_disabled {
+        for file in &["cef.pak", "cef_100_percent.pak", "cef_200_percent.pak"] {
+            let path = resources_dir.join(file);
+            if !path.is_file() {
+                report.error(
+                    path,
+                    "required resource pack; copy it from the Resources directory of the CEF distribution \
+                     or set Settings::resources_dir_path",
+                );
+            }
+        }
+        let optional = [
+            ("cef_extensions.pak", "extensions and the PDF viewer won't work"),
+            ("devtools_resources.pak", "DevTools won't open"),
+        ];
+        for (file, consequence) in &optional {
+            let path = resources_dir.join(file);
+            if !path.is_file() {
+                report.warning(path, format!("missing resource pack; {}", consequence));
+            }
+        }
+        check_locales(settings, &mut report);
+    }
+
+    if let Some(subprocess) = &settings.browser_subprocess_path {
+        if !subprocess.is_file() {
+            report.error(
+                subprocess.clone(),
+                "Settings::browser_subprocess_path doesn't exist, so no renderer, GPU or utility process can start",
+            );
+        }
+    } else if cfg!(target_os = "macos") {
+        check_macos_helper(&mut report);
+    }
+    report
+}
+
+fn check_locales(settings: &Settings, report: &mut DistributionReport) {
+    let locales_dir = match i18n::locales_dir(settings) {
+        Some(locales_dir) => locales_dir,
+        None => return,
+    };
+    let available = match i18n::available_locales(&locales_dir) {
+        Ok(available) => available,
+        Err(_) => {
+            report.error(
+                locales_dir,
+                "the locales directory is missing; copy it from the Resources directory of the CEF \
+                 distribution or set Settings::locales_dir_path",
+            );
+            return;
+        }
+    };
+    if available.is_empty() {
+        report.error(locales_dir, "the locales directory contains no locale pack files");
+        return;
+    }
+    // Linux takes the locale from the environment instead.
+    let locale = if cfg!(target_os = "linux") {
+        None
+    } else {
+        settings.locale.as_deref()
+    };
+    if i18n::best_available_locale(&available, DEFAULT_LOCALE).is_none() {
+        report.error(
+            locale_path(&locales_dir, DEFAULT_LOCALE),
+            "the fallback locale is missing; keep it when trimming the locales",
+        );
+    }
+    if let Some(locale) = locale {
+        if i18n::best_available_locale(&available, locale).is_none() {
+            report.warning(
+                locale_path(&locales_dir, locale),
+                format!("no pack file for Settings::locale, so {} is used", DEFAULT_LOCALE),
+            );
+        }
+    }
+}
+
+fn locale_path(locales_dir: &Path, locale: &str) -> PathBuf {
+    if cfg!(target_os = "macos") {
+        locales_dir.join(format!("{}.lproj", locale.replace('-', "_"))).join("locale.pak")
+    } else {
+        locales_dir.join(format!("{}.pak", locale))
+    }
+}
+
+fn check_macos_helper(report: &mut DistributionReport) {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(_) => return,
+    };
+    let (name, macos_dir) = match (exe.file_name(), exe.parent()) {
+        (Some(name), Some(macos_dir)) => (name.to_string_lossy().into_owned(), macos_dir),
+        _ => return,
+    };
+    let helper = macos_dir.join(format!(
+        "../Frameworks/{} Helper.app/Contents/MacOS/{} Helper",
+        name, name
+    ));
+    if !helper.is_file() {
+        report.error(
+            helper,
+            "the helper app for subprocesses is missing; add it to the bundle or set \
+             Settings::browser_subprocess_path",
+        );
+    }
+}
@@ -0,0 +1,389 @@
+//! Higher-level downloads on top of [DownloadHandlerCallbacks].
+//!
+//! A [DownloadManager] accepts every download into a configured directory and
+//! hands out a [DownloadTask] for each one. A task can be awaited for the final
+//! path, watched for progress, and paused, resumed or canceled. While a
+//! download is in progress it's written to a temporary file next to its target,
+//! which is renamed once the download completes and removed if it's canceled.
+//!
+//! The futures in this module don't depend on any executor; they are woken from
+//! the browser process UI thread.
+
+use crate::{
+    browser::Browser,
+    client::download_handler::{
+        BeforeDownloadCallback, DownloadHandler, DownloadHandlerCallbacks, DownloadItem,
+        DownloadItemCallback,
+    },
+};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    future::Future,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+};
+
+/// Where and how downloads are written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadPolicy {
+    target_dir: PathBuf,
+    temp_suffix: Option<String>,
+    unique_names: bool,
+    delete_on_cancel: bool,
+}
+
+impl DownloadPolicy {
+    /// Download into `target_dir`, using a ".part" temporary file, choosing a
+    /// unique file name and deleting partial files of canceled downloads.
+    pub fn new(target_dir: impl Into<PathBuf>) -> Self {
+        DownloadPolicy {
+            target_dir: target_dir.into(),
+            temp_suffix: Some(".part".to_owned()),
+            unique_names: true,
+            delete_on_cancel: true,
+        }
+    }
+    /// Suffix appended to the file name while downloading. With `None` the
+    /// download is written to its final path directly.
+    pub fn with_temp_suffix(mut self, temp_suffix: Option<&str>) -> Self {
+        self.temp_suffix = temp_suffix.map(str::to_owned);
+        self
+    }
+    /// If true, " (1)", " (2)", ... is appended to the file stem when a file
+    /// with the suggested name exists. Otherwise existing files are
+    /// overwritten.
+    pub fn with_unique_names(mut self, unique_names: bool) -> Self {
+        self.unique_names = unique_names;
+        self
+    }
+    /// Whether the partial file of a canceled download is deleted.
+    pub fn with_delete_on_cancel(mut self, delete_on_cancel: bool) -> Self {
+        self.delete_on_cancel = delete_on_cancel;
+        self
+    }
+
+    fn target_path(&self, suggested_name: &str) -> PathBuf {
+        // Never let the server pick the directory.
+        let name = Path::new(suggested_name)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| "download".to_owned());
+        let path = self.target_dir.join(&name);
+        if !self.unique_names {
+            return path;
+        }
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let extension = path.extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+        let mut candidate = path;
+        let mut n = 1;
+        while candidate.exists() || self.temp_path(&candidate).exists() {
+            candidate = self.target_dir.join(format!("{} ({}){}", stem, n, extension));
+            n += 1;
+        }
+        candidate
+    }
+    fn temp_path(&self, target: &Path) -> PathBuf {
+        match &self.temp_suffix {
+            Some(suffix) => {
+                let mut name = target.file_name().unwrap_or_default().to_os_string();
+                name.push(suffix);
+                target.with_file_name(name)
+            }
+            None => target.to_owned(),
+        }
+    }
+}
+
+/// State of a [DownloadTask].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DownloadState {
+    InProgress,
+    Paused,
+    Complete,
+    Canceled,
+}
+
+/// Snapshot of a download's progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadProgress {
+    pub state: DownloadState,
+    pub received_bytes: u64,
+    /// Total size, if known.
+    pub total_bytes: Option<u64>,
+    /// Rough percentage, if the total size is known.
+    pub percent_complete: Option<u8>,
+    /// Simple speed estimate in bytes/s.
+    pub current_speed: u64,
+}
+
+/// Reason a download didn't produce a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadError {
+    /// The download was canceled or interrupted.
+    Canceled,
+    /// The completed download couldn't be moved to its target path.
+    Io(io::ErrorKind),
+}
+
+struct TaskState {
+    id: u32,
+    url: String,
+    target_path: PathBuf,
+    temp_path: PathBuf,
+    delete_on_cancel: bool,
+    progress: DownloadProgress,
+    /// Incremented on every progress change.
+    version: u64,
+    result: Option<Result<PathBuf, DownloadError>>,
+    callback: Option<DownloadItemCallback>,
+    wakers: Vec<Waker>,
+}
+
+impl TaskState {
+    fn wake(&mut self) {
+        for waker in self.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+    fn register(&mut self, waker: &Waker) {
+        if !self.wakers.iter().any(|w| w.will_wake(waker)) {
+            self.wakers.push(waker.clone());
+        }
+    }
+}
+
+/// Handle to a download accepted by a [DownloadManager]. Clones refer to the
+/// same download.
+#[derive(Clone)]
+pub struct DownloadTask(Arc<Mutex<TaskState>>);
+
+impl DownloadTask {
+    /// Returns the download's unique identifier, as reported by
+    /// [DownloadItem::get_id].
+    pub fn id(&self) -> u32 {
+        self.0.lock().id
+    }
+    pub fn url(&self) -> String {
+        self.0.lock().url.clone()
+    }
+    /// Returns the path the file will have once the download completes.
+    pub fn target_path(&self) -> PathBuf {
+        self.0.lock().target_path.clone()
+    }
+    pub fn progress(&self) -> DownloadProgress {
+        self.0.lock().progress
+    }
+    /// Returns a future resolving to the final path of the file once the
+    /// download completes.
+    pub fn completion(&self) -> DownloadCompletion {
+        DownloadCompletion(self.0.clone())
+    }
+    /// Returns a watcher reporting progress changes from now on.
+    pub fn watch_progress(&self) -> ProgressWatch {
+        let seen = self.0.lock().version;
+        ProgressWatch {
+            state: self.0.clone(),
+            seen,
+        }
+    }
+    pub fn pause(&self) {
+        let mut state = self.0.lock();
+        if let Some(callback) = &state.callback {
+            callback.pause();
+            state.progress.state = DownloadState::Paused;
+            state.version += 1;
+            state.wake();
+        }
+    }
+    pub fn resume(&self) {
+        let mut state = self.0.lock();
+        if let Some(callback) = &state.callback {
+            callback.resume();
+            state.progress.state = DownloadState::InProgress;
+            state.version += 1;
+            state.wake();
+        }
+    }
+    /// Cancel the download. The partial file is removed according to the
+    /// [DownloadPolicy] once CEF reports the cancellation.
+    pub fn cancel(&self) {
+        if let Some(callback) = &self.0.lock().callback {
+            callback.cancel();
+        }
+    }
+}
+
+/// Future returned by [DownloadTask::completion].
+pub struct DownloadCompletion(Arc<Mutex<TaskState>>);
+
+impl Future for DownloadCompletion {
+    type Output = Result<PathBuf, DownloadError>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let mut state = self.0.lock();
+        match &state.result {
+            Some(result) => Poll::Ready(result.clone()),
+            None => {
+                state.register(cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Watches the progress of a [DownloadTask].
+pub struct ProgressWatch {
+    state: Arc<Mutex<TaskState>>,
+    seen: u64,
+}
+
+impl ProgressWatch {
+    /// Returns a future resolving to the progress once it differs from the
+    /// last one seen by this watcher, or to `None` once the download has
+    /// finished and its final progress was seen.
+    pub fn changed(&mut self) -> ProgressChanged<'_> {
+        ProgressChanged(self)
+    }
+}
+
+/// Future returned by [ProgressWatch::changed].
+pub struct ProgressChanged<'a>(&'a mut ProgressWatch);
+
+impl Future for ProgressChanged<'_> {
+    type Output = Option<DownloadProgress>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let watch = &mut *self.0;
+        let mut state = watch.state.lock();
+        if state.version != watch.seen {
+            watch.seen = state.version;
+            Poll::Ready(Some(state.progress))
+        } else if state.result.is_some() {
+            Poll::Ready(None)
+        } else {
+            state.register(cx.waker());
+            Poll::Pending
+        }
+    }
+}
+
+/// Function receiving every download accepted by a [DownloadManager].
+pub trait DownloadStartedCallback = 'static + Send + FnMut(&Browser, DownloadTask);
+
+/// [DownloadHandlerCallbacks] implementation accepting all downloads according
+/// to a [DownloadPolicy].
+pub struct DownloadManager {
+    policy: DownloadPolicy,
+    on_started: Box<dyn DownloadStartedCallback>,
+    tasks: HashMap<u32, DownloadTask>,
+}
+
+impl DownloadManager {
+    pub fn new(policy: DownloadPolicy, on_started: impl DownloadStartedCallback) -> Self {
+        DownloadManager {
+            policy,
+            on_started: Box::new(on_started),
+            tasks: HashMap::new(),
+        }
+    }
+    pub fn build(self) -> DownloadHandler {
+        DownloadHandler::new(self)
+    }
+}
+
+impl DownloadHandlerCallbacks for DownloadManager {
+    fn on_before_download(
+        &mut self,
+        browser: Browser,
+        download_item: DownloadItem,
+        suggested_name: &str,
+        callback: BeforeDownloadCallback,
+    ) {
+        let target_path = self.policy.target_path(suggested_name);
+        let temp_path = self.policy.temp_path(&target_path);
+        let task = DownloadTask(Arc::new(Mutex::new(TaskState {
+            id: download_item.get_id(),
+            url: download_item.get_url(),
+            target_path,
+            temp_path: temp_path.clone(),
+            delete_on_cancel: self.policy.delete_on_cancel,
+            progress: progress_of(&download_item, DownloadState::InProgress),
+            version: 0,
+            result: None,
+            callback: None,
+            wakers: Vec::new(),
+        })));
+        self.tasks.insert(download_item.get_id(), task.clone());
+        callback.cont(&temp_path.to_string_lossy(), false);
+        (self.on_started)(&browser, task);
+    }
+    fn on_download_updated(
+        &mut self,
+        browser: Browser,
+        download_item: DownloadItem,
+        callback: DownloadItemCallback,
+    ) {
+        let id = download_item.get_id();
+        let task = match self.tasks.get(&id) {
+            Some(task) => task.clone(),
+            None => return,
+        };
+        let mut state = task.0.lock();
+        let progress_state = if download_item.is_complete() {
+            DownloadState::Complete
+        } else if download_item.is_canceled() {
+            DownloadState::Canceled
+        } else if state.progress.state == DownloadState::Paused {
+            DownloadState::Paused
+        } else {
+            DownloadState::InProgress
+        };
+        let progress = progress_of(&download_item, progress_state);
+        if progress != state.progress {
+            state.progress = progress;
+            state.version += 1;
+        }
+        state.callback = Some(callback);
+        match progress_state {
+            DownloadState::Complete => {
+                let result = if state.temp_path == state.target_path {
+                    Ok(state.target_path.clone())
+                } else {
+                    std::fs::rename(&state.temp_path, &state.target_path)
+                        .map(|_| state.target_path.clone())
+                        .map_err(|e| DownloadError::Io(e.kind()))
+                };
+                state.result = Some(result);
+            }
+            DownloadState::Canceled => {
+                if state.delete_on_cancel {
+                    let _ = std::fs::remove_file(&state.temp_path);
+                }
+                state.result = Some(Err(DownloadError::Canceled));
+            }
+            _ => (),
+        }
+        if state.result.is_some() {
+            state.callback = None;
+            drop(state);
+            self.tasks.remove(&id);
+            task.0.lock().wake();
+        } else {
+            state.wake();
+        }
+    }
+}
+
+fn progress_of(item: &DownloadItem, state: DownloadState) -> DownloadProgress {
+    let total_bytes = item.get_total_bytes();
+    DownloadProgress {
+        state,
+        received_bytes: item.get_received_bytes(),
+        total_bytes: if total_bytes > 0 { Some(total_bytes) } else { None },
+        percent_complete: item.get_percent_complete(),
+        current_speed: item.get_current_speed(),
+    }
+}
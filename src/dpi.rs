@@ -0,0 +1,113 @@
+//! Keeping browsers sharp when their window moves between monitors.
+//!
+//! When a window is dragged to a monitor with a different scale factor, CEF
+//! has to be told so it renders at the new scale; otherwise the page is
+//! scaled by the OS and looks blurry. A [ScaleFactorTracker] is told the scale
+//! factor of the browser's window, either by the application's windowing
+//! library or, on Windows, from `WM_DPICHANGED` of the top-level window, and
+//! notifies CEF when it changes.
+
+use crate::{
+    browser_host::BrowserHost,
+    zoom::ZOOM_LEVEL_BASE,
+};
+use parking_lot::Mutex;
+
+/// What happens to the size of page content when the scale factor changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZoomCompensation {
+    /// Content keeps its size in logical pixels, so it grows physically on a
+    /// monitor with a higher scale factor, like other applications do.
+    None,
+    /// The zoom level is adjusted so content keeps its physical size, like in
+    /// an application that isn't DPI aware, but without the blur.
+    KeepPhysicalSize,
+}
+
+/// Notifies a browser of changes to the scale factor of its window.
+pub struct ScaleFactorTracker {
+    host: BrowserHost,
+    scale_factor: Mutex<f64>,
+    compensation: ZoomCompensation,
+}
+
+impl ScaleFactorTracker {
+    /// Track the scale factor of the window of `host`, which is currently
+    /// `scale_factor` (1.0 for 96 DPI).
+    pub fn new(host: BrowserHost, scale_factor: f64) -> ScaleFactorTracker {
+        ScaleFactorTracker {
+            host,
+            scale_factor: Mutex::new(scale_factor),
+            compensation: ZoomCompensation::None,
+        }
+    }
+    pub fn with_zoom_compensation(mut self, compensation: ZoomCompensation) -> Self {
+        self.compensation = compensation;
+        self
+    }
+    pub fn scale_factor(&self) -> f64 {
+        *self.scale_factor.lock()
+    }
+    /// Call when the window's scale factor changed, e.g. on winit's
+    /// `WindowEvent::ScaleFactorChanged` or when the backing scale factor of
+    /// the window changed on macOS. Call this after the window has its new
+    /// size.
+    ///
+    /// Windowless browsers report the new scale factor from
+    /// [RenderHandlerCallbacks::get_screen_info](crate::client::render_handler::RenderHandlerCallbacks::get_screen_info),
+    /// which is queried again from here.
+    pub fn set_scale_factor(&self, scale_factor: f64) {
+        let old = {
+            let mut current = self.scale_factor.lock();
+            if (*current - scale_factor).abs() < 0.001 {
+                return;
+            }
+            std::mem::replace(&mut *current, scale_factor)
+        };
+        self.host.notify_screen_info_changed();
+        self.host.was_resized();
+        if self.compensation == ZoomCompensation::KeepPhysicalSize {
+            let delta = (old / scale_factor).ln() / ZOOM_LEVEL_BASE.ln();
+            self.host.change_zoom_level(move |level| level + delta);
+        }
+    }
+    /// Handle a message received by the browser's top-level window. On
+    /// `WM_DPICHANGED` this moves the window to the rectangle Windows suggests
+    /// for the new DPI and updates the scale factor. Returns true if the
+    /// message was handled and shouldn't be passed to `DefWindowProc`.
+    ///
+    /// Child windows, such as a browser embedded with
+    /// [WindowsEmbedder](crate::window::windows::WindowsEmbedder), don't
+    /// receive this message, which is why they need to be notified.
+    #[cfg(target_os = "windows")]
+    pub unsafe fn handle_message(
+        &self,
+        hwnd: winapi::shared::windef::HWND,
+        message: winapi::shared::minwindef::UINT,
+        wparam: winapi::shared::minwindef::WPARAM,
+        lparam: winapi::shared::minwindef::LPARAM,
+    ) -> bool {
+        use winapi::{
+            shared::{minwindef::HIWORD, windef::RECT},
+            um::winuser::{SetWindowPos, SWP_NOACTIVATE, SWP_NOZORDER, USER_DEFAULT_SCREEN_DPI, WM_DPICHANGED},
+        };
+        if message != WM_DPICHANGED {
+            return false;
+        }
+        // Both words hold the same value, the Y axis DPI is in the high word.
+        let dpi = HIWORD(wparam as u32);
+        if let Some(rect) = (lparam as *const RECT).as_ref() {
+            SetWindowPos(
+                hwnd,
+                std::ptr::null_mut(),
+                rect.left,
+                rect.top,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                SWP_NOZORDER | SWP_NOACTIVATE,
+            );
+        }
+        self.set_scale_factor(f64::from(dpi) / f64::from(USER_DEFAULT_SCREEN_DPI));
+        true
+    }
+}
@@ -21,6 +21,7 @@ bitflags!{
         const PRIVATE = cef_drag_operations_mask_t::DRAG_OPERATION_PRIVATE.0;
         const MOVE = cef_drag_operations_mask_t::DRAG_OPERATION_MOVE.0;
         const DELETE = cef_drag_operations_mask_t::DRAG_OPERATION_DELETE.0;
+        const EVERY = cef_drag_operations_mask_t::DRAG_OPERATION_EVERY.0;
     }
 }
 
@@ -28,6 +29,22 @@ impl DragOperation {
     pub unsafe fn from_unchecked(c: crate::CEnumType) -> Self {
         std::mem::transmute(c)
     }
+    /// Returns the single operation to perform out of these allowed
+    /// operations, preferring copy over move over link, like Chromium does.
+    /// Returns [DragOperation::NONE] if none of them applies.
+    pub fn preferred(self) -> DragOperation {
+        [DragOperation::COPY, DragOperation::MOVE, DragOperation::LINK, DragOperation::GENERIC]
+            .iter()
+            .copied()
+            .find(|&op| self.contains(op))
+            .unwrap_or(DragOperation::NONE)
+    }
+}
+
+impl From<&[DragOperation]> for DragOperation {
+    fn from(ops: &[DragOperation]) -> Self {
+        ops.iter().copied().collect()
+    }
 }
 
 ref_counted_ptr! {
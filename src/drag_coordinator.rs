@@ -0,0 +1,272 @@
+//! Drag and drop between windowless browsers.
+//!
+//! With window rendering disabled CEF leaves drag and drop to the application:
+//! [RenderHandlerCallbacks::start_dragging](crate::client::render_handler::RenderHandlerCallbacks::start_dragging)
+//! reports a drag leaving a browser, and the `drag_target_*` functions of
+//! [BrowserHost] feed a drag into one. A [DragCoordinator] connects the two for
+//! a set of browsers sharing one coordinate space (usually the screen), so
+//! content can be dragged from one view into another. Drags released outside
+//! of every view are handed to a callback, which can e.g. tear a tab off into a
+//! new window or start an OS drag.
+//!
+//! While a drag is in progress, the application forwards the pointer position
+//! to [DragCoordinator::drag_move] and the button release to
+//! [DragCoordinator::drop_at] instead of sending mouse events to the browsers.
+
+use crate::{
+    browser::Browser,
+    browser_host::BrowserHost,
+    drag::{DragData, DragOperation},
+    events::{EventFlags, MouseEvent},
+    helper_traits::DeepClone,
+    values::{Point, Rect},
+};
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// Function called when a drag is released outside of all registered views,
+/// with the dragged data, the allowed operations and the release position.
+/// Returns the operation that was performed, or [DragOperation::NONE].
+pub trait DropOutsideCallback = 'static + Send + FnMut(&DragData, DragOperation, Point) -> DragOperation;
+
+struct View {
+    browser: Browser,
+    rect: Rect,
+}
+
+struct ActiveDrag {
+    source: Browser,
+    data: DragData,
+    allowed_ops: DragOperation,
+    /// Identifier of the browser the pointer is currently over.
+    target: Option<i32>,
+    /// Last operation reported by the target through
+    /// [DragCoordinator::update_drag_cursor].
+    operation: DragOperation,
+}
+
+struct CoordinatorState {
+    views: Vec<View>,
+    drag: Option<ActiveDrag>,
+    /// Called without holding the state's lock, so it can call into the
+    /// coordinator.
+    drop_outside: Option<Arc<Mutex<dyn DropOutsideCallback>>>,
+}
+
+impl CoordinatorState {
+    fn view(&self, browser_id: i32) -> Option<&View> {
+        self.views.iter().find(|view| view.browser.get_identifier() == browser_id)
+    }
+    /// Returns the topmost view containing `point`. Views added later are on
+    /// top.
+    fn view_at(&self, point: Point) -> Option<&View> {
+        self.views.iter().rev().find(|view| {
+            point.x >= view.rect.x && point.y >= view.rect.y
+                && point.x < view.rect.x + view.rect.width
+                && point.y < view.rect.y + view.rect.height
+        })
+    }
+}
+
+/// Routes drags between the registered browsers. Clones refer to the same
+/// coordinator.
+#[derive(Clone)]
+pub struct DragCoordinator(Arc<Mutex<CoordinatorState>>);
+
+impl DragCoordinator {
+    pub fn new() -> DragCoordinator {
+        DragCoordinator(Arc::new(Mutex::new(CoordinatorState {
+            views: Vec::new(),
+            drag: None,
+            drop_outside: None,
+        })))
+    }
+    /// Set the function handling drags released outside of all views. Without
+    /// one, such drags are canceled.
+    pub fn with_drop_outside(self, callback: impl DropOutsideCallback) -> Self {
+        self.0.lock().drop_outside = Some(Arc::new(Mutex::new(callback)));
+        self
+    }
+    /// Register a browser whose view covers `rect`.
+    pub fn add_view(&self, browser: Browser, rect: Rect) {
+        self.0.lock().views.push(View { browser, rect });
+    }
+    /// Update the area covered by a registered browser's view.
+    pub fn set_view_rect(&self, browser: &Browser, rect: Rect) {
+        let browser_id = browser.get_identifier();
+        if let Some(view) = self.0.lock().views.iter_mut().find(|view| view.browser.get_identifier() == browser_id) {
+            view.rect = rect;
+        }
+    }
+    /// Unregister a browser. A drag it is the target of leaves it.
+    pub fn remove_view(&self, browser: &Browser) {
+        let browser_id = browser.get_identifier();
+        let left = {
+            let mut state = self.0.lock();
+            state.views.retain(|view| view.browser.get_identifier() != browser_id);
+            match &mut state.drag {
+                Some(drag) if drag.target == Some(browser_id) => {
+                    drag.target = None;
+                    true
+                }
+                _ => false,
+            }
+        };
+        if left {
+            browser.get_host().drag_target_drag_leave();
+        }
+    }
+    /// Returns true while a drag is in progress.
+    pub fn is_dragging(&self) -> bool {
+        self.0.lock().drag.is_some()
+    }
+
+    /// Call from [RenderHandlerCallbacks::start_dragging](crate::client::render_handler::RenderHandlerCallbacks::start_dragging)
+    /// and return the result. `drag_start` is in the coordinate space of the
+    /// views.
+    pub fn start_dragging(
+        &self,
+        browser: Browser,
+        drag_data: DragData,
+        allowed_ops: DragOperation,
+        drag_start: Point,
+    ) -> bool {
+        {
+            let mut state = self.0.lock();
+            if state.drag.is_some() || state.view(browser.get_identifier()).is_none() {
+                return false;
+            }
+            // The drag data passed to start_dragging is read-only, and file
+            // contents can't be dragged into a view.
+            let data = drag_data.deep_clone();
+            data.reset_file_contents();
+            state.drag = Some(ActiveDrag {
+                source: browser,
+                data,
+                allowed_ops,
+                target: None,
+                operation: DragOperation::NONE,
+            });
+        }
+        self.drag_move(drag_start, EventFlags::LEFT_MOUSE_BUTTON);
+        true
+    }
+    /// Call from [RenderHandlerCallbacks::update_drag_cursor](crate::client::render_handler::RenderHandlerCallbacks::update_drag_cursor)
+    /// of every registered browser. Returns the operation the current target
+    /// would perform, which the application can use to update the cursor.
+    pub fn update_drag_cursor(&self, browser: &Browser, operation: DragOperation) -> DragOperation {
+        let mut state = self.0.lock();
+        match &mut state.drag {
+            Some(drag) if drag.target == Some(browser.get_identifier()) => {
+                drag.operation = operation;
+                operation
+            }
+            _ => DragOperation::NONE,
+        }
+    }
+    /// Move the dragged content to `point`, entering and leaving views as
+    /// needed.
+    pub fn drag_move(&self, point: Point, modifiers: EventFlags) {
+        // CEF is called after releasing the lock, as the targets can report
+        // their operation through update_drag_cursor right away.
+        let (old_target, new_target, entered_data, allowed_ops, event) = {
+            let mut state = self.0.lock();
+            let (new_target, rect) = match state.view_at(point) {
+                Some(view) => (Some(view.browser.clone()), view.rect),
+                None => (None, Rect { x: 0, y: 0, width: 0, height: 0 }),
+            };
+            let new_target_id = new_target.as_ref().map(Browser::get_identifier);
+            let old_target = match &state.drag {
+                Some(drag) if drag.target != new_target_id => drag.target.and_then(|id| state.view(id)).map(|view| view.browser.clone()),
+                Some(_) => None,
+                None => return,
+            };
+            let drag = state.drag.as_mut().unwrap();
+            let entered_data = if drag.target != new_target_id {
+                drag.target = new_target_id;
+                drag.operation = DragOperation::NONE;
+                Some(drag.data.clone())
+            } else {
+                None
+            };
+            let event = MouseEvent {
+                x: point.x - rect.x,
+                y: point.y - rect.y,
+                modifiers,
+            };
+            (old_target, new_target, entered_data, drag.allowed_ops, event)
+        };
+        if let Some(old_target) = old_target {
+            old_target.get_host().drag_target_drag_leave();
+        }
+        if let Some(new_target) = new_target {
+            if let Some(data) = entered_data {
+                new_target.get_host().drag_target_drag_enter(data, &event, allowed_ops);
+            }
+            new_target.get_host().drag_target_drag_over(&event, allowed_ops);
+        }
+    }
+    /// Release the dragged content at `point`, ending the drag.
+    pub fn drop_at(&self, point: Point, modifiers: EventFlags) {
+        self.drag_move(point, modifiers);
+        let (drag, target, source_rect, drop_outside) = {
+            let mut state = self.0.lock();
+            let drag = match state.drag.take() {
+                Some(drag) => drag,
+                None => return,
+            };
+            let target = drag.target.and_then(|id| state.view(id)).map(|view| (view.browser.clone(), view.rect));
+            let source_rect = state.view(drag.source.get_identifier()).map(|view| view.rect);
+            (drag, target, source_rect, state.drop_outside.clone())
+        };
+        let operation = match target {
+            Some((target, rect)) => {
+                let event = MouseEvent {
+                    x: point.x - rect.x,
+                    y: point.y - rect.y,
+                    modifiers,
+                };
+                if drag.operation.is_empty() {
+                    target.get_host().drag_target_drag_leave();
+                } else {
+                    target.get_host().drag_target_drop(&event);
+                }
+                drag.operation
+            }
+            None => match drop_outside {
+                Some(drop_outside) => (&mut *drop_outside.lock())(&drag.data, drag.allowed_ops, point) & drag.allowed_ops,
+                None => DragOperation::NONE,
+            },
+        };
+        end_source_drag(&drag.source.get_host(), source_rect, point, operation);
+    }
+    /// Cancel the drag in progress, e.g. when Escape is pressed.
+    pub fn cancel(&self) {
+        let (drag, target, source_rect) = {
+            let mut state = self.0.lock();
+            let drag = match state.drag.take() {
+                Some(drag) => drag,
+                None => return,
+            };
+            let target = drag.target.and_then(|id| state.view(id)).map(|view| view.browser.clone());
+            let source_rect = state.view(drag.source.get_identifier()).map(|view| view.rect);
+            (drag, target, source_rect)
+        };
+        if let Some(target) = target {
+            target.get_host().drag_target_drag_leave();
+        }
+        end_source_drag(&drag.source.get_host(), source_rect, Point::new(0, 0), DragOperation::NONE);
+    }
+}
+
+impl Default for DragCoordinator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn end_source_drag(source: &BrowserHost, source_rect: Option<Rect>, point: Point, operation: DragOperation) {
+    let origin = source_rect.map(|rect| Point::new(rect.x, rect.y)).unwrap_or(Point::new(0, 0));
+    source.drag_source_ended_at(point.x - origin.x, point.y - origin.y, operation);
+    source.drag_source_system_drag_ended();
+}
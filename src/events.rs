@@ -3,7 +3,7 @@ use cef_sys::{
     cef_mouse_event_t, cef_pointer_type_t, cef_touch_event_t, cef_touch_event_type_t,
 };
 use bitflags::bitflags;
-use std::mem;
+use std::{convert::TryFrom, fmt, mem};
 
 bitflags!{
     #[derive(Default)]
@@ -28,12 +28,41 @@ impl EventFlags {
     pub unsafe fn from_unchecked(i: crate::CEnumType) -> EventFlags {
         EventFlags::from_bits_unchecked(i)
     }
+    /// Returns the flag of a pressed mouse button. Mouse events of windowless
+    /// browsers need the flags of all buttons held down, or drags won't work.
+    pub fn mouse_button(button: MouseButtonType) -> EventFlags {
+        match button {
+            MouseButtonType::Left => EventFlags::LEFT_MOUSE_BUTTON,
+            MouseButtonType::Middle => EventFlags::MIDDLE_MOUSE_BUTTON,
+            MouseButtonType::Right => EventFlags::RIGHT_MOUSE_BUTTON,
+        }
+    }
 }
 
 // TODO: VERIFY FIELD USAGES. CEF DOESN'T SEEM TO USE ALL THE FIELDS SO I DONT KNOW IF WE'RE
 // UNDERREPORTING DATA TO CEF HERE.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum KeyEvent {
+    /// A key was pressed, without translation into a character. Windows sends
+    /// these for `WM_KEYDOWN` and `WM_SYSKEYDOWN`, followed by
+    /// [KeyEvent::Char] for keys that produce characters.
+    ///
+    /// Key presses CEF reports with `KEYEVENT_RAWKEYDOWN`, which are most of
+    /// them on Windows, used to be converted to [KeyEvent::KeyDown]. Code
+    /// matching only `KeyDown` misses them now; use [KeyEvent::is_key_down] or
+    /// match both variants.
+    RawKeyDown {
+        /// Bit flags describing any pressed modifier keys. See
+        /// cef_event_flags_t for values.
+        modifiers: EventFlags,
+        windows_key_code: WindowsKeyCode,
+        /// Also known as scan code.
+        native_key_code: i32,
+        is_system_key: bool,
+        focus_on_editable_field: bool,
+    },
+    /// A key was pressed. Use this on platforms that report key presses and
+    /// characters together.
     KeyDown {
         /// Bit flags describing any pressed modifier keys. See
         /// cef_event_flags_t for values.
@@ -54,6 +83,9 @@ pub enum KeyEvent {
         is_system_key: bool,
         focus_on_editable_field: bool,
     },
+    /// A character was typed. Characters outside the Basic Multilingual Plane
+    /// are sent as two events, one per UTF-16 code unit, by
+    /// [BrowserHost::send_key_event](crate::browser_host::BrowserHost::send_key_event).
     Char {
         modifiers: EventFlags,
         char: char,
@@ -61,39 +93,93 @@ pub enum KeyEvent {
 }
 
 impl KeyEvent {
-    pub fn as_cef(&self) -> cef_key_event_t {
+    /// Returns the event as a single CEF event. Fails for characters outside
+    /// the Basic Multilingual Plane, which take two events; use
+    /// [KeyEvent::to_cef_events] for those.
+    pub fn as_cef(&self) -> Result<cef_key_event_t, NonBmpCharError> {
         match *self {
-            KeyEvent::KeyDown{modifiers, windows_key_code, native_key_code, is_system_key, focus_on_editable_field} => cef_key_event_t {
-                type_: cef_key_event_type_t::KEYEVENT_KEYDOWN,
+            KeyEvent::Char{char, ..} if char.len_utf16() > 1 => Err(NonBmpCharError(char)),
+            _ => Ok(self.to_cef_events()[0]),
+        }
+    }
+    /// Returns the events to send to CEF, which are two for characters that
+    /// take two UTF-16 code units.
+    pub fn to_cef_events(&self) -> Vec<cef_key_event_t> {
+        match *self {
+            KeyEvent::RawKeyDown{modifiers, windows_key_code, native_key_code, is_system_key, focus_on_editable_field} => vec![cef_key_event_t {
+                type_: cef_key_event_type_t::KEYEVENT_RAWKEYDOWN,
                 modifiers: modifiers.bits() as _,
                 windows_key_code: windows_key_code.0,
                 native_key_code,
                 is_system_key: is_system_key as _,
                 focus_on_editable_field: focus_on_editable_field as _,
                 ..unsafe{ mem::zeroed() }
-            },
-            KeyEvent::KeyUp{modifiers, windows_key_code, native_key_code, is_system_key, focus_on_editable_field} => cef_key_event_t {
-                type_: cef_key_event_type_t::KEYEVENT_KEYUP,
+            }],
+            KeyEvent::KeyDown{modifiers, windows_key_code, native_key_code, is_system_key, focus_on_editable_field} => vec![cef_key_event_t {
+                type_: cef_key_event_type_t::KEYEVENT_KEYDOWN,
                 modifiers: modifiers.bits() as _,
                 windows_key_code: windows_key_code.0,
                 native_key_code,
                 is_system_key: is_system_key as _,
                 focus_on_editable_field: focus_on_editable_field as _,
                 ..unsafe{ mem::zeroed() }
-            },
-            KeyEvent::Char{modifiers, char} => cef_key_event_t {
-                type_: cef_key_event_type_t::KEYEVENT_CHAR,
+            }],
+            KeyEvent::KeyUp{modifiers, windows_key_code, native_key_code, is_system_key, focus_on_editable_field} => vec![cef_key_event_t {
+                type_: cef_key_event_type_t::KEYEVENT_KEYUP,
                 modifiers: modifiers.bits() as _,
-                windows_key_code: char as _,
-                character: char as _,
+                windows_key_code: windows_key_code.0,
+                native_key_code,
+                is_system_key: is_system_key as _,
+                focus_on_editable_field: focus_on_editable_field as _,
                 ..unsafe{ mem::zeroed() }
+            }],
+            KeyEvent::Char{modifiers, char} => {
+                let mut units = [0; 2];
+                char.encode_utf16(&mut units)
+                    .iter()
+                    .map(|&unit| Self::char_event(modifiers, unit))
+                    .collect()
             }
         }
     }
+    /// Returns true for [KeyEvent::RawKeyDown] and [KeyEvent::KeyDown]. CEF
+    /// reports key presses as either, depending on the platform, so match on
+    /// this instead of a single variant to handle all key presses.
+    pub fn is_key_down(&self) -> bool {
+        match self {
+            KeyEvent::RawKeyDown{..} | KeyEvent::KeyDown{..} => true,
+            KeyEvent::KeyUp{..} | KeyEvent::Char{..} => false,
+        }
+    }
+    fn char_event(modifiers: EventFlags, unit: u16) -> cef_key_event_t {
+        cef_key_event_t {
+            type_: cef_key_event_type_t::KEYEVENT_CHAR,
+            modifiers: modifiers.bits() as _,
+            windows_key_code: unit as _,
+            character: unit as _,
+            unmodified_character: unit as _,
+            ..unsafe{ mem::zeroed() }
+        }
+    }
+}
+
+/// A [KeyEvent::Char] with a character outside the Basic Multilingual Plane
+/// was converted to a single CEF event, which can only hold one UTF-16 code
+/// unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NonBmpCharError(pub char);
+
+impl fmt::Display for NonBmpCharError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "character {:?} takes two key events", self.0)
+    }
 }
 
-impl From<KeyEvent> for cef_key_event_t {
-    fn from(event: KeyEvent) -> cef_key_event_t {
+impl std::error::Error for NonBmpCharError {}
+
+impl TryFrom<KeyEvent> for cef_key_event_t {
+    type Error = NonBmpCharError;
+    fn try_from(event: KeyEvent) -> Result<cef_key_event_t, NonBmpCharError> {
         event.as_cef()
     }
 }
@@ -101,8 +187,14 @@ impl From<KeyEvent> for cef_key_event_t {
 impl From<cef_key_event_t> for KeyEvent {
     fn from(event: cef_key_event_t) -> KeyEvent {
         match event.type_ {
-            cef_key_event_type_t::KEYEVENT_KEYDOWN |
-            cef_key_event_type_t::KEYEVENT_RAWKEYDOWN => KeyEvent::KeyDown {
+            cef_key_event_type_t::KEYEVENT_RAWKEYDOWN => KeyEvent::RawKeyDown {
+                modifiers: EventFlags::from_bits_truncate(event.modifiers as _),
+                windows_key_code: WindowsKeyCode(event.windows_key_code),
+                native_key_code: event.native_key_code,
+                is_system_key: event.is_system_key != 0,
+                focus_on_editable_field: event.focus_on_editable_field != 0,
+            },
+            cef_key_event_type_t::KEYEVENT_KEYDOWN => KeyEvent::KeyDown {
                 modifiers: EventFlags::from_bits_truncate(event.modifiers as _),
                 windows_key_code: WindowsKeyCode(event.windows_key_code),
                 native_key_code: event.native_key_code,
@@ -466,3 +558,109 @@ impl WindowsKeyCode {
     pub const Kanji: Self = Self::Hanja;
     pub const OemFjJish: Self = Self::OemNecEqual;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cef_key_event(type_: cef_key_event_type_t::Type, modifiers: u32, windows_key_code: i32) -> cef_key_event_t {
+        cef_key_event_t {
+            type_,
+            modifiers,
+            windows_key_code,
+            ..unsafe{ mem::zeroed() }
+        }
+    }
+
+    fn key_down(modifiers: EventFlags) -> KeyEvent {
+        KeyEvent::KeyDown {
+            modifiers,
+            windows_key_code: WindowsKeyCode::A,
+            native_key_code: 30,
+            is_system_key: false,
+            focus_on_editable_field: true,
+        }
+    }
+
+    #[test]
+    fn modifiers_are_passed_as_cef_flags() {
+        let modifiers = EventFlags::SHIFT_DOWN | EventFlags::CONTROL_DOWN | EventFlags::ALT_DOWN;
+        let event = key_down(modifiers).as_cef().unwrap();
+        assert_eq!(
+            event.modifiers,
+            (cef_event_flags_t::EVENTFLAG_SHIFT_DOWN.0
+                | cef_event_flags_t::EVENTFLAG_CONTROL_DOWN.0
+                | cef_event_flags_t::EVENTFLAG_ALT_DOWN.0) as u32,
+        );
+        assert_eq!(event.windows_key_code, 0x41);
+        assert_eq!(event.native_key_code, 30);
+        assert_eq!(event.focus_on_editable_field, 1);
+    }
+
+    #[test]
+    fn modifiers_round_trip() {
+        for &modifiers in &[
+            EventFlags::empty(),
+            EventFlags::COMMAND_DOWN,
+            EventFlags::ALTGR_DOWN | EventFlags::IS_KEY_PAD,
+            EventFlags::CAPS_LOCK_ON | EventFlags::NUM_LOCK_ON | EventFlags::IS_RIGHT,
+        ] {
+            let event = key_down(modifiers);
+            assert_eq!(KeyEvent::from(event.as_cef().unwrap()), event);
+        }
+    }
+
+    #[test]
+    fn unknown_modifier_bits_are_dropped() {
+        let modifiers = cef_event_flags_t::EVENTFLAG_SHIFT_DOWN.0 as u32 | 1 << 30;
+        let event = KeyEvent::from(cef_key_event(cef_key_event_type_t::KEYEVENT_KEYUP, modifiers, 0x41));
+        match event {
+            KeyEvent::KeyUp { modifiers, .. } => assert_eq!(modifiers, EventFlags::SHIFT_DOWN),
+            event => panic!("unexpected {:?}", event),
+        }
+    }
+
+    #[test]
+    fn mouse_buttons_map_to_their_flags() {
+        assert_eq!(EventFlags::mouse_button(MouseButtonType::Left), EventFlags::LEFT_MOUSE_BUTTON);
+        assert_eq!(EventFlags::mouse_button(MouseButtonType::Middle), EventFlags::MIDDLE_MOUSE_BUTTON);
+        assert_eq!(EventFlags::mouse_button(MouseButtonType::Right), EventFlags::RIGHT_MOUSE_BUTTON);
+    }
+
+    #[test]
+    fn raw_key_down_is_not_reported_as_key_down() {
+        let event = KeyEvent::from(cef_key_event(cef_key_event_type_t::KEYEVENT_RAWKEYDOWN, 0, 0x41));
+        assert!(matches!(event, KeyEvent::RawKeyDown { windows_key_code: WindowsKeyCode::A, .. }));
+        assert!(!matches!(event, KeyEvent::KeyDown { .. }));
+        assert!(event.is_key_down());
+        assert_eq!(event.as_cef().unwrap().type_, cef_key_event_type_t::KEYEVENT_RAWKEYDOWN);
+
+        let event = KeyEvent::from(cef_key_event(cef_key_event_type_t::KEYEVENT_KEYDOWN, 0, 0x41));
+        assert!(matches!(event, KeyEvent::KeyDown { .. }));
+        assert!(event.is_key_down());
+        assert_eq!(event.as_cef().unwrap().type_, cef_key_event_type_t::KEYEVENT_KEYDOWN);
+    }
+
+    #[test]
+    fn chars_outside_the_bmp_take_two_events() {
+        let event = KeyEvent::Char { modifiers: EventFlags::SHIFT_DOWN, char: '😀' };
+        let events = event.to_cef_events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].character, 0xD83D);
+        assert_eq!(events[1].character, 0xDE00);
+        assert!(events.iter().all(|event| event.type_ == cef_key_event_type_t::KEYEVENT_CHAR));
+        assert!(events.iter().all(|event| event.modifiers == EventFlags::SHIFT_DOWN.bits() as u32));
+        assert_eq!(event.as_cef().unwrap_err(), NonBmpCharError('😀'));
+        assert!(cef_key_event_t::try_from(event).is_err());
+    }
+
+    #[test]
+    fn bmp_chars_take_one_event() {
+        let event = KeyEvent::Char { modifiers: EventFlags::empty(), char: 'é' };
+        let events = event.to_cef_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].character, 'é' as u32 as u16);
+        assert_eq!(events[0].unmodified_character, 'é' as u32 as u16);
+        assert_eq!(KeyEvent::from(cef_key_event_t::try_from(event).unwrap()), event);
+    }
+}
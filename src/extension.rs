@@ -15,7 +15,9 @@ use crate::client::Client;
 use cef_sys::{cef_extension_t, cef_extension_handler_t, cef_errorcode_t};
 use std::{
     collections::HashMap,
+    convert::TryFrom,
     path::PathBuf,
+    ptr::null_mut,
 };
 
 use crate::{
@@ -24,6 +26,7 @@ use crate::{
     send_protector::SendProtector,
     refcounted::{RefCountedPtr, Wrapper},
     values::{DictionaryValue, StoredValue},
+    devtools::{get_bool, get_dictionary, get_dictionary_list, get_i32, get_list, get_string},
 };
 
 ref_counted_ptr! {
@@ -54,8 +57,8 @@ impl Extension {
     }
 
     /// Returns the absolute path to the extension directory on disk. This value
-    /// will be prefixed with PK_DIR_RESOURCES if a relative path was passed to
-    /// [RequestContext::load_extension].
+    /// will be prefixed with the [PathKey::Resources](crate::PathKey::Resources)
+    /// directory if a relative path was passed to [RequestContext::load_extension].
     pub fn get_path(&self) -> PathBuf {
         self.0
             .get_path
@@ -65,14 +68,15 @@ impl Extension {
             .map(PathBuf::from)
             .unwrap_or_default()
     }
-    // Returns the extension manifest contents as a dictionary object.
-    // See https://developer.chrome.com/extensions/manifest for details.
-    pub fn get_manifest(&self) -> HashMap<String, StoredValue> {
-        self.0
+    /// Returns the extension manifest contents.
+    /// See https://developer.chrome.com/extensions/manifest for details.
+    pub fn get_manifest(&self) -> ExtensionManifest {
+        let manifest: HashMap<String, StoredValue> = self.0
             .get_manifest
             .and_then(|get_manifest| unsafe { DictionaryValue::from_ptr(get_manifest(self.0.as_ptr())) })
             .map(DictionaryValue::into)
-            .unwrap_or_default()
+            .unwrap_or_default();
+        ExtensionManifest::from(manifest)
     }
     /// Returns the handler for this extension. Will return None for internal
     /// extensions or if no handler was passed to [RequestContext::load_extension].
@@ -112,6 +116,111 @@ impl Extension {
     }
 }
 
+/// When a content script is injected into a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentScriptRunAt {
+    /// Before any other script of the document runs.
+    DocumentStart,
+    /// After the document is parsed, before subresources like images are
+    /// loaded.
+    DocumentEnd,
+    /// Between `DocumentEnd` and right after the `load` event. The default.
+    DocumentIdle,
+}
+
+impl Default for ContentScriptRunAt {
+    fn default() -> Self {
+        ContentScriptRunAt::DocumentIdle
+    }
+}
+
+/// An entry of the "content_scripts" manifest key.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ManifestContentScript {
+    /// Match patterns of the pages to inject into, like `https://*.example.com/*`.
+    pub matches: Vec<String>,
+    /// Match patterns of pages to leave out.
+    pub exclude_matches: Vec<String>,
+    /// Paths of the scripts to inject, relative to the extension directory.
+    pub js: Vec<String>,
+    /// Paths of the style sheets to inject, relative to the extension directory.
+    pub css: Vec<String>,
+    pub run_at: ContentScriptRunAt,
+    /// Inject into all frames instead of only the top frame.
+    pub all_frames: bool,
+}
+
+/// The manifest of an extension, see [Extension::get_manifest]. Keys that
+/// aren't modelled here can be read from `raw`.
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionManifest {
+    pub name: String,
+    pub version: String,
+    pub manifest_version: i32,
+    pub description: String,
+    /// API permissions and host match patterns from the "permissions" key.
+    pub permissions: Vec<String>,
+    pub optional_permissions: Vec<String>,
+    /// Paths of the background scripts, relative to the extension directory.
+    pub background_scripts: Vec<String>,
+    /// Path of the background page, if the extension uses one instead of
+    /// scripts.
+    pub background_page: Option<String>,
+    /// False for event pages, which are unloaded while idle.
+    pub background_persistent: bool,
+    pub content_scripts: Vec<ManifestContentScript>,
+    /// All keys of the manifest.
+    pub raw: HashMap<String, StoredValue>,
+}
+
+impl From<HashMap<String, StoredValue>> for ExtensionManifest {
+    fn from(raw: HashMap<String, StoredValue>) -> Self {
+        let strings = |map: &HashMap<String, StoredValue>, key: &str| -> Vec<String> {
+            get_list(map, key)
+                .into_iter()
+                .filter_map(|value| match value {
+                    StoredValue::String(s) => Some(s),
+                    _ => None,
+                })
+                .collect()
+        };
+        let background = get_dictionary(&raw, "background").unwrap_or_default();
+        let background_page = Some(get_string(&background, "page")).filter(|page| !page.is_empty());
+        let content_scripts = get_dictionary_list(&raw, "content_scripts")
+            .iter()
+            .map(|script| ManifestContentScript {
+                matches: strings(script, "matches"),
+                exclude_matches: strings(script, "exclude_matches"),
+                js: strings(script, "js"),
+                css: strings(script, "css"),
+                run_at: match &*get_string(script, "run_at") {
+                    "document_start" => ContentScriptRunAt::DocumentStart,
+                    "document_end" => ContentScriptRunAt::DocumentEnd,
+                    _ => ContentScriptRunAt::DocumentIdle,
+                },
+                all_frames: get_bool(script, "all_frames"),
+            })
+            .collect();
+        ExtensionManifest {
+            name: get_string(&raw, "name"),
+            version: get_string(&raw, "version"),
+            manifest_version: get_i32(&raw, "manifest_version"),
+            description: get_string(&raw, "description"),
+            permissions: strings(&raw, "permissions"),
+            optional_permissions: strings(&raw, "optional_permissions"),
+            background_scripts: strings(&background, "scripts"),
+            background_persistent: match background.get("persistent") {
+                Some(StoredValue::Bool(persistent)) => *persistent,
+                // Background pages are persistent unless declared otherwise.
+                _ => !background.is_empty(),
+            },
+            background_page,
+            content_scripts,
+            raw,
+        }
+    }
+}
+
 impl GetExtensionResourceCallback {
     pub fn cont(&self, stream: StreamReader) {
         unsafe { self.0.cont.unwrap()(self.as_ptr(), stream.into_raw()) }
@@ -136,18 +245,22 @@ pub trait ExtensionHandlerCallbacks: 'static + Send {
     fn on_extension_load_failed(
         &self,
         result: ErrorCode
-    );
+    ) {
+        log::error!("Loading an extension failed: {:?}", result);
+    }
     /// Called if the cef_request_tContext::LoadExtension request succeeds.
     /// `extension` is the loaded extension.
     fn on_extension_loaded(
         &self,
         extension: Extension
-    );
+    ) {
+    }
     /// Called after the cef_extension_t::Unload request has completed.
     fn on_extension_unloaded(
         &self,
         extension: Extension
-    );
+    ) {
+    }
     /// Called when an extension needs a browser to host a background script
     /// specified via the "background" manifest key. The browser will have no
     /// visible window and cannot be displayed. `extension` is the extension that
@@ -167,7 +280,9 @@ pub trait ExtensionHandlerCallbacks: 'static + Send {
         url: &str,
         client: &mut Client,
         settings: &mut BrowserSettings
-    ) -> bool;
+    ) -> bool {
+        false
+    }
     /// Called when an extension API (e.g. chrome.tabs.create) requests creation of
     /// a new browser. `extension` and `browser` are the source of the API call.
     /// `active_browser` may optionally be specified via the windowId property or
@@ -185,18 +300,20 @@ pub trait ExtensionHandlerCallbacks: 'static + Send {
         &self,
         extension: Extension,
         browser: Browser,
-        active_browser: Browser,
-        index: usize,
+        active_browser: Option<Browser>,
+        index: Option<usize>,
         url: &str,
         active: bool,
         window_info: &mut WindowInfo,
         client: &mut Client,
         settings: &mut BrowserSettings,
-    ) -> bool;
+    ) -> bool {
+        false
+    }
     /// Called when no tabId is specified to an extension API call that accepts a
     /// tabId parameter (e.g. chrome.tabs.*). `extension` and `browser` are the
     /// source of the API call. Return the browser that will be acted on by the API
-    /// call or return NULL to act on `browser`. The returned browser must share
+    /// call or return `None` to act on `browser`. The returned browser must share
     /// the same cef_request_tContext as `browser`. Incognito browsers should not
     /// be considered unless the source extension has incognito access enabled, in
     /// which case `include_incognito` will be `true`.
@@ -205,20 +322,28 @@ pub trait ExtensionHandlerCallbacks: 'static + Send {
         extension: Extension,
         browser: Browser,
         include_incognito: bool
-    ) -> Browser;
+    ) -> Option<Browser> {
+        None
+    }
     /// Called when the tabId associated with `target_browser` is specified to an
     /// extension API call that accepts a tabId parameter (e.g. chrome.tabs.*).
     /// `extension` and `browser` are the source of the API call. Return `true`
     /// to allow access of `false` to deny access. Access to incognito browsers
     /// should not be allowed unless the source extension has incognito access
-    /// enabled, in which case `include_incognito` will be `true`.
+    /// enabled, in which case `include_incognito` will be `true`. By default,
+    /// access is allowed to browsers sharing the request context of `browser`.
     fn can_access_browser(
         &self,
         extension: Extension,
         browser: Browser,
         include_incognito: bool,
         target_browser: Browser,
-    ) -> bool;
+    ) -> bool {
+        browser
+            .get_host()
+            .get_request_context()
+            .is_sharing_with(target_browser.get_host().get_request_context())
+    }
     /// Called to retrieve an extension resource that would normally be loaded from
     /// disk (e.g. if a file parameter is specified to chrome.tabs.executeScript).
     /// `extension` and `browser` are the source of the resource request. `file` is
@@ -233,7 +358,9 @@ pub trait ExtensionHandlerCallbacks: 'static + Send {
         browser: Browser,
         file: &str,
         callback: GetExtensionResourceCallback,
-    ) -> bool;
+    ) -> bool {
+        false
+    }
 }
 
 struct ExtensionHandlerWrapper {
@@ -309,7 +436,7 @@ cef_callback_impl!{
             &self,
             extension: Extension: *mut cef_extension_t,
             browser: Browser: *mut cef_browser_t,
-            active_browser: Browser: *mut cef_browser_t,
+            active_browser: Option<Browser>: *mut cef_browser_t,
             index: c_int: c_int,
             url: &CefString: *const cef_string_t,
             active: c_int: c_int,
@@ -323,7 +450,7 @@ cef_callback_impl!{
                 extension,
                 browser,
                 active_browser,
-                index as usize,
+                usize::try_from(index).ok(),
                 &String::from(url),
                 active != 0,
                 &mut window_info_rs,
@@ -340,7 +467,10 @@ cef_callback_impl!{
             browser: Browser: *mut cef_browser_t,
             include_incognito: c_int: c_int
         ) -> *mut cef_browser_t {
-            unsafe{ self.delegate.get() }.get_active_browser(extension, browser, include_incognito != 0).into_raw()
+            unsafe{ self.delegate.get() }
+                .get_active_browser(extension, browser, include_incognito != 0)
+                .map(Browser::into_raw)
+                .unwrap_or_else(null_mut)
         }
         fn can_access_browser(
             &self,
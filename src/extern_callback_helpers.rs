@@ -279,7 +279,7 @@ impl CToRustType for Option<crate::resource_bundle_handler::ScaleFactor> {
 impl CToRustType for crate::cookie::Cookie {
     type CType = *const cef_sys::cef_cookie_t;
     unsafe fn from_c_type(c_type: Self::CType) -> Self {
-        Self::new(c_type)
+        Self::from_raw(c_type)
     }
 }
 impl CToRustType for crate::client::life_span_handler::PopupFeatures {
@@ -384,6 +384,17 @@ macro_rules! cef_callback_impl {
                     $(
                         let $field_name: $field_ty = unsafe{ <$field_ty as crate::extern_callback_helpers::CToRustType>::from_c_type($field_name) };
                     )*
+                    let _timer = if crate::handler_coverage::is_enabled() || crate::callback_watchdog::is_enabled() {
+                        #[allow(unused_imports)]
+                        use crate::handler_coverage::{ArgumentProbe, ProbeBrowser, ProbeOther};
+                        let browser_id = None$(.or_else(|| (&ArgumentProbe(&$field_name)).browser_id()))*;
+                        Some(crate::handler_coverage::CallbackTimer::start(
+                            concat!(stringify!($CType), "::", stringify!($fn_name)),
+                            browser_id,
+                        ))
+                    } else {
+                        None
+                    };
                     let ret = this.inner($($field_name),*);
                     ret
                 }
@@ -0,0 +1,143 @@
+//! Deciding what happens with links to protocols Chromium can't load itself.
+//!
+//! Navigating to a URL like `mailto:` or `tel:`, or to a custom protocol
+//! registered with the OS, ends up in
+//! [ResourceRequestHandlerCallbacks::on_protocol_execution], which either lets
+//! the OS open the URL or drops it. [ExternalProtocolPolicy] wraps an existing
+//! implementation and makes that decision per scheme: allow the OS handler,
+//! deny the URL, or handle it in Rust instead, e.g. to open a compose window
+//! of the application's own mail client:
+//!
+//! ```rust,ignore
+//! let handler = ExternalProtocolPolicy::new(callbacks)
+//!     .allow("tel")
+//!     .deny("ms-settings")
+//!     .handle("mailto", |browser, url| compose_mail(url))
+//!     .build();
+//! ```
+//!
+//! Schemes without a rule are left to the wrapped implementation, which
+//! denies them by default. Install the resulting handler through
+//! [RequestHandlerCallbacks::get_resource_request_handler](crate::client::request_handler::RequestHandlerCallbacks::get_resource_request_handler).
+
+use crate::{
+    browser::Browser,
+    frame::Frame,
+    request::Request,
+    resource_request_handler::{
+        ResourceRequestHandler, ResourceRequestHandlerCallbacks, ResourceRequestHandlerDecorator,
+    },
+    task::{TaskRunner, ThreadId},
+};
+use std::{collections::HashMap, sync::Arc};
+
+/// Function handling an external protocol URL instead of the OS. Called on
+/// the browser process UI thread with the browser the URL was opened in, if
+/// any, and the URL.
+pub trait ProtocolCallback = 'static + Send + Sync + Fn(Option<Browser>, &str);
+
+/// What to do with URLs of a scheme.
+#[derive(Clone)]
+pub enum ProtocolAction {
+    /// Open the URL with the protocol handler registered with the OS, if any.
+    Allow,
+    /// Drop the URL.
+    Deny,
+    /// Pass the URL to a function instead of the OS.
+    Handle(Arc<dyn ProtocolCallback>),
+}
+
+impl std::fmt::Debug for ProtocolAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolAction::Allow => f.write_str("Allow"),
+            ProtocolAction::Deny => f.write_str("Deny"),
+            ProtocolAction::Handle(_) => f.write_str("Handle"),
+        }
+    }
+}
+
+/// Returns the scheme of `url` in lowercase, without the colon, or `None` if
+/// it has none.
+pub fn url_scheme(url: &str) -> Option<String> {
+    let end = url.find(':')?;
+    let scheme = &url[..end];
+    let mut chars = scheme.chars();
+    let valid = chars.next()?.is_ascii_alphabetic()
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.');
+    if valid {
+        Some(scheme.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+/// [ResourceRequestHandlerCallbacks] wrapper deciding per scheme how external
+/// protocol URLs are executed.
+pub struct ExternalProtocolPolicy<C: ResourceRequestHandlerCallbacks> {
+    inner: C,
+    rules: HashMap<String, ProtocolAction>,
+}
+
+impl<C: ResourceRequestHandlerCallbacks> ExternalProtocolPolicy<C> {
+    /// Apply rules to the external protocol URLs handled by `inner`. Every
+    /// other callback is forwarded unchanged.
+    pub fn new(inner: C) -> Self {
+        ExternalProtocolPolicy {
+            inner,
+            rules: HashMap::new(),
+        }
+    }
+    /// Set the action for URLs with `scheme`, like `"mailto"`. Schemes are
+    /// matched case-insensitively. Replaces an earlier rule for the scheme.
+    pub fn rule(mut self, scheme: &str, action: ProtocolAction) -> Self {
+        let scheme = scheme.trim_end_matches(':').to_ascii_lowercase();
+        self.rules.insert(scheme, action);
+        self
+    }
+    /// Let the OS open URLs with `scheme`.
+    pub fn allow(self, scheme: &str) -> Self {
+        self.rule(scheme, ProtocolAction::Allow)
+    }
+    /// Drop URLs with `scheme`.
+    pub fn deny(self, scheme: &str) -> Self {
+        self.rule(scheme, ProtocolAction::Deny)
+    }
+    /// Pass URLs with `scheme` to `callback` instead of the OS.
+    pub fn handle(self, scheme: &str, callback: impl ProtocolCallback) -> Self {
+        self.rule(scheme, ProtocolAction::Handle(Arc::new(callback)))
+    }
+    /// Returns the action for `url`, or `None` if its scheme has no rule.
+    pub fn action_for(&self, url: &str) -> Option<&ProtocolAction> {
+        self.rules.get(&url_scheme(url)?)
+    }
+    pub fn build(self) -> ResourceRequestHandler {
+        ResourceRequestHandler::new(self)
+    }
+}
+
+impl<C: ResourceRequestHandlerCallbacks> ResourceRequestHandlerDecorator for ExternalProtocolPolicy<C> {
+    type Inner = C;
+    fn inner(&self) -> &C {
+        &self.inner
+    }
+    fn on_protocol_execution(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+    ) -> bool {
+        let url = request.get_url();
+        match self.action_for(&url) {
+            Some(ProtocolAction::Allow) => true,
+            Some(ProtocolAction::Deny) => false,
+            Some(ProtocolAction::Handle(callback)) => {
+                // Called on the IO thread, where browsers can't be used.
+                let callback = callback.clone();
+                TaskRunner::post_task_on(ThreadId::UI, move || callback(browser, &url));
+                false
+            }
+            None => self.inner.on_protocol_execution(browser, frame, request),
+        }
+    }
+}
@@ -2,6 +2,7 @@ use cef_sys::{cef_file_dialog_mode_t, cef_run_file_dialog_callback_t, cef_string
 use parking_lot::Mutex;
 use std::{
     convert::TryFrom,
+    fmt,
     mem::ManuallyDrop,
 };
 use crate::{
@@ -79,6 +80,112 @@ impl Into<cef_file_dialog_mode_t> for FileDialogMode {
     }
 }
 
+/// A filter restricting the file types selectable in a file dialog.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum AcceptFilter {
+    /// A lower-cased MIME type like `text/plain`, or a wildcard like `image/*`.
+    MimeType(String),
+    /// A single file extension including the leading dot, like `.txt`.
+    Extension(String),
+    /// Several file extensions listed under a description, like "Image Types"
+    /// with `.png`, `.gif` and `.jpg`.
+    NamedGroup {
+        description: String,
+        extensions: Vec<String>,
+    },
+}
+
+/// An [AcceptFilter] that CEF would silently ignore or misinterpret.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct InvalidAcceptFilter {
+    /// Index of the filter in the list passed to
+    /// [BrowserHost::run_file_dialog](crate::browser_host::BrowserHost::run_file_dialog).
+    pub index: usize,
+    pub filter: AcceptFilter,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for InvalidAcceptFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid accept filter {} ({}): {}", self.index, self.filter, self.reason)
+    }
+}
+
+impl std::error::Error for InvalidAcceptFilter {}
+
+impl AcceptFilter {
+    /// Check that the filter has the syntax CEF expects.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        match self {
+            AcceptFilter::MimeType(mime_type) => {
+                let mut parts = mime_type.splitn(2, '/');
+                let (top_level, subtype) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+                if top_level.is_empty() || subtype.is_empty() {
+                    Err("MIME types need a type and a subtype")
+                } else if mime_type.chars().any(|c| c.is_ascii_uppercase()) {
+                    Err("MIME types must be lower-cased")
+                } else if !mime_type.chars().all(|c| c.is_ascii_graphic()) || subtype.contains('/') {
+                    Err("MIME types can't contain whitespace or further slashes")
+                } else if top_level == "*" {
+                    Err("the type of a MIME type can't be a wildcard")
+                } else {
+                    Ok(())
+                }
+            }
+            AcceptFilter::Extension(extension) => validate_extension(extension),
+            AcceptFilter::NamedGroup { description, extensions } => {
+                if description.contains('|') {
+                    Err("descriptions can't contain '|'")
+                } else if extensions.is_empty() {
+                    Err("named groups need at least one extension")
+                } else {
+                    extensions.iter().map(|extension| validate_extension(extension)).collect()
+                }
+            }
+        }
+    }
+}
+
+fn validate_extension(extension: &str) -> Result<(), &'static str> {
+    if !extension.starts_with('.') || extension.len() < 2 {
+        Err("extensions must start with a dot")
+    } else if extension.chars().any(|c| c.is_whitespace() || c == ';' || c == '|' || c == '/') {
+        Err("extensions can't contain whitespace, ';', '|' or '/'")
+    } else {
+        Ok(())
+    }
+}
+
+/// Formats the filter in CEF's syntax, e.g. `Image Types|.png;.gif;.jpg`.
+impl fmt::Display for AcceptFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AcceptFilter::MimeType(mime_type) => f.write_str(mime_type),
+            AcceptFilter::Extension(extension) => f.write_str(extension),
+            AcceptFilter::NamedGroup { description, extensions } => {
+                write!(f, "{}|{}", description, extensions.join(";"))
+            }
+        }
+    }
+}
+
+/// Parses a filter in CEF's syntax, as received by
+/// [DialogHandlerCallbacks::on_file_dialog](crate::client::dialog_handler::DialogHandlerCallbacks::on_file_dialog).
+impl From<&str> for AcceptFilter {
+    fn from(filter: &str) -> AcceptFilter {
+        if let Some(separator) = filter.find('|') {
+            AcceptFilter::NamedGroup {
+                description: filter[..separator].to_owned(),
+                extensions: filter[separator + 1..].split(';').map(str::to_owned).collect(),
+            }
+        } else if filter.starts_with('.') {
+            AcceptFilter::Extension(filter.to_owned())
+        } else {
+            AcceptFilter::MimeType(filter.to_owned())
+        }
+    }
+}
+
 ref_counted_ptr! {
     pub(crate) struct RunFileDialogCallback(*mut cef_run_file_dialog_callback_t);
 }
@@ -129,3 +236,73 @@ cef_callback_impl! {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(description: &str, extensions: &[&str]) -> AcceptFilter {
+        AcceptFilter::NamedGroup {
+            description: description.to_owned(),
+            extensions: extensions.iter().map(|&extension| extension.to_owned()).collect(),
+        }
+    }
+
+    #[test]
+    fn filters_round_trip_through_cef_syntax() {
+        for filter in &[
+            AcceptFilter::MimeType("image/*".to_owned()),
+            AcceptFilter::Extension(".txt".to_owned()),
+            group("Image Types", &[".png", ".gif", ".jpg"]),
+        ] {
+            assert_eq!(AcceptFilter::from(&*filter.to_string()), *filter);
+        }
+    }
+
+    #[test]
+    fn cef_syntax_is_parsed() {
+        assert_eq!(AcceptFilter::from("text/plain"), AcceptFilter::MimeType("text/plain".to_owned()));
+        assert_eq!(AcceptFilter::from(".png"), AcceptFilter::Extension(".png".to_owned()));
+        assert_eq!(AcceptFilter::from("Images|.png;.jpg"), group("Images", &[".png", ".jpg"]));
+        assert_eq!(group("Images", &[".png", ".jpg"]).to_string(), "Images|.png;.jpg");
+    }
+
+    #[test]
+    fn valid_filters_pass() {
+        assert_eq!(AcceptFilter::MimeType("text/plain".to_owned()).validate(), Ok(()));
+        assert_eq!(AcceptFilter::MimeType("image/*".to_owned()).validate(), Ok(()));
+        assert_eq!(AcceptFilter::Extension(".tar.gz".to_owned()).validate(), Ok(()));
+        assert_eq!(group("Images", &[".png", ".jpg"]).validate(), Ok(()));
+    }
+
+    #[test]
+    fn invalid_mime_types_are_rejected() {
+        for mime_type in &["text", "text/", "/plain", "Text/Plain", "text/pla in", "text/plain/x", "*/*"] {
+            assert!(AcceptFilter::MimeType((*mime_type).to_owned()).validate().is_err(), "{}", mime_type);
+        }
+    }
+
+    #[test]
+    fn invalid_extensions_are_rejected() {
+        for extension in &["txt", ".", ".t xt", ".a;.b", ".a|b", ".a/b"] {
+            assert!(AcceptFilter::Extension((*extension).to_owned()).validate().is_err(), "{}", extension);
+        }
+    }
+
+    #[test]
+    fn invalid_groups_are_rejected() {
+        assert!(group("Images", &[]).validate().is_err());
+        assert!(group("Images|Photos", &[".png"]).validate().is_err());
+        assert!(group("Images", &[".png", "jpg"]).validate().is_err());
+    }
+
+    #[test]
+    fn errors_name_the_filter() {
+        let error = InvalidAcceptFilter {
+            index: 1,
+            filter: AcceptFilter::Extension("txt".to_owned()),
+            reason: "extensions must start with a dot",
+        };
+        assert_eq!(error.to_string(), "invalid accept filter 1 (txt): extensions must start with a dot");
+    }
+}
@@ -1,13 +1,15 @@
 use crate::{
     browser::Browser,
+    helper_traits::IsValid,
     dom::{DOMVisitor},
     request::Request,
-    string::{CefString, StringVisitor},
+    string::{CefString, StringVisitor, Utf16String, quote_string},
     url_request::{URLRequest, URLRequestClient},
     v8context::V8Context,
     process::{ProcessId, ProcessMessage},
+    CefError,
 };
-use cef_sys::{cef_frame_t, cef_string_userfree_utf16_free};
+use cef_sys::cef_frame_t;
 
 ref_counted_ptr! {
     /// Structure used to represent a frame in the browser window. When used in the
@@ -125,6 +127,15 @@ impl Frame {
             }
         }
     }
+    /// Like [Frame::load_url], with a `url` that's already converted, e.g. a
+    /// large `data:` URL loaded repeatedly.
+    pub fn load_url_utf16(&self, url: &Utf16String) {
+        if let Some(load_url) = self.0.load_url {
+            unsafe {
+                load_url(self.0.as_ptr(), &url.as_cef_string());
+            }
+        }
+    }
     /// Execute a string of JavaScript code in this frame. The `script_url`
     /// parameter is the URL where the script in question can be found, if any. The
     /// renderer may request this URL to show the developer the source of the
@@ -142,6 +153,26 @@ impl Frame {
             }
         }
     }
+    /// Like [Frame::execute_java_script], with `code` that's already converted,
+    /// so executing the same large script repeatedly doesn't convert it again.
+    pub fn execute_java_script_utf16(&self, code: &Utf16String, script_url: &str, start_line: i32) {
+        if let Some(execute_java_script) = self.0.execute_java_script {
+            unsafe {
+                execute_java_script(
+                    self.0.as_ptr(),
+                    &code.as_cef_string(),
+                    CefString::new(script_url).as_ptr(),
+                    start_line,
+                );
+            }
+        }
+    }
+    /// Insert `css` into the document currently loaded in this frame as a
+    /// `<style>` element. The stylesheet is discarded when the frame navigates;
+    /// use [BrowserHost::add_stylesheet] to apply it to every document.
+    pub fn insert_css(&self, css: &str) {
+        self.execute_java_script(&insert_css_script(css, None), "", 0);
+    }
     /// Returns true if this is the main (top-level) frame.
     pub fn is_main(&self) -> bool {
         if let Some(is_main) = self.0.is_main {
@@ -164,18 +195,10 @@ impl Frame {
     /// parent hierarchy. The main (top-level) frame will always have an None name
     /// value.
     pub fn get_name(&self) -> Option<String> {
-        if let Some(get_name) = self.0.get_name {
-            let name = unsafe { get_name(self.0.as_ptr()) };
-            let result = unsafe { CefString::from_ptr(name) }.map(String::from);
-            if result.is_some() {
-                unsafe {
-                    cef_string_userfree_utf16_free(name);
-                }
-            }
-            result
-        } else {
-            None
-        }
+        self.0
+            .get_name
+            .and_then(|get_name| unsafe { CefString::from_userfree(get_name(self.0.as_ptr())) })
+            .map(String::from)
     }
     /// Returns the globally unique identifier for this frame or None if the
     /// underlying frame does not yet exist.
@@ -204,12 +227,8 @@ impl Frame {
     pub fn get_url(&self) -> String {
         self.0
             .get_url
-            .and_then(|get_url| unsafe { get_url(self.as_ptr()).as_mut() })
-            .map(|url| unsafe {
-                let s = String::from(CefString::from_ptr_unchecked(url));
-                cef_string_userfree_utf16_free(url);
-                s
-            })
+            .and_then(|get_url| unsafe { CefString::from_userfree(get_url(self.as_ptr())) })
+            .map(String::from)
             .unwrap_or_default()
     }
     /// Returns the browser that this frame belongs to.
@@ -217,6 +236,22 @@ impl Frame {
         let browser = unsafe { self.0.get_browser.unwrap()(self.0.as_ptr()) };
         unsafe { Browser::from_ptr(browser).expect("CEF: Frame without a browser!") }
     }
+    /// Like [Frame::get_browser], but fails instead of panicking if this frame
+    /// was detached or its browser was closed.
+    pub fn try_get_browser(&self) -> Result<Browser, CefError> {
+        self.ensure_valid()?;
+        let browser = unsafe { self.0.get_browser.unwrap()(self.0.as_ptr()) };
+        match unsafe { Browser::from_ptr(browser) } {
+            Some(browser) if browser.is_valid() => Ok(browser),
+            _ => Err(CefError::ObjectInvalid),
+        }
+    }
+    /// Like [Frame::get_url], but fails if this frame was detached instead of
+    /// returning an empty URL.
+    pub fn try_get_url(&self) -> Result<String, CefError> {
+        self.ensure_valid()?;
+        Ok(self.get_url())
+    }
     /// Get the V8 context associated with the frame. This function can only be
     /// called from the render process.
     pub fn get_v8context(&self) -> V8Context {
@@ -284,3 +319,152 @@ impl Frame {
         }
     }
 }
+
+/// Returns a script that appends `css` to the document as a `<style>` element,
+/// tagged with `id` if specified. Safe to run before the document element exists.
+pub(crate) fn insert_css_script(css: &str, id: Option<u64>) -> String {
+    let tag = id
+        .map(|id| format!("style.setAttribute('data-cef-stylesheet', '{}');", id))
+        .unwrap_or_default();
+    format!(
+        "(function() {{\n\
+         var insert = function() {{\n\
+         var style = document.createElement('style');\n\
+         style.textContent = {};\n\
+         {}\n\
+         (document.head || document.documentElement).appendChild(style);\n\
+         }};\n\
+         if (document.documentElement) {{\n\
+         insert();\n\
+         }} else {{\n\
+         new MutationObserver(function(records, observer) {{\n\
+         if (document.documentElement) {{\n\
+         observer.disconnect();\n\
+         insert();\n\
+         }}\n\
+         }}).observe(document, {{ childList: true }});\n\
+         }}\n\
+         }})();",
+        quote_string(css),
+        tag,
+    )
+}
+
+impl IsValid for Frame {
+    fn is_valid(&self) -> bool {
+        Frame::is_valid(self)
+    }
+}
+
+/// Snapshot of a single frame, as returned by [Browser::frame_tree].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameTreeNode {
+    pub identifier: i64,
+    pub name: String,
+    pub url: String,
+    /// Identifier of the parent frame, or None for the main frame.
+    pub parent: Option<i64>,
+    pub is_main: bool,
+    pub is_focused: bool,
+    pub children: Vec<FrameTreeNode>,
+}
+
+/// Hierarchical snapshot of all frames in a browser, as returned by
+/// [Browser::frame_tree]. The snapshot is not updated when frames are
+/// created, navigated or destroyed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameTree {
+    /// The main frame, followed by any frames whose parent no longer exists.
+    pub roots: Vec<FrameTreeNode>,
+}
+
+impl FrameTreeNode {
+    fn new(frame: &Frame) -> Option<FrameTreeNode> {
+        Some(FrameTreeNode {
+            identifier: frame.get_identifier()?,
+            name: frame.get_name().unwrap_or_default(),
+            url: frame.get_url(),
+            parent: frame.get_parent().and_then(|parent| parent.get_identifier()),
+            is_main: frame.is_main(),
+            is_focused: frame.is_focused(),
+            children: Vec::new(),
+        })
+    }
+
+    fn fmt_indented(&self, f: &mut std::fmt::Formatter, depth: usize) -> std::fmt::Result {
+        write!(f, "{:indent$}[{}]", "", self.identifier, indent = depth * 2)?;
+        if !self.name.is_empty() {
+            write!(f, " {:?}", self.name)?;
+        }
+        write!(f, " {}", self.url)?;
+        if self.is_focused {
+            write!(f, " (focused)")?;
+        }
+        writeln!(f)?;
+        for child in &self.children {
+            child.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl FrameTree {
+    pub(crate) fn new(frames: impl IntoIterator<Item = Frame>) -> FrameTree {
+        let mut nodes: Vec<FrameTreeNode> = frames
+            .into_iter()
+            .filter_map(|frame| FrameTreeNode::new(&frame))
+            .collect();
+        // Attach the deepest frames first, so every node is complete by the time
+        // it's moved into its parent.
+        let depth = |nodes: &[FrameTreeNode], node: &FrameTreeNode| {
+            let mut depth = 0;
+            let mut parent = node.parent;
+            while let Some(p) = parent {
+                depth += 1;
+                parent = nodes.iter().find(|n| n.identifier == p).and_then(|n| n.parent);
+                if depth > nodes.len() {
+                    break;
+                }
+            }
+            depth
+        };
+        let mut depths: Vec<(usize, i64)> = nodes.iter().map(|n| (depth(&nodes, n), n.identifier)).collect();
+        depths.sort_by(|a, b| b.0.cmp(&a.0));
+        for (_, identifier) in depths {
+            let index = nodes.iter().position(|n| n.identifier == identifier).unwrap();
+            let parent_index = nodes[index]
+                .parent
+                .and_then(|parent| nodes.iter().position(|n| n.identifier == parent));
+            if let Some(parent_index) = parent_index {
+                let node = nodes.remove(index);
+                let parent_index = if parent_index > index { parent_index - 1 } else { parent_index };
+                nodes[parent_index].children.push(node);
+            }
+        }
+        nodes.sort_by_key(|n| !n.is_main);
+        FrameTree { roots: nodes }
+    }
+
+    /// Returns the node with the given frame identifier, if any.
+    pub fn find(&self, identifier: i64) -> Option<&FrameTreeNode> {
+        fn find_in(nodes: &[FrameTreeNode], identifier: i64) -> Option<&FrameTreeNode> {
+            nodes.iter().find_map(|node| {
+                if node.identifier == identifier {
+                    Some(node)
+                } else {
+                    find_in(&node.children, identifier)
+                }
+            })
+        }
+        find_in(&self.roots, identifier)
+    }
+}
+
+impl std::fmt::Display for FrameTree {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for root in &self.roots {
+            root.fmt_indented(f, 0)?;
+        }
+        Ok(())
+    }
+}
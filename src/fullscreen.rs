@@ -0,0 +1,407 @@
+//! Handling HTML fullscreen and pointer lock requests of pages.
+//!
+//! CEF grants every `requestFullscreen` and `requestPointerLock` call and only
+//! reports the result afterwards through
+//! [DisplayHandlerCallbacks::on_fullscreen_mode_change]. A [FullscreenPolicy]
+//! decides per origin which requests are allowed; denied requests fail in the
+//! page the same way they do in Chrome, with a `fullscreenerror` or
+//! `pointerlockerror` event. [display_handler] resizes the application's window
+//! along with the page, and tracks the state for [BrowserHost::is_fullscreen].
+//!
+//! [FullscreenCoordinator] does the window part for applications: it makes
+//! the window fullscreen when the page does, and restores its previous state
+//! afterwards.
+//!
+//! Pointer lock is only supported for windowed browsers; windowless browsers
+//! always fail to lock the pointer.
+
+use crate::{
+    browser::Browser,
+    browser_host::BrowserHost,
+    client::display_handler::{DisplayHandler, DisplayHandlerCallbacks, DisplayHandlerDecorator},
+    devtools::{page::UserScriptSlot, DevToolsError},
+    permission_store::{origin_of, Permission, PermissionDecision, PermissionStore},
+    string::quote_string,
+};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Which origins may show elements fullscreen and lock the mouse pointer. Both
+/// are allowed by default, as in CEF.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FullscreenPolicy {
+    fullscreen: PermissionDecision,
+    pointer_lock: PermissionDecision,
+    origins: HashMap<String, HashMap<Permission, PermissionDecision>>,
+}
+
+impl Default for FullscreenPolicy {
+    fn default() -> Self {
+        FullscreenPolicy {
+            fullscreen: PermissionDecision::Granted,
+            pointer_lock: PermissionDecision::Granted,
+            origins: HashMap::new(),
+        }
+    }
+}
+
+impl FullscreenPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Take the decisions for [Permission::Fullscreen] and
+    /// [Permission::PointerLock] from `store`. Origins without a decision get
+    /// the defaults of the returned policy.
+    pub fn from_store(store: &PermissionStore) -> Self {
+        let mut policy = Self::default();
+        for (origin, decisions) in store.decisions() {
+            for (permission, decision) in decisions {
+                if permission == Permission::Fullscreen || permission == Permission::PointerLock {
+                    policy = policy.origin(&origin, permission, decision);
+                }
+            }
+        }
+        policy
+    }
+    /// Set the decision for fullscreen requests of origins without their own
+    /// decision.
+    pub fn fullscreen(mut self, decision: PermissionDecision) -> Self {
+        self.fullscreen = decision;
+        self
+    }
+    /// Set the decision for pointer lock requests of origins without their own
+    /// decision.
+    pub fn pointer_lock(mut self, decision: PermissionDecision) -> Self {
+        self.pointer_lock = decision;
+        self
+    }
+    /// Set the decision for the origin of `url`. Permissions other than
+    /// [Permission::Fullscreen] and [Permission::PointerLock] are ignored.
+    pub fn origin(mut self, url: &str, permission: Permission, decision: PermissionDecision) -> Self {
+        self.origins.entry(origin_of(url)).or_default().insert(permission, decision);
+        self
+    }
+    /// Returns the decision for `permission` in documents loaded from `url`.
+    pub fn decision(&self, url: &str, permission: &Permission) -> PermissionDecision {
+        let default = match permission {
+            Permission::Fullscreen => self.fullscreen,
+            Permission::PointerLock => self.pointer_lock,
+            _ => PermissionDecision::Granted,
+        };
+        self.origins
+            .get(&origin_of(url))
+            .and_then(|decisions| decisions.get(permission))
+            .copied()
+            .unwrap_or(default)
+    }
+
+    /// Returns a script enforcing this policy in a document. Running it again
+    /// in the same document replaces the policy, and leaves fullscreen or
+    /// pointer lock if they are no longer allowed.
+    fn script(&self) -> String {
+        let allowed = |decision: Option<&PermissionDecision>| match decision {
+            Some(PermissionDecision::Granted) => "true",
+            Some(PermissionDecision::Denied) => "false",
+            None => "undefined",
+        };
+        let origins = self
+            .origins
+            .iter()
+            .map(|(origin, decisions)| {
+                format!(
+                    "{}: {{ fullscreen: {}, pointerLock: {} }}",
+                    quote_string(origin),
+                    allowed(decisions.get(&Permission::Fullscreen)),
+                    allowed(decisions.get(&Permission::PointerLock)),
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "(function() {{\n\
+             var state = window.__cefFullscreenPolicy;\n\
+             if (!state) {{\n\
+             state = window.__cefFullscreenPolicy = {{}};\n\
+             state.allowed = function(permission) {{\n\
+             var origin = state.policy.origins[location.origin.toLowerCase()];\n\
+             if (origin && origin[permission] !== undefined) return origin[permission];\n\
+             return state.policy[permission];\n\
+             }};\n\
+             ['requestFullscreen', 'webkitRequestFullscreen'].forEach(function(name) {{\n\
+             var request = Element.prototype[name];\n\
+             if (!request) return;\n\
+             Element.prototype[name] = function() {{\n\
+             if (state.allowed('fullscreen')) return request.apply(this, arguments);\n\
+             this.dispatchEvent(new Event('fullscreenerror', {{ bubbles: true }}));\n\
+             if (name === 'requestFullscreen') return Promise.reject(new TypeError('Permissions check failed'));\n\
+             }};\n\
+             }});\n\
+             var lock = Element.prototype.requestPointerLock;\n\
+             if (lock) {{\n\
+             Element.prototype.requestPointerLock = function() {{\n\
+             if (state.allowed('pointerLock')) return lock.apply(this, arguments);\n\
+             document.dispatchEvent(new Event('pointerlockerror'));\n\
+             }};\n\
+             }}\n\
+             }}\n\
+             state.policy = {{ fullscreen: {}, pointerLock: {}, origins: {{ {} }} }};\n\
+             if (!state.allowed('fullscreen') && document.fullscreenElement) document.exitFullscreen();\n\
+             if (!state.allowed('pointerLock') && document.pointerLockElement) document.exitPointerLock();\n\
+             }})();",
+            allowed(Some(&self.fullscreen)),
+            allowed(Some(&self.pointer_lock)),
+            origins,
+        )
+    }
+}
+
+lazy_static! {
+    /// The policy script of each browser that got a policy with
+    /// [BrowserHost::set_fullscreen_policy], by browser identifier.
+    static ref POLICY_SCRIPTS: Mutex<HashMap<i32, Arc<UserScriptSlot>>> = Mutex::new(HashMap::new());
+    /// Identifiers of the browsers that are fullscreen, as tracked by
+    /// [display_handler].
+    static ref FULLSCREEN: Mutex<HashSet<i32>> = Mutex::new(HashSet::new());
+}
+
+/// Drop the policy and fullscreen state of a closed browser. A browser
+/// closed while fullscreen doesn't report leaving fullscreen.
+pub(crate) fn forget_browser(browser_id: i32) {
+    POLICY_SCRIPTS.lock().remove(&browser_id);
+    FULLSCREEN.lock().remove(&browser_id);
+}
+
+impl BrowserHost {
+    /// Enforce `policy` in the documents currently loaded in this browser and
+    /// in every document loaded afterwards, until the browser is closed or the
+    /// policy is replaced. Elements that are fullscreen or hold the pointer lock
+    /// against the new policy leave that state. When called again before an
+    /// earlier call completed, the policies are still applied in call order.
+    ///
+    /// `callback` will be called on the browser process UI thread.
+    pub fn set_fullscreen_policy(
+        &self,
+        policy: &FullscreenPolicy,
+        callback: impl 'static + Send + FnOnce(Result<(), DevToolsError>),
+    ) {
+        let browser_id = self.get_browser().get_identifier();
+        let script = policy.script();
+        self.execute_in_all_frames(&script);
+        let slot = POLICY_SCRIPTS.lock().entry(browser_id).or_default().clone();
+        slot.set(self, Some(script), callback);
+    }
+    /// Make the fullscreen element of the page, if any, leave fullscreen, e.g.
+    /// when the user presses Escape or the application's window leaves
+    /// fullscreen on its own.
+    pub fn exit_fullscreen(&self) {
+        self.execute_in_all_frames("if (document.fullscreenElement) document.exitFullscreen();");
+    }
+    /// Release the pointer lock held by the page, if any.
+    pub fn exit_pointer_lock(&self) {
+        self.execute_in_all_frames("if (document.pointerLockElement) document.exitPointerLock();");
+    }
+    /// Returns true if the page shows an element fullscreen. Only tracked for
+    /// browsers whose client uses a handler created by [display_handler].
+    pub fn is_fullscreen(&self) -> bool {
+        FULLSCREEN.lock().contains(&self.get_browser().get_identifier())
+    }
+}
+
+/// Called when the page enters or leaves fullscreen, so the application can
+/// make its window fullscreen along with it.
+pub trait FullscreenWindowCallback = 'static + Send + FnMut(&Browser, bool);
+
+/// Create a [DisplayHandler] that calls `on_fullscreen` and tracks the
+/// fullscreen state of pages before calling into `callbacks`.
+pub fn display_handler<C: DisplayHandlerCallbacks>(
+    on_fullscreen: impl FullscreenWindowCallback,
+    callbacks: C,
+) -> DisplayHandler {
+    DisplayHandler::new(FullscreenDisplayHandler {
+        on_fullscreen: Box::new(on_fullscreen),
+        inner: callbacks,
+    })
+}
+
+struct FullscreenDisplayHandler<C: DisplayHandlerCallbacks> {
+    on_fullscreen: Box<dyn FullscreenWindowCallback>,
+    inner: C,
+}
+
+impl<C: DisplayHandlerCallbacks> DisplayHandlerDecorator for FullscreenDisplayHandler<C> {
+    type Inner = C;
+    fn inner(&mut self) -> &mut C {
+        &mut self.inner
+    }
+    fn on_fullscreen_mode_change(&mut self, browser: Browser, fullscreen: bool) {
+        let browser_id = browser.get_identifier();
+        if fullscreen {
+            FULLSCREEN.lock().insert(browser_id);
+        } else {
+            FULLSCREEN.lock().remove(&browser_id);
+        }
+        (self.on_fullscreen)(&browser, fullscreen);
+        self.inner.on_fullscreen_mode_change(browser, fullscreen)
+    }
+}
+
+/// The application window hosting a browser, as controlled by a
+/// [FullscreenCoordinator].
+pub trait FullscreenWindow: 'static + Send {
+    /// Remember the window's state and make it cover its monitor without
+    /// decorations.
+    fn enter_fullscreen(&mut self, browser: &Browser);
+    /// Restore the state remembered by [FullscreenWindow::enter_fullscreen].
+    fn exit_fullscreen(&mut self, browser: &Browser);
+}
+
+/// Makes a [FullscreenWindow] follow the fullscreen state of the page.
+/// Transitions are only applied once, so a page repeatedly requesting
+/// fullscreen doesn't overwrite the remembered window state.
+pub struct FullscreenCoordinator<W: FullscreenWindow> {
+    window: W,
+    fullscreen: bool,
+}
+
+impl<W: FullscreenWindow> FullscreenCoordinator<W> {
+    pub fn new(window: W) -> Self {
+        FullscreenCoordinator {
+            window,
+            fullscreen: false,
+        }
+    }
+    /// Create a [DisplayHandler] that applies the page's fullscreen state to
+    /// the window before calling into `callbacks`. See [display_handler].
+    pub fn display_handler<C: DisplayHandlerCallbacks>(self, callbacks: C) -> DisplayHandler {
+        let mut coordinator = self;
+        display_handler(
+            move |browser: &Browser, fullscreen| coordinator.set_fullscreen(browser, fullscreen),
+            callbacks,
+        )
+    }
+    /// Apply `fullscreen` to the window if it differs from the current state.
+    pub fn set_fullscreen(&mut self, browser: &Browser, fullscreen: bool) {
+        if fullscreen == self.fullscreen {
+            return;
+        }
+        self.fullscreen = fullscreen;
+        if fullscreen {
+            self.window.enter_fullscreen(browser);
+        } else {
+            self.window.exit_fullscreen(browser);
+        }
+    }
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub use self::windows::Win32FullscreenWindow;
+
+#[cfg(target_os = "windows")]
+mod windows {
+    use super::FullscreenWindow;
+    use crate::browser::Browser;
+    use std::mem;
+    use winapi::{
+        shared::{
+            basetsd::LONG_PTR,
+            windef::{HWND, RECT},
+        },
+        um::winuser::{
+            GetMonitorInfoW, GetWindowLongPtrW, GetWindowRect, IsZoomed, MonitorFromWindow,
+            SendMessageW, SetWindowLongPtrW, SetWindowPos, GWL_EXSTYLE, GWL_STYLE, MONITORINFO,
+            MONITOR_DEFAULTTONEAREST, SC_MAXIMIZE, SWP_FRAMECHANGED, SWP_NOACTIVATE, SWP_NOZORDER,
+            WM_SYSCOMMAND, WS_CAPTION, WS_EX_CLIENTEDGE, WS_EX_DLGMODALFRAME, WS_EX_STATICEDGE,
+            WS_EX_WINDOWEDGE, WS_THICKFRAME,
+        },
+    };
+
+    struct SavedWindow {
+        style: LONG_PTR,
+        ex_style: LONG_PTR,
+        rect: RECT,
+        maximized: bool,
+    }
+
+    /// [FullscreenWindow] for a top-level Win32 window, doing what Chrome does
+    /// for its own windows.
+    pub struct Win32FullscreenWindow {
+        hwnd: HWND,
+        saved: Option<SavedWindow>,
+    }
+
+    unsafe impl Send for Win32FullscreenWindow {}
+
+    impl Win32FullscreenWindow {
+        /// `hwnd` must be a valid top-level window that outlives this object.
+        pub unsafe fn new(hwnd: HWND) -> Self {
+            Win32FullscreenWindow { hwnd, saved: None }
+        }
+    }
+
+    impl FullscreenWindow for Win32FullscreenWindow {
+        fn enter_fullscreen(&mut self, browser: &Browser) {
+            unsafe {
+                let mut saved = SavedWindow {
+                    style: GetWindowLongPtrW(self.hwnd, GWL_STYLE),
+                    ex_style: GetWindowLongPtrW(self.hwnd, GWL_EXSTYLE),
+                    rect: mem::zeroed(),
+                    maximized: IsZoomed(self.hwnd) != 0,
+                };
+                GetWindowRect(self.hwnd, &mut saved.rect);
+                SetWindowLongPtrW(self.hwnd, GWL_STYLE, saved.style & !((WS_CAPTION | WS_THICKFRAME) as LONG_PTR));
+                SetWindowLongPtrW(
+                    self.hwnd,
+                    GWL_EXSTYLE,
+                    saved.ex_style
+                        & !((WS_EX_DLGMODALFRAME | WS_EX_WINDOWEDGE | WS_EX_CLIENTEDGE | WS_EX_STATICEDGE) as LONG_PTR),
+                );
+                let mut monitor = MONITORINFO {
+                    cbSize: mem::size_of::<MONITORINFO>() as u32,
+                    ..mem::zeroed()
+                };
+                GetMonitorInfoW(MonitorFromWindow(self.hwnd, MONITOR_DEFAULTTONEAREST), &mut monitor);
+                let rect = monitor.rcMonitor;
+                SetWindowPos(
+                    self.hwnd,
+                    std::ptr::null_mut(),
+                    rect.left,
+                    rect.top,
+                    rect.right - rect.left,
+                    rect.bottom - rect.top,
+                    SWP_NOZORDER | SWP_NOACTIVATE | SWP_FRAMECHANGED,
+                );
+                self.saved = Some(saved);
+            }
+        }
+        fn exit_fullscreen(&mut self, browser: &Browser) {
+            let saved = match self.saved.take() {
+                Some(saved) => saved,
+                None => return,
+            };
+            unsafe {
+                SetWindowLongPtrW(self.hwnd, GWL_STYLE, saved.style);
+                SetWindowLongPtrW(self.hwnd, GWL_EXSTYLE, saved.ex_style);
+                let rect = saved.rect;
+                SetWindowPos(
+                    self.hwnd,
+                    std::ptr::null_mut(),
+                    rect.left,
+                    rect.top,
+                    rect.right - rect.left,
+                    rect.bottom - rect.top,
+                    SWP_NOZORDER | SWP_NOACTIVATE | SWP_FRAMECHANGED,
+                );
+                if saved.maximized {
+                    SendMessageW(self.hwnd, WM_SYSCOMMAND, SC_MAXIMIZE, 0);
+                }
+            }
+        }
+    }
+}
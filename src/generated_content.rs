@@ -0,0 +1,261 @@
+//! Loading content generated by the application into a frame.
+//!
+//! Small content is loaded as a `data:` URL. Chromium refuses to navigate to
+//! URLs longer than [MAX_URL_LENGTH], so larger content is instead served once
+//! by a scheme handler registered with the browser's request context, under a
+//! URL in the reserved `.invalid` domain that nothing else answers.
+//!
+//! [Frame::load_html] can also make generated HTML appear as if it was loaded
+//! from a given URL, so relative links and same-origin requests resolve
+//! against that URL.
+
+use crate::{
+    browser::Browser,
+    callback::Callback,
+    frame::Frame,
+    load_handler::ErrorCode,
+    request::Request,
+    request_context::RequestContext,
+    response::Response,
+    scheme::{SchemeHandlerFactory, SchemeHandlerFactoryCallbacks},
+    url_request::{ResourceHandler, ResourceHandlerCallbacks, ResourceReadHandler, ResourceSkipCallback},
+};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// The longest URL Chromium navigates to.
+pub const MAX_URL_LENGTH: usize = 2 * 1024 * 1024;
+
+/// Domain of the URLs large content is served from.
+const GENERATED_CONTENT_DOMAIN: &str = "generated-content.invalid";
+
+static NEXT_CONTENT_ID: AtomicU64 = AtomicU64::new(1);
+
+lazy_static! {
+    /// Content waiting to be requested, by URL without fragment.
+    static ref PENDING: Mutex<HashMap<String, GeneratedContent>> = Mutex::new(HashMap::new());
+}
+
+struct GeneratedContent {
+    data: Vec<u8>,
+    mime_type: String,
+    charset: String,
+}
+
+/// Returns a `data:` URL containing `data`, base64-encoded.
+pub fn data_url(data: &[u8], mime_type: &str) -> String {
+    format!("data:{};base64,{}", mime_type, encode_base64(data))
+}
+
+/// Returns a `data:` URL containing the UTF-8 encoded `html`.
+pub fn html_data_url(html: &str) -> String {
+    data_url(html.as_bytes(), "text/html;charset=utf-8")
+}
+
+fn encode_base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &b)| bits | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+impl Frame {
+    /// Load `data` with the given mime type, e.g. `image/svg+xml`. Data that
+    /// doesn't fit into a `data:` URL is served once from a generated URL.
+    pub fn load_data_url(&self, data: &[u8], mime_type: &str) {
+        // Four characters per three bytes, plus the prefix.
+        if (data.len() + 2) / 3 * 4 + mime_type.len() + 13 <= MAX_URL_LENGTH {
+            self.load_url(&data_url(data, mime_type));
+        } else {
+            let url = format!(
+                "https://{}/{}",
+                GENERATED_CONTENT_DOMAIN,
+                NEXT_CONTENT_ID.fetch_add(1, Ordering::SeqCst)
+            );
+            self.serve_once(&url, data, mime_type, "");
+        }
+    }
+    /// Load `html` as the document of this frame. If `fake_url` is set, the
+    /// document appears to be loaded from that URL, which must be an `http` or
+    /// `https` URL. Otherwise it's loaded as a `data:` URL if it fits.
+    ///
+    /// Serving from `fake_url` registers a scheme handler factory for its
+    /// scheme and host with the browser's request context until the document
+    /// was requested, replacing one the application may have registered for
+    /// the same host. Other requests to that host are still handled normally.
+    pub fn load_html(&self, html: &str, fake_url: Option<&str>) {
+        match fake_url {
+            Some(url) => self.serve_once(url, html.as_bytes(), "text/html", "utf-8"),
+            None => self.load_data_url(html.as_bytes(), "text/html;charset=utf-8"),
+        }
+    }
+
+    fn serve_once(&self, url: &str, data: &[u8], mime_type: &str, charset: &str) {
+        let (scheme, host) = match scheme_and_host(url) {
+            Some(parts) => parts,
+            None => {
+                log::error!("Cannot serve generated content from {}", url);
+                return;
+            }
+        };
+        PENDING.lock().insert(
+            strip_fragment(url).to_owned(),
+            GeneratedContent {
+                data: data.to_vec(),
+                mime_type: mime_type.to_owned(),
+                charset: charset.to_owned(),
+            },
+        );
+        let context = self.get_browser().get_host().get_request_context();
+        let factory = GeneratedContentFactory {
+            context: context.clone(),
+            scheme: scheme.to_owned(),
+            host: host.to_owned(),
+        };
+        if !context.register_scheme_handler_factory(scheme, Some(host), SchemeHandlerFactory::new(factory)) {
+            log::error!("Cannot register a scheme handler for {}", url);
+            PENDING.lock().remove(strip_fragment(url));
+            return;
+        }
+        self.load_url(url);
+    }
+}
+
+fn strip_fragment(url: &str) -> &str {
+    url.split('#').next().unwrap_or(url)
+}
+
+fn scheme_and_host(url: &str) -> Option<(&str, &str)> {
+    let separator = url.find("://")?;
+    let scheme = &url[..separator];
+    if scheme != "http" && scheme != "https" {
+        return None;
+    }
+    let rest = &url[separator + 3..];
+    let host = &rest[..rest.find(|c| c == '/' || c == ':' || c == '?' || c == '#').unwrap_or(rest.len())];
+    if host.is_empty() {
+        None
+    } else {
+        Some((scheme, host))
+    }
+}
+
+/// Serves the pending content of one scheme and host, and unregisters itself
+/// once there's none left.
+struct GeneratedContentFactory {
+    context: RequestContext,
+    scheme: String,
+    host: String,
+}
+
+impl SchemeHandlerFactoryCallbacks for GeneratedContentFactory {
+    fn create(
+        &self,
+        browser: Browser,
+        frame: Frame,
+        scheme_name: &str,
+        request: Request,
+    ) -> Option<ResourceHandler> {
+        let content = {
+            let mut pending = PENDING.lock();
+            let content = pending.remove(strip_fragment(&request.get_url()));
+            let served_here = |url: &String| scheme_and_host(url) == Some((&*self.scheme, &*self.host));
+            if !pending.keys().any(served_here) {
+                self.context.unregister_scheme_handler_factory(&self.scheme, Some(&self.host));
+            }
+            content
+        }?;
+        Some(ResourceHandler::new(GeneratedContentHandler { content, offset: 0 }))
+    }
+}
+
+struct GeneratedContentHandler {
+    content: GeneratedContent,
+    offset: usize,
+}
+
+impl ResourceHandlerCallbacks for GeneratedContentHandler {
+    fn open(&mut self, request: Request, handle_request: &mut bool, callback: Callback) -> bool {
+        *handle_request = true;
+        true
+    }
+    fn get_response_headers(
+        &self,
+        response: Response,
+        response_length: &mut Option<u64>,
+        redirect_url: &mut String,
+    ) {
+        response.set_status(200);
+        response.set_status_text("OK");
+        response.set_mime_type(&self.content.mime_type);
+        if !self.content.charset.is_empty() {
+            response.set_charset(&self.content.charset);
+        }
+        *response_length = Some(self.content.data.len() as u64);
+    }
+    fn skip(&mut self, bytes_to_skip: u64, bytes_skipped: &mut u64, callback: ResourceSkipCallback) -> Result<(), ErrorCode> {
+        let skipped = (self.content.data.len() - self.offset).min(bytes_to_skip as usize);
+        self.offset += skipped;
+        *bytes_skipped = skipped as u64;
+        Ok(())
+    }
+    fn read(&mut self, mut handler: ResourceReadHandler) -> Option<ResourceReadHandler> {
+        let remaining = &self.content.data[self.offset..];
+        let buffer = handler.as_buffer_ref();
+        let len = remaining.len().min(buffer.len());
+        buffer[..len].copy_from_slice(&remaining[..len]);
+        self.offset += len;
+        handler.set_bytes_read(len as i32);
+        Some(handler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_pads_partial_chunks() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(encode_base64(&[0xfb, 0xff, 0xbf]), "+/+/");
+    }
+
+    #[test]
+    fn data_urls_are_base64_encoded() {
+        assert_eq!(data_url(b"<b>", "text/html"), "data:text/html;base64,PGI+");
+        assert_eq!(html_data_url("é"), "data:text/html;charset=utf-8;base64,w6k=");
+    }
+
+    #[test]
+    fn only_http_urls_can_be_served() {
+        assert_eq!(scheme_and_host("https://example.com/a"), Some(("https", "example.com")));
+        assert_eq!(scheme_and_host("http://example.com:8080?q"), Some(("http", "example.com")));
+        assert_eq!(scheme_and_host("http://example.com#top"), Some(("http", "example.com")));
+        assert_eq!(scheme_and_host("file:///tmp/a.html"), None);
+        assert_eq!(scheme_and_host("https:///a"), None);
+        assert_eq!(scheme_and_host("example.com"), None);
+    }
+
+    #[test]
+    fn content_is_looked_up_without_fragment() {
+        assert_eq!(strip_fragment("https://example.com/a#b#c"), "https://example.com/a");
+        assert_eq!(strip_fragment("https://example.com/a"), "https://example.com/a");
+    }
+}
@@ -0,0 +1,127 @@
+//! Falling back to software rendering on machines where the GPU doesn't work.
+//!
+//! Broken drivers can make the GPU process crash or fail to initialize on every
+//! start. Chromium then renders in software, or in the worst case shows blank
+//! browsers. A [GpuFallback] counts starts on which hardware acceleration
+//! wasn't available in a small state file, and disables the GPU on the command
+//! line once that happened too often in a row:
+//!
+//! ```rust,ignore
+//! let fallback = GpuFallback::new(cache_dir.join("gpu_failures"), 3);
+//!
+//! // In AppCallbacks::on_before_command_line_processing of the browser process:
+//! let software_rendering = fallback.apply(&command_line);
+//!
+//! // Once the first browser was created:
+//! let fallback = fallback.clone();
+//! browser.get_host().get_gpu_info(move |info| match info {
+//!     Ok(info) if !fallback.record(&info)
+//!         && !software_rendering
+//!         && fallback.failures() >= fallback.max_failures() => {
+//!         GpuFallback::restart_with_software_rendering().ok();
+//!     }
+//!     _ => (),
+//! });
+//! ```
+
+use crate::{command_line::CommandLine, devtools::system_info::GpuInfo};
+use std::{
+    fs, io,
+    path::PathBuf,
+    process::Command,
+};
+
+const SOFTWARE_RENDERING_SWITCHES: &[&str] = &["disable-gpu", "disable-gpu-compositing"];
+
+/// Tracks GPU failures across application starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GpuFallback {
+    state_file: PathBuf,
+    max_failures: u32,
+}
+
+impl GpuFallback {
+    /// Disable the GPU after `max_failures` consecutive failed starts. The
+    /// failure count is stored in `state_file`, which should be in a directory
+    /// the application can write to, e.g. next to the cache.
+    pub fn new(state_file: impl Into<PathBuf>, max_failures: u32) -> Self {
+        GpuFallback {
+            state_file: state_file.into(),
+            max_failures,
+        }
+    }
+    pub fn max_failures(&self) -> u32 {
+        self.max_failures
+    }
+    /// Returns the number of consecutive failed starts.
+    pub fn failures(&self) -> u32 {
+        fs::read_to_string(&self.state_file)
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+            .unwrap_or(0)
+    }
+    /// Add the switches for software rendering to `command_line`, which has to
+    /// be the command line of the browser process, if the GPU failed too often.
+    /// Returns true if software rendering is used.
+    pub fn apply(&self, command_line: &CommandLine) -> bool {
+        if command_line.has_switch("disable-gpu") {
+            return true;
+        }
+        if self.failures() < self.max_failures {
+            return false;
+        }
+        log::warn!(
+            "GPU failed on {} consecutive starts, falling back to software rendering",
+            self.failures()
+        );
+        for switch in SOFTWARE_RENDERING_SWITCHES {
+            command_line.append_switch(switch);
+        }
+        true
+    }
+    /// Record the outcome of this start based on `info`, as returned by
+    /// [BrowserHost::get_gpu_info](crate::browser_host::BrowserHost::get_gpu_info).
+    /// Returns true if hardware acceleration is available. Once software
+    /// rendering is used, failures aren't counted anymore, so the GPU stays
+    /// disabled until [GpuFallback::reset] is called.
+    pub fn record(&self, info: &GpuInfo) -> bool {
+        let accelerated = info.is_hardware_accelerated();
+        if accelerated {
+            self.reset();
+        } else if self.failures() < self.max_failures {
+            self.record_failure();
+        }
+        accelerated
+    }
+    /// Count a failed start, e.g. because the GPU process crashed.
+    pub fn record_failure(&self) {
+        let failures = self.failures().saturating_add(1);
+        if let Err(e) = fs::write(&self.state_file, failures.to_string()) {
+            log::warn!("Failed to write GPU failure count to {}: {}", self.state_file.display(), e);
+        }
+    }
+    /// Forget previous failures, e.g. after a driver update. The GPU is used
+    /// again from the next start on.
+    pub fn reset(&self) {
+        match fs::remove_file(&self.state_file) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                log::warn!("Failed to remove GPU failure count {}: {}", self.state_file.display(), e);
+            }
+            _ => (),
+        }
+    }
+    /// Start a new instance of the application with the same arguments and
+    /// software rendering. The caller is responsible for shutting down the
+    /// current instance, e.g. by closing all browsers, once this returns Ok.
+    pub fn restart_with_software_rendering() -> io::Result<()> {
+        let mut args: Vec<String> = std::env::args().skip(1).collect();
+        for switch in SOFTWARE_RENDERING_SWITCHES {
+            let switch = format!("--{}", switch);
+            if !args.contains(&switch) {
+                args.push(switch);
+            }
+        }
+        Command::new(std::env::current_exe()?).args(args).spawn()?;
+        Ok(())
+    }
+}
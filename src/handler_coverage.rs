@@ -0,0 +1,185 @@
+//! Diagnostics of which handler callbacks CEF invokes.
+//!
+//! While enabled, every callback from CEF into a handler of this crate is
+//! counted and timed, per browser for callbacks that receive one. A
+//! [HandlerCoverage] snapshot shows which callbacks fire, including the
+//! `get_*_handler` queries of [ClientCallbacks](crate::client::ClientCallbacks)
+//! for handlers that aren't implemented yet, and where time is spent on CEF's
+//! threads.
+//!
+//! Recording takes a lock per callback, so it's meant for debugging sessions,
+//! not for production use.
+
+use crate::{browser::Browser, string::quote_string};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref STATS: Mutex<HashMap<Option<i32>, HashMap<&'static str, CallbackStats>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Start recording callbacks.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Stop recording callbacks. Recorded statistics are kept until [reset].
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Discard the recorded statistics.
+pub fn reset() {
+    STATS.lock().clear();
+}
+
+/// Returns the statistics recorded so far.
+pub fn snapshot() -> HandlerCoverage {
+    let stats = STATS.lock();
+    let mut coverage = HandlerCoverage::default();
+    for (browser_id, callbacks) in stats.iter() {
+        let target = match browser_id {
+            Some(id) => coverage.browsers.entry(*id).or_default(),
+            None => &mut coverage.global,
+        };
+        target.extend(callbacks.iter().map(|(&name, &stats)| (name.to_owned(), stats)));
+    }
+    coverage
+}
+
+/// How often a callback was invoked, and how long it took.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallbackStats {
+    pub calls: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+/// Recorded callbacks, by name like `cef_load_handler_t::on_load_end`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HandlerCoverage {
+    /// Callbacks that received a browser, by browser identifier.
+    pub browsers: BTreeMap<i32, BTreeMap<String, CallbackStats>>,
+    /// Callbacks that didn't receive a browser.
+    pub global: BTreeMap<String, CallbackStats>,
+}
+
+impl HandlerCoverage {
+    /// Returns the names of all recorded callbacks.
+    pub fn callbacks(&self) -> Vec<&str> {
+        let mut names = self
+            .browsers
+            .values()
+            .chain(std::iter::once(&self.global))
+            .flat_map(|callbacks| callbacks.keys().map(|name| &**name))
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+        names
+    }
+    /// Returns the statistics as a JSON object, with durations in
+    /// microseconds:
+    /// `{"global": {name: {"calls", "total_us", "max_us"}}, "browsers": {id: {...}}}`.
+    pub fn to_json(&self) -> String {
+        fn callbacks_json(callbacks: &BTreeMap<String, CallbackStats>) -> String {
+            let entries = callbacks
+                .iter()
+                .map(|(name, stats)| {
+                    format!(
+                        "{}: {{\"calls\": {}, \"total_us\": {}, \"max_us\": {}}}",
+                        quote_string(name),
+                        stats.calls,
+                        stats.total.as_micros(),
+                        stats.max.as_micros(),
+                    )
+                })
+                .collect::<Vec<_>>();
+            format!("{{{}}}", entries.join(", "))
+        }
+        let browsers = self
+            .browsers
+            .iter()
+            .map(|(id, callbacks)| format!("\"{}\": {}", id, callbacks_json(callbacks)))
+            .collect::<Vec<_>>();
+        format!(
+            "{{\"global\": {}, \"browsers\": {{{}}}}}",
+            callbacks_json(&self.global),
+            browsers.join(", ")
+        )
+    }
+}
+
+/// Records a callback when dropped, and passes its duration to the
+/// [callback_watchdog](crate::callback_watchdog). Created by
+/// `cef_callback_impl!` if either is enabled.
+pub(crate) struct CallbackTimer {
+    name: &'static str,
+    browser_id: Option<i32>,
+    start: Instant,
+}
+
+impl CallbackTimer {
+    pub(crate) fn start(name: &'static str, browser_id: Option<i32>) -> CallbackTimer {
+        CallbackTimer {
+            name,
+            browser_id,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for CallbackTimer {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        crate::callback_watchdog::check(self.name, self.browser_id, elapsed);
+        if !is_enabled() {
+            return;
+        }
+        let mut stats = STATS.lock();
+        let stats = stats.entry(self.browser_id).or_default().entry(self.name).or_default();
+        stats.calls += 1;
+        stats.total += elapsed;
+        stats.max = stats.max.max(elapsed);
+    }
+}
+
+/// Finds the browser among the arguments of a callback. `cef_callback_impl!`
+/// calls `(&ArgumentProbe(&argument)).browser_id()`, which resolves to
+/// [ProbeBrowser] for browser arguments and to [ProbeOther] otherwise.
+pub(crate) struct ArgumentProbe<'a, T>(pub(crate) &'a T);
+
+pub(crate) trait ProbeBrowser {
+    fn browser_id(&self) -> Option<i32>;
+}
+
+impl ProbeBrowser for ArgumentProbe<'_, Browser> {
+    fn browser_id(&self) -> Option<i32> {
+        Some(self.0.get_identifier())
+    }
+}
+
+impl ProbeBrowser for ArgumentProbe<'_, Option<Browser>> {
+    fn browser_id(&self) -> Option<i32> {
+        self.0.as_ref().map(Browser::get_identifier)
+    }
+}
+
+pub(crate) trait ProbeOther {
+    fn browser_id(&self) -> Option<i32> {
+        None
+    }
+}
+
+impl<T> ProbeOther for &ArgumentProbe<'_, T> {}
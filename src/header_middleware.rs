@@ -0,0 +1,256 @@
+//! Composable request and response header rewriting.
+//!
+//! A [HeaderMiddlewareChain] collects any number of functions that modify
+//! outgoing request headers or incoming response headers and turns them into a
+//! single [ResourceRequestHandler]. Middleware runs in registration order, so
+//! independent modules (e.g. auth-token injection and CSP stripping) can be
+//! registered side by side without knowing about each other.
+//!
+//! Request headers are rewritten in
+//! [ResourceRequestHandlerCallbacks::on_before_resource_load]. CEF doesn't allow
+//! modifying responses received from the network, so requests that need their
+//! response headers rewritten are instead fetched by a [URLRequest] and served
+//! to the browser through a [ResourceHandler]. The full response body is
+//! buffered before it is handed to the browser, which is why response rewriting
+//! is restricted to the resource types set with
+//! [HeaderMiddlewareChain::rewrite_responses_for].
+//!
+//! Install the resulting handler through
+//! [RequestHandlerCallbacks::get_resource_request_handler]. Proxied requests are
+//! not associated with a browser and are only seen by the
+//! [RequestContextHandlerCallbacks] of the global request context, so installing
+//! the handler there would rewrite every proxied request twice.
+
+use crate::{
+    browser::Browser,
+    callback::Callback,
+    frame::Frame,
+    load_handler::ErrorCode,
+    request::{Request, ResourceType, URLRequestFlags},
+    resource_request_handler::{ResourceRequestHandler, ResourceRequestHandlerCallbacks},
+    response::Response,
+    url_request::{
+        RequestCallback, ResourceHandler, ResourceHandlerCallbacks, ResourceReadHandler,
+        ResourceSkipCallback, URLRequest, URLRequestClient, URLRequestClientCallbacks,
+        URLRequestStatus,
+    },
+    ReturnValue,
+};
+use parking_lot::Mutex;
+use std::{collections::HashMap, sync::Arc};
+
+/// Function modifying the headers of an outgoing request. The Referer header is
+/// not part of the map; use [Request::set_referrer] to change it.
+pub trait RequestHeaderMiddleware = 'static + Send + Sync + Fn(&Request, &mut HashMap<String, Vec<String>>);
+/// Function modifying the headers of an incoming response. The [Response]
+/// passed in is the unmodified response received from the network.
+pub trait ResponseHeaderMiddleware = 'static + Send + Sync + Fn(&Request, &Response, &mut HashMap<String, Vec<String>>);
+
+/// Builder composing header middleware into a [ResourceRequestHandler].
+pub struct HeaderMiddlewareChain {
+    request: Vec<Box<dyn RequestHeaderMiddleware>>,
+    response: Vec<Box<dyn ResponseHeaderMiddleware>>,
+    response_resource_types: Vec<ResourceType>,
+}
+
+impl HeaderMiddlewareChain {
+    /// Create an empty chain. Response headers are only rewritten for main frame
+    /// and sub frame documents unless changed with
+    /// [HeaderMiddlewareChain::rewrite_responses_for].
+    pub fn new() -> Self {
+        Self {
+            request: Vec::new(),
+            response: Vec::new(),
+            response_resource_types: vec![ResourceType::MainFrame, ResourceType::SubFrame],
+        }
+    }
+    /// Append a function modifying outgoing request headers.
+    pub fn with_request_middleware(mut self, middleware: impl RequestHeaderMiddleware) -> Self {
+        self.request.push(Box::new(middleware));
+        self
+    }
+    /// Append a function modifying incoming response headers.
+    pub fn with_response_middleware(mut self, middleware: impl ResponseHeaderMiddleware) -> Self {
+        self.response.push(Box::new(middleware));
+        self
+    }
+    /// Set the resource types whose response headers are rewritten.
+    pub fn rewrite_responses_for(mut self, resource_types: &[ResourceType]) -> Self {
+        self.response_resource_types = resource_types.to_vec();
+        self
+    }
+    pub fn build(self) -> ResourceRequestHandler {
+        ResourceRequestHandler::new(HeaderMiddlewareHandler {
+            request: self.request,
+            response: Arc::new(self.response),
+            response_resource_types: self.response_resource_types,
+        })
+    }
+}
+
+impl Default for HeaderMiddlewareChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct HeaderMiddlewareHandler {
+    request: Vec<Box<dyn RequestHeaderMiddleware>>,
+    response: Arc<Vec<Box<dyn ResponseHeaderMiddleware>>>,
+    response_resource_types: Vec<ResourceType>,
+}
+
+impl ResourceRequestHandlerCallbacks for HeaderMiddlewareHandler {
+    fn on_before_resource_load(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        callback: RequestCallback,
+    ) -> ReturnValue {
+        if !self.request.is_empty() {
+            let mut headers = request.get_header_map();
+            for middleware in &self.request {
+                middleware(&request, &mut headers);
+            }
+            request.set_header_map(&headers);
+        }
+        ReturnValue::Continue
+    }
+    fn get_resource_handler(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+    ) -> Option<ResourceHandler> {
+        if self.response.is_empty() || !self.response_resource_types.contains(&request.get_resource_type()) {
+            return None;
+        }
+        Some(ResourceHandler::new(ProxyResourceHandler {
+            middleware: self.response.clone(),
+            request: None,
+            upstream: None,
+            state: Arc::new(Mutex::new(UpstreamState::default())),
+            offset: 0,
+        }))
+    }
+}
+
+#[derive(Default)]
+struct UpstreamState {
+    body: Vec<u8>,
+    response: Option<Response>,
+    error: Option<ErrorCode>,
+    callback: Option<Callback>,
+}
+
+/// Serves a request by fetching it with a [URLRequest], so the response headers
+/// can be rewritten before the browser sees them.
+struct ProxyResourceHandler {
+    middleware: Arc<Vec<Box<dyn ResponseHeaderMiddleware>>>,
+    request: Option<Request>,
+    upstream: Option<URLRequest>,
+    state: Arc<Mutex<UpstreamState>>,
+    offset: usize,
+}
+
+impl ResourceHandlerCallbacks for ProxyResourceHandler {
+    fn open(&mut self, request: Request, handle_request: &mut bool, callback: Callback) -> bool {
+        let mut upstream_request = Request::new();
+        upstream_request.set_url(&request.get_url());
+        upstream_request.set_method(&request.get_method());
+        upstream_request.set_header_map(&request.get_header_map());
+        if let Some(post_data) = request.get_post_data() {
+            upstream_request.set_post_data(post_data);
+        }
+        let referrer = request.get_referrer_url();
+        if !referrer.is_empty() {
+            upstream_request.set_referrer(Some(&referrer), request.get_referrer_policy());
+        }
+        upstream_request.set_first_party_for_cookies(&request.get_first_party_for_cookies());
+        // Redirects are passed on to the browser, so that the document ends up
+        // with the right URL.
+        upstream_request.set_flags(&[URLRequestFlags::AllowStoredCredentials, URLRequestFlags::StopOnRedirect]);
+
+        self.state.lock().callback = Some(callback);
+        self.upstream = Some(URLRequest::new(
+            &mut upstream_request,
+            URLRequestClient::new(UpstreamClient(self.state.clone())),
+            None,
+        ));
+        self.request = Some(request);
+        *handle_request = false;
+        true
+    }
+    fn get_response_headers(
+        &self,
+        response: Response,
+        response_length: &mut Option<u64>,
+        redirect_url: &mut String,
+    ) {
+        let state = self.state.lock();
+        match (&state.response, &self.request) {
+            (Some(upstream), Some(request)) if upstream.get_status() != 0 => {
+                response.set_status(upstream.get_status());
+                response.set_status_text(&upstream.get_status_text());
+                response.set_mime_type(&upstream.get_mime_type());
+                response.set_charset(&upstream.get_charset());
+                let mut headers = upstream.get_header_map();
+                // The body has already been decoded by the network stack.
+                headers.retain(|name, _| {
+                    !name.eq_ignore_ascii_case("content-encoding") && !name.eq_ignore_ascii_case("content-length")
+                });
+                for middleware in self.middleware.iter() {
+                    middleware(request, upstream, &mut headers);
+                }
+                response.set_header_map(&headers);
+                *response_length = Some(state.body.len() as u64);
+            }
+            _ => response.set_error(state.error.unwrap_or(ErrorCode::Failed)),
+        }
+    }
+    fn skip(&mut self, bytes_to_skip: u64, bytes_skipped: &mut u64, callback: ResourceSkipCallback) -> Result<(), ErrorCode> {
+        let remaining = self.state.lock().body.len() - self.offset;
+        let skipped = remaining.min(bytes_to_skip as usize);
+        self.offset += skipped;
+        *bytes_skipped = skipped as u64;
+        Ok(())
+    }
+    fn read(&mut self, mut handler: ResourceReadHandler) -> Option<ResourceReadHandler> {
+        let state = self.state.lock();
+        let remaining = &state.body[self.offset..];
+        let buffer = handler.as_buffer_ref();
+        let len = remaining.len().min(buffer.len());
+        buffer[..len].copy_from_slice(&remaining[..len]);
+        self.offset += len;
+        handler.set_bytes_read(len as i32);
+        Some(handler)
+    }
+    fn cancel(&mut self) {
+        if let Some(upstream) = self.upstream.take() {
+            upstream.cancel();
+        }
+        self.state.lock().callback = None;
+    }
+}
+
+struct UpstreamClient(Arc<Mutex<UpstreamState>>);
+
+impl URLRequestClientCallbacks for UpstreamClient {
+    fn on_download_data(&self, request: URLRequest, data: &[u8]) {
+        self.0.lock().body.extend_from_slice(data);
+    }
+    fn on_request_complete(&self, request: URLRequest) {
+        let callback = {
+            let mut state = self.0.lock();
+            state.response = request.get_response();
+            if request.get_request_status() != URLRequestStatus::Success {
+                state.error = Some(request.get_request_error());
+            }
+            state.callback.take()
+        };
+        if let Some(callback) = callback {
+            callback.cont();
+        }
+    }
+}
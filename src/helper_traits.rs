@@ -1,4 +1,4 @@
-use crate::refcounted::RefCountedPtr;
+use crate::{refcounted::RefCountedPtr, CefError};
 
 pub trait IsSame {
     fn is_same(&self, other: Self) -> bool;
@@ -33,6 +33,14 @@ is_same!(_cef_v8value_t);
 
 pub trait IsValid {
     fn is_valid(&self) -> bool;
+    /// Returns [CefError::ObjectInvalid] if this object is no longer valid.
+    fn ensure_valid(&self) -> Result<(), CefError> {
+        if self.is_valid() {
+            Ok(())
+        } else {
+            Err(CefError::ObjectInvalid)
+        }
+    }
 }
 
 macro_rules! is_valid {
@@ -0,0 +1,417 @@
+//! Sharing the browser's session with a [reqwest] client.
+//!
+//! Native code often needs to call the same APIs as the web app, as the user
+//! that's logged in there. [BrowserCookieJar] lets a reqwest client send the
+//! cookies of a [CookieManager] and stores cookies set in its responses back
+//! into the browser. [ProxyConfig] reads and writes the proxy settings of a
+//! [RequestContext], so both sides go through the same proxy.
+//!
+//! Requires the `reqwest` feature.
+
+use crate::{
+    cookie::{cookie_for_import, Cookie, CookieManager, CookieSameSite},
+    request_context::RequestContext,
+    values::{DictionaryValue, StoredValue},
+};
+use chrono::DateTime;
+use parking_lot::Mutex;
+use reqwest::{cookie::CookieStore, header::HeaderValue, Url};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+/// A [CookieStore] backed by a [CookieManager].
+///
+/// Cookie stores are queried synchronously, so requests are served from a
+/// snapshot of the browser's cookies taken by [BrowserCookieJar::refresh].
+/// Cookies set by responses are added to the browser, and to the snapshot
+/// once the browser accepted them, which is right away for host cookies;
+/// cookies the browser receives afterwards are only seen after the next
+/// refresh.
+#[derive(Clone)]
+pub struct BrowserCookieJar {
+    manager: CookieManager,
+    cookies: Arc<Mutex<Vec<Cookie>>>,
+}
+
+impl BrowserCookieJar {
+    /// Create a jar without cookies. Call [BrowserCookieJar::refresh] before
+    /// making requests.
+    pub fn new(manager: CookieManager) -> BrowserCookieJar {
+        BrowserCookieJar {
+            manager,
+            cookies: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+    /// Replace the snapshot with the current cookies of the browser. Returns
+    /// false if cookies cannot be accessed.
+    pub async fn refresh(&self) -> bool {
+        match self.manager.export_cookies().await {
+            Some(cookies) => {
+                *self.cookies.lock() = cookies;
+                true
+            }
+            None => false,
+        }
+    }
+    /// Returns a client builder using this jar and the proxy settings of
+    /// `context`. Must be called on the browser process UI thread.
+    pub fn client_builder(&self, context: &RequestContext) -> reqwest::ClientBuilder {
+        let mut builder = reqwest::Client::builder().cookie_provider(Arc::new(self.clone()));
+        if let Some(proxy) = ProxyConfig::from_context(context) {
+            match proxy.to_reqwest() {
+                Ok(proxies) => {
+                    for proxy in proxies {
+                        builder = builder.proxy(proxy);
+                    }
+                }
+                Err(e) => log::warn!("Ignoring proxy {}: {}", proxy.server, e),
+            }
+        }
+        builder
+    }
+}
+
+impl CookieStore for BrowserCookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        for header in cookie_headers {
+            let cookie = match header.to_str().ok().and_then(|header| parse_set_cookie(header, url)) {
+                Some(cookie) => cookie,
+                None => continue,
+            };
+            let (import_url, import_cookie) = cookie_for_import(&cookie);
+            if cookie.domain.starts_with('.') {
+                // The browser rejects domain cookies for public suffixes
+                // parse_set_cookie doesn't know about.
+                let cookies = self.cookies.clone();
+                self.manager.set_cookie(&import_url, &import_cookie, move |success| {
+                    if success {
+                        store_cookie(&cookies, cookie);
+                    }
+                });
+            } else {
+                store_cookie(&self.cookies, cookie);
+                self.manager.set_cookie(&import_url, &import_cookie, |_| ());
+            }
+        }
+    }
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let host = url.host_str()?.to_lowercase();
+        let secure = url.scheme() == "https" || url.scheme() == "wss";
+        let now = SystemTime::now();
+        let mut cookies = self
+            .cookies
+            .lock()
+            .iter()
+            .filter(|c| domain_matches(&c.domain, &host) && path_matches(&c.path, url.path()))
+            .filter(|c| secure || !c.secure)
+            .filter(|c| c.expires.map_or(true, |expires| expires > now))
+            .cloned()
+            .collect::<Vec<_>>();
+        if cookies.is_empty() {
+            return None;
+        }
+        // Longer paths first, as browsers do.
+        cookies.sort_by(|a, b| b.path.len().cmp(&a.path.len()).then(a.creation.cmp(&b.creation)));
+        let header = cookies
+            .iter()
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        HeaderValue::from_str(&header).ok()
+    }
+}
+
+/// Add `cookie` to the snapshot, replacing the cookie it overwrites.
+fn store_cookie(cookies: &Mutex<Vec<Cookie>>, cookie: Cookie) {
+    let mut cookies = cookies.lock();
+    cookies.retain(|c| !(c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path));
+    cookies.push(cookie);
+}
+
+/// `domain` is a cookie domain as reported by CEF: the host for host cookies,
+/// and prefixed with a dot for domain cookies.
+fn domain_matches(domain: &str, host: &str) -> bool {
+    let domain = domain.to_lowercase();
+    if domain.starts_with('.') {
+        host == &domain[1..] || host.ends_with(&domain)
+    } else {
+        host == domain
+    }
+}
+
+fn path_matches(cookie_path: &str, request_path: &str) -> bool {
+    if cookie_path.is_empty() || cookie_path == request_path {
+        return true;
+    }
+    request_path.starts_with(cookie_path)
+        && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/'))
+}
+
+/// Longest lifetime given to a cookie by its `Max-Age`, as in RFC 6265bis.
+/// Larger values, which a server can send to any client, are capped so the
+/// expiry can be represented by the system and by CEF.
+const MAX_COOKIE_AGE: Duration = Duration::from_secs(400 * 24 * 60 * 60);
+
+/// Parses a `Set-Cookie` header received from `url` (RFC 6265, section 5.2).
+///
+/// Cookies for a top-level domain like `.com` are rejected, or set as host
+/// cookies if `url` is on that domain itself. Other public suffixes like
+/// `.co.uk` are left to the browser, which checks the full public suffix list
+/// when the cookie is stored.
+fn parse_set_cookie(header: &str, url: &Url) -> Option<Cookie> {
+    let host = url.host_str()?.to_lowercase();
+    let mut parts = header.split(';');
+    let (name, value) = {
+        let pair = parts.next()?;
+        let eq = pair.find('=')?;
+        (pair[..eq].trim(), pair[eq + 1..].trim())
+    };
+    if name.is_empty() {
+        return None;
+    }
+    let mut cookie = Cookie::new(name, value);
+    cookie.domain = host.clone();
+    cookie.path = default_path(url.path());
+    let mut max_age = None;
+    for attribute in parts {
+        let (key, value) = match attribute.find('=') {
+            Some(eq) => (attribute[..eq].trim(), attribute[eq + 1..].trim()),
+            None => (attribute.trim(), ""),
+        };
+        match &*key.to_lowercase() {
+            "domain" if !value.is_empty() => {
+                let domain = value.trim_start_matches('.').to_lowercase();
+                if !domain.contains('.') {
+                    if host == domain {
+                        continue;
+                    }
+                    return None;
+                }
+                if host != domain && !host.ends_with(&format!(".{}", domain)) {
+                    return None;
+                }
+                cookie.domain = format!(".{}", domain);
+            }
+            "path" if value.starts_with('/') => cookie.path = value.to_owned(),
+            "secure" => cookie.secure = true,
+            "httponly" => cookie.httponly = true,
+            "samesite" => {
+                cookie.same_site = match &*value.to_lowercase() {
+                    "strict" => CookieSameSite::StrictMode,
+                    "lax" => CookieSameSite::LaxMode,
+                    "none" => CookieSameSite::NoRestriction,
+                    _ => CookieSameSite::Unspecified,
+                }
+            }
+            "max-age" => max_age = value.parse::<i64>().ok(),
+            // Older servers separate the date with dashes.
+            "expires" => {
+                if let Ok(expires) = DateTime::parse_from_rfc2822(&value.replace('-', " ")) {
+                    cookie.expires = Some(expires.into());
+                }
+            }
+            _ => (),
+        }
+    }
+    if let Some(max_age) = max_age {
+        cookie.expires = if max_age > 0 {
+            // Should the addition overflow anyway, the cookie is kept for the
+            // session.
+            SystemTime::now().checked_add(Duration::from_secs(max_age as u64).min(MAX_COOKIE_AGE))
+        } else {
+            Some(SystemTime::UNIX_EPOCH)
+        };
+    }
+    Some(cookie)
+}
+
+/// The directory of `path`, used for cookies without a `Path` attribute.
+fn default_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) | None => "/".to_owned(),
+        Some(i) => path[..i].to_owned(),
+    }
+}
+
+/// Fixed proxy servers, in Chromium's format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyConfig {
+    /// Either a single proxy used for all schemes, like `http://proxy:8080` or
+    /// `socks5://proxy:1080`, or rules per scheme like
+    /// `http=proxy:8080;https=secure-proxy:8443`. Proxies without a scheme are
+    /// HTTP proxies.
+    pub server: String,
+    /// Hosts that are connected to directly, like `localhost` or
+    /// `*.example.com`.
+    pub bypass_list: Vec<String>,
+}
+
+impl ProxyConfig {
+    pub fn new(server: &str) -> ProxyConfig {
+        ProxyConfig {
+            server: server.to_owned(),
+            bypass_list: Vec::new(),
+        }
+    }
+    pub fn bypass(mut self, host: &str) -> Self {
+        self.bypass_list.push(host.to_owned());
+        self
+    }
+    /// Returns the fixed proxy servers used by `context`, or `None` if it
+    /// connects directly or uses the system settings or a PAC script. Must be
+    /// called on the browser process UI thread.
+    pub fn from_context(context: &RequestContext) -> Option<ProxyConfig> {
+        let proxy = match context.get_preference("proxy")? {
+            StoredValue::Dictionary(proxy) => proxy,
+            _ => return None,
+        };
+        if proxy.get_string("mode") != "fixed_servers" {
+            return None;
+        }
+        let server = proxy.get_string("server");
+        if server.is_empty() {
+            return None;
+        }
+        Some(ProxyConfig {
+            server,
+            bypass_list: proxy
+                .get_string("bypass_list")
+                .split(|c| c == ',' || c == ';')
+                .map(str::trim)
+                .filter(|host| !host.is_empty())
+                .map(str::to_owned)
+                .collect(),
+        })
+    }
+    /// Make `context` use these proxy servers. Must be called on the browser
+    /// process UI thread.
+    pub fn apply(&self, context: &RequestContext) -> Result<(), String> {
+        let proxy = DictionaryValue::new();
+        proxy.insert_string("mode", "fixed_servers");
+        proxy.insert_string("server", &self.server);
+        proxy.insert_string("bypass_list", &self.bypass_list.join(","));
+        context.set_preference("proxy", Some(StoredValue::Dictionary(proxy)))
+    }
+    /// Make `context` connect directly again. Must be called on the browser
+    /// process UI thread.
+    pub fn clear(context: &RequestContext) -> Result<(), String> {
+        let proxy = DictionaryValue::new();
+        proxy.insert_string("mode", "direct");
+        context.set_preference("proxy", Some(StoredValue::Dictionary(proxy)))
+    }
+    /// Returns the equivalent reqwest proxies.
+    pub fn to_reqwest(&self) -> reqwest::Result<Vec<reqwest::Proxy>> {
+        let no_proxy = reqwest::NoProxy::from_string(&self.bypass_list.join(","));
+        let url = |server: &str| {
+            if server.contains("://") {
+                server.to_owned()
+            } else {
+                format!("http://{}", server)
+            }
+        };
+        let mut proxies = Vec::new();
+        for rule in self.server.split(';').map(str::trim).filter(|rule| !rule.is_empty()) {
+            let proxy = match rule.find('=') {
+                Some(eq) => match &rule[..eq] {
+                    "http" => reqwest::Proxy::http(url(&rule[eq + 1..]))?,
+                    "https" => reqwest::Proxy::https(url(&rule[eq + 1..]))?,
+                    _ => continue,
+                },
+                None => reqwest::Proxy::all(url(rule))?,
+            };
+            proxies.push(proxy.no_proxy(no_proxy.clone()));
+        }
+        Ok(proxies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(header: &str, url: &str) -> Option<Cookie> {
+        parse_set_cookie(header, &Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn host_cookies_default_to_the_directory_of_the_url() {
+        let cookie = parse("id=42", "https://www.example.com/app/login").unwrap();
+        assert_eq!((&*cookie.name, &*cookie.value), ("id", "42"));
+        assert_eq!(cookie.domain, "www.example.com");
+        assert_eq!(cookie.path, "/app");
+        assert!(!cookie.secure && !cookie.httponly);
+    }
+
+    #[test]
+    fn attributes_are_case_insensitive() {
+        let cookie = parse(
+            "id=42; DOMAIN=Example.com; Path=/api; SECURE; HttpOnly; SameSite=Lax",
+            "https://www.example.com/",
+        )
+        .unwrap();
+        assert_eq!(cookie.domain, ".example.com");
+        assert_eq!(cookie.path, "/api");
+        assert!(cookie.secure && cookie.httponly);
+        assert_eq!(cookie.same_site, CookieSameSite::LaxMode);
+    }
+
+    #[test]
+    fn cookies_for_other_domains_are_rejected() {
+        assert!(parse("id=42; Domain=example.org", "https://www.example.com/").is_none());
+        assert!(parse("id=42; Domain=ample.com", "https://www.example.com/").is_none());
+        assert!(parse("=42", "https://www.example.com/").is_none());
+        assert!(parse("id", "https://www.example.com/").is_none());
+    }
+
+    #[test]
+    fn cookies_for_top_level_domains_are_rejected() {
+        assert!(parse("id=42; Domain=.com", "https://www.example.com/").is_none());
+        assert!(parse("id=42; Domain=com", "https://example.com/").is_none());
+    }
+
+    #[test]
+    fn top_level_domain_of_the_host_itself_sets_a_host_cookie() {
+        let cookie = parse("id=42; Domain=localhost", "http://localhost/").unwrap();
+        assert_eq!(cookie.domain, "localhost");
+    }
+
+    #[test]
+    fn max_age_overrides_expires() {
+        let cookie = parse(
+            "id=42; Max-Age=0; Expires=Wed, 21 Oct 2099 07:28:00 GMT",
+            "https://example.com/",
+        )
+        .unwrap();
+        assert_eq!(cookie.expires, Some(SystemTime::UNIX_EPOCH));
+        let cookie = parse("id=42; Expires=Wed, 21-Oct-2099 07:28:00 GMT", "https://example.com/").unwrap();
+        assert!(cookie.expires.unwrap() > SystemTime::now());
+    }
+
+    #[test]
+    fn out_of_range_max_age_is_capped() {
+        let cookie = parse("id=42; Max-Age=9223372036854775807", "https://example.com/").unwrap();
+        let expires = cookie.expires.unwrap();
+        assert!(expires > SystemTime::now());
+        assert!(expires <= SystemTime::now() + MAX_COOKIE_AGE);
+    }
+
+    #[test]
+    fn domain_cookies_match_subdomains() {
+        assert!(domain_matches(".example.com", "example.com"));
+        assert!(domain_matches(".example.com", "www.example.com"));
+        assert!(!domain_matches(".example.com", "badexample.com"));
+        assert!(domain_matches("example.com", "example.com"));
+        assert!(!domain_matches("example.com", "www.example.com"));
+    }
+
+    #[test]
+    fn paths_match_on_segment_boundaries() {
+        assert!(path_matches("/api", "/api"));
+        assert!(path_matches("/api", "/api/users"));
+        assert!(path_matches("/api/", "/api/users"));
+        assert!(!path_matches("/api", "/apiary"));
+        assert!(path_matches("", "/anything"));
+    }
+}
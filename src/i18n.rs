@@ -0,0 +1,176 @@
+//! Text direction and the locales CEF can display its UI strings in.
+//!
+//! CEF loads its localized strings from one pack file per locale. A locale
+//! without a pack file falls back to `en-US`, which is easily missed when
+//! trimming the distribution, so applications can check the locales they
+//! intend to use with [available_locales] before initializing CEF.
+
+use crate::{distribution, settings::Settings};
+use cef_sys::cef_is_rtl;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// The locale CEF uses if [Settings::locale] isn't set.
+pub const DEFAULT_LOCALE: &str = "en-US";
+
+/// Returns true if the application text direction is right-to-left, as
+/// determined by CEF's locale. Call this after CEF has been initialized.
+pub fn is_rtl() -> bool {
+    unsafe { cef_is_rtl() != 0 }
+}
+
+/// Returns the directory CEF loads locale pack files from with `settings`:
+/// [Settings::locales_dir_path], or the `locales` directory next to the CEF
+/// library. On macOS, it's the Resources directory of the CEF framework,
+/// which holds a `<locale>.lproj` directory per locale.
+pub fn locales_dir(settings: &Settings) -> Option<PathBuf> {
+    if cfg!(target_os = "macos") {
+        let framework_dir = match &settings.framework_dir_path {
+            Some(framework_dir) => framework_dir.clone(),
+            None => std::env::current_exe()
+                .ok()?
+                .parent()?
+                .join("../Frameworks/Chromium Embedded Framework.framework"),
+        };
+        Some(framework_dir.join("Resources"))
+    } else {
+        match &settings.locales_dir_path {
+            Some(locales_dir) => Some(locales_dir.clone()),
+            None => Some(distribution::module_dir()?.join("locales")),
+        }
+    }
+}
+
+/// Returns the locales with a pack file in `locales_dir`, like `en-US` or
+/// `de`, sorted.
+pub fn available_locales(locales_dir: &Path) -> io::Result<Vec<String>> {
+    let mut locales = Vec::new();
+    for entry in fs::read_dir(locales_dir)? {
+        let path = entry?.path();
+        let locale = if cfg!(target_os = "macos") {
+            // Bundles name the directories with underscores, like en_GB.lproj.
+            match path.extension() {
+                Some(extension) if extension == "lproj" && path.join("locale.pak").is_file() => {
+                    path.file_stem().map(|stem| stem.to_string_lossy().replace('_', "-"))
+                }
+                _ => None,
+            }
+        } else {
+            match path.extension() {
+                Some(extension) if extension == "pak" => path.file_stem().map(|stem| stem.to_string_lossy().into_owned()),
+                _ => None,
+            }
+        };
+        locales.extend(locale);
+    }
+    locales.sort();
+    Ok(locales)
+}
+
+/// Returns the locale of `available` that CEF would use for `locale`, or
+/// `None` if CEF would fall back to [DEFAULT_LOCALE]. Like Chromium, this
+/// tries the locale itself, then the fallback for its region:
+///
+/// - `es-*` other than `es-ES` falls back to `es-419`,
+/// - `pt-*` to `pt-PT`,
+/// - `zh-HK` and `zh-MO` to `zh-TW`, other `zh-*` to `zh-CN`,
+/// - `en-AU`, `en-CA`, `en-IN`, `en-NZ` and `en-ZA` to `en-GB`, other `en-*`
+///   to `en-US`,
+/// - and any other locale to its language without the region, like `de` for
+///   `de-AT`.
+///
+/// Finally, a few bare languages are mapped to the locale Chromium ships for
+/// them, like `pt` to `pt-BR` and `zh` to `zh-CN`.
+pub fn best_available_locale<'a>(available: &'a [String], locale: &str) -> Option<&'a str> {
+    let find = |wanted: &str| {
+        available
+            .iter()
+            .find(|candidate| candidate.eq_ignore_ascii_case(wanted))
+            .map(String::as_str)
+    };
+    if let Some(found) = find(locale) {
+        return Some(found);
+    }
+    if let Some(separator) = locale.find(|c| c == '-' || c == '_').filter(|&separator| separator > 0) {
+        let language = locale[..separator].to_ascii_lowercase();
+        let region = locale[separator + 1..].to_ascii_lowercase();
+        let fallback = match (language.as_str(), region.as_str()) {
+            ("es", "es") => "es".to_owned(),
+            ("es", _) => "es-419".to_owned(),
+            ("pt", _) => "pt-PT".to_owned(),
+            ("zh", "hk") | ("zh", "mo") => "zh-TW".to_owned(),
+            ("zh", _) => "zh-CN".to_owned(),
+            ("en", "au") | ("en", "ca") | ("en", "in") | ("en", "nz") | ("en", "za") => "en-GB".to_owned(),
+            ("en", _) => "en-US".to_owned(),
+            _ => language,
+        };
+        if let Some(found) = find(&fallback) {
+            return Some(found);
+        }
+    }
+    // Aliases for the languages Chromium only ships with a region, and for
+    // the old codes some systems still use.
+    const ALIASES: &[(&str, &str)] = &[
+        ("en", "en-US"),
+        ("iw", "he"),
+        ("no", "nb"),
+        ("pt", "pt-BR"),
+        ("tl", "fil"),
+        ("zh", "zh-CN"),
+    ];
+    ALIASES
+        .iter()
+        .find(|(alias, _)| alias.eq_ignore_ascii_case(locale))
+        .and_then(|(_, target)| find(target))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn available() -> Vec<String> {
+        ["de", "en-GB", "en-US", "es", "es-419", "fil", "pt-BR", "pt-PT", "zh-CN", "zh-TW"]
+            .iter()
+            .map(|locale| locale.to_string())
+            .collect()
+    }
+
+    fn best(locale: &str) -> Option<String> {
+        best_available_locale(&available(), locale).map(str::to_owned)
+    }
+
+    #[test]
+    fn exact_matches_ignore_case() {
+        assert_eq!(best("pt-BR").as_deref(), Some("pt-BR"));
+        assert_eq!(best("EN-gb").as_deref(), Some("en-GB"));
+    }
+
+    #[test]
+    fn regions_fall_back_like_chromium() {
+        assert_eq!(best("es-MX").as_deref(), Some("es-419"));
+        assert_eq!(best("es-ES").as_deref(), Some("es"));
+        assert_eq!(best("pt-AO").as_deref(), Some("pt-PT"));
+        assert_eq!(best("zh-HK").as_deref(), Some("zh-TW"));
+        assert_eq!(best("zh-SG").as_deref(), Some("zh-CN"));
+        assert_eq!(best("en-AU").as_deref(), Some("en-GB"));
+        assert_eq!(best("en-PH").as_deref(), Some("en-US"));
+        assert_eq!(best("de_AT").as_deref(), Some("de"));
+    }
+
+    #[test]
+    fn bare_languages_use_their_aliases() {
+        assert_eq!(best("pt").as_deref(), Some("pt-BR"));
+        assert_eq!(best("zh").as_deref(), Some("zh-CN"));
+        assert_eq!(best("en").as_deref(), Some("en-US"));
+        assert_eq!(best("tl").as_deref(), Some("fil"));
+    }
+
+    #[test]
+    fn unknown_locales_have_no_match() {
+        assert_eq!(best("fr-FR").as_deref(), None);
+        assert_eq!(best("fr").as_deref(), None);
+        assert_eq!(best("-FR").as_deref(), None);
+    }
+}
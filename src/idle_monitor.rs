@@ -0,0 +1,210 @@
+//! Resetting a browser after a period without user input, as kiosks require.
+//!
+//! CEF doesn't see input before the application forwards it, so the
+//! [IdleMonitor] is fed by forwarding input through it instead of calling the
+//! [BrowserHost] directly. Input that reaches the browser some other way (e.g.
+//! windowed browsers receiving input from the OS) has to be reported with
+//! [IdleMonitor::record_activity].
+
+use crate::{
+    browser::Browser,
+    browser_host::BrowserHost,
+    events::{KeyEvent, MouseButtonType, MouseEvent, TouchEvent},
+    permission_store::origin_of,
+    task::{TaskRunner, ThreadId},
+    values::StoredValue,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak},
+    time::{Duration, Instant},
+};
+
+/// The shortest timeout a monitor uses. Shorter ones, including zero, would
+/// reset the browser over and over again.
+pub const MIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Function called after a browser was reset for being idle.
+pub trait IdleResetCallback = 'static + Send + FnMut(&Browser);
+
+struct IdleState {
+    browser: Browser,
+    timeout: Duration,
+    home_url: String,
+    clear_session_data: bool,
+    last_activity: Instant,
+    on_reset: Option<Box<dyn IdleResetCallback>>,
+}
+
+/// Watches a browser for user input and resets it once no input was received
+/// for the configured timeout. Resetting navigates the main frame back to the
+/// home URL and, unless disabled, clears session data first.
+///
+/// Monitoring stops when the monitor is dropped.
+pub struct IdleMonitor(Arc<Mutex<IdleState>>);
+
+impl IdleMonitor {
+    /// Start monitoring `browser`. It's reset to `home_url` after `timeout`
+    /// without input. Timeouts shorter than [MIN_TIMEOUT] are raised to it.
+    pub fn new(browser: Browser, timeout: Duration, home_url: &str) -> IdleMonitor {
+        let timeout = clamp_timeout(timeout);
+        let monitor = IdleMonitor(Arc::new(Mutex::new(IdleState {
+            browser,
+            timeout,
+            home_url: home_url.to_owned(),
+            clear_session_data: true,
+            last_activity: Instant::now(),
+            on_reset: None,
+        })));
+        schedule_check(Arc::downgrade(&monitor.0), timeout);
+        monitor
+    }
+    /// Set whether cookies and the storage (local storage, IndexedDB, cache,
+    /// ...) of the current page's origin are cleared on reset. Enabled by
+    /// default. Cookies are cleared for the browser's whole request context.
+    pub fn with_clear_session_data(self, clear_session_data: bool) -> Self {
+        self.0.lock().clear_session_data = clear_session_data;
+        self
+    }
+    /// Call `on_reset` after every reset, e.g. to reset native UI as well.
+    pub fn with_reset_callback(self, on_reset: impl IdleResetCallback) -> Self {
+        self.0.lock().on_reset = Some(Box::new(on_reset));
+        self
+    }
+    /// Change the timeout. Takes effect from the next input on. Timeouts
+    /// shorter than [MIN_TIMEOUT] are raised to it.
+    pub fn set_timeout(&self, timeout: Duration) {
+        self.0.lock().timeout = clamp_timeout(timeout);
+    }
+    /// Returns the time since the last input.
+    pub fn idle_time(&self) -> Duration {
+        self.0.lock().last_activity.elapsed()
+    }
+    /// Record user input that wasn't forwarded through this monitor.
+    pub fn record_activity(&self) {
+        self.0.lock().last_activity = Instant::now();
+    }
+    /// Reset the browser immediately.
+    pub fn reset_now(&self) {
+        reset(&self.0);
+    }
+
+    /// Forwards to [BrowserHost::send_key_event].
+    pub fn send_key_event(&self, event: KeyEvent) {
+        self.host().send_key_event(event);
+    }
+    /// Forwards to [BrowserHost::send_mouse_click_event].
+    pub fn send_mouse_click_event(
+        &self,
+        event: &MouseEvent,
+        button_type: MouseButtonType,
+        mouse_up: bool,
+        click_count: i32,
+    ) {
+        self.host().send_mouse_click_event(event, button_type, mouse_up, click_count);
+    }
+    /// Forwards to [BrowserHost::send_mouse_move_event].
+    pub fn send_mouse_move_event(&self, event: &MouseEvent, mouse_leave: bool) {
+        self.host().send_mouse_move_event(event, mouse_leave);
+    }
+    /// Forwards to [BrowserHost::send_mouse_wheel_event].
+    pub fn send_mouse_wheel_event(&self, event: &MouseEvent, delta_x: i32, delta_y: i32) {
+        self.host().send_mouse_wheel_event(event, delta_x, delta_y);
+    }
+    /// Forwards to [BrowserHost::send_touch_event].
+    pub fn send_touch_event(&self, event: &TouchEvent) {
+        self.host().send_touch_event(event);
+    }
+
+    /// Records activity and returns the host to forward input to.
+    fn host(&self) -> BrowserHost {
+        let mut state = self.0.lock();
+        state.last_activity = Instant::now();
+        state.browser.get_host()
+    }
+}
+
+fn clamp_timeout(timeout: Duration) -> Duration {
+    timeout.max(MIN_TIMEOUT)
+}
+
+fn schedule_check(state: Weak<Mutex<IdleState>>, delay: Duration) {
+    TaskRunner::post_delayed_task_on(
+        ThreadId::UI,
+        move || {
+            let state = match state.upgrade() {
+                Some(state) => state,
+                None => return,
+            };
+            let (idle_time, timeout) = {
+                let state = state.lock();
+                (state.last_activity.elapsed(), state.timeout)
+            };
+            let next_check = if idle_time >= timeout {
+                reset(&state);
+                timeout
+            } else {
+                timeout - idle_time
+            };
+            schedule_check(Arc::downgrade(&state), next_check);
+        },
+        delay.as_millis() as i64,
+    );
+}
+
+fn reset(state: &Mutex<IdleState>) {
+    let (browser, home_url, clear_session_data) = {
+        let mut state = state.lock();
+        state.last_activity = Instant::now();
+        (state.browser.clone(), state.home_url.clone(), state.clear_session_data)
+    };
+    if clear_session_data {
+        // Navigate only once the data is gone, so the home page starts fresh.
+        let host = browser.get_host();
+        let browser_load = browser.clone();
+        let clear_cookies = move || {
+            let load_browser = browser_load.clone();
+            let load_url = home_url.clone();
+            let cookies_cleared = host.get_request_context()
+                .get_cookie_manager(|| ())
+                .delete_cookies("", "", move |_| load_browser.get_main_frame().load_url(&load_url));
+            if !cookies_cleared {
+                log::warn!("Failed to clear cookies on idle reset");
+                browser_load.get_main_frame().load_url(&home_url);
+            }
+        };
+        let origin = origin_of(&browser.get_main_frame().get_url());
+        if origin.starts_with("http://") || origin.starts_with("https://") {
+            let mut params = HashMap::new();
+            params.insert("origin".to_owned(), StoredValue::String(origin));
+            params.insert("storageTypes".to_owned(), StoredValue::String("all".to_owned()));
+            browser.get_host().call_dev_tools_method_unit("Storage.clearDataForOrigin", Some(params), move |_| clear_cookies());
+        } else {
+            clear_cookies();
+        }
+    } else {
+        browser.get_main_frame().load_url(&home_url);
+    }
+
+    let on_reset = state.lock().on_reset.take();
+    if let Some(mut on_reset) = on_reset {
+        on_reset(&browser);
+        let mut state = state.lock();
+        if state.on_reset.is_none() {
+            state.on_reset = Some(on_reset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_timeouts_are_raised_to_the_minimum() {
+        assert_eq!(clamp_timeout(Duration::from_secs(0)), MIN_TIMEOUT);
+        assert_eq!(clamp_timeout(Duration::from_millis(999)), MIN_TIMEOUT);
+        assert_eq!(clamp_timeout(Duration::from_secs(90)), Duration::from_secs(90));
+    }
+}
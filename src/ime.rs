@@ -1,6 +1,9 @@
 use cef_sys::{cef_composition_underline_t, cef_composition_underline_style_t};
 
-use crate::{color::Color, values::Range};
+use crate::{
+    color::Color,
+    values::{Range, Rect},
+};
 
 #[repr(C)]
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
@@ -50,3 +53,26 @@ impl From<CompositionUnderline> for cef_composition_underline_t {
         }
     }
 }
+
+/// Returns the smallest rectangle containing all of `character_bounds`, as
+/// passed to
+/// [RenderHandlerCallbacks::on_ime_composition_range_changed](crate::client::render_handler::RenderHandlerCallbacks::on_ime_composition_range_changed),
+/// or `None` if there's no composition. IME candidate windows are usually
+/// placed right below it.
+pub fn composition_bounds(character_bounds: &[Rect]) -> Option<Rect> {
+    let first = character_bounds.first()?;
+    let (mut left, mut top) = (first.x, first.y);
+    let (mut right, mut bottom) = (first.x + first.width, first.y + first.height);
+    for bounds in &character_bounds[1..] {
+        left = left.min(bounds.x);
+        top = top.min(bounds.y);
+        right = right.max(bounds.x + bounds.width);
+        bottom = bottom.max(bounds.y + bounds.height);
+    }
+    Some(Rect {
+        x: left,
+        y: top,
+        width: right - left,
+        height: bottom - top,
+    })
+}
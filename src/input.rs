@@ -0,0 +1,126 @@
+//! High-level input synthesis for automation and accessibility tooling.
+//!
+//! The helpers in this module expand a single gesture into the sequence of
+//! low-level events a real mouse would produce, with matching click counts and
+//! short pauses between the events, so pages see the same `mousedown`,
+//! `mouseup`, `click`, `dblclick` and `mousemove` events as for user input.
+//! Coordinates are in view coordinates, relative to the upper-left corner of
+//! the view.
+
+use crate::{
+    browser_host::BrowserHost,
+    events::{EventFlags, MouseButtonType, MouseEvent},
+    task::{TaskRunner, ThreadId},
+    values::Point,
+};
+
+/// Time between pressing and releasing a button, in milliseconds.
+const BUTTON_HOLD_MS: i64 = 50;
+/// Time between the two clicks of a double click, in milliseconds. This has to
+/// stay well below the platform's double click interval.
+const DOUBLE_CLICK_GAP_MS: i64 = 100;
+/// Time between two mouse moves of a drag, in milliseconds. One frame at 60Hz.
+const DRAG_STEP_MS: i64 = 16;
+
+enum MouseStep {
+    Move(MouseEvent),
+    Button {
+        event: MouseEvent,
+        mouse_up: bool,
+        click_count: i32,
+    },
+}
+
+impl MouseStep {
+    fn send(&self, host: &BrowserHost) {
+        match self {
+            MouseStep::Move(event) => host.send_mouse_move_event(event, false),
+            MouseStep::Button { event, mouse_up, click_count } => {
+                host.send_mouse_click_event(event, MouseButtonType::Left, *mouse_up, *click_count)
+            }
+        }
+    }
+}
+
+impl BrowserHost {
+    /// Move the mouse to `point` and click the left button.
+    pub fn click_at(&self, point: Point) {
+        let mut steps = Vec::new();
+        push_click(&mut steps, point, 1, 0);
+        self.play_mouse_steps(steps);
+    }
+    /// Move the mouse to `point` and double click the left button.
+    pub fn double_click_at(&self, point: Point) {
+        let mut steps = Vec::new();
+        push_click(&mut steps, point, 1, 0);
+        push_click(&mut steps, point, 2, DOUBLE_CLICK_GAP_MS);
+        self.play_mouse_steps(steps);
+    }
+    /// Press the left button at `from`, move the mouse to `to` in `steps`
+    /// evenly spaced moves and release the button there. At least one move is
+    /// always sent, as pages only start a drag once the mouse moves.
+    pub fn drag(&self, from: Point, to: Point, steps: u32) {
+        let steps_count = steps.max(1);
+        let mut steps = Vec::new();
+        steps.push((0, MouseStep::Move(mouse_event(from, EventFlags::empty()))));
+        steps.push((0, MouseStep::Button {
+            event: mouse_event(from, EventFlags::LEFT_MOUSE_BUTTON),
+            mouse_up: false,
+            click_count: 1,
+        }));
+        for i in 1..=steps_count {
+            let point = Point::new(
+                from.x + (to.x - from.x) * i as i32 / steps_count as i32,
+                from.y + (to.y - from.y) * i as i32 / steps_count as i32,
+            );
+            steps.push((DRAG_STEP_MS, MouseStep::Move(mouse_event(point, EventFlags::LEFT_MOUSE_BUTTON))));
+        }
+        steps.push((BUTTON_HOLD_MS, MouseStep::Button {
+            event: mouse_event(to, EventFlags::empty()),
+            mouse_up: true,
+            click_count: 1,
+        }));
+        self.play_mouse_steps(steps);
+    }
+
+    /// Send `steps` on the UI thread, waiting the given number of milliseconds
+    /// before each one.
+    fn play_mouse_steps(&self, steps: Vec<(i64, MouseStep)>) {
+        play(self.clone(), steps.into_iter());
+    }
+}
+
+fn play(host: BrowserHost, mut steps: std::vec::IntoIter<(i64, MouseStep)>) {
+    if let Some((delay_ms, step)) = steps.next() {
+        TaskRunner::post_delayed_task_on(
+            ThreadId::UI,
+            move || {
+                step.send(&host);
+                play(host, steps);
+            },
+            delay_ms,
+        );
+    }
+}
+
+fn push_click(steps: &mut Vec<(i64, MouseStep)>, point: Point, click_count: i32, delay_ms: i64) {
+    steps.push((delay_ms, MouseStep::Move(mouse_event(point, EventFlags::empty()))));
+    steps.push((0, MouseStep::Button {
+        event: mouse_event(point, EventFlags::LEFT_MOUSE_BUTTON),
+        mouse_up: false,
+        click_count,
+    }));
+    steps.push((BUTTON_HOLD_MS, MouseStep::Button {
+        event: mouse_event(point, EventFlags::empty()),
+        mouse_up: true,
+        click_count,
+    }));
+}
+
+fn mouse_event(point: Point, modifiers: EventFlags) -> MouseEvent {
+    MouseEvent {
+        x: point.x,
+        y: point.y,
+        modifiers,
+    }
+}
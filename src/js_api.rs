@@ -0,0 +1,148 @@
+//! Declaring JavaScript APIs implemented in Rust, in the render process.
+//!
+//! Functions created with [V8Value::new_function] only exist in the context
+//! they were created in, so they have to be created again for every document
+//! and frame in [RenderProcessHandlerCallbacks::on_context_created]. A
+//! [JsApiRegistry] holds the functions the application declares once, by
+//! dotted path like `app.files.read`, and installs them in every new context
+//! through the render process handler it creates.
+
+use crate::{
+    browser::Browser,
+    frame::Frame,
+    render_process_handler::{RenderProcessHandler, RenderProcessHandlerCallbacks, RenderProcessHandlerDecorator},
+    v8context::{V8Context, V8PropertyAttribute, V8Value},
+};
+use std::sync::Arc;
+
+/// A function called from JavaScript. Return `Err` to throw an exception with
+/// the given message.
+pub trait JsFunction = 'static + Send + Sync + Fn(&JsCall) -> Result<V8Value, String>;
+
+/// Decides which frames the API is installed in.
+pub trait JsFrameFilter = 'static + Send + Sync + Fn(&Frame) -> bool;
+
+/// A call of a registered function.
+pub struct JsCall<'a> {
+    /// The dotted path the function was registered under.
+    pub path: &'a str,
+    /// The frame whose context the function was installed in.
+    pub frame: &'a Frame,
+    pub this: V8Value,
+    pub arguments: &'a [V8Value],
+}
+
+/// JavaScript functions to install in every V8 context of the render process.
+#[derive(Clone, Default)]
+pub struct JsApiRegistry {
+    functions: Vec<(String, Arc<dyn JsFunction>)>,
+    filter: Option<Arc<dyn JsFrameFilter>>,
+}
+
+impl JsApiRegistry {
+    pub fn new() -> JsApiRegistry {
+        JsApiRegistry::default()
+    }
+    /// Declare a function at `path`, like `app.files.read`. Objects for the
+    /// namespaces along the path are created as needed, and are shared by
+    /// the functions in them.
+    pub fn function(mut self, path: &str, function: impl JsFunction) -> Self {
+        self.functions.push((path.to_owned(), Arc::new(function)));
+        self
+    }
+    /// Only install the API in frames for which `filter` returns true, e.g.
+    /// frames of the application's own origin.
+    pub fn frames(mut self, filter: impl JsFrameFilter) -> Self {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+    /// Only install the API in main frames.
+    pub fn main_frame_only(self) -> Self {
+        self.frames(Frame::is_main)
+    }
+    /// Install the declared functions in `context`, which belongs to `frame`.
+    /// Called by the handler of [JsApiRegistry::render_process_handler], or
+    /// call it from your own
+    /// [RenderProcessHandlerCallbacks::on_context_created].
+    pub fn install(&self, frame: &Frame, context: &V8Context) {
+        if let Some(filter) = &self.filter {
+            if !filter(frame) {
+                return;
+            }
+        }
+        context.execute_in_context(|| {
+            let global = match context.get_global() {
+                Some(global) => global,
+                None => return,
+            };
+            for (path, function) in &self.functions {
+                let mut segments = path.split('.').collect::<Vec<_>>();
+                let name = match segments.pop() {
+                    Some(name) if !name.is_empty() => name,
+                    _ => continue,
+                };
+                let namespace = segments.iter().try_fold(global.clone(), |object, segment| {
+                    match object.get_value_bykey(segment) {
+                        Some(existing) if existing.is_object() => Some(existing),
+                        _ => {
+                            let created = V8Value::new_object(None, None);
+                            if object.set_value_bykey(segment, created.clone(), V8PropertyAttribute::DONT_DELETE) {
+                                Some(created)
+                            } else {
+                                None
+                            }
+                        }
+                    }
+                });
+                let namespace = match namespace {
+                    Some(namespace) => namespace,
+                    None => {
+                        log::error!("Cannot create the namespace of {}", path);
+                        continue;
+                    }
+                };
+                let function = function.clone();
+                let frame = frame.clone();
+                let full_path = path.clone();
+                let value = V8Value::new_function(name, move |_, this, arguments| {
+                    function(&JsCall {
+                        path: &full_path,
+                        frame: &frame,
+                        this,
+                        arguments,
+                    })
+                });
+                namespace.set_value_bykey(name, value, V8PropertyAttribute::READ_ONLY | V8PropertyAttribute::DONT_DELETE);
+            }
+        });
+    }
+    /// Create a render process handler that installs the declared functions
+    /// in every new context before calling into `callbacks`.
+    pub fn render_process_handler<C: RenderProcessHandlerCallbacks>(self, callbacks: C) -> RenderProcessHandler {
+        RenderProcessHandler::new(JsApiRenderProcessHandler {
+            registry: self,
+            inner: callbacks,
+        })
+    }
+}
+
+struct JsApiRenderProcessHandler<C: RenderProcessHandlerCallbacks> {
+    registry: JsApiRegistry,
+    inner: C,
+}
+
+impl<C: RenderProcessHandlerCallbacks> RenderProcessHandlerDecorator for JsApiRenderProcessHandler<C> {
+    type Inner = C;
+    fn inner(&self) -> &C {
+        &self.inner
+    }
+    fn on_context_created(&self, browser: Browser, frame: Frame, context: V8Context) {
+        self.registry.install(&frame, &context);
+        self.inner.on_context_created(browser, frame, context)
+    }
+
+    fn on_context_released(&self, browser: Browser, frame: Frame, context: V8Context) {
+        // The installed functions are released along with the context.
+        self.inner.on_context_released(browser, frame, context)
+    }
+}
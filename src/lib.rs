@@ -19,51 +19,124 @@ pub mod resource_bundle_handler;
 pub mod browser_process_handler;
 pub mod browser;
 pub mod browser_host;
+pub mod browser_template;
+pub mod chrome_command;
+pub mod zoom;
 pub mod frame;
 pub mod load_handler;
 pub mod registration;
 pub mod render_process_handler;
+pub mod renderer_bootstrap;
 pub mod dom;
 pub mod v8context;
+pub mod js_api;
 pub mod process;
 pub mod request;
 pub mod response;
 pub mod url_request;
 pub mod request_context;
+pub mod context_isolation;
 pub mod web_plugin;
 pub mod cookie;
 pub mod devtools_message_observer;
+pub mod devtools;
 pub mod callback;
 pub mod resource_request_handler;
+pub mod header_middleware;
+pub mod permission_store;
+pub mod request_tracing;
+pub mod external_protocol;
+pub mod resource_load_report;
 pub mod client;
+pub mod download_task;
 pub mod image;
 
 pub mod command_line;
+pub mod process_model;
+pub mod gpu_fallback;
+pub mod web_features;
 pub mod app;
 
 pub mod sandbox;
 mod main_args;
 pub mod media_router;
+pub mod audio_output;
 pub mod scheme;
+pub mod generated_content;
+pub mod video_feed;
+pub mod reader_mode;
 pub mod settings;
+pub mod i18n;
+pub mod distribution;
 pub mod color;
 pub mod events;
+pub mod input;
+pub mod shortcuts;
+pub mod idle_monitor;
+pub mod osr_tiling;
+pub mod background_throttling;
+pub mod fullscreen;
+pub mod notifications;
+pub mod display_capture;
 pub mod drag;
+pub mod drag_coordinator;
 pub mod file_dialog;
 pub mod printing;
 pub mod window;
+pub mod dpi;
 pub mod x509_certificate;
 pub mod ime;
+pub mod selection;
 pub mod navigation;
+pub mod address_bar;
 pub mod extension;
+pub mod content_script;
 pub mod stream;
 pub mod ssl;
 pub mod task;
 pub mod logging;
+pub mod source_map;
+pub mod handler_coverage;
+pub mod callback_watchdog;
+pub mod native_messaging;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "reqwest")]
+pub mod http_client;
+#[cfg(feature = "asset_watch")]
+pub mod asset_watch;
 mod send_protector;
 #[cfg(target_os = "macos")] mod framework_loader_macos;
 #[cfg(target_os = "macos")] pub use framework_loader_macos::load_framework;
 
+/// Errors of operations on wrapped CEF objects.
+///
+/// Stale handles are reported by [helper_traits::IsValid::ensure_valid] and
+/// by the `try_` accessors that hand out other objects:
+/// [browser::Browser::try_get_host], [browser::Browser::try_get_main_frame],
+/// [browser_host::BrowserHost::try_get_browser], [frame::Frame::try_get_browser]
+/// and [frame::Frame::try_get_url]. The other functions keep their
+/// signatures: on a closed [browser::Browser] they panic, and CEF ignores
+/// most calls on a detached [frame::Frame] or the [browser_host::BrowserHost]
+/// of a closed browser. Check `is_valid` first where a handle can outlive
+/// its object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CefError {
+    /// The object no longer refers to a live CEF object, e.g. a [browser::Browser]
+    /// after it was closed or a [frame::Frame] that was detached by a navigation.
+    ObjectInvalid,
+}
+
+impl std::fmt::Display for CefError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CefError::ObjectInvalid => f.write_str("the CEF object is no longer valid"),
+        }
+    }
+}
+
+impl std::error::Error for CefError {}
+
 /// Return value types.
 #[repr(C)]
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
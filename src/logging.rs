@@ -2,7 +2,21 @@ use cef_sys::{cef_log, cef_log_severity_t, cef_get_min_log_level};
 use log::{Record, Level, Metadata};
 use std::borrow::Cow;
 use std::ffi::CString;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 use crate::misc_fns::panic_if_cef_not_loaded;
+use crate::settings::LogSeverity;
+
+/// Target of the records forwarded by a [LogForwarder]. [Logger] drops records
+/// with this target, as they came from CEF's log in the first place.
+pub const LOG_TARGET: &str = "libcef";
 
 /// Integration of Rust's log crate with CEF. Example usage:
 ///
@@ -19,6 +33,9 @@ use crate::misc_fns::panic_if_cef_not_loaded;
 /// Note that you have to call [cef::Context::initialize] before logging can be used.
 /// Also, don't forget to configure CEF's log level, which is separate from the one managed by [log].
 /// Only if a message's level passes both filters, it will actually be logged.
+///
+/// For the opposite direction, sending CEF's own log output to [log], see
+/// [LogForwarder].
 pub struct Logger {
     level: log::LevelFilter,
     module_path_levels: Vec<(Cow<'static, str>, log::LevelFilter)>,
@@ -60,6 +77,9 @@ impl log::Log for Logger {
     }
 
     fn log(&self, record: &Record) {
+        if record.target() == LOG_TARGET {
+            return;
+        }
         if self.enabled(record.metadata()) {
             panic_if_cef_not_loaded();
             let log_module_path = record.module_path().or(record.module_path_static());
@@ -132,3 +152,196 @@ impl LoggerBuilder {
         }
     }
 }
+
+/// Forwards the messages CEF writes to its log file to [log], with the target
+/// [LOG_TARGET], so Chromium's warnings end up in the application's log
+/// instead of a separate file. Example usage:
+///
+/// ```rust,ignore
+///  let log_file = data_dir.join("cef.log");
+///  let _forwarder = cef::logging::LogForwarder::start(&log_file).unwrap();
+///  let settings = Settings {
+///      log_file: Some(log_file),
+///      log_severity: LogSeverity::Warning,
+///      ..Settings::default()
+///  };
+/// ```
+///
+/// The file is polled from a thread of its own, so forwarding can start before
+/// CEF is initialized and catches messages of all processes. Only messages
+/// written after [LogForwarder::start] are forwarded. Set
+/// [Settings::log_file](crate::settings::Settings::log_file) to a path of the
+/// application's, as the default location differs by platform.
+pub struct LogForwarder {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LogForwarder {
+    /// How often the log file is checked for new messages.
+    const INTERVAL: Duration = Duration::from_millis(250);
+
+    /// Start forwarding the messages appended to the log file at `path`. The
+    /// file doesn't need to exist yet.
+    pub fn start(path: impl Into<PathBuf>) -> io::Result<LogForwarder> {
+        let path = path.into();
+        let position = match std::fs::metadata(&path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e),
+        };
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+        let thread = thread::Builder::new()
+            .name("cef-log-forwarder".to_owned())
+            .spawn(move || forward_log(path, position, thread_running))?;
+        Ok(LogForwarder {
+            running,
+            thread: Some(thread),
+        })
+    }
+    /// Stop forwarding, after forwarding the messages already written. Same as
+    /// dropping the forwarder.
+    pub fn stop(self) {}
+}
+
+impl Drop for LogForwarder {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn forward_log(path: PathBuf, mut position: u64, running: Arc<AtomicBool>) {
+    let mut level = Level::Info;
+    loop {
+        // Read once more after being stopped, for the last messages.
+        let stopping = !running.load(Ordering::Relaxed);
+        if let Ok(file) = File::open(&path) {
+            let len = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            // CEF truncates the file when it's started with an old one.
+            if len < position {
+                position = 0;
+            }
+            let mut reader = BufReader::new(file);
+            if reader.seek(SeekFrom::Start(position)).is_ok() {
+                let mut line = Vec::new();
+                // Only complete lines are read, the rest is read on the next
+                // check once it has been written.
+                while let Ok(len) = reader.read_until(b'\n', &mut line) {
+                    if len == 0 || line.last() != Some(&b'\n') {
+                        break;
+                    }
+                    position += len as u64;
+                    forward_line(&String::from_utf8_lossy(&line), &mut level);
+                    line.clear();
+                }
+            }
+        }
+        if stopping {
+            break;
+        }
+        thread::sleep(LogForwarder::INTERVAL);
+    }
+}
+
+/// Forward one line of the log file. Lines without a prefix continue the
+/// message before them and are logged with its `level`.
+fn forward_line(line: &str, level: &mut Level) {
+    let line = line.trim_end();
+    if line.is_empty() {
+        return;
+    }
+    match parse_line(line) {
+        Some((line_level, location, message)) => {
+            *level = line_level;
+            log::log!(target: LOG_TARGET, line_level, "{} {}", location, message);
+        }
+        None => log::log!(target: LOG_TARGET, *level, "{}", line),
+    }
+}
+
+/// Split a line like `[1234:5678:1016/120000.123:WARNING:file.cc(42)] message`
+/// into its level, source location and message. Which of the process id,
+/// thread id and time stamp fields are present depends on the configuration,
+/// so the severity is found by name.
+fn parse_line(line: &str) -> Option<(Level, &str, &str)> {
+    if !line.starts_with('[') {
+        return None;
+    }
+    let end = line.find(']')?;
+    let mut fields = line[1..end].split(':');
+    let severity = fields.by_ref().find_map(parse_severity)?;
+    let location = fields.next().unwrap_or("");
+    let message = &line[end + 1..];
+    Some((severity.to_log_level()?, location, message.strip_prefix(' ').unwrap_or(message)))
+}
+
+/// Returns the severity with the given name, as Chromium writes it to the log.
+fn parse_severity(name: &str) -> Option<LogSeverity> {
+    match name {
+        "FATAL" => Some(LogSeverity::Fatal),
+        "ERROR" => Some(LogSeverity::Error),
+        "WARNING" => Some(LogSeverity::Warning),
+        "INFO" => Some(LogSeverity::Info),
+        name if name.starts_with("VERBOSE") => Some(LogSeverity::Verbose),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_are_split_into_level_location_and_message() {
+        assert_eq!(
+            parse_line("[1234:5678:1016/120000.123:WARNING:file.cc(42)] disk full"),
+            Some((Level::Warn, "file.cc(42)", "disk full"))
+        );
+        assert_eq!(
+            parse_line("[1016/120000.123:ERROR:gpu_init.cc(7)] no GPU"),
+            Some((Level::Error, "gpu_init.cc(7)", "no GPU"))
+        );
+        assert_eq!(parse_line("[FATAL:main.cc(1)] crash"), Some((Level::Error, "main.cc(1)", "crash")));
+        assert_eq!(parse_line("[INFO:a.cc(1)] "), Some((Level::Info, "a.cc(1)", "")));
+        assert_eq!(parse_line("[VERBOSE2:a.cc(1)] x"), Some((Level::Debug, "a.cc(1)", "x")));
+    }
+
+    #[test]
+    fn message_can_follow_the_prefix_directly() {
+        assert_eq!(parse_line("[INFO:a.cc(1)]message"), Some((Level::Info, "a.cc(1)", "message")));
+        assert_eq!(parse_line("[INFO:a.cc(1)]"), Some((Level::Info, "a.cc(1)", "")));
+    }
+
+    #[test]
+    fn continuation_lines_have_no_prefix() {
+        assert_eq!(parse_line("    at foo (app.js:1:2)"), None);
+        assert_eq!(parse_line("[object Object] was thrown"), None);
+        assert_eq!(parse_line("[1234:5678] no severity"), None);
+        assert_eq!(parse_line("[INFO unterminated"), None);
+    }
+
+    #[test]
+    fn continuation_lines_keep_the_previous_level() {
+        let mut level = Level::Info;
+        forward_line("[1234:5678:ERROR:a.cc(1)] first line\n", &mut level);
+        assert_eq!(level, Level::Error);
+        forward_line("second line\n", &mut level);
+        assert_eq!(level, Level::Error);
+        forward_line("\n", &mut level);
+        assert_eq!(level, Level::Error);
+        forward_line("[1234:5678:INFO:a.cc(2)] next message", &mut level);
+        assert_eq!(level, Level::Info);
+    }
+
+    #[test]
+    fn severities_are_parsed_by_name() {
+        assert_eq!(parse_severity("WARNING"), Some(LogSeverity::Warning));
+        assert_eq!(parse_severity("VERBOSE1"), Some(LogSeverity::Verbose));
+        assert_eq!(parse_severity("1016/120000.123"), None);
+        assert_eq!(parse_severity("warning"), None);
+    }
+}
@@ -6,11 +6,13 @@
 use crate::{
     app::App,
     main_args::MainArgs,
+    request_context::RequestContext,
     sandbox::SandboxInfo,
     settings::Settings,
+    string::CefString,
 };
 use cef_sys::{
-    cef_do_message_loop_work, cef_execute_process,
+    cef_do_message_loop_work, cef_execute_process, cef_get_path, cef_path_key_t,
     cef_initialize, cef_quit_message_loop, cef_run_message_loop, cef_shutdown,
 };
 use std::{
@@ -82,6 +84,7 @@ pub fn execute_process(
         {
             crate::framework_loader_macos::load_framework(framework_dir_path()).expect("framework path must have parent to be valid");
         }
+        let cookieable_schemes = settings.cookieable_schemes.clone();
         let args = MainArgs::new();
         enable_highdpi_support();
         unsafe {
@@ -137,6 +140,49 @@ pub fn process_type() -> ProcessType {
     *PROCESS_TYPE
 }
 
+/// Directories and files CEF can resolve with [get_path].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathKey {
+    /// Current directory.
+    Current = cef_path_key_t::PK_DIR_CURRENT as isize,
+    /// Directory containing the executable.
+    ExeDir = cef_path_key_t::PK_DIR_EXE as isize,
+    /// Directory containing the CEF library.
+    ModuleDir = cef_path_key_t::PK_DIR_MODULE as isize,
+    /// Temporary directory.
+    Temp = cef_path_key_t::PK_DIR_TEMP as isize,
+    /// Path and filename of the executable.
+    ExeFile = cef_path_key_t::PK_FILE_EXE as isize,
+    /// Path and filename of the CEF library.
+    ModuleFile = cef_path_key_t::PK_FILE_MODULE as isize,
+    /// The local application data directory on Windows.
+    LocalAppData = cef_path_key_t::PK_LOCAL_APP_DATA as isize,
+    /// The user data directory, e.g. `~/.config` on Linux.
+    UserData = cef_path_key_t::PK_USER_DATA as isize,
+    /// The directory containing the pack files, which relative extension
+    /// directories passed to
+    /// [RequestContext::load_extension](crate::request_context::RequestContext::load_extension)
+    /// are resolved against.
+    Resources = cef_path_key_t::PK_DIR_RESOURCES as isize,
+}
+
+/// Returns the path CEF resolves for `key`, or `None` if it can't. Only
+/// available once CEF is loaded.
+///
+/// Use these paths to keep files next to CEF's, e.g. a cache directory under
+/// [PathKey::UserData] or extensions relative to [PathKey::Resources].
+pub fn get_path(key: PathKey) -> Option<PathBuf> {
+    panic_if_cef_not_loaded();
+    let mut path = CefString::null();
+    let found = unsafe { cef_get_path(key as cef_path_key_t::Type, path.as_ptr_mut()) } != 0;
+    if found {
+        Some(PathBuf::from(String::from(path)))
+    } else {
+        None
+    }
+}
+
 pub(crate) fn panic_if_cef_not_loaded() {
     #[cfg(target_os = "macos")]
     {
@@ -186,6 +232,12 @@ impl Context {
                     .unwrap_or_else(null_mut),
             ) != 0;
             crate::settings::drop_settings(settings);
+            if worked {
+                if let Some(cookieable_schemes) = &cookieable_schemes {
+                    let manager = RequestContext::global().get_cookie_manager(|| ());
+                    cookieable_schemes.apply(&manager, || ());
+                }
+            }
             match worked {
                 true => Ok(Context(std::marker::PhantomData)),
                 false => Err(std::io::Error::new(std::io::ErrorKind::Other, "context creation failed!")),
@@ -0,0 +1,284 @@
+//! Support for running the embedding executable as a Chrome native messaging host.
+//!
+//! Extensions talk to native hosts through `chrome.runtime.connectNative` and
+//! `chrome.runtime.sendNativeMessage`. The browser launches the host executable
+//! with the calling extension's origin as the first argument and exchanges
+//! messages over the host's stdin/stdout. Every message is UTF-8 encoded JSON
+//! preceded by its length as a 32-bit integer in native byte order.
+//!
+//! See https://developer.chrome.com/apps/nativeMessaging for details.
+
+use std::{
+    io::{self, Read, Write},
+    path::PathBuf,
+};
+
+/// Maximum size of a single message sent from the host to the browser.
+pub const MAX_HOST_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// Maximum size of a single message [read_message] accepts from the browser.
+/// The browser itself allows up to 4 GiB, but the length prefix isn't
+/// trusted with an allocation that large.
+pub const MAX_BROWSER_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Information passed by the browser on the command line when it launches a
+/// native messaging host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NativeMessagingLaunch {
+    /// Origin of the extension that started the host, e.g.
+    /// `chrome-extension://knldjmfmopnpolahpmmgbagdohdnhkik/`.
+    pub origin: String,
+    /// Handle of the calling browser window. Only passed on Windows, and only
+    /// if the host was started from a browser window.
+    pub parent_window: Option<isize>,
+}
+
+impl NativeMessagingLaunch {
+    /// Returns the launch information if the current process was started by a
+    /// browser as a native messaging host, or None otherwise.
+    pub fn from_env() -> Option<NativeMessagingLaunch> {
+        Self::from_args(std::env::args().skip(1))
+    }
+
+    /// Parses launch information from a list of command line arguments, excluding
+    /// the executable name.
+    pub fn from_args<I, S>(args: I) -> Option<NativeMessagingLaunch>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let flag = "--parent-window=";
+        let mut origin = None;
+        let mut parent_window = None;
+        for arg in args {
+            let arg = arg.as_ref();
+            if arg.starts_with("chrome-extension://") {
+                origin = Some(arg.to_owned());
+            } else if let Some(handle) = arg.strip_prefix(flag) {
+                parent_window = handle.parse().ok();
+            }
+        }
+        origin.map(|origin| NativeMessagingLaunch { origin, parent_window })
+    }
+
+    /// Returns the identifier of the calling extension, as reported by
+    /// [crate::extension::Extension::get_identifier], or `None` if
+    /// [NativeMessagingLaunch::origin] isn't an extension origin.
+    pub fn extension_id(&self) -> Option<&str> {
+        self.origin
+            .strip_prefix("chrome-extension://")
+            .map(|id| id.trim_end_matches('/'))
+    }
+}
+
+/// Reads a single message from `reader`.
+///
+/// Returns `Ok(None)` if the stream was closed before a new message started,
+/// which is how the browser signals that the port has been disconnected.
+/// Messages larger than [MAX_BROWSER_MESSAGE_SIZE] are rejected with
+/// [io::ErrorKind::InvalidData] without being read.
+pub fn read_message<R: Read>(reader: &mut R) -> io::Result<Option<String>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => (),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_ne_bytes(len) as usize;
+    if len > MAX_BROWSER_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("message of {} bytes exceeds the limit of 64 MiB", len),
+        ));
+    }
+    let mut message = vec![0; len];
+    reader.read_exact(&mut message)?;
+    String::from_utf8(message)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Writes a single message to `writer` and flushes it.
+///
+/// `message` must be a serialized JSON value. Messages larger than
+/// [MAX_HOST_MESSAGE_SIZE] are rejected by the browser, so they are refused
+/// here instead of being sent.
+pub fn write_message<W: Write>(writer: &mut W, message: &str) -> io::Result<()> {
+    if message.len() > MAX_HOST_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "native messaging host messages may not exceed 1 MB",
+        ));
+    }
+    writer.write_all(&(message.len() as u32).to_ne_bytes())?;
+    writer.write_all(message.as_bytes())?;
+    writer.flush()
+}
+
+/// Handler invoked for every message received from the extension. The returned
+/// messages are written back to the extension in order.
+pub trait NativeMessageHandler = 'static + FnMut(&NativeMessagingLaunch, String) -> Vec<String>;
+
+/// Runs the native messaging host loop over the process' stdin and stdout until
+/// the browser disconnects.
+///
+/// Call this early in `main`, before [crate::execute_process], when
+/// [NativeMessagingLaunch::from_env] returns `Some`. Nothing else may write
+/// to stdout while the loop is running or the stream will be corrupted.
+pub fn run_host(launch: &NativeMessagingLaunch, handler: impl NativeMessageHandler) -> io::Result<()> {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    run_host_with(launch, &mut stdin.lock(), &mut stdout.lock(), handler)
+}
+
+/// Same as [run_host], but reads and writes messages using the given streams.
+pub fn run_host_with<R: Read, W: Write>(
+    launch: &NativeMessagingLaunch,
+    reader: &mut R,
+    writer: &mut W,
+    mut handler: impl NativeMessageHandler,
+) -> io::Result<()> {
+    while let Some(message) = read_message(reader)? {
+        for reply in handler(launch, message) {
+            write_message(writer, &reply)?;
+        }
+    }
+    Ok(())
+}
+
+/// Native messaging host manifest. The browser uses this file to locate the
+/// host executable and to decide which extensions may connect to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NativeMessagingHostManifest {
+    /// Name of the host, e.g. `com.my_company.my_application`. Only lowercase
+    /// alphanumerics, underscores and dots are allowed.
+    pub name: String,
+    /// Short application description.
+    pub description: String,
+    /// Absolute path to the host executable. On Windows it may be relative to
+    /// the directory containing the manifest.
+    pub path: PathBuf,
+    /// Origins of the extensions allowed to connect to the host, e.g.
+    /// `chrome-extension://knldjmfmopnpolahpmmgbagdohdnhkik/`. Wildcards are
+    /// not allowed.
+    pub allowed_origins: Vec<String>,
+}
+
+impl NativeMessagingHostManifest {
+    pub fn new(name: &str, description: &str, path: PathBuf) -> NativeMessagingHostManifest {
+        NativeMessagingHostManifest {
+            name: name.to_owned(),
+            description: description.to_owned(),
+            path,
+            allowed_origins: Vec::new(),
+        }
+    }
+
+    /// Allows the extension with the given identifier to connect to the host.
+    pub fn allow_extension(mut self, extension_id: &str) -> Self {
+        self.allowed_origins.push(format!("chrome-extension://{}/", extension_id));
+        self
+    }
+
+    /// Returns `true` if `name` is a valid host name.
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && !name.starts_with('.')
+            && !name.ends_with('.')
+            && !name.contains("..")
+            && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '.')
+    }
+
+    /// Serializes the manifest to the JSON document expected by the browser.
+    pub fn to_json(&self) -> String {
+        let origins = self.allowed_origins
+            .iter()
+            .map(|origin| json_string(origin))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "{{\n  \"name\": {},\n  \"description\": {},\n  \"path\": {},\n  \"type\": \"stdio\",\n  \"allowed_origins\": [{}]\n}}\n",
+            json_string(&self.name),
+            json_string(&self.description),
+            json_string(&self.path.to_string_lossy()),
+            origins,
+        )
+    }
+
+    /// Writes the manifest to `<dir>/<name>.json` and returns the file path.
+    ///
+    /// On Windows the browser finds the manifest through the registry key
+    /// `HKEY_CURRENT_USER\Software\Google\Chrome\NativeMessagingHosts\<name>`,
+    /// which must be set to the returned path by the installer.
+    pub fn write_to_dir(&self, dir: &std::path::Path) -> io::Result<PathBuf> {
+        if !Self::is_valid_name(&self.name) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid native messaging host name"));
+        }
+        let path = dir.join(format!("{}.json", self.name));
+        std::fs::write(&path, self.to_json())?;
+        Ok(path)
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn launch_information_is_parsed_from_the_arguments() {
+        let launch = NativeMessagingLaunch::from_args(&[
+            "chrome-extension://knldjmfmopnpolahpmmgbagdohdnhkik/",
+            "--parent-window=1234",
+        ])
+        .unwrap();
+        assert_eq!(launch.parent_window, Some(1234));
+        assert_eq!(launch.extension_id(), Some("knldjmfmopnpolahpmmgbagdohdnhkik"));
+        assert_eq!(NativeMessagingLaunch::from_args(&["--parent-window=1234"]), None);
+    }
+
+    #[test]
+    fn messages_are_read_back_as_written() {
+        let mut stream = Vec::new();
+        write_message(&mut stream, "{\"a\":1}").unwrap();
+        let mut reader = &stream[..];
+        assert_eq!(read_message(&mut reader).unwrap().as_deref(), Some("{\"a\":1}"));
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn oversized_messages_are_rejected() {
+        let mut stream = ((MAX_BROWSER_MESSAGE_SIZE + 1) as u32).to_ne_bytes().to_vec();
+        stream.extend_from_slice(b"{}");
+        let error = read_message(&mut &stream[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = read_message(&mut &u32::MAX.to_ne_bytes()[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn other_origins_have_no_extension_id() {
+        let launch = NativeMessagingLaunch {
+            origin: "https://example.com/".to_owned(),
+            parent_window: None,
+        };
+        assert_eq!(launch.extension_id(), None);
+    }
+}
@@ -1,11 +1,15 @@
 use cef_sys::{cef_navigation_entry_t};
 use std::convert::TryInto;
 use crate::{
+    browser_host::BrowserHost,
+    devtools::{get_dictionary_list, get_i32, get_string, DevToolsError},
     load_handler::TransitionType,
-    ssl::SSLStatus,
+    ssl::{CertStatus, ContentStatus, SSLStatus},
     string::CefString,
+    values::StoredValue,
 };
 use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 
 ref_counted_ptr! {
     /// Structure used to represent an entry in navigation history.
@@ -34,13 +38,234 @@ impl NavigationEntry {
     pub fn has_post_data(&self) -> bool {
         unsafe{ self.0.has_post_data.unwrap()(self.as_ptr()) != 0 }
     }
-    pub fn get_completion_time(&self) -> DateTime<Utc> {
-        crate::values::cef_time_to_date_time(unsafe{ self.0.get_completion_time.unwrap()(self.as_ptr()) })
+    /// Returns the time of the last known successful navigation completion, or
+    /// `None` if the navigation hasn't completed yet.
+    pub fn get_completion_time(&self) -> Option<DateTime<Utc>> {
+        crate::values::cef_time_to_system_time(unsafe{ self.0.get_completion_time.unwrap()(self.as_ptr()) })
+            .map(DateTime::from)
     }
     pub fn get_http_status_code(&self) -> u16 {
         unsafe{ self.0.get_http_status_code.unwrap()(self.as_ptr()) as u16 }
     }
-    pub fn get_ssl_status(&self) -> SSLStatus {
-        unsafe{ SSLStatus::from_ptr_unchecked(self.0.get_sslstatus.unwrap()(self.as_ptr())) }
+    pub fn get_ssl_status(&self) -> Option<SSLStatus> {
+        unsafe{ SSLStatus::from_ptr(self.0.get_sslstatus.unwrap()(self.as_ptr())) }
+    }
+}
+
+/// A copy of the fields of a [NavigationEntry], which can be kept after the
+/// entry is gone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NavigationHistoryEntry {
+    pub url: String,
+    pub display_url: String,
+    pub original_url: String,
+    pub title: String,
+    pub transition_type: TransitionType,
+    pub has_post_data: bool,
+    pub completion_time: Option<DateTime<Utc>>,
+    /// 0 if no response was received yet.
+    pub http_status_code: u16,
+    pub is_secure_connection: bool,
+    pub cert_status: CertStatus,
+    pub content_status: ContentStatus,
+    /// Whether this is the currently loaded entry.
+    pub current: bool,
+}
+
+impl NavigationHistoryEntry {
+    pub(crate) fn new(entry: &NavigationEntry, current: bool) -> NavigationHistoryEntry {
+        let ssl_status = entry.get_ssl_status();
+        NavigationHistoryEntry {
+            url: entry.get_url(),
+            display_url: entry.get_display_url(),
+            original_url: entry.get_original_url(),
+            title: entry.get_title(),
+            transition_type: entry.get_transition_type(),
+            has_post_data: entry.has_post_data(),
+            completion_time: entry.get_completion_time(),
+            http_status_code: entry.get_http_status_code(),
+            is_secure_connection: ssl_status.as_ref().map_or(false, SSLStatus::is_secure_connection),
+            cert_status: ssl_status.as_ref().map(SSLStatus::get_cert_status).unwrap_or_default(),
+            content_status: ssl_status.as_ref().map(SSLStatus::get_content_status).unwrap_or_else(ContentStatus::empty),
+            current,
+        }
+    }
+}
+
+/// The back/forward list of a browser, e.g. for the dropdown menus of back and
+/// forward buttons. This is a snapshot taken by [BrowserHost::history_model];
+/// take a new one when the menu is opened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryModel {
+    entries: Vec<NavigationHistoryEntry>,
+    current_index: Option<usize>,
+}
+
+impl HistoryModel {
+    pub fn new(entries: Vec<NavigationHistoryEntry>) -> HistoryModel {
+        let current_index = entries.iter().position(|entry| entry.current);
+        HistoryModel { entries, current_index }
+    }
+    /// All entries, oldest first.
+    pub fn entries(&self) -> &[NavigationHistoryEntry] {
+        &self.entries
+    }
+    pub fn get(&self, index: usize) -> Option<&NavigationHistoryEntry> {
+        self.entries.get(index)
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// Index of the currently loaded entry, or `None` if nothing was loaded yet.
+    pub fn current_index(&self) -> Option<usize> {
+        self.current_index
+    }
+    pub fn current(&self) -> Option<&NavigationHistoryEntry> {
+        self.current_index.map(|index| &self.entries[index])
+    }
+    /// Entries before the current one with their indices, nearest first, as
+    /// listed by the back button's menu.
+    pub fn back_entries(&self) -> impl Iterator<Item = (usize, &NavigationHistoryEntry)> {
+        let end = self.current_index.unwrap_or(0);
+        self.entries[..end].iter().enumerate().rev()
+    }
+    /// Entries after the current one with their indices, nearest first, as
+    /// listed by the forward button's menu.
+    pub fn forward_entries(&self) -> impl Iterator<Item = (usize, &NavigationHistoryEntry)> {
+        let start = self.current_index.map_or(self.entries.len(), |index| index + 1);
+        self.entries.iter().enumerate().skip(start)
+    }
+    /// Returns how many entries `index` is away from the current entry,
+    /// negative for entries in the back list.
+    pub fn offset_of(&self, index: usize) -> Option<isize> {
+        match self.current_index {
+            Some(current) if index < self.entries.len() => Some(index as isize - current as isize),
+            _ => None,
+        }
+    }
+    /// Navigate `host` to the entry at `index`. Neighbouring entries are loaded
+    /// with [Browser::go_back](crate::browser::Browser::go_back) and
+    /// [Browser::go_forward](crate::browser::Browser::go_forward), others
+    /// through DevTools, as CEF has no function to go back or forward by more
+    /// than one entry.
+    ///
+    /// `callback` receives `false` if `index` is out of range, is the current
+    /// entry, or no longer has the URL it had when this snapshot was taken. It
+    /// is called before this function returns unless DevTools is used, in
+    /// which case it's called on the browser process UI thread.
+    pub fn go_to_index(
+        &self,
+        host: &BrowserHost,
+        index: usize,
+        callback: impl 'static + Send + FnOnce(Result<bool, DevToolsError>),
+    ) {
+        let browser = host.get_browser();
+        match self.offset_of(index) {
+            Some(-1) => {
+                browser.go_back();
+                return callback(Ok(true));
+            }
+            Some(1) => {
+                browser.go_forward();
+                return callback(Ok(true));
+            }
+            Some(0) | None => return callback(Ok(false)),
+            Some(_) => (),
+        }
+        let url = self.entries[index].url.clone();
+        let host_navigate = host.clone();
+        host.call_dev_tools_method("Page.getNavigationHistory", None, move |result| {
+            let entries = match result {
+                Ok(result) => get_dictionary_list(&result, "entries"),
+                Err(e) => return callback(Err(e)),
+            };
+            let entry_id = match entries.get(index) {
+                Some(entry) if get_string(entry, "url") == url => get_i32(entry, "id"),
+                _ => return callback(Ok(false)),
+            };
+            let mut params = HashMap::new();
+            params.insert("entryId".to_owned(), StoredValue::Int(entry_id));
+            host_navigate.call_dev_tools_method("Page.navigateToHistoryEntry", Some(params), move |result| {
+                callback(result.map(|_| true))
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load_handler::TransitionTypeQualifiers;
+
+    fn entry(url: &str, current: bool) -> NavigationHistoryEntry {
+        NavigationHistoryEntry {
+            url: url.to_owned(),
+            display_url: url.to_owned(),
+            original_url: url.to_owned(),
+            title: String::new(),
+            transition_type: TransitionType::Link(TransitionTypeQualifiers::empty()),
+            has_post_data: false,
+            completion_time: None,
+            http_status_code: 200,
+            is_secure_connection: true,
+            cert_status: CertStatus::default(),
+            content_status: ContentStatus::empty(),
+            current,
+        }
+    }
+
+    fn model(urls: &[&str], current: usize) -> HistoryModel {
+        HistoryModel::new(urls.iter().enumerate().map(|(i, url)| entry(url, i == current)).collect())
+    }
+
+    fn urls<'a>(entries: impl Iterator<Item = (usize, &'a NavigationHistoryEntry)>) -> Vec<(usize, &'a str)> {
+        entries.map(|(i, entry)| (i, &*entry.url)).collect()
+    }
+
+    #[test]
+    fn current_entry_is_found() {
+        let model = model(&["a", "b", "c"], 1);
+        assert_eq!(model.current_index(), Some(1));
+        assert_eq!(model.current().map(|entry| &*entry.url), Some("b"));
+        assert_eq!(model.len(), 3);
+    }
+
+    #[test]
+    fn menus_list_the_nearest_entry_first() {
+        let model = model(&["a", "b", "c", "d", "e"], 2);
+        assert_eq!(urls(model.back_entries()), [(1, "b"), (0, "a")]);
+        assert_eq!(urls(model.forward_entries()), [(3, "d"), (4, "e")]);
+    }
+
+    #[test]
+    fn menus_at_the_ends_are_empty() {
+        let first = model(&["a", "b"], 0);
+        assert_eq!(urls(first.back_entries()), []);
+        assert_eq!(urls(first.forward_entries()), [(1, "b")]);
+        let last = model(&["a", "b"], 1);
+        assert_eq!(urls(last.back_entries()), [(0, "a")]);
+        assert_eq!(urls(last.forward_entries()), []);
+    }
+
+    #[test]
+    fn empty_history_has_no_current_entry() {
+        let model = HistoryModel::new(Vec::new());
+        assert!(model.is_empty());
+        assert_eq!(model.current(), None);
+        assert_eq!(urls(model.back_entries()), []);
+        assert_eq!(urls(model.forward_entries()), []);
+        assert_eq!(model.offset_of(0), None);
+    }
+
+    #[test]
+    fn offsets_are_relative_to_the_current_entry() {
+        let model = model(&["a", "b", "c", "d"], 2);
+        assert_eq!(model.offset_of(0), Some(-2));
+        assert_eq!(model.offset_of(2), Some(0));
+        assert_eq!(model.offset_of(3), Some(1));
+        assert_eq!(model.offset_of(4), None);
     }
 }
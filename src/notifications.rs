@@ -0,0 +1,438 @@
+//! Bridging Web Notifications to the application.
+//!
+//! Chromium shows notifications with its own UI, which CEF doesn't have, so
+//! pages can neither get permission nor show anything. After
+//! [BrowserHost::enable_notifications], the page's `Notification` API is
+//! replaced by one that forwards permission requests and notifications to a
+//! [NotificationHandlerCallbacks] implementation, which can e.g. show native
+//! toasts. Clicks on and dismissal of those toasts are reported back to the
+//! page with [BrowserHost::click_notification] and
+//! [BrowserHost::close_notification].
+//!
+//! Messages from pages arrive through a DevTools binding. The origin of a
+//! message is taken from the DevTools execution context it was sent from, not
+//! from the page, so a page can't act on behalf of another origin. Service
+//! worker notifications (`ServiceWorkerRegistration.showNotification`) aren't
+//! supported.
+
+use crate::{
+    browser::Browser,
+    browser_host::BrowserHost,
+    devtools::{get_bool, get_dictionary, get_i32, get_string, page::UserScriptSlot, parse_json_dictionary},
+    devtools_message_observer::{DevToolsMessageObserver, DevToolsMessageObserverCallbacks},
+    permission_store::{origin_of, Permission, PermissionDecision, PermissionStore},
+    registration::Registration,
+    string::quote_string,
+    values::StoredValue,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// Name of the DevTools binding pages send their messages through.
+const BINDING: &str = "__cefNotify";
+
+/// Replaces `window.Notification`. `{permissions}` is replaced by an object
+/// mapping origins to their permission. Running it again in the same document
+/// only updates the permissions.
+const NOTIFICATION_SCRIPT: &str = r#"(function() {
+var state = window.__cefNotifications;
+if (!state) {
+  state = window.__cefNotifications = { shown: {}, pending: {}, next: 1 };
+  var send = function(message) { window.__cefNotify(JSON.stringify(message)); };
+  state.permission = function() {
+    return state.permissions[location.origin.toLowerCase()] || 'default';
+  };
+  var fire = function(notification, type) {
+    var event = new Event(type);
+    var handler = notification['on' + type];
+    if (typeof handler === 'function') handler.call(notification, event);
+    notification.dispatchEvent(event);
+  };
+  class Notification extends EventTarget {
+    constructor(title, options) {
+      super();
+      if (arguments.length === 0) {
+        throw new TypeError("Failed to construct 'Notification': 1 argument required, but only 0 present.");
+      }
+      options = options || {};
+      this.title = String(title);
+      this.body = options.body ? String(options.body) : '';
+      this.icon = options.icon ? new URL(options.icon, location.href).href : '';
+      this.tag = options.tag ? String(options.tag) : '';
+      this.data = options.data === undefined ? null : options.data;
+      this.silent = !!options.silent;
+      this.requireInteraction = !!options.requireInteraction;
+      this.onclick = this.onshow = this.onclose = this.onerror = null;
+      var notification = this;
+      var id = this.__cefId = String(state.next++);
+      setTimeout(function() {
+        if (state.permission() !== 'granted') {
+          fire(notification, 'error');
+          return;
+        }
+        state.shown[id] = notification;
+        send({
+          type: 'show', id: id, title: notification.title, body: notification.body,
+          icon: notification.icon, tag: notification.tag, silent: notification.silent,
+          requireInteraction: notification.requireInteraction
+        });
+        fire(notification, 'show');
+      }, 0);
+    }
+    close() {
+      var id = this.__cefId;
+      if (!state.shown[id]) return;
+      delete state.shown[id];
+      send({ type: 'close', id: id });
+      fire(this, 'close');
+    }
+    static get permission() {
+      return state.permission();
+    }
+    static requestPermission(callback) {
+      return new Promise(function(resolve) {
+        var done = function(permission) {
+          if (typeof callback === 'function') callback(permission);
+          resolve(permission);
+        };
+        if (state.permission() !== 'default') {
+          done(state.permission());
+          return;
+        }
+        var id = String(state.next++);
+        state.pending[id] = done;
+        send({ type: 'permission', id: id });
+      });
+    }
+  }
+  state.resolve = function(id, permission) {
+    var done = state.pending[id];
+    delete state.pending[id];
+    if (permission !== 'default') state.permissions[location.origin.toLowerCase()] = permission;
+    if (done) done(permission);
+  };
+  state.event = function(id, type) {
+    var notification = state.shown[id];
+    if (!notification) return;
+    if (type === 'close') delete state.shown[id];
+    fire(notification, type);
+  };
+  Object.defineProperty(window, 'Notification', { value: Notification, writable: true, configurable: true });
+}
+state.permissions = {permissions};
+})();"#;
+
+/// A notification a page wants to show.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebNotification {
+    /// Identifier of the notification, unique within its document.
+    pub id: String,
+    /// Origin of the page that created the notification.
+    pub origin: String,
+    pub title: String,
+    pub body: String,
+    /// Absolute URL of the icon, or an empty string.
+    pub icon: String,
+    /// Notifications with the same non-empty tag replace each other.
+    pub tag: String,
+    /// True if no sound or vibration should accompany the notification.
+    pub silent: bool,
+    /// True if the notification should stay visible until the user dismisses
+    /// it.
+    pub require_interaction: bool,
+    context_id: i32,
+}
+
+/// Implement this trait to display notifications of pages.
+pub trait NotificationHandlerCallbacks: 'static + Send {
+    /// Called when a page of `origin` asks for permission to show
+    /// notifications and neither the store nor an earlier decision answers the
+    /// request. Show a prompt and call [NotificationPermissionCallback::cont]
+    /// with the user's choice, now or later. Denies the request by default.
+    fn on_permission_request(
+        &mut self,
+        browser: &Browser,
+        origin: &str,
+        callback: NotificationPermissionCallback,
+    ) {
+        callback.cont(PermissionDecision::Denied);
+    }
+    /// Called when a page with permission shows a notification.
+    fn on_show(&mut self, browser: &Browser, notification: WebNotification);
+    /// Called when a page closes one of its notifications.
+    fn on_close(&mut self, browser: &Browser, origin: &str, notification_id: &str) {}
+}
+
+/// Decisions shared by the observer and the permission callbacks of a browser.
+struct Shared {
+    store: Option<Arc<PermissionStore>>,
+    decisions: Mutex<HashMap<String, PermissionDecision>>,
+    user_script: Arc<UserScriptSlot>,
+    /// Set once the registration was dropped, after which the script isn't
+    /// registered again.
+    disabled: AtomicBool,
+}
+
+impl Shared {
+    fn decision(&self, origin: &str) -> Option<PermissionDecision> {
+        let origin = origin_of(origin);
+        let decision = self.decisions.lock().get(&origin).copied();
+        decision.or_else(|| {
+            self.store
+                .as_ref()
+                .and_then(|store| store.get(&origin, &Permission::Notifications))
+        })
+    }
+    fn script(&self) -> String {
+        let mut permissions = HashMap::new();
+        if let Some(store) = &self.store {
+            for (origin, decisions) in store.decisions() {
+                if let Some(decision) = decisions.get(&Permission::Notifications) {
+                    permissions.insert(origin, *decision);
+                }
+            }
+        }
+        permissions.extend(self.decisions.lock().iter().map(|(origin, decision)| (origin.clone(), *decision)));
+        let permissions = permissions
+            .iter()
+            .map(|(origin, decision)| format!("{}: {}", quote_string(origin), quote_string(permission_name(*decision))))
+            .collect::<Vec<_>>()
+            .join(", ");
+        NOTIFICATION_SCRIPT.replace("{permissions}", &format!("{{ {} }}", permissions))
+    }
+    /// Register the script for new documents, replacing the previous one.
+    fn update_script(&self, host: &BrowserHost) {
+        if !self.disabled.load(Ordering::SeqCst) {
+            self.user_script.set(host, Some(self.script()), |_| ());
+        }
+    }
+}
+
+fn permission_name(decision: PermissionDecision) -> &'static str {
+    match decision {
+        PermissionDecision::Granted => "granted",
+        PermissionDecision::Denied => "denied",
+    }
+}
+
+/// Evaluate `expression` in the execution context with the given id.
+fn evaluate_in_context(host: &BrowserHost, context_id: i32, expression: String) {
+    let mut params = HashMap::new();
+    params.insert("expression".to_owned(), StoredValue::String(expression));
+    params.insert("contextId".to_owned(), StoredValue::Int(context_id));
+    host.call_dev_tools_method_unit("Runtime.evaluate", Some(params), |_| ());
+}
+
+fn resolve_permission(host: &BrowserHost, context_id: i32, request_id: &str, permission: &str) {
+    evaluate_in_context(
+        host,
+        context_id,
+        format!(
+            "window.__cefNotifications && window.__cefNotifications.resolve({}, {})",
+            quote_string(request_id),
+            quote_string(permission)
+        ),
+    );
+}
+
+struct PendingRequest {
+    host: BrowserHost,
+    shared: Arc<Shared>,
+    request_id: String,
+    context_id: i32,
+}
+
+/// Answers a notification permission request. Dropping it without calling
+/// [NotificationPermissionCallback::cont] is treated like a dismissed prompt:
+/// the page is told that the user hasn't decided yet and may ask again.
+pub struct NotificationPermissionCallback {
+    origin: String,
+    request: Option<PendingRequest>,
+}
+
+impl NotificationPermissionCallback {
+    /// The origin asking for permission.
+    pub fn origin(&self) -> &str {
+        &self.origin
+    }
+    /// Answer the request. The decision is remembered for the origin in this
+    /// browser, and in the permission store if one was passed to
+    /// [BrowserHost::enable_notifications].
+    pub fn cont(mut self, decision: PermissionDecision) {
+        let request = self.request.take().unwrap();
+        let origin = origin_of(&self.origin);
+        if let Some(store) = &request.shared.store {
+            if let Err(e) = store.set(&origin, Permission::Notifications, decision) {
+                log::warn!("Failed to save notification permission of {}: {}", origin, e);
+            }
+        }
+        request.shared.decisions.lock().insert(origin, decision);
+        request.shared.update_script(&request.host);
+        resolve_permission(&request.host, request.context_id, &request.request_id, permission_name(decision));
+    }
+}
+
+impl Drop for NotificationPermissionCallback {
+    fn drop(&mut self) {
+        if let Some(request) = self.request.take() {
+            resolve_permission(&request.host, request.context_id, &request.request_id, "default");
+        }
+    }
+}
+
+impl BrowserHost {
+    /// Forward the Web Notifications of pages in this browser to `callbacks`
+    /// until the returned [Registration] is dropped. Permission decisions are
+    /// taken from `store` first, if given. Afterwards, new documents get
+    /// Chromium's `Notification` API back. Documents that are already loaded
+    /// keep the replaced API, but nothing is shown anymore.
+    ///
+    /// `callbacks` will be called on the browser process UI thread.
+    pub fn enable_notifications(
+        &self,
+        store: Option<Arc<PermissionStore>>,
+        callbacks: impl NotificationHandlerCallbacks,
+    ) -> Registration {
+        let shared = Arc::new(Shared {
+            store,
+            decisions: Mutex::new(HashMap::new()),
+            user_script: Arc::new(UserScriptSlot::default()),
+            disabled: AtomicBool::new(false),
+        });
+        let registration = self.add_dev_tools_message_observer(DevToolsMessageObserver::new(NotificationObserver {
+            host: self.clone(),
+            shared: shared.clone(),
+            callbacks,
+            contexts: HashMap::new(),
+        }));
+        let mut params = HashMap::new();
+        params.insert("name".to_owned(), StoredValue::String(BINDING.to_owned()));
+        let host = self.clone();
+        self.call_dev_tools_method_unit("Runtime.addBinding", Some(params), move |result| {
+            if result.is_err() || shared.disabled.load(Ordering::SeqCst) {
+                return;
+            }
+            // Reports the existing execution contexts, whose origins are
+            // needed to attribute messages.
+            host.call_dev_tools_method_unit("Runtime.enable", None, |_| ());
+            host.execute_in_all_frames(&shared.script());
+            shared.update_script(&host);
+        });
+        registration
+    }
+    /// Tell the page that the user clicked `notification`.
+    pub fn click_notification(&self, notification: &WebNotification) {
+        self.notification_event(notification, "click");
+    }
+    /// Tell the page that `notification` was dismissed by the user or the
+    /// system.
+    pub fn close_notification(&self, notification: &WebNotification) {
+        self.notification_event(notification, "close");
+    }
+    fn notification_event(&self, notification: &WebNotification, event: &str) {
+        evaluate_in_context(
+            self,
+            notification.context_id,
+            format!(
+                "window.__cefNotifications && window.__cefNotifications.event({}, {})",
+                quote_string(&notification.id),
+                quote_string(event)
+            ),
+        );
+    }
+}
+
+struct NotificationObserver<C: NotificationHandlerCallbacks> {
+    host: BrowserHost,
+    shared: Arc<Shared>,
+    callbacks: C,
+    /// Origins of the execution contexts, keyed by context id.
+    contexts: HashMap<i32, String>,
+}
+
+impl<C: NotificationHandlerCallbacks> NotificationObserver<C> {
+    fn on_message(&mut self, browser: Browser, context_id: i32, message: HashMap<String, StoredValue>) {
+        let origin = match self.contexts.get(&context_id) {
+            Some(origin) => origin.clone(),
+            None => return,
+        };
+        let id = get_string(&message, "id");
+        match &*get_string(&message, "type") {
+            "permission" => match self.shared.decision(&origin) {
+                Some(decision) => resolve_permission(&self.host, context_id, &id, permission_name(decision)),
+                None => {
+                    let callback = NotificationPermissionCallback {
+                        origin: origin.clone(),
+                        request: Some(PendingRequest {
+                            host: self.host.clone(),
+                            shared: self.shared.clone(),
+                            request_id: id,
+                            context_id,
+                        }),
+                    };
+                    self.callbacks.on_permission_request(&browser, &origin, callback);
+                }
+            },
+            "show" if self.shared.decision(&origin) == Some(PermissionDecision::Granted) => {
+                self.callbacks.on_show(&browser, WebNotification {
+                    id,
+                    origin,
+                    title: get_string(&message, "title"),
+                    body: get_string(&message, "body"),
+                    icon: get_string(&message, "icon"),
+                    tag: get_string(&message, "tag"),
+                    silent: get_bool(&message, "silent"),
+                    require_interaction: get_bool(&message, "requireInteraction"),
+                    context_id,
+                });
+            }
+            "close" => self.callbacks.on_close(&browser, &origin, &id),
+            _ => (),
+        }
+    }
+}
+
+impl<C: NotificationHandlerCallbacks> DevToolsMessageObserverCallbacks for NotificationObserver<C> {
+    fn on_dev_tools_event(&mut self, browser: Browser, method: &str, params: &[u8]) {
+        let params = match parse_json_dictionary(params) {
+            Some(params) => params,
+            None => return,
+        };
+        match method {
+            "Runtime.executionContextCreated" => {
+                if let Some(context) = get_dictionary(&params, "context") {
+                    self.contexts.insert(get_i32(&context, "id"), get_string(&context, "origin"));
+                }
+            }
+            "Runtime.executionContextDestroyed" => {
+                self.contexts.remove(&get_i32(&params, "executionContextId"));
+            }
+            "Runtime.executionContextsCleared" => self.contexts.clear(),
+            "Runtime.bindingCalled" if get_string(&params, "name") == BINDING => {
+                let payload = get_string(&params, "payload");
+                if let Some(message) = parse_json_dictionary(payload.as_bytes()) {
+                    self.on_message(browser, get_i32(&params, "executionContextId"), message);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// CEF releases the observer when the [Registration] returned by
+/// [BrowserHost::enable_notifications] is dropped.
+impl<C: NotificationHandlerCallbacks> Drop for NotificationObserver<C> {
+    fn drop(&mut self) {
+        self.shared.disabled.store(true, Ordering::SeqCst);
+        self.shared.user_script.set(&self.host, None, |_| ());
+        let mut params = HashMap::new();
+        params.insert("name".to_owned(), StoredValue::String(BINDING.to_owned()));
+        self.host.call_dev_tools_method_unit("Runtime.removeBinding", Some(params), |_| ());
+    }
+}
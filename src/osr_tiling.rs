@@ -0,0 +1,590 @@
+//! Compositing several windowless browsers into one output image.
+//!
+//! A [TileCompositor] owns a BGRA output buffer divided into tiles. Every tile
+//! has its own [RenderHandler] which paints into the tile's region of the
+//! buffer, so a dashboard or video wall can be driven by any number of
+//! browsers while only uploading a single texture. Input sent through the
+//! compositor is routed to the tile under the pointer, with coordinates
+//! translated into that browser's view.
+//!
+//! To set up a tile, create it with [TileCompositor::add_tile], return its
+//! render handler from the [ClientCallbacks::get_render_handler](crate::client::ClientCallbacks::get_render_handler)
+//! of the client used for the browser, and once the browser has been created
+//! hand it to [TileCompositor::attach_browser].
+//!
+//! Tiles are laid out in physical pixels and browsers are rendered with a
+//! device scale factor of 1.
+
+use crate::{
+    browser::Browser,
+    browser_host::{BrowserHost, PaintElementType},
+    client::render_handler::{CursorHandle, CursorType, RenderHandler, RenderHandlerCallbacks, ScreenInfo},
+    drag::DragOperation,
+    events::{KeyEvent, MouseButtonType, MouseEvent, TouchEvent},
+    values::{Point, Rect},
+};
+use parking_lot::Mutex;
+use std::{
+    os::raw::c_void,
+    sync::{Arc, Weak},
+};
+
+/// Identifier of a tile of a [TileCompositor].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TileId(u64);
+
+struct Tile {
+    id: TileId,
+    rect: Rect,
+    frame_rate: i32,
+    browser: Option<Browser>,
+    popup: Option<Rect>,
+}
+
+struct CompositorState {
+    width: i32,
+    height: i32,
+    buffer: Vec<u8>,
+    dirty: bool,
+    tiles: Vec<Tile>,
+    next_id: u64,
+    focused: Option<TileId>,
+    /// Tile that received the last mouse press, which keeps receiving mouse
+    /// events until the button is released, like a captured window.
+    captured: Option<TileId>,
+}
+
+impl CompositorState {
+    fn tile(&self, id: TileId) -> Option<&Tile> {
+        self.tiles.iter().find(|tile| tile.id == id)
+    }
+    fn tile_mut(&mut self, id: TileId) -> Option<&mut Tile> {
+        self.tiles.iter_mut().find(|tile| tile.id == id)
+    }
+    /// Returns the topmost tile containing `point`. Tiles added later are on
+    /// top.
+    fn tile_at(&self, point: Point) -> Option<&Tile> {
+        self.tiles.iter().rev().find(|tile| contains(&tile.rect, point))
+    }
+    /// Copy the `dirty_rects` of `source` into the output, placed at `origin`
+    /// and clipped to `clip`.
+    fn blit(&mut self, source: &[u8], source_width: i32, source_height: i32, origin: Point, clip: Rect, dirty_rects: &[Rect]) {
+        for dirty in dirty_rects {
+            let left = dirty.x.max(0).max(clip.x - origin.x).max(-origin.x);
+            let top = dirty.y.max(0).max(clip.y - origin.y).max(-origin.y);
+            let right = (dirty.x + dirty.width)
+                .min(source_width)
+                .min(clip.x + clip.width - origin.x)
+                .min(self.width - origin.x);
+            let bottom = (dirty.y + dirty.height)
+                .min(source_height)
+                .min(clip.y + clip.height - origin.y)
+                .min(self.height - origin.y);
+            if left >= right || top >= bottom {
+                continue;
+            }
+            let row_len = ((right - left) as usize) * 4;
+            for y in top..bottom {
+                let src = offset(left, y, source_width);
+                let dst = offset(origin.x + left, origin.y + y, self.width);
+                if src + row_len > source.len() {
+                    break;
+                }
+                self.buffer[dst..dst + row_len].copy_from_slice(&source[src..src + row_len]);
+            }
+            self.dirty = true;
+        }
+    }
+}
+
+/// Returns the length of a BGRA image of `width` x `height` pixels. Negative
+/// sizes count as empty.
+///
+/// Panics if the image can't be addressed, which is the case long before it
+/// could be allocated.
+fn buffer_len(width: i32, height: i32) -> usize {
+    (width.max(0) as usize)
+        .checked_mul(height.max(0) as usize)
+        .and_then(|pixels| pixels.checked_mul(4))
+        .expect("image size overflows usize")
+}
+
+/// Returns the offset of the pixel at `x`, `y` in a BGRA image `width` pixels
+/// wide. The coordinates must be inside the image.
+fn offset(x: i32, y: i32, width: i32) -> usize {
+    (y as usize * width as usize + x as usize) * 4
+}
+
+fn contains(rect: &Rect, point: Point) -> bool {
+    point.x >= rect.x && point.y >= rect.y && point.x < rect.x + rect.width && point.y < rect.y + rect.height
+}
+
+/// Composites windowless browsers into a grid of tiles. Clones refer to the
+/// same compositor.
+#[derive(Clone)]
+pub struct TileCompositor(Arc<Mutex<CompositorState>>);
+
+impl TileCompositor {
+    /// Create a compositor with a transparent output of `width` x `height`
+    /// pixels.
+    pub fn new(width: i32, height: i32) -> TileCompositor {
+        TileCompositor(Arc::new(Mutex::new(CompositorState {
+            width,
+            height,
+            buffer: vec![0; buffer_len(width, height)],
+            dirty: true,
+            tiles: Vec::new(),
+            next_id: 0,
+            focused: None,
+            captured: None,
+        })))
+    }
+    /// Add a tile covering `rect` of the output, rendering at `frame_rate`
+    /// frames per second. Returns the tile's identifier and the render handler
+    /// to use for its browser.
+    pub fn add_tile(&self, rect: Rect, frame_rate: i32) -> (TileId, RenderHandler) {
+        let mut state = self.0.lock();
+        let id = TileId(state.next_id);
+        state.next_id += 1;
+        state.tiles.push(Tile {
+            id,
+            rect,
+            frame_rate,
+            browser: None,
+            popup: None,
+        });
+        let handler = RenderHandler::new(TileRenderHandler {
+            compositor: Arc::downgrade(&self.0),
+            id,
+        });
+        (id, handler)
+    }
+    /// Associate the browser rendering into `id`, so input can be routed to
+    /// it. Returns false if there is no such tile.
+    pub fn attach_browser(&self, id: TileId, browser: Browser) -> bool {
+        let mut state = self.0.lock();
+        match state.tile_mut(id) {
+            Some(tile) => {
+                browser.get_host().set_windowless_frame_rate(tile.frame_rate);
+                tile.browser = Some(browser);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Move or resize a tile. The tile's browser is notified and repaints.
+    pub fn set_tile_rect(&self, id: TileId, rect: Rect) {
+        let browser = {
+            let mut state = self.0.lock();
+            let old_rect = match state.tile_mut(id) {
+                Some(tile) => std::mem::replace(&mut tile.rect, rect),
+                None => return,
+            };
+            clear(&mut state, old_rect);
+            state.tile(id).and_then(|tile| tile.browser.clone())
+        };
+        if let Some(browser) = browser {
+            let host = browser.get_host();
+            host.was_resized();
+            host.invalidate(PaintElementType::View);
+        }
+    }
+    /// Change a tile's frame rate.
+    pub fn set_tile_frame_rate(&self, id: TileId, frame_rate: i32) {
+        let mut state = self.0.lock();
+        if let Some(tile) = state.tile_mut(id) {
+            tile.frame_rate = frame_rate;
+            if let Some(browser) = &tile.browser {
+                browser.get_host().set_windowless_frame_rate(frame_rate);
+            }
+        }
+    }
+    /// Remove a tile and clear its region. The browser isn't closed.
+    pub fn remove_tile(&self, id: TileId) -> Option<Browser> {
+        let mut state = self.0.lock();
+        let index = state.tiles.iter().position(|tile| tile.id == id)?;
+        let tile = state.tiles.remove(index);
+        clear(&mut state, tile.rect);
+        if state.focused == Some(id) {
+            state.focused = None;
+        }
+        if state.captured == Some(id) {
+            state.captured = None;
+        }
+        tile.browser
+    }
+    /// Returns the tiles, topmost last.
+    pub fn tiles(&self) -> Vec<(TileId, Rect)> {
+        self.0.lock().tiles.iter().map(|tile| (tile.id, tile.rect)).collect()
+    }
+    /// Returns the tile at `point` in output coordinates.
+    pub fn tile_at(&self, point: Point) -> Option<TileId> {
+        self.0.lock().tile_at(point).map(|tile| tile.id)
+    }
+    /// Resize the output. The content of tiles outside the new bounds is lost.
+    pub fn resize(&self, width: i32, height: i32) {
+        let browsers: Vec<Browser> = {
+            let mut state = self.0.lock();
+            state.width = width;
+            state.height = height;
+            state.buffer = vec![0; buffer_len(width, height)];
+            state.dirty = true;
+            state.tiles.iter().filter_map(|tile| tile.browser.clone()).collect()
+        };
+        for browser in browsers {
+            browser.get_host().invalidate(PaintElementType::View);
+        }
+    }
+    /// Call `f` with the output as a BGRA image with an upper-left origin, its
+    /// width and its height, if it changed since the last call.
+    pub fn with_updated_output<R>(&self, f: impl FnOnce(&[u8], i32, i32) -> R) -> Option<R> {
+        let mut state = self.0.lock();
+        if !state.dirty {
+            return None;
+        }
+        state.dirty = false;
+        Some(f(&state.buffer, state.width, state.height))
+    }
+
+    /// Route a mouse click at output coordinates to the tile under the
+    /// pointer, which also receives keyboard focus. Returns false if there's
+    /// no browser there.
+    pub fn send_mouse_click_event(
+        &self,
+        event: &MouseEvent,
+        button_type: MouseButtonType,
+        mouse_up: bool,
+        click_count: i32,
+    ) -> bool {
+        let target = {
+            let mut state = self.0.lock();
+            let target = self.mouse_target(&state, event);
+            if let Some((id, _, _)) = &target {
+                if mouse_up {
+                    state.captured = None;
+                } else {
+                    state.captured = Some(*id);
+                    state.focused = Some(*id);
+                }
+            }
+            target
+        };
+        match target {
+            Some((id, browser, event)) => {
+                let host = browser.get_host();
+                if !mouse_up {
+                    self.focus(id, &host);
+                }
+                host.send_mouse_click_event(&event, button_type, mouse_up, click_count);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Route a mouse move at output coordinates to the tile under the pointer.
+    pub fn send_mouse_move_event(&self, event: &MouseEvent, mouse_leave: bool) -> bool {
+        let target = self.mouse_target(&self.0.lock(), event);
+        match target {
+            Some((_, browser, event)) => {
+                browser.get_host().send_mouse_move_event(&event, mouse_leave);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Route a mouse wheel event at output coordinates to the tile under the
+    /// pointer.
+    pub fn send_mouse_wheel_event(&self, event: &MouseEvent, delta_x: i32, delta_y: i32) -> bool {
+        let target = self.mouse_target(&self.0.lock(), event);
+        match target {
+            Some((_, browser, event)) => {
+                browser.get_host().send_mouse_wheel_event(&event, delta_x, delta_y);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Route a touch event at output coordinates to the tile under the touch
+    /// point.
+    pub fn send_touch_event(&self, event: &TouchEvent) -> bool {
+        let target = {
+            let state = self.0.lock();
+            state.tile_at(Point::new(event.x as i32, event.y as i32))
+                .and_then(|tile| tile.browser.clone().map(|browser| (browser, tile.rect)))
+        };
+        match target {
+            Some((browser, rect)) => {
+                let mut event = *event;
+                event.x -= rect.x as f32;
+                event.y -= rect.y as f32;
+                browser.get_host().send_touch_event(&event);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Send a key event to the tile that was clicked last.
+    pub fn send_key_event(&self, event: KeyEvent) -> bool {
+        let browser = {
+            let state = self.0.lock();
+            state.focused
+                .and_then(|id| state.tile(id))
+                .and_then(|tile| tile.browser.clone())
+        };
+        match browser {
+            Some(browser) => {
+                browser.get_host().send_key_event(event);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the tile that should receive `event` and the event translated
+    /// into its view coordinates.
+    fn mouse_target(&self, state: &CompositorState, event: &MouseEvent) -> Option<(TileId, Browser, MouseEvent)> {
+        let point = Point::new(event.x, event.y);
+        let tile = state.captured
+            .and_then(|id| state.tile(id))
+            .or_else(|| state.tile_at(point))?;
+        let browser = tile.browser.clone()?;
+        let mut event = *event;
+        event.x -= tile.rect.x;
+        event.y -= tile.rect.y;
+        Some((tile.id, browser, event))
+    }
+    fn focus(&self, id: TileId, host: &BrowserHost) {
+        let others: Vec<Browser> = self.0.lock().tiles
+            .iter()
+            .filter(|tile| tile.id != id)
+            .filter_map(|tile| tile.browser.clone())
+            .collect();
+        for browser in others {
+            browser.get_host().send_focus_event(false);
+        }
+        host.send_focus_event(true);
+    }
+}
+
+fn clear(state: &mut CompositorState, rect: Rect) {
+    let left = rect.x.max(0);
+    let right = (rect.x + rect.width).min(state.width);
+    if left >= right {
+        return;
+    }
+    for y in rect.y.max(0)..(rect.y + rect.height).min(state.height) {
+        let start = offset(left, y, state.width);
+        let end = offset(right, y, state.width);
+        for byte in &mut state.buffer[start..end] {
+            *byte = 0;
+        }
+    }
+    state.dirty = true;
+}
+
+struct TileRenderHandler {
+    compositor: Weak<Mutex<CompositorState>>,
+    id: TileId,
+}
+
+impl TileRenderHandler {
+    fn with_tile<R>(&self, f: impl FnOnce(&mut CompositorState, TileId) -> R) -> Option<R> {
+        let compositor = self.compositor.upgrade()?;
+        let mut state = compositor.lock();
+        state.tile(self.id)?;
+        Some(f(&mut state, self.id))
+    }
+}
+
+impl RenderHandlerCallbacks for TileRenderHandler {
+    fn get_view_rect(&self, browser: Browser) -> Rect {
+        self.with_tile(|state, id| state.tile(id).map(|tile| tile.rect))
+            .flatten()
+            .map(|rect| Rect { x: 0, y: 0, width: rect.width.max(1), height: rect.height.max(1) })
+            .unwrap_or(Rect { x: 0, y: 0, width: 1, height: 1 })
+    }
+    fn get_screen_point(&self, browser: Browser, view_point: Point) -> Option<Point> {
+        self.with_tile(|state, id| state.tile(id).map(|tile| tile.rect))
+            .flatten()
+            .map(|rect| Point::new(rect.x + view_point.x, rect.y + view_point.y))
+    }
+    fn get_screen_info(&self, browser: Browser) -> Option<ScreenInfo> {
+        None
+    }
+    fn on_popup_show(&self, browser: Browser, show: bool) {
+        if !show {
+            self.with_tile(|state, id| {
+                if let Some(tile) = state.tile_mut(id) {
+                    tile.popup = None;
+                }
+            });
+            // The view repaints the area the popup covered.
+            browser.get_host().invalidate(PaintElementType::View);
+        }
+    }
+    fn on_popup_size(&self, browser: Browser, rect: Rect) {
+        self.with_tile(|state, id| {
+            if let Some(tile) = state.tile_mut(id) {
+                tile.popup = Some(rect);
+            }
+        });
+    }
+    fn on_paint(
+        &self,
+        browser: Browser,
+        type_: PaintElementType,
+        dirty_rects: &[Rect],
+        buffer: &[u8],
+        width: i32,
+        height: i32,
+    ) {
+        self.with_tile(|state, id| {
+            let (tile_rect, popup) = match state.tile(id) {
+                Some(tile) => (tile.rect, tile.popup),
+                None => return,
+            };
+            let origin = Point::new(tile_rect.x, tile_rect.y);
+            match type_ {
+                PaintElementType::View => {
+                    state.blit(buffer, width, height, origin, tile_rect, dirty_rects);
+                }
+                PaintElementType::Popup => {
+                    if let Some(popup) = popup {
+                        let origin = Point::new(origin.x + popup.x, origin.y + popup.y);
+                        state.blit(buffer, width, height, origin, tile_rect, dirty_rects);
+                    }
+                }
+            }
+        });
+    }
+    fn on_accelerated_paint(
+        &self,
+        browser: Browser,
+        type_: PaintElementType,
+        dirty_rects: &[Rect],
+        shared_handle: *mut c_void,
+    ) {
+        // Tiles are composited on the CPU; shared textures aren't supported.
+    }
+    fn on_cursor_change(&self, browser: Browser, cursor: CursorHandle, type_: CursorType<'_>) {}
+    fn update_drag_cursor(&self, browser: Browser, operation: DragOperation) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: i32, height: i32) -> Rect {
+        Rect { x, y, width, height }
+    }
+
+    fn state(width: i32, height: i32, tiles: &[Rect]) -> CompositorState {
+        CompositorState {
+            width,
+            height,
+            buffer: vec![0; buffer_len(width, height)],
+            dirty: false,
+            tiles: tiles
+                .iter()
+                .enumerate()
+                .map(|(id, rect)| Tile {
+                    id: TileId(id as u64),
+                    rect: *rect,
+                    frame_rate: 30,
+                    browser: None,
+                    popup: None,
+                })
+                .collect(),
+            next_id: tiles.len() as u64,
+            focused: None,
+            captured: None,
+        }
+    }
+
+    /// A `width` x `height` source whose pixels are all `value`.
+    fn source(width: i32, height: i32, value: u8) -> Vec<u8> {
+        vec![value; buffer_len(width, height)]
+    }
+
+    fn pixel(state: &CompositorState, x: i32, y: i32) -> u8 {
+        state.buffer[offset(x, y, state.width)]
+    }
+
+    #[test]
+    fn only_dirty_rects_are_copied() {
+        let mut state = state(8, 8, &[]);
+        let output = rect(0, 0, 8, 8);
+        state.blit(&source(4, 4, 7), 4, 4, Point::new(2, 2), output, &[rect(1, 1, 2, 1)]);
+        assert!(state.dirty);
+        assert_eq!(pixel(&state, 3, 3), 7);
+        assert_eq!(pixel(&state, 4, 3), 7);
+        assert_eq!(pixel(&state, 2, 2), 0);
+        assert_eq!(pixel(&state, 5, 3), 0);
+        assert_eq!(pixel(&state, 3, 4), 0);
+    }
+
+    #[test]
+    fn blits_are_clipped_to_the_tile() {
+        let mut state = state(8, 8, &[]);
+        state.blit(&source(4, 4, 7), 4, 4, Point::new(2, 2), rect(2, 2, 2, 2), &[rect(0, 0, 4, 4)]);
+        assert_eq!(pixel(&state, 3, 3), 7);
+        assert_eq!(pixel(&state, 4, 3), 0);
+        assert_eq!(pixel(&state, 3, 4), 0);
+    }
+
+    #[test]
+    fn blits_outside_the_output_are_clipped() {
+        let mut state = state(4, 4, &[]);
+        let output = rect(-10, -10, 100, 100);
+        state.blit(&source(4, 4, 7), 4, 4, Point::new(-2, -2), output, &[rect(0, 0, 4, 4)]);
+        assert_eq!(pixel(&state, 0, 0), 7);
+        assert_eq!(pixel(&state, 1, 1), 7);
+        assert_eq!(pixel(&state, 2, 2), 0);
+        state.blit(&source(4, 4, 9), 4, 4, Point::new(3, 3), output, &[rect(0, 0, 4, 4)]);
+        assert_eq!(pixel(&state, 3, 3), 9);
+        assert_eq!(pixel(&state, 2, 3), 0);
+    }
+
+    #[test]
+    fn blits_entirely_outside_the_output_change_nothing() {
+        let mut state = state(4, 4, &[]);
+        let output = rect(-10, -10, 100, 100);
+        state.blit(&source(4, 4, 7), 4, 4, Point::new(4, 0), output, &[rect(0, 0, 4, 4)]);
+        state.blit(&source(4, 4, 7), 4, 4, Point::new(0, -4), output, &[rect(0, 0, 4, 4)]);
+        state.blit(&source(4, 4, 7), 4, 4, Point::new(0, 0), output, &[rect(4, 4, 2, 2)]);
+        assert!(!state.dirty);
+        assert!(state.buffer.iter().all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn short_sources_are_not_read_past_their_end() {
+        let mut state = state(4, 4, &[]);
+        state.blit(&source(4, 2, 7), 4, 4, Point::new(0, 0), rect(0, 0, 4, 4), &[rect(0, 0, 4, 4)]);
+        assert_eq!(pixel(&state, 0, 1), 7);
+        assert_eq!(pixel(&state, 0, 2), 0);
+    }
+
+    #[test]
+    fn tile_lookup_excludes_the_right_and_bottom_edges() {
+        let state = state(20, 10, &[rect(0, 0, 10, 10), rect(10, 0, 10, 10)]);
+        assert_eq!(state.tile_at(Point::new(0, 0)).map(|tile| tile.id), Some(TileId(0)));
+        assert_eq!(state.tile_at(Point::new(9, 9)).map(|tile| tile.id), Some(TileId(0)));
+        assert_eq!(state.tile_at(Point::new(10, 0)).map(|tile| tile.id), Some(TileId(1)));
+        assert!(state.tile_at(Point::new(20, 0)).is_none());
+        assert!(state.tile_at(Point::new(0, 10)).is_none());
+        assert!(state.tile_at(Point::new(-1, 0)).is_none());
+    }
+
+    #[test]
+    fn later_tiles_are_on_top() {
+        let state = state(10, 10, &[rect(0, 0, 10, 10), rect(5, 5, 5, 5)]);
+        assert_eq!(state.tile_at(Point::new(7, 7)).map(|tile| tile.id), Some(TileId(1)));
+        assert_eq!(state.tile_at(Point::new(2, 2)).map(|tile| tile.id), Some(TileId(0)));
+    }
+
+    #[test]
+    fn buffer_sizes_are_computed_without_overflow() {
+        assert_eq!(buffer_len(-5, 10), 0);
+        assert_eq!(buffer_len(30_000, 20_000), 2_400_000_000);
+    }
+}
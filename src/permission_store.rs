@@ -0,0 +1,371 @@
+//! Remembered per-origin permission decisions.
+//!
+//! A [PermissionStore] records whether an origin has been granted or denied a
+//! permission and optionally persists those decisions to a JSON file, so
+//! prompts only need to be shown once. [PermissionStore::request_handler] wraps
+//! an existing [RequestHandlerCallbacks] implementation and answers
+//! authentication, storage quota and certificate error requests from the store
+//! before falling back to the wrapped handler.
+//!
+//! Reading and writing the JSON file uses CEF's JSON parser, so the store can
+//! only be opened after CEF has been loaded.
+
+use crate::{
+    browser::Browser,
+    client::request_handler::{
+        RequestHandler, RequestHandlerCallbacks, SelectClientCertificateCallback,
+        TerminationStatus, WindowOpenDisposition,
+    },
+    frame::Frame,
+    load_handler::ErrorCode,
+    request::Request,
+    resource_request_handler::ResourceRequestHandler,
+    ssl::SSLInfo,
+    url_request::{AuthCallback, RequestCallback},
+    values::{DictionaryValue, StoredValue},
+    x509_certificate::X509Certificate,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// A permission that can be remembered for an origin.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Permission {
+    /// Answering HTTP authentication challenges.
+    Authentication,
+    /// Granting storage quota requests.
+    StorageQuota,
+    /// Continuing despite an invalid SSL certificate.
+    CertificateError,
+    /// Showing an element fullscreen, see [FullscreenPolicy](crate::fullscreen::FullscreenPolicy).
+    Fullscreen,
+    /// Locking the mouse pointer, see [FullscreenPolicy](crate::fullscreen::FullscreenPolicy).
+    PointerLock,
+    /// Showing notifications, see [BrowserHost::enable_notifications](crate::browser_host::BrowserHost::enable_notifications).
+    Notifications,
+    /// Application-defined permission.
+    Other(String),
+}
+
+impl Permission {
+    fn key(&self) -> &str {
+        match self {
+            Permission::Authentication => "authentication",
+            Permission::StorageQuota => "storage_quota",
+            Permission::CertificateError => "certificate_error",
+            Permission::Fullscreen => "fullscreen",
+            Permission::PointerLock => "pointer_lock",
+            Permission::Notifications => "notifications",
+            Permission::Other(name) => name,
+        }
+    }
+    fn from_key(key: &str) -> Permission {
+        match key {
+            "authentication" => Permission::Authentication,
+            "storage_quota" => Permission::StorageQuota,
+            "certificate_error" => Permission::CertificateError,
+            "fullscreen" => Permission::Fullscreen,
+            "pointer_lock" => Permission::PointerLock,
+            "notifications" => Permission::Notifications,
+            name => Permission::Other(name.to_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PermissionDecision {
+    Granted,
+    Denied,
+}
+
+pub struct PermissionStore {
+    path: Option<PathBuf>,
+    decisions: Mutex<HashMap<String, HashMap<Permission, PermissionDecision>>>,
+}
+
+impl PermissionStore {
+    /// Create a store that isn't persisted.
+    pub fn in_memory() -> PermissionStore {
+        PermissionStore {
+            path: None,
+            decisions: Mutex::new(HashMap::new()),
+        }
+    }
+    /// Open the store persisted at `path`. If the file doesn't exist an empty
+    /// store is created, and the file is written on the first change.
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<PermissionStore> {
+        let path = path.into();
+        let decisions = match std::fs::read_to_string(&path) {
+            Ok(json) => Self::parse(&json)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed permission store"))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(PermissionStore {
+            path: Some(path),
+            decisions: Mutex::new(decisions),
+        })
+    }
+    /// Returns the path the store is persisted to, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+    /// Returns the remembered decision for `permission` on the origin of `url`.
+    pub fn get(&self, url: &str, permission: &Permission) -> Option<PermissionDecision> {
+        self.decisions
+            .lock()
+            .get(&origin_of(url))
+            .and_then(|permissions| permissions.get(permission))
+            .copied()
+    }
+    /// Remember `decision` for `permission` on the origin of `url`.
+    pub fn set(&self, url: &str, permission: Permission, decision: PermissionDecision) -> io::Result<()> {
+        self.decisions
+            .lock()
+            .entry(origin_of(url))
+            .or_default()
+            .insert(permission, decision);
+        self.save()
+    }
+    /// Forget the decision for `permission` on the origin of `url`.
+    pub fn remove(&self, url: &str, permission: &Permission) -> io::Result<()> {
+        let origin = origin_of(url);
+        {
+            let mut decisions = self.decisions.lock();
+            if let Some(permissions) = decisions.get_mut(&origin) {
+                permissions.remove(permission);
+                if permissions.is_empty() {
+                    decisions.remove(&origin);
+                }
+            }
+        }
+        self.save()
+    }
+    /// Forget all decisions for the origin of `url`.
+    pub fn clear_origin(&self, url: &str) -> io::Result<()> {
+        self.decisions.lock().remove(&origin_of(url));
+        self.save()
+    }
+    /// Forget all decisions.
+    pub fn clear(&self) -> io::Result<()> {
+        self.decisions.lock().clear();
+        self.save()
+    }
+    /// Returns all remembered decisions, keyed by origin.
+    pub fn decisions(&self) -> HashMap<String, HashMap<Permission, PermissionDecision>> {
+        self.decisions.lock().clone()
+    }
+    /// Write the store to its file. Called automatically on every change.
+    pub fn save(&self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let json = self.to_json()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "failed to serialize permission store"))?;
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, json)?;
+        std::fs::rename(&tmp_path, path)
+    }
+
+    fn to_json(&self) -> Option<String> {
+        let origins: HashMap<String, StoredValue> = self.decisions
+            .lock()
+            .iter()
+            .map(|(origin, permissions)| {
+                let permissions: HashMap<String, StoredValue> = permissions
+                    .iter()
+                    .map(|(permission, decision)| {
+                        let decision = match decision {
+                            PermissionDecision::Granted => "granted",
+                            PermissionDecision::Denied => "denied",
+                        };
+                        (permission.key().to_owned(), StoredValue::String(decision.to_owned()))
+                    })
+                    .collect();
+                (origin.clone(), StoredValue::Dictionary(DictionaryValue::from(&permissions)))
+            })
+            .collect();
+        StoredValue::Dictionary(DictionaryValue::from(&origins)).to_json()
+    }
+
+    fn parse(json: &str) -> Option<HashMap<String, HashMap<Permission, PermissionDecision>>> {
+        let origins: HashMap<String, StoredValue> = match StoredValue::from_json(json)? {
+            StoredValue::Dictionary(origins) => origins.into(),
+            _ => return None,
+        };
+        let mut decisions = HashMap::new();
+        for (origin, permissions) in origins {
+            let permissions: HashMap<String, StoredValue> = match permissions {
+                StoredValue::Dictionary(permissions) => permissions.into(),
+                _ => return None,
+            };
+            let permissions = permissions
+                .into_iter()
+                .filter_map(|(permission, decision)| {
+                    let decision = match decision {
+                        StoredValue::String(ref s) if s == "granted" => PermissionDecision::Granted,
+                        StoredValue::String(ref s) if s == "denied" => PermissionDecision::Denied,
+                        _ => return None,
+                    };
+                    Some((Permission::from_key(&permission), decision))
+                })
+                .collect();
+            decisions.insert(origin, permissions);
+        }
+        Some(decisions)
+    }
+
+    /// Create a [RequestHandler] that consults `store` before calling into
+    /// `callbacks`.
+    ///
+    /// Denied origins have authentication, storage quota and certificate error
+    /// requests canceled immediately. Granted origins have storage quota and
+    /// certificate error requests continued immediately; since the store doesn't
+    /// hold credentials, authentication requests are always passed on unless
+    /// denied. Use [PermissionStore::set] from `callbacks` to remember the
+    /// user's choice.
+    pub fn request_handler<C: RequestHandlerCallbacks>(store: Arc<PermissionStore>, callbacks: C) -> RequestHandler {
+        RequestHandler::new(PermissionRequestHandler { store, inner: callbacks })
+    }
+}
+
+/// Returns the origin (`scheme://host[:port]`) of `url`, lowercased. Strings
+/// that aren't URLs are returned unchanged.
+pub fn origin_of(url: &str) -> String {
+    let scheme_end = match url.find("://") {
+        Some(i) => i + 3,
+        None => return url.to_owned(),
+    };
+    let authority = &url[scheme_end..];
+    let authority_end = authority.find(|c| c == '/' || c == '?' || c == '#').unwrap_or(authority.len());
+    let authority = &authority[..authority_end];
+    // Strip any user info.
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    format!("{}{}", &url[..scheme_end], host).to_lowercase()
+}
+
+struct PermissionRequestHandler<C: RequestHandlerCallbacks> {
+    store: Arc<PermissionStore>,
+    inner: C,
+}
+
+impl<C: RequestHandlerCallbacks> RequestHandlerCallbacks for PermissionRequestHandler<C> {
+    fn on_before_browse(
+        &self,
+        browser: Browser,
+        frame: Frame,
+        request: Request,
+        user_gesture: bool,
+        is_redirect: bool,
+    ) -> bool {
+        self.inner.on_before_browse(browser, frame, request, user_gesture, is_redirect)
+    }
+    fn on_open_url_from_tab(
+        &self,
+        browser: Browser,
+        frame: Frame,
+        target_url: &str,
+        target_disposition: WindowOpenDisposition,
+        user_gesture: bool,
+    ) -> bool {
+        self.inner.on_open_url_from_tab(browser, frame, target_url, target_disposition, user_gesture)
+    }
+    fn get_resource_request_handler(
+        &self,
+        browser: Browser,
+        frame: Frame,
+        request: Request,
+        is_navigation: bool,
+        is_download: bool,
+        request_initiator: &str,
+        disable_default_handling: &mut bool,
+    ) -> Option<ResourceRequestHandler> {
+        self.inner.get_resource_request_handler(
+            browser,
+            frame,
+            request,
+            is_navigation,
+            is_download,
+            request_initiator,
+            disable_default_handling,
+        )
+    }
+    fn get_auth_credentials(
+        &self,
+        browser: Browser,
+        origin_url: &str,
+        is_proxy: bool,
+        host: &str,
+        port: u16,
+        realm: Option<&str>,
+        scheme: Option<&str>,
+        callback: AuthCallback,
+    ) -> bool {
+        if self.store.get(origin_url, &Permission::Authentication) == Some(PermissionDecision::Denied) {
+            return false;
+        }
+        self.inner.get_auth_credentials(browser, origin_url, is_proxy, host, port, realm, scheme, callback)
+    }
+    fn on_quota_request(
+        &self,
+        browser: Browser,
+        origin_url: &str,
+        new_size: i64,
+        callback: RequestCallback,
+    ) -> bool {
+        match self.store.get(origin_url, &Permission::StorageQuota) {
+            Some(PermissionDecision::Granted) => {
+                callback.cont(true);
+                true
+            }
+            Some(PermissionDecision::Denied) => false,
+            None => self.inner.on_quota_request(browser, origin_url, new_size, callback),
+        }
+    }
+    fn on_certificate_error(
+        &self,
+        browser: Browser,
+        cert_error: ErrorCode,
+        request_url: &str,
+        ssl_info: SSLInfo,
+        callback: RequestCallback,
+    ) -> bool {
+        match self.store.get(request_url, &Permission::CertificateError) {
+            Some(PermissionDecision::Granted) => {
+                callback.cont(true);
+                true
+            }
+            Some(PermissionDecision::Denied) => false,
+            None => self.inner.on_certificate_error(browser, cert_error, request_url, ssl_info, callback),
+        }
+    }
+    fn on_select_client_certificate(
+        &self,
+        browser: Browser,
+        is_proxy: bool,
+        host: &str,
+        port: u16,
+        certificates: &[X509Certificate],
+        callback: SelectClientCertificateCallback,
+    ) -> bool {
+        self.inner.on_select_client_certificate(browser, is_proxy, host, port, certificates, callback)
+    }
+    fn on_plugin_crashed(&self, browser: Browser, plugin_path: &str) {
+        self.inner.on_plugin_crashed(browser, plugin_path)
+    }
+    fn on_render_view_ready(&self, browser: Browser) {
+        self.inner.on_render_view_ready(browser)
+    }
+    fn on_render_process_terminated(&self, browser: Browser, status: TerminationStatus) {
+        self.inner.on_render_process_terminated(browser, status)
+    }
+    fn on_document_available_in_main_frame(&self, browser: Browser) {
+        self.inner.on_document_available_in_main_frame(browser)
+    }
+}
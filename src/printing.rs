@@ -1,6 +1,7 @@
 use cef_sys::{cef_pdf_print_margin_type_t, cef_pdf_print_settings_t};
 
 use crate::string::CefString;
+use std::{io, path::Path};
 
 #[repr(C)]
 #[derive(Eq, PartialEq, Clone, Copy, Debug)]
@@ -90,3 +91,19 @@ impl Default for PDFPrintMargin {
         Self::Default
     }
 }
+
+/// Checks that `path` can be passed to
+/// [BrowserHost::print_to_pdf](crate::browser_host::BrowserHost::print_to_pdf).
+/// CEF only reports that printing failed, so the reason is determined here.
+pub(crate) fn check_pdf_output_path(path: &Path) -> io::Result<()> {
+    if !path.is_absolute() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "path is not absolute"));
+    }
+    if path.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "path is a directory"));
+    }
+    match path.parent() {
+        Some(parent) if parent.is_dir() => Ok(()),
+        _ => Err(io::Error::new(io::ErrorKind::NotFound, "directory does not exist")),
+    }
+}
@@ -0,0 +1,100 @@
+//! Typed configuration of Chromium's process model.
+//!
+//! Chromium doesn't expose the process model through [Settings](crate::settings::Settings);
+//! it's controlled by command-line switches of the browser process. Apply a
+//! [ProcessModelSettings] from [AppCallbacks::on_before_command_line_processing](crate::app::AppCallbacks::on_before_command_line_processing):
+//!
+//! ```rust,ignore
+//! fn on_before_command_line_processing(&self, process_type: Option<&str>, command_line: CommandLine) {
+//!     if process_type.is_none() {
+//!         ProcessModelSettings::new()
+//!             .model(ProcessModel::ProcessPerSite)
+//!             .renderer_process_limit(4)
+//!             .apply(&command_line);
+//!     }
+//! }
+//! ```
+
+use crate::command_line::CommandLine;
+
+/// How pages are assigned to renderer processes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProcessModel {
+    /// Chromium's default for the platform.
+    Default,
+    /// Strict site isolation: each site, including cross-site iframes, gets its
+    /// own renderer process. Most secure, uses the most memory.
+    SitePerProcess,
+    /// All browsers showing the same site share one renderer process.
+    ProcessPerSite,
+    /// Each browser gets one renderer process, which is reused for every site
+    /// it navigates to.
+    ProcessPerTab,
+    /// Renderers run as threads of the browser process.
+    ///
+    /// **For debugging only.** This mode is unsupported by Chromium, crashes
+    /// in renderers take down the whole application, and many features are
+    /// known to be broken.
+    SingleProcess,
+}
+
+impl Default for ProcessModel {
+    fn default() -> Self {
+        ProcessModel::Default
+    }
+}
+
+/// Process model settings applied as command-line switches.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessModelSettings {
+    pub model: ProcessModel,
+    /// Maximum number of renderer processes. Once the limit is reached, new
+    /// pages share existing processes, which reduces memory use at the cost of
+    /// isolation and responsiveness. Has no effect with
+    /// [ProcessModel::SingleProcess], and is ignored by Chromium for sites that
+    /// have to be isolated with [ProcessModel::SitePerProcess].
+    pub renderer_process_limit: Option<u32>,
+}
+
+impl ProcessModelSettings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn model(mut self, model: ProcessModel) -> Self {
+        self.model = model;
+        self
+    }
+    pub fn renderer_process_limit(mut self, renderer_process_limit: u32) -> Self {
+        self.renderer_process_limit = Some(renderer_process_limit);
+        self
+    }
+    /// Add the switches for these settings to `command_line`, which has to be
+    /// the command line of the browser process. Switches that are already
+    /// present, e.g. because they were passed by the user, are left alone.
+    pub fn apply(&self, command_line: &CommandLine) {
+        let switch = match self.model {
+            ProcessModel::Default => None,
+            ProcessModel::SitePerProcess => Some("site-per-process"),
+            ProcessModel::ProcessPerSite => Some("process-per-site"),
+            ProcessModel::ProcessPerTab => Some("process-per-tab"),
+            ProcessModel::SingleProcess => Some("single-process"),
+        };
+        if let Some(switch) = switch {
+            if !command_line.has_switch(switch) {
+                command_line.append_switch(switch);
+            }
+        }
+        if self.model == ProcessModel::SingleProcess || command_line.has_switch("single-process") {
+            log::warn!(
+                "Running in single-process mode. This mode is only meant for debugging: \
+                 it is unsupported, unstable, and a crashing renderer will take down \
+                 the whole application. Do not ship it."
+            );
+        }
+        if let Some(limit) = self.renderer_process_limit {
+            if !command_line.has_switch("renderer-process-limit") {
+                command_line.append_switch_with_value("renderer-process-limit", &limit.to_string());
+            }
+        }
+    }
+}
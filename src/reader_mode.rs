@@ -0,0 +1,238 @@
+//! A simplified, readable view of article-like pages.
+//!
+//! [Frame::extract_readable_content] finds the element holding most of the
+//! page's running text, similar to the reader modes of other browsers, and
+//! strips it of scripts, styling, forms and navigation. The result can be
+//! shown in place of the page with [Browser::show_reader_mode], which is
+//! meant for documentation viewers and similar apps rather than for
+//! arbitrary web pages, where the heuristic is easily confused.
+//!
+//! The extraction runs in an isolated world (see [Frame::evaluate]), so the
+//! page's scripts can't tamper with the DOM functions it uses. The generated
+//! document forbids all scripts with a Content Security Policy, in case the
+//! cleanup misses a way to run one.
+
+use crate::{
+    browser::Browser,
+    devtools::{get_f64, get_string, DevToolsError},
+    frame::Frame,
+    values::StoredValue,
+};
+use std::collections::HashMap;
+
+/// Pages with less text in their best candidate element aren't considered
+/// readable.
+const MIN_TEXT_LENGTH: usize = 250;
+
+/// Scores the parents of text blocks by the amount of text they contain,
+/// penalizing link-heavy and navigation-like elements, and returns a cleaned
+/// copy of the best one with absolute URLs.
+const EXTRACT_SCRIPT: &str = r#"(function() {
+var scores = new Map();
+document.querySelectorAll('p, pre, td, blockquote').forEach(function(block) {
+  var text = block.textContent.trim();
+  if (text.length < 25) return;
+  var score = 1 + text.split(',').length + Math.min(Math.floor(text.length / 100), 3);
+  var parent = block.parentElement;
+  if (!parent) return;
+  scores.set(parent, (scores.get(parent) || 0) + score);
+  if (parent.parentElement) {
+    scores.set(parent.parentElement, (scores.get(parent.parentElement) || 0) + score / 2);
+  }
+});
+var best = null, bestScore = 0;
+scores.forEach(function(score, element) {
+  var textLength = element.textContent.length;
+  var linkLength = 0;
+  element.querySelectorAll('a').forEach(function(a) { linkLength += a.textContent.length; });
+  score *= 1 - (textLength ? linkLength / textLength : 0);
+  if (/comment|footer|sidebar|nav|menu|banner|promo|share|related/i.test(element.className + ' ' + element.id)) {
+    score *= 0.2;
+  }
+  if (score > bestScore) { best = element; bestScore = score; }
+});
+best = best || document.querySelector('article, main');
+if (!best) return null;
+var content = best.cloneNode(true);
+content.querySelectorAll('script, style, link, noscript, iframe, object, embed, form, button, input, select, textarea, nav, aside, footer, svg, canvas').forEach(function(element) {
+  element.remove();
+});
+var allowed = { href: true, src: true, alt: true, title: true, colspan: true, rowspan: true };
+[content].concat(Array.from(content.querySelectorAll('*'))).forEach(function(element) {
+  Array.from(element.attributes).forEach(function(attribute) {
+    if (!allowed[attribute.name]) element.removeAttribute(attribute.name);
+  });
+  ['href', 'src'].forEach(function(name) {
+    if (!element.hasAttribute(name)) return;
+    try {
+      var url = new URL(element.getAttribute(name), document.baseURI);
+      if (url.protocol === 'javascript:') element.removeAttribute(name);
+      else element.setAttribute(name, url.href);
+    } catch (e) {
+      element.removeAttribute(name);
+    }
+  });
+});
+var byline = document.querySelector('meta[name="author"], [rel="author"], .byline, .author');
+return {
+  title: document.title,
+  byline: byline ? (byline.getAttribute('content') || byline.textContent).trim() : '',
+  html: content.innerHTML,
+  textLength: content.textContent.trim().length,
+  url: document.URL
+};
+})()"#;
+
+/// The main content of a page, with scripts, styling and navigation removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadableContent {
+    pub title: String,
+    /// The author, if the page names one.
+    pub byline: Option<String>,
+    /// The content as HTML, with only structural markup, links and images.
+    /// URLs are absolute.
+    pub html: String,
+    /// The URL the content was extracted from.
+    pub url: String,
+    /// Length of the content's text in UTF-16 code units.
+    pub text_length: usize,
+}
+
+impl ReadableContent {
+    /// Returns a complete, minimally styled document showing the content, in
+    /// which no scripts run.
+    pub fn to_html(&self) -> String {
+        let byline = match &self.byline {
+            Some(byline) => format!("<p class=\"byline\">{}</p>\n", escape_html(byline)),
+            None => String::new(),
+        };
+        format!(
+            "<!DOCTYPE html>\n\
+             <html><head><meta charset=\"utf-8\">\n\
+             <meta http-equiv=\"Content-Security-Policy\" content=\"script-src 'none'\">\n\
+             <base href=\"{}\">\n\
+             <title>{}</title>\n\
+             <style>\n\
+             body {{ max-width: 40em; margin: 2em auto; padding: 0 1em; font: 18px/1.6 Georgia, serif; color: #222; }}\n\
+             img, video {{ max-width: 100%; height: auto; }}\n\
+             pre {{ overflow-x: auto; font-size: 0.8em; }}\n\
+             .byline {{ color: #666; font-style: italic; }}\n\
+             </style></head><body>\n\
+             <h1>{}</h1>\n\
+             {}{}\n\
+             </body></html>\n",
+            escape_html(&self.url),
+            escape_html(&self.title),
+            escape_html(&self.title),
+            byline,
+            self.html,
+        )
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl Frame {
+    /// Extract the main content of the document in this frame and pass it to
+    /// `callback` on the browser process UI thread, or `None` if the document
+    /// doesn't contain enough running text to be considered readable.
+    pub fn extract_readable_content(
+        &self,
+        callback: impl 'static + Send + FnOnce(Result<Option<ReadableContent>, DevToolsError>),
+    ) {
+        self.evaluate(EXTRACT_SCRIPT, move |result| {
+            callback(result.and_then(|value| match value {
+                StoredValue::Null => Ok(None),
+                StoredValue::Dictionary(content) => {
+                    let content: HashMap<String, StoredValue> = content.into();
+                    let text_length = get_f64(&content, "textLength") as usize;
+                    if text_length < MIN_TEXT_LENGTH {
+                        return Ok(None);
+                    }
+                    let byline = get_string(&content, "byline");
+                    Ok(Some(ReadableContent {
+                        title: get_string(&content, "title"),
+                        byline: if byline.is_empty() { None } else { Some(byline) },
+                        html: get_string(&content, "html"),
+                        url: get_string(&content, "url"),
+                        text_length,
+                    }))
+                }
+                _ => Err(DevToolsError::InvalidResult),
+            }))
+        });
+    }
+}
+
+impl Browser {
+    /// Replace the page in the main frame with a simplified view of its
+    /// content, loaded with [Frame::load_html]. `callback` receives false if
+    /// the page isn't readable, in which case it is left as it is. Going back
+    /// in history returns to the original page.
+    pub fn show_reader_mode(&self, callback: impl 'static + Send + FnOnce(Result<bool, DevToolsError>)) {
+        let frame = self.get_main_frame();
+        let target = frame.clone();
+        frame.extract_readable_content(move |result| {
+            callback(result.map(|content| match content {
+                Some(content) => {
+                    target.load_html(&content.to_html(), None);
+                    true
+                }
+                None => false,
+            }))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content() -> ReadableContent {
+        ReadableContent {
+            title: "Fish & <Chips>".to_owned(),
+            byline: Some("A \"Cook\"".to_owned()),
+            html: "<p>Text</p>".to_owned(),
+            url: "https://example.com/a?b=1&c=2".to_owned(),
+            text_length: 4,
+        }
+    }
+
+    #[test]
+    fn document_forbids_scripts_before_any_content() {
+        let html = content().to_html();
+        let csp = html
+            .find("<meta http-equiv=\"Content-Security-Policy\" content=\"script-src 'none'\">")
+            .unwrap();
+        assert!(csp < html.find("<base").unwrap());
+        assert!(csp < html.find("<body>").unwrap());
+    }
+
+    #[test]
+    fn metadata_is_escaped_and_content_is_not() {
+        let html = content().to_html();
+        assert!(html.contains("<base href=\"https://example.com/a?b=1&amp;c=2\">"));
+        assert!(html.contains("<title>Fish &amp; &lt;Chips&gt;</title>"));
+        assert!(html.contains("<p class=\"byline\">A &quot;Cook&quot;</p>"));
+        assert!(html.contains("<p>Text</p>"));
+    }
+
+    #[test]
+    fn missing_byline_is_left_out() {
+        let html = ReadableContent { byline: None, ..content() }.to_html();
+        assert!(!html.contains("byline\">"));
+    }
+}
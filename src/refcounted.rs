@@ -91,7 +91,7 @@ impl<C: RefCounter> RefCountedPtr<C> {
         }
     }
 
-    fn check_poisoned(&self) -> bool {
+    pub(crate) fn check_poisoned(&self) -> bool {
         if C::POISONABLE {
             POISON_TABLE.contains_key(&(self.cef.as_ptr() as usize))
         } else {
@@ -222,6 +222,17 @@ impl<W: Wrapper> RefCounted<W> {
         &(*(ptr as *const W::Cef as *const Self)).object
     }
 
+    /// Like [RefCounted::wrapper], but returns `None` if `ptr` wasn't created
+    /// by wrapping a `W`, which is told by the release function in its base.
+    pub(crate) unsafe fn try_wrapper<'a>(ptr: *mut W::Cef) -> Option<&'a W> {
+        let release = (*ptr).base().release?;
+        if release as usize == Self::release as usize {
+            Some(Self::wrapper(ptr))
+        } else {
+            None
+        }
+    }
+
     unsafe fn to_arc(ptr: *mut W::Cef) -> ManuallyDrop<Arc<Self>> {
         ManuallyDrop::new(Arc::from_raw(ptr as *mut Self))
     }
@@ -86,6 +86,101 @@ pub trait RenderProcessHandlerCallbacks: 'static + Send {
     }
 }
 
+/// Base for [RenderProcessHandlerCallbacks] implementations that wrap another one, see
+/// [ResourceRequestHandlerDecorator](crate::resource_request_handler::ResourceRequestHandlerDecorator).
+pub trait RenderProcessHandlerDecorator: 'static + Send {
+    type Inner: RenderProcessHandlerCallbacks;
+    /// Returns the wrapped callbacks.
+    fn inner(&self) -> &Self::Inner;
+    fn on_render_thread_created(&self, extra_info: ListValue) {
+        self.inner().on_render_thread_created(extra_info)
+    }
+    fn on_web_kit_initialized(&self) {
+        self.inner().on_web_kit_initialized()
+    }
+    fn on_browser_created(&self, browser: Browser, extra_info: DictionaryValue) {
+        self.inner().on_browser_created(browser, extra_info)
+    }
+    fn on_browser_destroyed(&self, browser: Browser) {
+        self.inner().on_browser_destroyed(browser)
+    }
+    fn get_load_handler(&self) -> Option<LoadHandler> {
+        self.inner().get_load_handler()
+    }
+    fn on_context_created(&self, browser: Browser, frame: Frame, context: V8Context) {
+        self.inner().on_context_created(browser, frame, context)
+    }
+    fn on_context_released(&self, browser: Browser, frame: Frame, context: V8Context) {
+        self.inner().on_context_released(browser, frame, context)
+    }
+    fn on_uncaught_exception(
+        &self,
+        browser: Browser,
+        frame: Frame,
+        context: V8Context,
+        exception: V8Exception,
+        stack_trace: &[V8StackFrame],
+    ) {
+        self.inner().on_uncaught_exception(browser, frame, context, exception, stack_trace)
+    }
+    fn on_focused_node_changed(&self, browser: Browser, frame: Frame, node: Option<DOMNode>) {
+        self.inner().on_focused_node_changed(browser, frame, node)
+    }
+    fn on_process_message_received(
+        &self,
+        browser: Browser,
+        frame: Frame,
+        message: ProcessMessage,
+    ) -> bool {
+        self.inner().on_process_message_received(browser, frame, message)
+    }
+}
+
+impl<D: RenderProcessHandlerDecorator> RenderProcessHandlerCallbacks for D {
+    fn on_render_thread_created(&self, extra_info: ListValue) {
+        RenderProcessHandlerDecorator::on_render_thread_created(self, extra_info)
+    }
+    fn on_web_kit_initialized(&self) {
+        RenderProcessHandlerDecorator::on_web_kit_initialized(self)
+    }
+    fn on_browser_created(&self, browser: Browser, extra_info: DictionaryValue) {
+        RenderProcessHandlerDecorator::on_browser_created(self, browser, extra_info)
+    }
+    fn on_browser_destroyed(&self, browser: Browser) {
+        RenderProcessHandlerDecorator::on_browser_destroyed(self, browser)
+    }
+    fn get_load_handler(&self) -> Option<LoadHandler> {
+        RenderProcessHandlerDecorator::get_load_handler(self)
+    }
+    fn on_context_created(&self, browser: Browser, frame: Frame, context: V8Context) {
+        RenderProcessHandlerDecorator::on_context_created(self, browser, frame, context)
+    }
+    fn on_context_released(&self, browser: Browser, frame: Frame, context: V8Context) {
+        RenderProcessHandlerDecorator::on_context_released(self, browser, frame, context)
+    }
+    fn on_uncaught_exception(
+        &self,
+        browser: Browser,
+        frame: Frame,
+        context: V8Context,
+        exception: V8Exception,
+        stack_trace: &[V8StackFrame],
+    ) {
+        RenderProcessHandlerDecorator::on_uncaught_exception(self, browser, frame, context, exception, stack_trace)
+    }
+    fn on_focused_node_changed(&self, browser: Browser, frame: Frame, node: Option<DOMNode>) {
+        RenderProcessHandlerDecorator::on_focused_node_changed(self, browser, frame, node)
+    }
+    fn on_process_message_received(
+        &self,
+        browser: Browser,
+        frame: Frame,
+        message: ProcessMessage,
+    ) -> bool {
+        RenderProcessHandlerDecorator::on_process_message_received(self, browser, frame, message)
+    }
+}
+
 #[repr(transparent)]
 pub(crate) struct RenderProcessHandlerWrapper(SendProtector<Box<dyn RenderProcessHandlerCallbacks>>);
 
@@ -173,7 +268,7 @@ cef_callback_impl! {
             frame: Frame: *mut cef_frame_t,
             context: V8Context: *mut cef_v8context_t,
         ) {
-            unsafe{ self.0.get() }.on_context_created(
+            unsafe{ self.0.get() }.on_context_released(
                 browser,
                 frame,
                 context,
@@ -0,0 +1,311 @@
+//! Typed data passed from the browser process to a browser's render process.
+//!
+//! The `extra_info` dictionary given when creating a browser arrives in
+//! [RenderProcessHandlerCallbacks::on_browser_created](crate::render_process_handler::RenderProcessHandlerCallbacks::on_browser_created).
+//! Reading it with string keys on one side and writing it on the other lets
+//! both sides drift apart unnoticed. A type implementing [RendererBootstrap]
+//! defines the encoding in one place, and is stored under its own key together
+//! with a version, so a renderer reading data of another version gets an
+//! error instead of misinterpreted fields.
+
+use crate::values::{DictionaryValue, ListValue, StoredValue, Value};
+use std::{collections::HashMap, convert::TryFrom, fmt};
+
+/// Data the browser process passes to the render process of a browser.
+pub trait RendererBootstrap: Sized {
+    /// Key of the data in the extra info dictionary. Several bootstrap types
+    /// can be passed to the same browser under different names.
+    const NAME: &'static str;
+    /// Increment when the fields change incompatibly.
+    const VERSION: i32;
+
+    fn encode(&self, encoder: &mut BootstrapEncoder);
+    fn decode(decoder: &BootstrapDecoder) -> Result<Self, BootstrapError>;
+
+    /// Add this data to the `extra_info` of a browser to be created, e.g.
+    /// with [BrowserTemplate::with_bootstrap](crate::browser_template::BrowserTemplate::with_bootstrap).
+    fn write_extra_info(&self, extra_info: &mut HashMap<String, StoredValue>) {
+        let mut encoder = BootstrapEncoder { values: HashMap::new() };
+        self.encode(&mut encoder);
+        let mut envelope = HashMap::new();
+        envelope.insert("version".to_owned(), StoredValue::Int(Self::VERSION));
+        envelope.insert("data".to_owned(), StoredValue::Dictionary(DictionaryValue::from(&encoder.values)));
+        extra_info.insert(Self::NAME.to_owned(), StoredValue::Dictionary(DictionaryValue::from(&envelope)));
+    }
+    /// Read this data from the `extra_info` received in the render process.
+    fn read_extra_info(extra_info: &DictionaryValue) -> Result<Self, BootstrapError> {
+        let extra_info: HashMap<String, StoredValue> = extra_info.into();
+        let envelope = find_envelope(&extra_info, Self::NAME)?.into();
+        let values = open_envelope(&envelope, Self::VERSION)?.into();
+        Self::decode(&BootstrapDecoder { values })
+    }
+}
+
+/// Returns the envelope stored under `name`.
+fn find_envelope<'a>(extra_info: &'a HashMap<String, StoredValue>, name: &str) -> Result<&'a DictionaryValue, BootstrapError> {
+    match extra_info.get(name) {
+        Some(StoredValue::Dictionary(envelope)) => Ok(envelope),
+        Some(_) => Err(invalid(name, "dictionary")),
+        None => Err(BootstrapError::Missing),
+    }
+}
+
+/// Checks that `envelope` was written with `version` and returns its data.
+fn open_envelope(envelope: &HashMap<String, StoredValue>, version: i32) -> Result<&DictionaryValue, BootstrapError> {
+    match envelope.get("version") {
+        Some(StoredValue::Int(found)) if *found == version => (),
+        Some(StoredValue::Int(found)) => {
+            return Err(BootstrapError::VersionMismatch { expected: version, found: *found })
+        }
+        _ => return Err(invalid("version", "int")),
+    }
+    match envelope.get("data") {
+        Some(StoredValue::Dictionary(data)) => Ok(data),
+        _ => Err(invalid("data", "dictionary")),
+    }
+}
+
+/// Why [RendererBootstrap::read_extra_info] failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BootstrapError {
+    /// The browser was created without this data.
+    Missing,
+    /// The data was written by a different version of the type, e.g. by a
+    /// browser process of another version of the application.
+    VersionMismatch { expected: i32, found: i32 },
+    /// A field is missing or has the wrong type.
+    InvalidField { field: String, expected: &'static str },
+}
+
+impl fmt::Display for BootstrapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BootstrapError::Missing => f.write_str("no bootstrap data was passed to the browser"),
+            BootstrapError::VersionMismatch { expected, found } => {
+                write!(f, "expected bootstrap data version {}, found {}", expected, found)
+            }
+            BootstrapError::InvalidField { field, expected } => {
+                write!(f, "bootstrap field {} is missing or not a {}", field, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BootstrapError {}
+
+/// Collects the fields of a [RendererBootstrap].
+pub struct BootstrapEncoder {
+    values: HashMap<String, StoredValue>,
+}
+
+impl BootstrapEncoder {
+    pub fn bool(&mut self, field: &str, value: bool) {
+        self.values.insert(field.to_owned(), StoredValue::Bool(value));
+    }
+    pub fn int(&mut self, field: &str, value: i32) {
+        self.values.insert(field.to_owned(), StoredValue::Int(value));
+    }
+    pub fn double(&mut self, field: &str, value: f64) {
+        self.values.insert(field.to_owned(), StoredValue::Double(value));
+    }
+    pub fn string(&mut self, field: &str, value: &str) {
+        self.values.insert(field.to_owned(), StoredValue::String(value.to_owned()));
+    }
+    pub fn strings(&mut self, field: &str, values: &[String]) {
+        let list = ListValue::new();
+        list.set_len(values.len());
+        for (index, value) in values.iter().enumerate() {
+            list.set_string(index, value);
+        }
+        self.values.insert(field.to_owned(), StoredValue::List(list));
+    }
+    /// Store a nested bootstrap type, e.g. settings shared by several types.
+    pub fn nested<B: RendererBootstrap>(&mut self, field: &str, value: &B) {
+        let mut encoder = BootstrapEncoder { values: HashMap::new() };
+        value.encode(&mut encoder);
+        self.values.insert(field.to_owned(), StoredValue::Dictionary(DictionaryValue::from(&encoder.values)));
+    }
+    /// Store any other value. Values that CEF can't represent are dropped.
+    pub fn value(&mut self, field: &str, value: StoredValue) {
+        if Value::try_from(value.clone()).is_ok() {
+            self.values.insert(field.to_owned(), value);
+        }
+    }
+}
+
+/// Reads the fields of a [RendererBootstrap].
+pub struct BootstrapDecoder {
+    values: HashMap<String, StoredValue>,
+}
+
+impl BootstrapDecoder {
+    pub fn bool(&self, field: &str) -> Result<bool, BootstrapError> {
+        match self.values.get(field) {
+            Some(StoredValue::Bool(value)) => Ok(*value),
+            _ => Err(invalid(field, "bool")),
+        }
+    }
+    pub fn int(&self, field: &str) -> Result<i32, BootstrapError> {
+        match self.values.get(field) {
+            Some(StoredValue::Int(value)) => Ok(*value),
+            _ => Err(invalid(field, "int")),
+        }
+    }
+    pub fn double(&self, field: &str) -> Result<f64, BootstrapError> {
+        match self.values.get(field) {
+            Some(StoredValue::Double(value)) => Ok(*value),
+            Some(StoredValue::Int(value)) => Ok(f64::from(*value)),
+            _ => Err(invalid(field, "double")),
+        }
+    }
+    pub fn string(&self, field: &str) -> Result<String, BootstrapError> {
+        match self.values.get(field) {
+            Some(StoredValue::String(value)) => Ok(value.clone()),
+            _ => Err(invalid(field, "string")),
+        }
+    }
+    pub fn strings(&self, field: &str) -> Result<Vec<String>, BootstrapError> {
+        let list: Vec<StoredValue> = match self.values.get(field) {
+            Some(StoredValue::List(list)) => list.clone().into(),
+            _ => return Err(invalid(field, "list")),
+        };
+        list.into_iter()
+            .map(|value| match value {
+                StoredValue::String(value) => Ok(value),
+                _ => Err(invalid(field, "list of strings")),
+            })
+            .collect()
+    }
+    pub fn nested<B: RendererBootstrap>(&self, field: &str) -> Result<B, BootstrapError> {
+        match self.values.get(field) {
+            Some(StoredValue::Dictionary(values)) => B::decode(&BootstrapDecoder { values: values.into() }),
+            _ => Err(invalid(field, "dictionary")),
+        }
+    }
+    /// Returns a field as stored, or `None` if it's missing. Use this for
+    /// optional fields added without incrementing the version.
+    pub fn value(&self, field: &str) -> Option<&StoredValue> {
+        self.values.get(field)
+    }
+}
+
+fn invalid(field: &str, expected: &'static str) -> BootstrapError {
+    BootstrapError::InvalidField { field: field.to_owned(), expected }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Values that don't need CEF to be stored.
+    #[derive(Debug, PartialEq)]
+    struct Settings {
+        dark_mode: bool,
+        zoom: f64,
+        user: String,
+        retries: i32,
+    }
+
+    impl RendererBootstrap for Settings {
+        const NAME: &'static str = "settings";
+        const VERSION: i32 = 2;
+
+        fn encode(&self, encoder: &mut BootstrapEncoder) {
+            encoder.bool("dark_mode", self.dark_mode);
+            encoder.double("zoom", self.zoom);
+            encoder.string("user", &self.user);
+            encoder.int("retries", self.retries);
+        }
+        fn decode(decoder: &BootstrapDecoder) -> Result<Self, BootstrapError> {
+            Ok(Settings {
+                dark_mode: decoder.bool("dark_mode")?,
+                zoom: decoder.double("zoom")?,
+                user: decoder.string("user")?,
+                retries: decoder.int("retries")?,
+            })
+        }
+    }
+
+    fn settings() -> Settings {
+        Settings {
+            dark_mode: true,
+            zoom: 1.25,
+            user: "ada".to_owned(),
+            retries: 3,
+        }
+    }
+
+    fn encode(settings: &Settings) -> HashMap<String, StoredValue> {
+        let mut encoder = BootstrapEncoder { values: HashMap::new() };
+        settings.encode(&mut encoder);
+        encoder.values
+    }
+
+    fn decode(values: HashMap<String, StoredValue>) -> Result<Settings, BootstrapError> {
+        Settings::decode(&BootstrapDecoder { values })
+    }
+
+    #[test]
+    fn fields_are_decoded_as_encoded() {
+        assert_eq!(decode(encode(&settings())), Ok(settings()));
+    }
+
+    #[test]
+    fn doubles_can_be_read_from_ints() {
+        let mut values = encode(&settings());
+        values.insert("zoom".to_owned(), StoredValue::Int(2));
+        assert_eq!(decode(values).unwrap().zoom, 2.0);
+    }
+
+    #[test]
+    fn missing_and_mistyped_fields_are_rejected() {
+        let mut values = encode(&settings());
+        values.remove("user");
+        assert_eq!(decode(values), Err(invalid("user", "string")));
+        let mut values = encode(&settings());
+        values.insert("retries".to_owned(), StoredValue::String("3".to_owned()));
+        assert_eq!(decode(values), Err(invalid("retries", "int")));
+        let mut values = encode(&settings());
+        values.insert("dark_mode".to_owned(), StoredValue::Int(1));
+        assert_eq!(decode(values), Err(invalid("dark_mode", "bool")));
+    }
+
+    #[test]
+    fn optional_fields_are_read_as_stored() {
+        let mut values = encode(&settings());
+        values.insert("extra".to_owned(), StoredValue::Null);
+        let decoder = BootstrapDecoder { values };
+        assert!(matches!(decoder.value("extra"), Some(StoredValue::Null)));
+        assert!(decoder.value("absent").is_none());
+    }
+
+    #[test]
+    fn envelopes_must_be_present_and_dictionaries() {
+        let mut extra_info = HashMap::new();
+        assert_eq!(find_envelope(&extra_info, "settings").unwrap_err(), BootstrapError::Missing);
+        extra_info.insert("settings".to_owned(), StoredValue::Int(1));
+        assert_eq!(find_envelope(&extra_info, "settings").unwrap_err(), invalid("settings", "dictionary"));
+    }
+
+    #[test]
+    fn envelopes_of_other_versions_are_rejected() {
+        let mut envelope = HashMap::new();
+        assert_eq!(open_envelope(&envelope, 2).unwrap_err(), invalid("version", "int"));
+        envelope.insert("version".to_owned(), StoredValue::Double(2.0));
+        assert_eq!(open_envelope(&envelope, 2).unwrap_err(), invalid("version", "int"));
+        envelope.insert("version".to_owned(), StoredValue::Int(1));
+        assert_eq!(
+            open_envelope(&envelope, 2).unwrap_err(),
+            BootstrapError::VersionMismatch { expected: 2, found: 1 },
+        );
+    }
+
+    #[test]
+    fn envelopes_without_data_are_rejected() {
+        let mut envelope = HashMap::new();
+        envelope.insert("version".to_owned(), StoredValue::Int(2));
+        assert_eq!(open_envelope(&envelope, 2).unwrap_err(), invalid("data", "dictionary"));
+        envelope.insert("data".to_owned(), StoredValue::String("{}".to_owned()));
+        assert_eq!(open_envelope(&envelope, 2).unwrap_err(), invalid("data", "dictionary"));
+    }
+}
@@ -3,7 +3,15 @@ use cef_sys::{
     cef_postdataelement_type_t, cef_referrer_policy_t, cef_request_create, cef_request_t,
     cef_resource_type_t, cef_string_userfree_utf16_free,
 };
-use std::{collections::HashMap, convert::TryFrom, ptr::null_mut};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fmt,
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    ptr::null_mut,
+};
 
 use crate::{load_handler::TransitionType, multimap::MultiMap, string::CefString};
 
@@ -106,9 +114,148 @@ impl URLRequestFlags {
     }
 }
 
+/// How a request uses the HTTP cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CachePolicy {
+    /// Use the cache according to the usual HTTP rules.
+    Default,
+    /// Don't use cached responses, but store the response.
+    /// Equivalent to "Cache-Control: no-cache".
+    SkipCache,
+    /// Fail unless the response can be served from the cache.
+    /// Equivalent to "Cache-Control: only-if-cached".
+    OnlyFromCache,
+    /// Neither use cached responses nor store the response.
+    /// Equivalent to "Cache-Control: no-store".
+    DisableCache,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        CachePolicy::Default
+    }
+}
+
+impl CachePolicy {
+    fn cache_control(self) -> Option<&'static str> {
+        match self {
+            CachePolicy::Default => None,
+            CachePolicy::SkipCache => Some("no-cache"),
+            CachePolicy::OnlyFromCache => Some("only-if-cached"),
+            CachePolicy::DisableCache => Some("no-store"),
+        }
+    }
+}
+
+/// Remove the "Cache-Control" values set for a [CachePolicy] from
+/// `header_map`, leaving other values alone. Returns false if there were none.
+fn remove_policy_cache_control(header_map: &mut HashMap<String, Vec<String>>) -> bool {
+    let policies = [CachePolicy::SkipCache, CachePolicy::OnlyFromCache, CachePolicy::DisableCache];
+    let mut removed = false;
+    header_map.retain(|name, values| {
+        if !name.eq_ignore_ascii_case("Cache-Control") {
+            return true;
+        }
+        let len = values.len();
+        values.retain(|value| !policies.iter().any(|policy| policy.cache_control() == Some(value.as_str())));
+        removed |= values.len() != len;
+        !values.is_empty()
+    });
+    removed
+}
+
+/// Typed form of the [URLRequestFlags] of a request. Mutually exclusive cache
+/// flags are combined into a single [CachePolicy], and flags that only make
+/// sense together can't be set inconsistently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RequestOptions {
+    pub cache_policy: CachePolicy,
+    /// Send cookies and stored credentials, and save cookies from the
+    /// response. Without this a [URLRequest] is made without cookies.
+    pub send_credentials: bool,
+    /// Report upload progress to
+    /// [URLRequestClientCallbacks::on_upload_progress](crate::url_request::URLRequestClientCallbacks::on_upload_progress).
+    pub report_upload_progress: bool,
+    /// Don't pass the response body to
+    /// [URLRequestClientCallbacks::on_download_data](crate::url_request::URLRequestClientCallbacks::on_download_data).
+    pub no_download_data: bool,
+    /// Report 5xx responses instead of retrying.
+    pub no_retry_on_5xx: bool,
+    /// Stop at redirects instead of following them.
+    pub stop_on_redirect: bool,
+}
+
+impl RequestOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn cache_policy(mut self, cache_policy: CachePolicy) -> Self {
+        self.cache_policy = cache_policy;
+        self
+    }
+    pub fn send_credentials(mut self, send_credentials: bool) -> Self {
+        self.send_credentials = send_credentials;
+        self
+    }
+    pub fn report_upload_progress(mut self, report_upload_progress: bool) -> Self {
+        self.report_upload_progress = report_upload_progress;
+        self
+    }
+    pub fn no_download_data(mut self, no_download_data: bool) -> Self {
+        self.no_download_data = no_download_data;
+        self
+    }
+    pub fn no_retry_on_5xx(mut self, no_retry_on_5xx: bool) -> Self {
+        self.no_retry_on_5xx = no_retry_on_5xx;
+        self
+    }
+    pub fn stop_on_redirect(mut self, stop_on_redirect: bool) -> Self {
+        self.stop_on_redirect = stop_on_redirect;
+        self
+    }
+
+    fn from_flags(flags: &[URLRequestFlags]) -> Self {
+        let has = |flag| flags.contains(&flag);
+        RequestOptions {
+            cache_policy: if has(URLRequestFlags::OnlyFromCache) {
+                CachePolicy::OnlyFromCache
+            } else if has(URLRequestFlags::DisableCache) {
+                CachePolicy::DisableCache
+            } else if has(URLRequestFlags::SkipCache) {
+                CachePolicy::SkipCache
+            } else {
+                CachePolicy::Default
+            },
+            send_credentials: has(URLRequestFlags::AllowStoredCredentials),
+            report_upload_progress: has(URLRequestFlags::ReportUploadProgress),
+            no_download_data: has(URLRequestFlags::NoDownloadData),
+            no_retry_on_5xx: has(URLRequestFlags::NoRetryOn5xx),
+            stop_on_redirect: has(URLRequestFlags::StopOnRedirect),
+        }
+    }
+    fn to_flags(&self) -> Vec<URLRequestFlags> {
+        let mut flags = Vec::new();
+        match self.cache_policy {
+            CachePolicy::Default => (),
+            CachePolicy::SkipCache => flags.push(URLRequestFlags::SkipCache),
+            CachePolicy::OnlyFromCache => flags.push(URLRequestFlags::OnlyFromCache),
+            CachePolicy::DisableCache => flags.push(URLRequestFlags::DisableCache),
+        }
+        let optional = [
+            (self.send_credentials, URLRequestFlags::AllowStoredCredentials),
+            (self.report_upload_progress, URLRequestFlags::ReportUploadProgress),
+            (self.no_download_data, URLRequestFlags::NoDownloadData),
+            (self.no_retry_on_5xx, URLRequestFlags::NoRetryOn5xx),
+            (self.stop_on_redirect, URLRequestFlags::StopOnRedirect),
+        ];
+        flags.extend(optional.iter().filter(|(set, _)| *set).map(|(_, flag)| *flag));
+        flags
+    }
+}
+
 /// Resource type for a request.
 #[repr(C)]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ResourceType {
     /// Top level page.
     MainFrame = cef_resource_type_t::RT_MAIN_FRAME as isize,
@@ -156,7 +303,7 @@ impl ResourceType {
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PostDataElementType {
     Empty = cef_postdataelement_type_t::PDE_TYPE_EMPTY as isize,
     Bytes = cef_postdataelement_type_t::PDE_TYPE_BYTES as isize,
@@ -175,6 +322,18 @@ ref_counted_ptr! {
     pub struct Request(*mut cef_request_t);
 }
 
+/// Globally unique identifier of a request, stable across all
+/// [ResourceRequestHandlerCallbacks](crate::resource_request_handler::ResourceRequestHandlerCallbacks)
+/// calls for the same request, including redirects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RequestId(pub u64);
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
 impl Request {
     /// Create a new Request object.
     pub fn new() -> Self {
@@ -266,10 +425,17 @@ impl Request {
             })
             .unwrap_or(ReferrerPolicy::Default)
     }
-    /// Get the post data.
-    pub fn get_post_data(&self) -> PostData {
-        let get_post_data = self.0.get_post_data.unwrap();
-        unsafe { PostData::from_ptr_unchecked(get_post_data(self.0.as_ptr())) }
+    /// Get the post data, or None if the request doesn't have a body.
+    ///
+    /// In [ResourceRequestHandlerCallbacks::on_before_resource_load] the returned
+    /// object is writable, so the body can be modified or replaced before it is
+    /// sent. File uploads are represented by [PostDataElementType::File] elements
+    /// that only carry the file path; see [PostData::has_excluded_elements] for
+    /// data that isn't represented at all.
+    pub fn get_post_data(&self) -> Option<PostData> {
+        self.0
+            .get_post_data
+            .and_then(|get_post_data| unsafe { PostData::from_ptr(get_post_data(self.0.as_ptr())) })
     }
     /// Set the post data.
     pub fn set_post_data(&self, post_data: PostData) {
@@ -279,6 +445,22 @@ impl Request {
             }
         }
     }
+    /// Replace the post data with a single element containing `bytes`. Has no
+    /// effect if the request is read-only.
+    pub fn set_post_data_bytes(&self, bytes: &[u8]) {
+        let post_data = PostData::new();
+        post_data.add_element(&PostDataElement::from_bytes(bytes));
+        self.set_post_data(post_data);
+    }
+    /// Replace the post data with a single element uploading the file at
+    /// `path`. The file is read in chunks while the request is sent, so this is
+    /// the way to upload large bodies from the browser process without holding
+    /// them in memory. Has no effect if the request is read-only.
+    pub fn set_post_data_file(&self, path: &Path) {
+        let post_data = PostData::new();
+        post_data.add_element(&PostDataElement::from_file(&path.to_string_lossy()));
+        self.set_post_data(post_data);
+    }
     /// Get the header values. Will not include the Referer value if any.
     pub fn get_header_map(&self) -> HashMap<String, Vec<String>> {
         if let Some(get_header_map) = self.0.get_header_map {
@@ -289,6 +471,14 @@ impl Request {
             HashMap::new()
         }
     }
+    /// Set the header values. If a Referer value exists in the header map it will
+    /// be removed and ignored.
+    pub fn set_header_map(&self, header_map: &HashMap<String, Vec<String>>) {
+        if let Some(set_header_map) = self.0.set_header_map {
+            let map = MultiMap::from(header_map);
+            unsafe { set_header_map(self.0.as_ptr(), map.as_ptr()) };
+        }
+    }
     /// Returns the first header value for `name` or None if not found.
     /// Will not return the Referer value if any. Use [Request::get_header_map] instead if
     /// `name` might have multiple values.
@@ -362,6 +552,27 @@ impl Request {
             }
         }
     }
+    /// Get the flags of this request as [RequestOptions].
+    pub fn get_options(&self) -> RequestOptions {
+        RequestOptions::from_flags(&self.get_flags())
+    }
+    /// Set the flags of this request from `options`. The flags are only used by
+    /// [URLRequest], so the cache policy is also set as "Cache-Control" header,
+    /// which makes it apply to requests intercepted in
+    /// [ResourceRequestHandlerCallbacks::on_before_resource_load](crate::resource_request_handler::ResourceRequestHandlerCallbacks::on_before_resource_load)
+    /// as well. [CachePolicy::Default] removes a header set for another
+    /// policy. The other options have no effect there.
+    pub fn set_options(&self, options: &RequestOptions) {
+        self.set_flags(&options.to_flags());
+        if let Some(cache_control) = options.cache_policy.cache_control() {
+            self.set_header_by_name("Cache-Control", cache_control, true);
+        } else {
+            let mut header_map = self.get_header_map();
+            if remove_policy_cache_control(&mut header_map) {
+                self.set_header_map(&header_map);
+            }
+        }
+    }
     /// Get the URL to the first party for cookies used in combination with
     /// [URLRequest].
     pub fn get_first_party_for_cookies(&self) -> String {
@@ -414,6 +625,15 @@ impl Request {
             0
         }
     }
+    /// Returns [Request::get_identifier] as a [RequestId], or `None` if the
+    /// request doesn't have an identifier (yet), e.g. because it hasn't been
+    /// sent.
+    pub fn get_request_id(&self) -> Option<RequestId> {
+        match self.get_identifier() {
+            0 => None,
+            id => Some(RequestId(id)),
+        }
+    }
 }
 
 impl Default for Request {
@@ -502,6 +722,87 @@ impl PostData {
             }
         }
     }
+    /// Copy the contents of all post data elements.
+    pub fn get_parts(&self) -> Vec<PostDataPart> {
+        self.get_elements()
+            .iter()
+            .map(PostDataElement::get_part)
+            .collect()
+    }
+    /// Returns the complete body if it only consists of in-memory bytes, or None
+    /// if it contains file uploads or excluded elements.
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        if self.has_excluded_elements() {
+            return None;
+        }
+        let mut bytes = Vec::new();
+        for part in self.get_parts() {
+            match part {
+                PostDataPart::Empty => (),
+                PostDataPart::Bytes(b) => bytes.extend_from_slice(&b),
+                PostDataPart::File(_) => return None,
+            }
+        }
+        Some(bytes)
+    }
+    /// Returns a reader streaming the complete body, or None if it contains
+    /// excluded elements. File uploads are opened only once the reader reaches
+    /// them and are read in chunks, so custom scheme handlers can consume large
+    /// uploads without buffering them.
+    pub fn reader(&self) -> Option<PostDataReader> {
+        if self.has_excluded_elements() {
+            return None;
+        }
+        Some(PostDataReader {
+            elements: self.get_elements().into_iter(),
+            current: None,
+        })
+    }
+    /// Returns the total size of the body in bytes, or None if it contains
+    /// excluded elements or a file upload whose size can't be determined.
+    pub fn content_length(&self) -> Option<u64> {
+        if self.has_excluded_elements() {
+            return None;
+        }
+        let mut length = 0;
+        for element in self.get_elements() {
+            length += match element.get_type() {
+                PostDataElementType::Empty => 0,
+                PostDataElementType::Bytes => element.get_bytes_count() as u64,
+                PostDataElementType::File => std::fs::metadata(element.get_file()).ok()?.len(),
+            };
+        }
+        Some(length)
+    }
+}
+
+/// [Read] implementation returned by [PostData::reader].
+pub struct PostDataReader {
+    elements: std::vec::IntoIter<PostDataElement>,
+    current: Option<Box<dyn Read + Send>>,
+}
+
+impl Read for PostDataReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if let Some(current) = &mut self.current {
+                let n = current.read(buf)?;
+                if n > 0 || buf.is_empty() {
+                    return Ok(n);
+                }
+                self.current = None;
+            }
+            let element = match self.elements.next() {
+                Some(element) => element,
+                None => return Ok(0),
+            };
+            self.current = match element.get_type() {
+                PostDataElementType::Empty => None,
+                PostDataElementType::Bytes => Some(Box::new(io::Cursor::new(element.get_bytes()))),
+                PostDataElementType::File => Some(Box::new(File::open(element.get_file())?)),
+            };
+        }
+    }
 }
 
 impl Default for PostData {
@@ -521,6 +822,18 @@ impl PostDataElement {
     pub fn new() -> Self {
         unsafe { Self::from_ptr_unchecked(cef_post_data_element_create()) }
     }
+    /// Create a new [PostDataElement] object containing a copy of `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let element = Self::new();
+        element.set_to_bytes(bytes);
+        element
+    }
+    /// Create a new [PostDataElement] object representing the file at `file_name`.
+    pub fn from_file(file_name: &str) -> Self {
+        let element = Self::new();
+        element.set_to_file(file_name);
+        element
+    }
 
     /// Returns true if this object is read-only.
     pub fn is_read_only(&self) -> bool {
@@ -607,6 +920,14 @@ impl PostDataElement {
             Vec::new()
         }
     }
+    /// Copy the contents of this element.
+    pub fn get_part(&self) -> PostDataPart {
+        match self.get_type() {
+            PostDataElementType::Empty => PostDataPart::Empty,
+            PostDataElementType::Bytes => PostDataPart::Bytes(self.get_bytes()),
+            PostDataElementType::File => PostDataPart::File(PathBuf::from(self.get_file())),
+        }
+    }
 }
 
 impl Default for PostDataElement {
@@ -614,3 +935,40 @@ impl Default for PostDataElement {
         Self::new()
     }
 }
+
+/// Owned copy of the contents of a [PostDataElement].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PostDataPart {
+    Empty,
+    Bytes(Vec<u8>),
+    /// A file upload. Only the path is available; the file contents are read
+    /// when the request is sent.
+    File(PathBuf),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_map(headers: &[(&str, &[&str])]) -> HashMap<String, Vec<String>> {
+        headers
+            .iter()
+            .map(|(name, values)| (name.to_string(), values.iter().map(|value| value.to_string()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn policy_cache_control_is_removed() {
+        let mut headers = header_map(&[("cache-control", &["no-store"]), ("Accept", &["text/html"])]);
+        assert!(remove_policy_cache_control(&mut headers));
+        assert_eq!(headers, header_map(&[("Accept", &["text/html"])]));
+    }
+
+    #[test]
+    fn other_cache_control_values_are_kept() {
+        let mut headers = header_map(&[("Cache-Control", &["max-age=0", "no-cache"])]);
+        assert!(remove_policy_cache_control(&mut headers));
+        assert_eq!(headers, header_map(&[("Cache-Control", &["max-age=0"])]));
+        assert!(!remove_policy_cache_control(&mut headers));
+    }
+}
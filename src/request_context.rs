@@ -6,8 +6,8 @@ use std::os::raw::c_int;
 use crate::load_handler::ErrorCode;
 use crate::scheme::SchemeHandlerFactory;
 use cef_sys::cef_resolve_callback_t;
-use crate::cookie::CookieManager;
-use crate::values::{StoredValue, DictionaryValue, Value};
+use crate::cookie::{CookieManager, CookieableSchemes};
+use crate::values::{StoredValue, DictionaryValue, ListValue, Value};
 use cef_sys::cef_string_list_t;
 use crate::extension::ExtensionHandler;
 use crate::extension::Extension;
@@ -346,6 +346,20 @@ impl RequestContext {
             ) != 0
         }
     }
+    /// Remove the factory registered for `scheme_name` and optional
+    /// `domain_name` with [RequestContext::register_scheme_handler_factory].
+    /// Returns `false` if an error occurs. This function may be called on any
+    /// thread in the browser process.
+    pub fn unregister_scheme_handler_factory(&self, scheme_name: &str, domain_name: Option<&str>) -> bool {
+        unsafe{
+            self.0.register_scheme_handler_factory.unwrap()(
+                self.as_ptr(),
+                CefString::new(scheme_name).as_ptr(),
+                domain_name.map(CefString::new).as_ref().map(CefString::as_ptr).unwrap_or(null()),
+                null_mut()
+            ) != 0
+        }
+    }
     /// Clear all registered scheme handler factories. Returns `false` on error.
     /// This function may be called on any thread in the browser process.
     pub fn clear_scheme_handler_factories(&self) -> bool {
@@ -429,6 +443,52 @@ impl RequestContext {
             Err(String::from(error))
         }
     }
+    /// Change the ordered list of languages used for the "Accept-Language" HTTP
+    /// header and `navigator.languages`, as a comma delimited list of language
+    /// codes without whitespace (e.g. "de-DE,de,en"). Pages that are already
+    /// loaded keep the old value, so those of `browsers` that use this context
+    /// are reloaded. This function must be called on the browser process UI
+    /// thread.
+    ///
+    /// The locale of CEF's own UI strings is fixed at startup by
+    /// [Settings::locale](crate::settings::Settings::locale); use a
+    /// [LocalizedStrings](crate::resource_bundle_handler::LocalizedStrings)
+    /// resource bundle handler to switch those at runtime.
+    pub fn set_accept_language_list(&self, list: &str, browsers: &[Browser]) -> Result<(), String> {
+        self.set_preference("intl.accept_languages", Some(StoredValue::String(list.to_owned())))?;
+        for browser in browsers {
+            if self.is_same(browser.get_host().get_request_context()) {
+                browser.reload(false);
+            }
+        }
+        Ok(())
+    }
+    /// Set the languages of the dictionaries used for spell checking in this
+    /// context, like `["en-US", "de-DE"]`. Missing dictionaries are downloaded
+    /// by Chromium, unless spell checking was disabled with the
+    /// `--disable-spell-checking` command line switch. This function must be
+    /// called on the browser process UI thread.
+    pub fn set_spellcheck_languages(&self, languages: &[&str]) -> Result<(), String> {
+        let list = ListValue::new();
+        list.set_len(languages.len());
+        for (index, language) in languages.iter().enumerate() {
+            list.set_string(index, language);
+        }
+        self.set_preference("spellcheck.dictionaries", Some(StoredValue::List(list)))
+    }
+    /// Returns the languages set with [RequestContext::set_spellcheck_languages].
+    /// This function must be called on the browser process UI thread.
+    pub fn get_spellcheck_languages(&self) -> Vec<String> {
+        match self.get_preference("spellcheck.dictionaries") {
+            Some(StoredValue::List(list)) => (0..list.len()).filter_map(|index| list.get_string(index)).collect(),
+            _ => Vec::new(),
+        }
+    }
+    /// Enable or disable spell checking of editable content in this context.
+    /// This function must be called on the browser process UI thread.
+    pub fn set_spellcheck_enabled(&self, enabled: bool) -> Result<(), String> {
+        self.set_preference("browser.enable_spellchecking", Some(StoredValue::Bool(enabled)))
+    }
     /// Clears all certificate exceptions that were added as part of handling
     /// cef_request_tHandler::on_certificate_error(). If you call this it is
     /// recommended that you also call close_all_connections() or you risk not
@@ -484,7 +544,8 @@ impl RequestContext {
     /// resources will be provided by the client (e.g. via cef_request_tHandler
     /// and/or cef_extension_tHandler) then `root_directory` should be a path
     /// component unique to the extension (if not absolute this will be internally
-    /// prefixed with the PK_DIR_RESOURCES path) and `manifest` should contain the
+    /// prefixed with the [PathKey::Resources](crate::PathKey::Resources)
+    /// path) and `manifest` should contain the
     /// contents that would otherwise be read from the "manifest.json" file on
     /// disk.
     ///
@@ -597,11 +658,12 @@ impl RequestContext {
 pub struct RequestContextBuilder(
     Option<cef_request_context_settings_t>,
     Option<RequestContextHandler>,
+    Option<CookieableSchemes>,
 );
 
 impl RequestContextBuilder {
     pub fn new() -> Self {
-        Self(None, None)
+        Self(None, None, None)
     }
     /// Creates a new context object with the specified `settings` and optional
     /// `handler`.
@@ -615,12 +677,16 @@ impl RequestContextBuilder {
         } else {
             null()
         };
-        unsafe {
+        let context = unsafe {
             RequestContext::from_ptr_unchecked(cef_request_context_create_context(
                 settings_ptr,
                 handler_ptr as *mut _,
             ))
+        };
+        if let Some(cookieable_schemes) = self.2 {
+            cookieable_schemes.apply(&context.get_cookie_manager(|| ()), || ());
         }
+        context
     }
 
     fn get_settings(&mut self) -> &mut cef_request_context_settings_t {
@@ -715,6 +781,17 @@ impl RequestContextBuilder {
         }
         self
     }
+
+    /// Schemes that can store cookies in this context's cookie manager. Can be
+    /// set globally using the [Settings::cookieable_schemes](crate::settings::Settings::cookieable_schemes)
+    /// value. If `cache_path` matches the
+    /// [Settings::cache_path](crate::settings::Settings::cache_path) value, the
+    /// context shares the global cookie manager and this overrides the global
+    /// setting.
+    pub fn cookieable_schemes(mut self, cookieable_schemes: CookieableSchemes) -> Self {
+        self.2 = Some(cookieable_schemes);
+        self
+    }
 }
 
 impl Default for RequestContextBuilder {
@@ -0,0 +1,168 @@
+//! Correlating the callbacks of a single request.
+//!
+//! [ResourceRequestHandlerCallbacks] receives a new [Request] object for every
+//! callback, so telling which `on_resource_response` belongs to which
+//! `on_before_resource_load` needs the request's [RequestId].
+//! [RequestTracer] wraps an existing implementation and reports every stage
+//! of a request, tagged with its id, to a trace function before forwarding the
+//! call. [log_request_trace] is a trace function writing to the `log` crate.
+
+use crate::{
+    browser::Browser,
+    frame::Frame,
+    request::{Request, RequestId, ResourceType},
+    resource_request_handler::{
+        ResourceRequestHandler, ResourceRequestHandlerCallbacks, ResourceRequestHandlerDecorator,
+    },
+    response::Response,
+    url_request::{RequestCallback, URLRequestStatus},
+    ReturnValue,
+};
+
+/// Stage of a request reported by a [RequestTracer].
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestTraceEvent {
+    /// The request is about to be sent.
+    BeforeResourceLoad {
+        method: String,
+        url: String,
+        resource_type: ResourceType,
+    },
+    /// The request was redirected to `new_url`.
+    Redirect {
+        url: String,
+        new_url: String,
+        status: i32,
+    },
+    /// The response headers were received.
+    Response {
+        url: String,
+        status: i32,
+        mime_type: String,
+    },
+    /// The request finished, successfully or not.
+    Complete {
+        url: String,
+        status: URLRequestStatus,
+        received_content_length: i64,
+    },
+}
+
+/// Function receiving the stages of traced requests. Called on the IO thread.
+pub trait RequestTraceCallback = 'static + Send + Sync + Fn(RequestId, &RequestTraceEvent);
+
+/// [ResourceRequestHandlerCallbacks] wrapper reporting every request it sees.
+pub struct RequestTracer<C: ResourceRequestHandlerCallbacks, T: RequestTraceCallback> {
+    inner: C,
+    trace: T,
+}
+
+impl<C: ResourceRequestHandlerCallbacks, T: RequestTraceCallback> RequestTracer<C, T> {
+    /// Trace the requests handled by `inner`. Changes `inner` makes to the
+    /// request in `on_before_resource_load` aren't reflected in the event.
+    pub fn new(inner: C, trace: T) -> Self {
+        RequestTracer { inner, trace }
+    }
+    pub fn build(self) -> ResourceRequestHandler {
+        ResourceRequestHandler::new(self)
+    }
+
+    fn trace(&self, request: &Request, event: RequestTraceEvent) {
+        if let Some(id) = request.get_request_id() {
+            (self.trace)(id, &event);
+        }
+    }
+}
+
+/// Trace function logging requests at debug level, with the `cef::requests`
+/// target.
+pub fn log_request_trace(id: RequestId, event: &RequestTraceEvent) {
+    match event {
+        RequestTraceEvent::BeforeResourceLoad { method, url, resource_type } => {
+            log::debug!(target: "cef::requests", "{} {} {} ({:?})", id, method, url, resource_type)
+        }
+        RequestTraceEvent::Redirect { url, new_url, status } => {
+            log::debug!(target: "cef::requests", "{} {} {} -> {}", id, status, url, new_url)
+        }
+        RequestTraceEvent::Response { url, status, mime_type } => {
+            log::debug!(target: "cef::requests", "{} {} {} ({})", id, status, url, mime_type)
+        }
+        RequestTraceEvent::Complete { url, status, received_content_length } => {
+            log::debug!(target: "cef::requests", "{} {:?} {} ({} bytes)", id, status, url, received_content_length)
+        }
+    }
+}
+
+impl<C: ResourceRequestHandlerCallbacks, T: RequestTraceCallback> ResourceRequestHandlerDecorator for RequestTracer<C, T> {
+    type Inner = C;
+    fn inner(&self) -> &C {
+        &self.inner
+    }
+    fn on_before_resource_load(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        callback: RequestCallback,
+    ) -> ReturnValue {
+        self.trace(&request, RequestTraceEvent::BeforeResourceLoad {
+            method: request.get_method(),
+            url: request.get_url(),
+            resource_type: request.get_resource_type(),
+        });
+        self.inner.on_before_resource_load(browser, frame, request, callback)
+    }
+
+    fn on_resource_redirect(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        response: Response,
+        new_url: &mut String,
+    ) {
+        let id = request.get_request_id();
+        let url = request.get_url();
+        let status = response.get_status();
+        self.inner.on_resource_redirect(browser, frame, request, response, new_url);
+        if let Some(id) = id {
+            (self.trace)(id, &RequestTraceEvent::Redirect {
+                url,
+                new_url: new_url.clone(),
+                status,
+            });
+        }
+    }
+
+    fn on_resource_response(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        response: Response,
+    ) {
+        self.trace(&request, RequestTraceEvent::Response {
+            url: request.get_url(),
+            status: response.get_status(),
+            mime_type: response.get_mime_type(),
+        });
+        self.inner.on_resource_response(browser, frame, request, response)
+    }
+
+    fn on_resource_load_complete(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        response: Response,
+        status: URLRequestStatus,
+        received_content_length: i64,
+    ) {
+        self.trace(&request, RequestTraceEvent::Complete {
+            url: request.get_url(),
+            status,
+            received_content_length,
+        });
+        self.inner.on_resource_load_complete(browser, frame, request, response, status, received_content_length)
+    }
+}
@@ -4,6 +4,8 @@ use crate::{
     refcounted::{RefCountedPtr, Wrapper},
     string::CefString,
 };
+use parking_lot::RwLock;
+use std::{collections::HashMap, sync::Arc};
 
 /// Supported UI scale factors for the platform. None is used for
 /// density independent resources such as string, html/js files or an image that
@@ -81,6 +83,50 @@ pub trait ResourceBundleHandlerCallbacks: 'static + Send + Sync {
     }
 }
 
+/// Replacement strings for CEF's localized UI strings (context menu entries,
+/// dialog buttons, error pages, ...), keyed by resource string ID.
+///
+/// Clones share the same table, so a clone can be installed with
+/// [LocalizedStrings::handler] at startup and the table swapped later to switch
+/// languages without restarting the process. Strings are looked up when they
+/// are displayed, so already visible UI keeps the previous language. IDs
+/// without a replacement fall back to the locale loaded at startup.
+#[derive(Debug, Clone, Default)]
+pub struct LocalizedStrings(Arc<RwLock<HashMap<i32, String>>>);
+
+impl LocalizedStrings {
+    pub fn new() -> LocalizedStrings {
+        LocalizedStrings::default()
+    }
+    /// Returns a [ResourceBundleHandler] answering `get_localized_string` from
+    /// this table. Return it from [AppCallbacks::get_resource_bundle_handler](crate::app::AppCallbacks::get_resource_bundle_handler)
+    /// in every process that displays localized strings.
+    pub fn handler(&self) -> ResourceBundleHandler {
+        ResourceBundleHandler::new(self.clone())
+    }
+    /// Replace the string for `string_id`.
+    pub fn set(&self, string_id: i32, string: &str) {
+        self.0.write().insert(string_id, string.to_owned());
+    }
+    /// Remove the replacement for `string_id`.
+    pub fn remove(&self, string_id: i32) {
+        self.0.write().remove(&string_id);
+    }
+    /// Replace the whole table, e.g. with the strings of another language.
+    pub fn replace_all(&self, strings: HashMap<i32, String>) {
+        *self.0.write() = strings;
+    }
+    pub fn get(&self, string_id: i32) -> Option<String> {
+        self.0.read().get(&string_id).cloned()
+    }
+}
+
+impl ResourceBundleHandlerCallbacks for LocalizedStrings {
+    fn get_localized_string(&self, string_id: i32, string: &str) -> Option<String> {
+        self.get(string_id)
+    }
+}
+
 pub(crate) struct ResourceBundleHandlerWrapper {
     delegate: Box<dyn ResourceBundleHandlerCallbacks>,
 }
@@ -0,0 +1,337 @@
+//! Structured reports of finished requests.
+//!
+//! A [ResourceLoadReport] describes one request once it succeeded, failed or
+//! was canceled. There are two sources of reports:
+//!
+//! - [ResourceLoadReporter] wraps a [ResourceRequestHandlerCallbacks]
+//!   implementation and reports what CEF passes to
+//!   `on_resource_load_complete`. It sees every request of the browser process
+//!   network stack, but CEF doesn't expose addresses or timings.
+//! - [BrowserHost::add_resource_load_observer] reports the requests of a
+//!   browser from DevTools `Network` events, which include the remote address
+//!   and the time spent in each phase of the request.
+
+use crate::{
+    browser::Browser,
+    browser_host::BrowserHost,
+    devtools::{get_bool, get_dictionary, get_f64, get_i32, get_string, parse_json_dictionary},
+    devtools_message_observer::{DevToolsMessageObserver, DevToolsMessageObserverCallbacks},
+    frame::Frame,
+    load_handler::ErrorCode,
+    registration::Registration,
+    request::{Request, RequestId, ResourceType},
+    resource_request_handler::{
+        ResourceRequestHandler, ResourceRequestHandlerCallbacks, ResourceRequestHandlerDecorator,
+    },
+    response::Response,
+    url_request::{RequestCallback, URLRequestStatus},
+    values::StoredValue,
+    ReturnValue,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Time spent in the phases of a request, as reported by DevTools. Phases that
+/// didn't happen, e.g. DNS resolution for a reused connection, are `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ResourceTiming {
+    /// Waiting for a connection or in a queue.
+    pub blocked: Option<Duration>,
+    pub dns: Option<Duration>,
+    /// Establishing the connection, including `ssl`.
+    pub connect: Option<Duration>,
+    pub ssl: Option<Duration>,
+    /// Sending the request.
+    pub send: Option<Duration>,
+    /// Waiting for the response headers.
+    pub wait: Option<Duration>,
+    /// Receiving the response body.
+    pub receive: Option<Duration>,
+}
+
+/// A finished request. Fields that the source of the report doesn't know are
+/// empty or `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceLoadReport {
+    pub url: String,
+    pub method: String,
+    /// Only known to [ResourceLoadReporter].
+    pub resource_type: Option<ResourceType>,
+    pub status: URLRequestStatus,
+    /// Network error of failed requests. Only known to [ResourceLoadReporter].
+    pub error: Option<ErrorCode>,
+    /// Network error of failed requests, like `net::ERR_NAME_NOT_RESOLVED`.
+    /// Only known to the DevTools observer.
+    pub error_text: Option<String>,
+    /// HTTP status code, or 0 if no response was received.
+    pub http_status: i32,
+    pub mime_type: String,
+    /// Bytes received. [ResourceLoadReporter] counts the decoded body, DevTools
+    /// the encoded bytes including headers.
+    pub received_bytes: i64,
+    /// True if the response was served from the cache. Only known to the
+    /// DevTools observer.
+    pub from_cache: bool,
+    /// Address of the server or proxy the response came from.
+    pub remote_address: Option<SocketAddr>,
+    /// Protocol of the response, like `http/1.1` or `h2`.
+    pub protocol: Option<String>,
+    /// When the request started.
+    pub started: Option<SystemTime>,
+    /// Time from the start of the request until it finished.
+    pub duration: Option<Duration>,
+    pub timing: Option<ResourceTiming>,
+}
+
+impl ResourceLoadReport {
+    fn new(url: String, method: String) -> Self {
+        ResourceLoadReport {
+            url,
+            method,
+            resource_type: None,
+            status: URLRequestStatus::Unknown,
+            error: None,
+            error_text: None,
+            http_status: 0,
+            mime_type: String::new(),
+            received_bytes: 0,
+            from_cache: false,
+            remote_address: None,
+            protocol: None,
+            started: None,
+            duration: None,
+            timing: None,
+        }
+    }
+}
+
+/// Function receiving reports of finished requests.
+pub trait ResourceLoadReportCallback = 'static + Send + Sync + Fn(&ResourceLoadReport);
+
+/// [ResourceRequestHandlerCallbacks] wrapper reporting every request that
+/// completes. Reports are made on the IO thread.
+pub struct ResourceLoadReporter<C: ResourceRequestHandlerCallbacks, F: ResourceLoadReportCallback> {
+    inner: C,
+    report: F,
+    /// Start of the requests in flight.
+    started: Mutex<HashMap<RequestId, (SystemTime, Instant)>>,
+}
+
+impl<C: ResourceRequestHandlerCallbacks, F: ResourceLoadReportCallback> ResourceLoadReporter<C, F> {
+    pub fn new(inner: C, report: F) -> Self {
+        ResourceLoadReporter {
+            inner,
+            report,
+            started: Mutex::new(HashMap::new()),
+        }
+    }
+    pub fn build(self) -> ResourceRequestHandler {
+        ResourceRequestHandler::new(self)
+    }
+}
+
+impl<C: ResourceRequestHandlerCallbacks, F: ResourceLoadReportCallback> ResourceRequestHandlerDecorator for ResourceLoadReporter<C, F> {
+    type Inner = C;
+    fn inner(&self) -> &C {
+        &self.inner
+    }
+    fn on_before_resource_load(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        callback: RequestCallback,
+    ) -> ReturnValue {
+        if let Some(id) = request.get_request_id() {
+            // Redirects call this again for the same request; keep the
+            // original start.
+            self.started.lock().entry(id).or_insert_with(|| (SystemTime::now(), Instant::now()));
+        }
+        self.inner.on_before_resource_load(browser, frame, request, callback)
+    }
+
+    fn on_resource_load_complete(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        response: Response,
+        status: URLRequestStatus,
+        received_content_length: i64,
+    ) {
+        let started = request.get_request_id().and_then(|id| self.started.lock().remove(&id));
+        let error = response.get_error();
+        let mut report = ResourceLoadReport::new(request.get_url(), request.get_method());
+        report.resource_type = Some(request.get_resource_type());
+        report.status = status;
+        report.error = if error == ErrorCode::None { None } else { Some(error) };
+        report.http_status = response.get_status();
+        report.mime_type = response.get_mime_type();
+        report.received_bytes = received_content_length;
+        report.started = started.map(|(started, _)| started);
+        report.duration = started.map(|(_, instant)| instant.elapsed());
+        (self.report)(&report);
+        self.inner.on_resource_load_complete(browser, frame, request, response, status, received_content_length)
+    }
+}
+
+/// Function receiving the reports of [BrowserHost::add_resource_load_observer].
+pub trait ResourceLoadObserverCallback = 'static + Send + FnMut(Browser, ResourceLoadReport);
+
+impl BrowserHost {
+    /// Call `callback` with a report of every request made by this browser
+    /// that finishes, until the returned [Registration] is dropped.
+    ///
+    /// `callback` will be called on the browser process UI thread. Reports
+    /// start once the `Network` domain has been enabled, which happens
+    /// asynchronously.
+    pub fn add_resource_load_observer(&self, callback: impl ResourceLoadObserverCallback) -> Registration {
+        let registration = self.add_dev_tools_message_observer(DevToolsMessageObserver::new(ResourceLoadObserver {
+            callback,
+            pending: HashMap::new(),
+        }));
+        self.call_dev_tools_method_unit("Network.enable", None, |_| ());
+        registration
+    }
+}
+
+struct PendingLoad {
+    report: ResourceLoadReport,
+    /// Monotonic DevTools timestamp of the start, in seconds.
+    start: f64,
+    /// Monotonic DevTools timestamp of the response headers, in seconds.
+    headers_received: Option<f64>,
+}
+
+struct ResourceLoadObserver<F: ResourceLoadObserverCallback> {
+    callback: F,
+    /// Requests in flight, keyed by DevTools request id.
+    pending: HashMap<String, PendingLoad>,
+}
+
+fn seconds(seconds: f64) -> Option<Duration> {
+    if seconds >= 0.0 && seconds.is_finite() {
+        Some(Duration::from_secs_f64(seconds))
+    } else {
+        None
+    }
+}
+
+/// Converts the `timing` object of a DevTools `Response`. Offsets are in
+/// milliseconds since `requestTime`, and -1 for phases that didn't happen.
+fn resource_timing(timing: &HashMap<String, StoredValue>) -> (ResourceTiming, f64) {
+    let request_time = get_f64(timing, "requestTime");
+    let offset = |key: &str| match timing.get(key) {
+        Some(StoredValue::Int(ms)) if *ms >= 0 => Some(*ms as f64),
+        Some(StoredValue::Double(ms)) if *ms >= 0.0 => Some(*ms),
+        _ => None,
+    };
+    let phase = |start: &str, end: &str| match (offset(start), offset(end)) {
+        (Some(start), Some(end)) => seconds((end - start) / 1000.0),
+        _ => None,
+    };
+    let first_phase = offset("dnsStart").or_else(|| offset("connectStart")).or_else(|| offset("sendStart"));
+    let headers_received = offset("receiveHeadersEnd").unwrap_or(0.0);
+    (
+        ResourceTiming {
+            blocked: first_phase.and_then(|start| seconds(start / 1000.0)),
+            dns: phase("dnsStart", "dnsEnd"),
+            connect: phase("connectStart", "connectEnd"),
+            ssl: phase("sslStart", "sslEnd"),
+            send: phase("sendStart", "sendEnd"),
+            wait: phase("sendEnd", "receiveHeadersEnd"),
+            receive: None,
+        },
+        request_time + headers_received / 1000.0,
+    )
+}
+
+impl<F: ResourceLoadObserverCallback> ResourceLoadObserver<F> {
+    fn finish(&mut self, browser: Browser, params: &HashMap<String, StoredValue>, status: URLRequestStatus) {
+        let mut pending = match self.pending.remove(&get_string(params, "requestId")) {
+            Some(pending) => pending,
+            None => return,
+        };
+        let timestamp = get_f64(params, "timestamp");
+        pending.report.status = status;
+        pending.report.duration = seconds(timestamp - pending.start);
+        if let (Some(timing), Some(headers_received)) = (&mut pending.report.timing, pending.headers_received) {
+            timing.receive = seconds(timestamp - headers_received);
+        }
+        (self.callback)(browser, pending.report);
+    }
+}
+
+impl<F: ResourceLoadObserverCallback> DevToolsMessageObserverCallbacks for ResourceLoadObserver<F> {
+    fn on_dev_tools_event(&mut self, browser: Browser, method: &str, params: &[u8]) {
+        let params = match parse_json_dictionary(params) {
+            Some(params) => params,
+            None => return,
+        };
+        match method {
+            "Network.requestWillBeSent" => {
+                let request_id = get_string(&params, "requestId");
+                let request = get_dictionary(&params, "request").unwrap_or_default();
+                let mut report = ResourceLoadReport::new(get_string(&request, "url"), get_string(&request, "method"));
+                report.started = seconds(get_f64(&params, "wallTime")).map(|since_epoch| UNIX_EPOCH + since_epoch);
+                let start = match self.pending.remove(&request_id) {
+                    // Redirects reuse the request id; keep the original start.
+                    Some(redirected) => {
+                        report.started = redirected.report.started;
+                        redirected.start
+                    }
+                    None => get_f64(&params, "timestamp"),
+                };
+                self.pending.insert(request_id, PendingLoad {
+                    report,
+                    start,
+                    headers_received: None,
+                });
+            }
+            "Network.responseReceived" => {
+                let pending = match self.pending.get_mut(&get_string(&params, "requestId")) {
+                    Some(pending) => pending,
+                    None => return,
+                };
+                let response = get_dictionary(&params, "response").unwrap_or_default();
+                let report = &mut pending.report;
+                report.http_status = get_i32(&response, "status");
+                report.mime_type = get_string(&response, "mimeType");
+                report.from_cache = get_bool(&response, "fromDiskCache");
+                report.protocol = Some(get_string(&response, "protocol")).filter(|protocol| !protocol.is_empty());
+                report.remote_address = get_string(&response, "remoteIPAddress")
+                    .trim_matches(|c| c == '[' || c == ']')
+                    .parse::<IpAddr>()
+                    .ok()
+                    .map(|ip| SocketAddr::new(ip, get_i32(&response, "remotePort") as u16));
+                if let Some(timing) = get_dictionary(&response, "timing") {
+                    let (timing, headers_received) = resource_timing(&timing);
+                    report.timing = Some(timing);
+                    pending.headers_received = Some(headers_received);
+                }
+            }
+            "Network.loadingFinished" => {
+                if let Some(pending) = self.pending.get_mut(&get_string(&params, "requestId")) {
+                    pending.report.received_bytes = get_f64(&params, "encodedDataLength") as i64;
+                }
+                self.finish(browser, &params, URLRequestStatus::Success);
+            }
+            "Network.loadingFailed" => {
+                if let Some(pending) = self.pending.get_mut(&get_string(&params, "requestId")) {
+                    pending.report.error_text = Some(get_string(&params, "errorText"));
+                }
+                let status = if get_bool(&params, "canceled") {
+                    URLRequestStatus::Canceled
+                } else {
+                    URLRequestStatus::Failed
+                };
+                self.finish(browser, &params, status);
+            }
+            _ => (),
+        }
+    }
+}
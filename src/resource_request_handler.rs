@@ -159,6 +159,189 @@ pub trait ResourceRequestHandlerCallbacks: 'static + Sync + Send {
     }
 }
 
+/// Base for [ResourceRequestHandlerCallbacks] implementations that wrap another one.
+///
+/// Helpers that add behavior to an application's handler, like
+/// [ResourceLoadReporter](crate::resource_load_report::ResourceLoadReporter),
+/// implement a decorator trait instead of the callbacks trait itself. Every
+/// function of a decorator calls the same function of [Self::inner] unless it
+/// is overridden, and the callbacks trait is implemented for all decorators,
+/// so a wrapper only implements the functions it changes:
+///
+/// ```rust,ignore
+/// struct Logging<C>(C);
+///
+/// impl<C: ResourceRequestHandlerCallbacks> ResourceRequestHandlerDecorator for Logging<C> {
+///     type Inner = C;
+///     fn inner(&self) -> &C {
+///         &self.0
+///     }
+///     fn on_resource_response(&self, browser: Option<Browser>, frame: Option<Frame>, request: Request, response: Response) {
+///         log::info!("{} {}", response.get_status(), request.get_url());
+///         self.0.on_resource_response(browser, frame, request, response)
+///     }
+/// }
+/// ```
+///
+/// The same pattern is used for the other handlers that are commonly wrapped,
+/// e.g. [DisplayHandlerDecorator](crate::client::display_handler::DisplayHandlerDecorator).
+/// Functions added to a callbacks trait have to be added to its decorator
+/// trait and to the implementation of the callbacks trait for decorators as
+/// well, or wrapped handlers silently stop receiving them.
+pub trait ResourceRequestHandlerDecorator: 'static + Sync + Send {
+    type Inner: ResourceRequestHandlerCallbacks;
+    /// Returns the wrapped callbacks.
+    fn inner(&self) -> &Self::Inner;
+    fn get_cookie_access_filter(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+    ) -> Option<CookieAccessFilter> {
+        self.inner().get_cookie_access_filter(browser, frame, request)
+    }
+    fn on_before_resource_load(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        callback: RequestCallback,
+    ) -> ReturnValue {
+        self.inner().on_before_resource_load(browser, frame, request, callback)
+    }
+    fn get_resource_handler(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+    ) -> Option<ResourceHandler> {
+        self.inner().get_resource_handler(browser, frame, request)
+    }
+    fn on_resource_redirect(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        response: Response,
+        new_url: &mut String,
+    ) {
+        self.inner().on_resource_redirect(browser, frame, request, response, new_url)
+    }
+    fn on_resource_response(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        response: Response,
+    ) {
+        self.inner().on_resource_response(browser, frame, request, response)
+    }
+    fn get_resource_response_filter(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        response: Response,
+    ) -> Option<ResponseFilter> {
+        self.inner().get_resource_response_filter(browser, frame, request, response)
+    }
+    fn on_resource_load_complete(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        response: Response,
+        status: URLRequestStatus,
+        received_content_length: i64,
+    ) {
+        self.inner().on_resource_load_complete(browser, frame, request, response, status, received_content_length)
+    }
+    fn on_protocol_execution(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+    ) -> bool {
+        self.inner().on_protocol_execution(browser, frame, request)
+    }
+}
+
+// Every function of ResourceRequestHandlerCallbacks has to be forwarded here.
+impl<D: ResourceRequestHandlerDecorator> ResourceRequestHandlerCallbacks for D {
+    fn get_cookie_access_filter(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+    ) -> Option<CookieAccessFilter> {
+        ResourceRequestHandlerDecorator::get_cookie_access_filter(self, browser, frame, request)
+    }
+    fn on_before_resource_load(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        callback: RequestCallback,
+    ) -> ReturnValue {
+        ResourceRequestHandlerDecorator::on_before_resource_load(self, browser, frame, request, callback)
+    }
+    fn get_resource_handler(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+    ) -> Option<ResourceHandler> {
+        ResourceRequestHandlerDecorator::get_resource_handler(self, browser, frame, request)
+    }
+    fn on_resource_redirect(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        response: Response,
+        new_url: &mut String,
+    ) {
+        ResourceRequestHandlerDecorator::on_resource_redirect(self, browser, frame, request, response, new_url)
+    }
+    fn on_resource_response(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        response: Response,
+    ) {
+        ResourceRequestHandlerDecorator::on_resource_response(self, browser, frame, request, response)
+    }
+    fn get_resource_response_filter(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        response: Response,
+    ) -> Option<ResponseFilter> {
+        ResourceRequestHandlerDecorator::get_resource_response_filter(self, browser, frame, request, response)
+    }
+    fn on_resource_load_complete(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+        response: Response,
+        status: URLRequestStatus,
+        received_content_length: i64,
+    ) {
+        ResourceRequestHandlerDecorator::on_resource_load_complete(self, browser, frame, request, response, status, received_content_length)
+    }
+    fn on_protocol_execution(
+        &self,
+        browser: Option<Browser>,
+        frame: Option<Frame>,
+        request: Request,
+    ) -> bool {
+        ResourceRequestHandlerDecorator::on_protocol_execution(self, browser, frame, request)
+    }
+}
+
 pub(crate) struct ResourceRequestHandlerWrapper(Box<dyn ResourceRequestHandlerCallbacks>);
 
 impl Wrapper for ResourceRequestHandlerWrapper {
@@ -6,7 +6,7 @@ bitflags!{
     /// Configuration options for registering a custom scheme.
     /// These values are used when calling [SchemeRegistrar::add_custom_scheme].
     pub struct SchemeOptions: crate::CEnumType {
-        /// If `Standard` is set the scheme will be treated as a
+        /// If `STANDARD` is set the scheme will be treated as a
         /// standard scheme. Standard schemes are subject to URL canonicalization and
         /// parsing rules as defined in the [Common Internet Scheme Syntax RFC 1738](http://www.ietf.org/rfc/rfc1738.txt)
         /// Section 3.1
@@ -29,7 +29,7 @@ bitflags!{
         /// is. For example, "scheme:///some%20text" will remain the same. Non-standard
         /// scheme URLs cannot be used as a target for form submission.
         const STANDARD = cef_scheme_options_t::CEF_SCHEME_OPTION_STANDARD.0;
-        /// If `Local` is set the scheme will be treated with the same
+        /// If `LOCAL` is set the scheme will be treated with the same
         /// security rules as those applied to "file" URLs. Normal pages cannot link to
         /// or access local URLs. Also, by default, local URLs can only perform
         /// XMLHttpRequest calls to the same URL (origin + path) that originated the
@@ -39,28 +39,61 @@ bitflags!{
         /// origins set the CefSettings.universal_access_from_file_urls_allowed value
         /// to true.
         const LOCAL = cef_scheme_options_t::CEF_SCHEME_OPTION_LOCAL.0;
-        /// If `DisplayIsolated` is set the scheme can only be
+        /// If `DISPLAY_ISOLATED` is set the scheme can only be
         /// displayed from other content hosted with the same scheme. For example,
         /// pages in other origins cannot create iframes or hyperlinks to URLs with the
         /// scheme. For schemes that must be accessible from other schemes don't set
-        /// this, set `CORSEnabled`, and use CORS
+        /// this, set `CORS_ENABLED`, and use CORS
         /// "Access-Control-Allow-Origin" headers to further restrict access.
         const DISPLAY_ISOLATED = cef_scheme_options_t::CEF_SCHEME_OPTION_DISPLAY_ISOLATED.0;
-        /// If `Secure` is set the scheme will be treated with the same
+        /// If `SECURE` is set the scheme will be treated with the same
         /// security rules as those applied to "https" URLs. For example, loading this
         /// scheme from other secure schemes will not trigger mixed content warnings.
         const SECURE = cef_scheme_options_t::CEF_SCHEME_OPTION_SECURE.0;
-        /// If `CORSEnabled` is set the scheme can be sent CORS
+        /// If `CORS_ENABLED` is set the scheme can be sent CORS
         /// requests. This value should be set in most cases where
-        /// `Standard` is set.
+        /// `STANDARD` is set.
         const CORS_ENABLED = cef_scheme_options_t::CEF_SCHEME_OPTION_CORS_ENABLED.0;
-        /// If `CSPBypassing` is set the scheme can bypass Content-
+        /// If `CSP_BYPASSING` is set the scheme can bypass Content-
         /// Security-Policy (CSP) checks. This value should not be set in most cases
-        /// where `Standard` is set.
+        /// where `STANDARD` is set.
         const CSP_BYPASSING = cef_scheme_options_t::CEF_SCHEME_OPTION_CSP_BYPASSING.0;
-        /// If `FetchEnabled` is set the scheme can perform Fetch API
-        /// requests.
+        /// If `FETCH_ENABLED` is set the scheme can perform Fetch API
+        /// requests. Without it, `fetch()` to URLs of the scheme fails with a
+        /// network error. Requires `STANDARD` and `CORS_ENABLED` to work from
+        /// pages of other origins.
         const FETCH_ENABLED = cef_scheme_options_t::CEF_SCHEME_OPTION_FETCH_ENABLED.0;
+        /// Options for a scheme serving an application's own UI, e.g.
+        /// `app://`: its pages behave like https pages of their own origin and
+        /// can use XMLHttpRequest and `fetch()`, including to and from other
+        /// origins that allow it with CORS headers.
+        const APP = Self::STANDARD.bits | Self::SECURE.bits | Self::CORS_ENABLED.bits | Self::FETCH_ENABLED.bits;
+    }
+}
+
+impl SchemeOptions {
+    /// Returns descriptions of option combinations that don't work as one would
+    /// expect, which Chromium accepts without complaint.
+    pub fn problems(self) -> Vec<&'static str> {
+        let mut problems = Vec::new();
+        if !self.contains(SchemeOptions::STANDARD) {
+            if self.intersects(SchemeOptions::CORS_ENABLED | SchemeOptions::FETCH_ENABLED) {
+                problems.push("CORS and fetch requests need a STANDARD scheme, as non-standard URLs have no origin");
+            }
+            if self.contains(SchemeOptions::SECURE) {
+                problems.push("SECURE has no effect without STANDARD, as non-standard URLs have no origin");
+            }
+        }
+        if self.contains(SchemeOptions::FETCH_ENABLED) && !self.contains(SchemeOptions::CORS_ENABLED) {
+            problems.push("FETCH_ENABLED without CORS_ENABLED only allows fetch() from pages of the same origin");
+        }
+        if self.contains(SchemeOptions::LOCAL | SchemeOptions::SECURE) {
+            problems.push("LOCAL and SECURE conflict; LOCAL pages are treated like file:// URLs");
+        }
+        if self.contains(SchemeOptions::CSP_BYPASSING | SchemeOptions::STANDARD) {
+            problems.push("CSP_BYPASSING on a STANDARD scheme lets pages of any origin load its content despite their CSP");
+        }
+        problems
     }
 }
 
@@ -83,7 +116,13 @@ impl SchemeRegistrar {
     /// This function may be called on any thread. It should only be called once
     /// per unique `scheme_name` value. If `scheme_name` is already registered or
     /// if an error occurs this function will return false.
+    ///
+    /// Suspicious `options` (see [SchemeOptions::problems]) are logged as
+    /// warnings.
     pub fn add_custom_scheme(&self, scheme_name: &str, options: SchemeOptions) -> bool {
+        for problem in options.problems() {
+            log::warn!("Custom scheme {:?}: {}", scheme_name, problem);
+        }
         unsafe {
             ((*self.0).add_custom_scheme.unwrap())(
                 self.0,
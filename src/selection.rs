@@ -0,0 +1,164 @@
+//! The page's text selection in windowless mode.
+//!
+//! Windowless browsers report selection changes to
+//! [RenderHandlerCallbacks::on_text_selection_changed] and the caret of an
+//! IME composition to
+//! [RenderHandlerCallbacks::on_ime_composition_range_changed], but CEF has
+//! no way to ask for either later. A [SelectionTracker] wraps the render
+//! handler of the browsers and remembers the last reported values, so a copy
+//! button or annotation tool can read the selection when it's clicked:
+//!
+//! ```rust,ignore
+//! let tracker = SelectionTracker::new();
+//! let render_handler = tracker.render_handler(callbacks);
+//! // Later, on the UI thread:
+//! if let Some(selection) = tracker.get_selection(&browser) {
+//!     annotate(&selection.text, selection.range);
+//! }
+//! ```
+//!
+//! [select_range] changes the selection of the focused text field.
+
+use crate::{
+    browser::Browser,
+    client::render_handler::{RenderHandler, RenderHandlerCallbacks, RenderHandlerDecorator},
+    ime,
+    values::{Range, Rect},
+};
+use parking_lot::Mutex;
+use std::{collections::HashMap, sync::Arc};
+
+/// The text selection of a browser, as last reported by CEF.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextSelection {
+    /// The selected text, empty if nothing is selected.
+    pub text: String,
+    /// The selected range in UTF-16 code units. If it's empty, it's the
+    /// position of the caret.
+    pub range: Range,
+}
+
+impl TextSelection {
+    /// Returns true if no text is selected.
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+}
+
+#[derive(Default)]
+struct BrowserSelection {
+    selection: Option<TextSelection>,
+    composition_bounds: Option<Rect>,
+}
+
+/// Remembers the text selection reported to the render handlers it creates.
+/// Clones share the same state.
+#[derive(Clone, Default)]
+pub struct SelectionTracker {
+    browsers: Arc<Mutex<HashMap<i32, BrowserSelection>>>,
+}
+
+impl SelectionTracker {
+    pub fn new() -> SelectionTracker {
+        SelectionTracker::default()
+    }
+    /// Create a [RenderHandler] that records the selection before calling
+    /// into `callbacks`.
+    pub fn render_handler<C: RenderHandlerCallbacks>(&self, callbacks: C) -> RenderHandler {
+        RenderHandler::new(SelectionRenderHandler {
+            tracker: self.clone(),
+            inner: callbacks,
+        })
+    }
+    /// Returns the last selection reported for `browser`, or `None` if none
+    /// was reported yet.
+    pub fn get_selection(&self, browser: &Browser) -> Option<TextSelection> {
+        self.browsers
+            .lock()
+            .get(&browser.get_identifier())
+            .and_then(|browser| browser.selection.clone())
+    }
+    /// Returns the selected text of `browser`, or an empty string if nothing
+    /// is selected.
+    pub fn get_selected_text(&self, browser: &Browser) -> String {
+        self.get_selection(browser).map(|selection| selection.text).unwrap_or_default()
+    }
+    /// Returns the bounds of the current IME composition of `browser` in view
+    /// coordinates, or `None` if there's no composition.
+    pub fn get_composition_bounds(&self, browser: &Browser) -> Option<Rect> {
+        self.browsers
+            .lock()
+            .get(&browser.get_identifier())
+            .and_then(|browser| browser.composition_bounds)
+    }
+    /// Forget the selection of `browser`, e.g. once it was closed.
+    pub fn remove(&self, browser: &Browser) {
+        self.browsers.lock().remove(&browser.get_identifier());
+    }
+}
+
+/// Select `range`, in UTF-16 code units, in the text field focused in
+/// `browser`, e.g. to restore a selection reported by a [SelectionTracker].
+/// Does nothing if no text field is focused. The change is reported to the
+/// render handler once the renderer applied it.
+pub fn select_range(browser: &Browser, range: Range) {
+    let frame = match browser.get_focused_frame() {
+        Some(frame) => frame,
+        None => return,
+    };
+    let code = format!(
+        "(function() {{\
+            var element = document.activeElement;\
+            if (element && typeof element.setSelectionRange === 'function') {{\
+                element.setSelectionRange({}, {});\
+            }}\
+        }})();",
+        range.from.max(0),
+        range.to.max(range.from).max(0),
+    );
+    frame.execute_java_script(&code, "", 0);
+}
+
+struct SelectionRenderHandler<C: RenderHandlerCallbacks> {
+    tracker: SelectionTracker,
+    inner: C,
+}
+
+impl<C: RenderHandlerCallbacks> RenderHandlerDecorator for SelectionRenderHandler<C> {
+    type Inner = C;
+    fn inner(&self) -> &C {
+        &self.inner
+    }
+    fn on_ime_composition_range_changed(
+        &self,
+        browser: Browser,
+        selected_range: Range,
+        character_bounds: &[Rect],
+    ) {
+        self.tracker
+            .browsers
+            .lock()
+            .entry(browser.get_identifier())
+            .or_default()
+            .composition_bounds = ime::composition_bounds(character_bounds);
+        self.inner.on_ime_composition_range_changed(browser, selected_range, character_bounds)
+    }
+
+    fn on_text_selection_changed(
+        &self,
+        browser: Browser,
+        selected_text: Option<&str>,
+        selected_range: Range,
+    ) {
+        self.tracker
+            .browsers
+            .lock()
+            .entry(browser.get_identifier())
+            .or_default()
+            .selection = Some(TextSelection {
+            text: selected_text.unwrap_or_default().to_owned(),
+            range: selected_range,
+        });
+        self.inner.on_text_selection_changed(browser, selected_text, selected_range)
+    }
+}
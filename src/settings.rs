@@ -1,6 +1,7 @@
 use std::os::raw::c_int;
 use crate::{
     color::Color,
+    cookie::CookieableSchemes,
     string::CefString,
 };
 use cef_sys::{cef_log_severity_t, cef_settings_t};
@@ -32,6 +33,17 @@ impl LogSeverity {
     pub unsafe fn from_unchecked(c: crate::CEnumType) -> Self {
         std::mem::transmute(c)
     }
+    /// Returns the `log` crate level matching this severity, e.g. to forward
+    /// console messages, or `None` for [LogSeverity::Disable].
+    pub fn to_log_level(self) -> Option<log::Level> {
+        match self {
+            LogSeverity::Default | LogSeverity::Info => Some(log::Level::Info),
+            LogSeverity::Verbose => Some(log::Level::Debug),
+            LogSeverity::Warning => Some(log::Level::Warn),
+            LogSeverity::Error | LogSeverity::Fatal => Some(log::Level::Error),
+            LogSeverity::Disable => None,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -210,6 +222,11 @@ pub struct Settings {
     /// will be an empty string and the file will be treated as an untrusted
     /// file when the GUID is empty.
     pub application_client_id_for_file_scanning: Option<Uuid>,
+    /// Schemes that can store cookies in the global cookie manager. Applied
+    /// right after initialization, before any cookies are accessed. Can be
+    /// overridden for individual [RequestContext] instances via
+    /// [RequestContextBuilder::cookieable_schemes].
+    pub cookieable_schemes: Option<CookieableSchemes>,
 }
 
 impl Settings {
@@ -242,6 +259,7 @@ impl Settings {
             background_color: Color::wrap(0),
             accept_language_list: None,
             application_client_id_for_file_scanning: None,
+            cookieable_schemes: None,
         }
     }
     pub(crate) fn to_cef(&self, use_sandbox: bool) -> Result<cef_settings_t, std::io::Error> {
@@ -387,6 +405,10 @@ impl Settings {
         self.application_client_id_for_file_scanning = Some(application_client_id_for_file_scanning.into());
         self
     }
+    pub fn cookieable_schemes(mut self, cookieable_schemes: CookieableSchemes) -> Self {
+        self.cookieable_schemes = Some(cookieable_schemes);
+        self
+    }
 }
 
 pub(crate) unsafe fn drop_settings(settings: cef_settings_t) {
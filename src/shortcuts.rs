@@ -0,0 +1,205 @@
+//! Application keyboard shortcuts in browsers.
+//!
+//! Keys pressed in a browser go to the page unless
+//! [KeyboardHandlerCallbacks::on_pre_key_event] claims them. A
+//! [ShortcutHandler] wraps an existing implementation and claims the key
+//! presses of the registered [Shortcut]s, so the page never sees them:
+//!
+//! ```rust,ignore
+//! let keyboard_handler = ShortcutHandler::new(callbacks)
+//!     .on(Shortcut::new(WindowsKeyCode::R).primary(), |browser| browser.reload(false))
+//!     .on(Shortcut::new(WindowsKeyCode::F11), |browser| toggle_fullscreen(browser))
+//!     .build();
+//! ```
+
+use crate::{
+    browser::Browser,
+    client::keyboard_handler::{KeyboardHandler, KeyboardHandlerCallbacks, KeyboardHandlerDecorator},
+    events::{EventFlags, KeyEvent, WindowsKeyCode},
+};
+
+/// A key together with the modifier keys that must be held down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+    pub key: WindowsKeyCode,
+    /// Only [EventFlags::SHIFT_DOWN], [EventFlags::CONTROL_DOWN],
+    /// [EventFlags::ALT_DOWN] and [EventFlags::COMMAND_DOWN] are compared.
+    pub modifiers: EventFlags,
+}
+
+impl Shortcut {
+    /// A shortcut for `key` without modifiers.
+    pub fn new(key: WindowsKeyCode) -> Shortcut {
+        Shortcut {
+            key,
+            modifiers: EventFlags::empty(),
+        }
+    }
+    pub fn shift(mut self) -> Shortcut {
+        self.modifiers |= EventFlags::SHIFT_DOWN;
+        self
+    }
+    pub fn ctrl(mut self) -> Shortcut {
+        self.modifiers |= EventFlags::CONTROL_DOWN;
+        self
+    }
+    pub fn alt(mut self) -> Shortcut {
+        self.modifiers |= EventFlags::ALT_DOWN;
+        self
+    }
+    /// Requires the Command key, which only exists on macOS.
+    pub fn command(mut self) -> Shortcut {
+        self.modifiers |= EventFlags::COMMAND_DOWN;
+        self
+    }
+    /// Requires the platform's usual modifier for shortcuts: Command on macOS
+    /// and Ctrl elsewhere.
+    pub fn primary(self) -> Shortcut {
+        if cfg!(target_os = "macos") {
+            self.command()
+        } else {
+            self.ctrl()
+        }
+    }
+    /// Returns true if `event` presses this shortcut. Key releases and typed
+    /// characters never match.
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        match *event {
+            KeyEvent::RawKeyDown { modifiers, windows_key_code, .. }
+            | KeyEvent::KeyDown { modifiers, windows_key_code, .. } => {
+                let mask = modifier_mask();
+                windows_key_code == self.key && modifiers & mask == self.modifiers & mask
+            }
+            KeyEvent::KeyUp { .. } | KeyEvent::Char { .. } => false,
+        }
+    }
+}
+
+/// Modifiers compared when matching key events.
+fn modifier_mask() -> EventFlags {
+    EventFlags::SHIFT_DOWN | EventFlags::CONTROL_DOWN | EventFlags::ALT_DOWN | EventFlags::COMMAND_DOWN
+}
+
+/// Function run when a shortcut is pressed, on the browser process UI thread.
+pub trait ShortcutAction = 'static + Send + FnMut(&Browser);
+
+/// [KeyboardHandlerCallbacks] wrapper running actions for shortcuts instead
+/// of sending them to the page.
+pub struct ShortcutHandler<C: KeyboardHandlerCallbacks> {
+    inner: C,
+    shortcuts: Vec<(Shortcut, Box<dyn ShortcutAction>)>,
+}
+
+impl<C: KeyboardHandlerCallbacks> ShortcutHandler<C> {
+    /// Handle shortcuts before any other key event reaches `inner`.
+    pub fn new(inner: C) -> Self {
+        ShortcutHandler {
+            inner,
+            shortcuts: Vec::new(),
+        }
+    }
+    /// Run `action` when `shortcut` is pressed. If several actions are
+    /// registered for a shortcut, the first one runs.
+    pub fn on(mut self, shortcut: Shortcut, action: impl ShortcutAction) -> Self {
+        self.shortcuts.push((shortcut, Box::new(action)));
+        self
+    }
+    pub fn build(self) -> KeyboardHandler {
+        KeyboardHandler::new(self)
+    }
+}
+
+impl<C: KeyboardHandlerCallbacks> KeyboardHandlerDecorator for ShortcutHandler<C> {
+    type Inner = C;
+    fn inner(&mut self) -> &mut C {
+        &mut self.inner
+    }
+    fn on_pre_key_event(
+        &mut self,
+        browser: Browser,
+        event: KeyEvent,
+        is_keyboard_shortcut: &mut bool,
+    ) -> bool {
+        let action = self
+            .shortcuts
+            .iter_mut()
+            .find(|(shortcut, _)| shortcut.matches(&event));
+        if let Some((_, action)) = action {
+            action(&browser);
+            // Chromium drops the character and release events of a handled
+            // key press itself.
+            return true;
+        }
+        self.inner.on_pre_key_event(browser, event, is_keyboard_shortcut)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_key_down(key: WindowsKeyCode, modifiers: EventFlags) -> KeyEvent {
+        KeyEvent::RawKeyDown {
+            modifiers,
+            windows_key_code: key,
+            native_key_code: 0,
+            is_system_key: false,
+            focus_on_editable_field: false,
+        }
+    }
+
+    #[test]
+    fn key_presses_with_the_same_modifiers_match() {
+        let shortcut = Shortcut::new(WindowsKeyCode::R).ctrl().shift();
+        assert!(shortcut.matches(&raw_key_down(WindowsKeyCode::R, EventFlags::CONTROL_DOWN | EventFlags::SHIFT_DOWN)));
+        assert!(shortcut.matches(&KeyEvent::KeyDown {
+            modifiers: EventFlags::CONTROL_DOWN | EventFlags::SHIFT_DOWN,
+            windows_key_code: WindowsKeyCode::R,
+            native_key_code: 0,
+            is_system_key: false,
+            focus_on_editable_field: false,
+        }));
+    }
+
+    #[test]
+    fn other_keys_and_modifiers_dont_match() {
+        let shortcut = Shortcut::new(WindowsKeyCode::R).ctrl();
+        assert!(!shortcut.matches(&raw_key_down(WindowsKeyCode::A, EventFlags::CONTROL_DOWN)));
+        assert!(!shortcut.matches(&raw_key_down(WindowsKeyCode::R, EventFlags::empty())));
+        assert!(!shortcut.matches(&raw_key_down(WindowsKeyCode::R, EventFlags::CONTROL_DOWN | EventFlags::ALT_DOWN)));
+        assert!(!Shortcut::new(WindowsKeyCode::F11).matches(&raw_key_down(WindowsKeyCode::F11, EventFlags::SHIFT_DOWN)));
+    }
+
+    #[test]
+    fn lock_keys_and_mouse_buttons_are_ignored() {
+        let shortcut = Shortcut::new(WindowsKeyCode::F11);
+        let modifiers = EventFlags::CAPS_LOCK_ON | EventFlags::NUM_LOCK_ON | EventFlags::LEFT_MOUSE_BUTTON;
+        assert!(shortcut.matches(&raw_key_down(WindowsKeyCode::F11, modifiers)));
+    }
+
+    #[test]
+    fn releases_and_characters_dont_match() {
+        let shortcut = Shortcut::new(WindowsKeyCode::R);
+        assert!(!shortcut.matches(&KeyEvent::KeyUp {
+            modifiers: EventFlags::empty(),
+            windows_key_code: WindowsKeyCode::R,
+            native_key_code: 0,
+            is_system_key: false,
+            focus_on_editable_field: false,
+        }));
+        assert!(!shortcut.matches(&KeyEvent::Char {
+            modifiers: EventFlags::empty(),
+            char: 'r',
+        }));
+    }
+
+    #[test]
+    fn primary_is_the_platform_modifier() {
+        let expected = if cfg!(target_os = "macos") {
+            EventFlags::COMMAND_DOWN
+        } else {
+            EventFlags::CONTROL_DOWN
+        };
+        assert_eq!(Shortcut::new(WindowsKeyCode::R).primary().modifiers, expected);
+    }
+}
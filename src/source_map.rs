@@ -0,0 +1,448 @@
+//! Reporting JavaScript positions in the original sources of minified code.
+//!
+//! Console messages and uncaught exceptions refer to the scripts as the
+//! browser runs them, which for a bundled front-end means line 1 of a
+//! minified file. A [SourceMapResolver] looks the positions up in the
+//! scripts' source maps, which the application provides through a loader,
+//! e.g. from the assets it serves:
+//!
+//! ```rust,ignore
+//! let resolver = SourceMapResolver::new(|script_url| {
+//!     let path = script_url.strip_prefix("app://ui/")?;
+//!     ASSETS.get(&format!("{}.map", path)).map(|map| String::from_utf8_lossy(map).into_owned())
+//! });
+//! let display_handler = resolver.display_handler(MyDisplayHandler);
+//! ```
+//!
+//! Only source map version 3 without index sections is supported.
+
+use crate::{
+    browser::Browser,
+    client::display_handler::{DisplayHandler, DisplayHandlerCallbacks, DisplayHandlerDecorator},
+    settings::LogSeverity,
+    v8context::{V8Exception, V8StackFrame},
+    values::StoredValue,
+};
+use parking_lot::Mutex;
+use std::{collections::HashMap, convert::TryFrom, fmt, sync::Arc};
+
+/// Returns the source map of the script at the given URL as JSON, or `None`
+/// if it has none. Called once per script, on the thread reporting the first
+/// position in it, so it shouldn't block for long.
+pub trait SourceMapLoader = 'static + Send + Sync + Fn(&str) -> Option<String>;
+
+/// A position in an original source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OriginalPosition {
+    /// The source's URL or path, including the map's `sourceRoot`.
+    pub source: String,
+    /// 1-based line.
+    pub line: u32,
+    /// 1-based column.
+    pub column: u32,
+    /// The original name of the identifier at the position, if recorded.
+    pub name: Option<String>,
+}
+
+impl fmt::Display for OriginalPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.source, self.line, self.column)
+    }
+}
+
+/// Why [SourceMap::parse] failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceMapError {
+    InvalidJson,
+    UnsupportedVersion(i32),
+    /// Index maps with `sections` aren't supported.
+    Sections,
+    MissingField(&'static str),
+    InvalidMappings,
+}
+
+impl fmt::Display for SourceMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceMapError::InvalidJson => f.write_str("the source map isn't a JSON object"),
+            SourceMapError::UnsupportedVersion(version) => write!(f, "unsupported source map version {}", version),
+            SourceMapError::Sections => f.write_str("index source maps with sections aren't supported"),
+            SourceMapError::MissingField(field) => write!(f, "the source map has no valid {} field", field),
+            SourceMapError::InvalidMappings => f.write_str("the source map's mappings are malformed"),
+        }
+    }
+}
+
+impl std::error::Error for SourceMapError {}
+
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    generated_column: u32,
+    source: Option<(usize, u32, u32)>,
+    name: Option<usize>,
+}
+
+/// A parsed source map.
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    sources: Vec<String>,
+    names: Vec<String>,
+    /// Segments of each generated line, sorted by column.
+    lines: Vec<Vec<Segment>>,
+}
+
+impl SourceMap {
+    pub fn parse(json: &str) -> Result<SourceMap, SourceMapError> {
+        let map: HashMap<String, StoredValue> = match StoredValue::from_json(json) {
+            Some(StoredValue::Dictionary(map)) => map.into(),
+            _ => return Err(SourceMapError::InvalidJson),
+        };
+        if map.contains_key("sections") {
+            return Err(SourceMapError::Sections);
+        }
+        match map.get("version") {
+            Some(StoredValue::Int(3)) => (),
+            Some(StoredValue::Int(version)) => return Err(SourceMapError::UnsupportedVersion(*version)),
+            _ => return Err(SourceMapError::MissingField("version")),
+        }
+        let source_root = match map.get("sourceRoot") {
+            Some(StoredValue::String(root)) => root.as_str(),
+            _ => "",
+        };
+        let sources = strings(&map, "sources")
+            .ok_or(SourceMapError::MissingField("sources"))?
+            .into_iter()
+            .map(|source| join_source_root(source_root, source))
+            .collect::<Vec<_>>();
+        let names = strings(&map, "names").unwrap_or_default();
+        let mappings = match map.get("mappings") {
+            Some(StoredValue::String(mappings)) => mappings,
+            _ => return Err(SourceMapError::MissingField("mappings")),
+        };
+        let lines = decode_mappings(mappings, sources.len(), names.len()).ok_or(SourceMapError::InvalidMappings)?;
+        Ok(SourceMap { sources, names, lines })
+    }
+    /// Returns the original position of the 0-based `line` and `column` in
+    /// the generated script. Without a column, the first mapped position on
+    /// the line is returned.
+    pub fn lookup(&self, line: u32, column: Option<u32>) -> Option<OriginalPosition> {
+        let segments = self.lines.get(line as usize)?;
+        let segment = match column {
+            Some(column) => {
+                let index = segments.partition_point(|segment| segment.generated_column <= column);
+                segments.get(index.checked_sub(1)?)?
+            }
+            None => segments.iter().find(|segment| segment.source.is_some())?,
+        };
+        let (source, source_line, source_column) = segment.source?;
+        Some(OriginalPosition {
+            source: self.sources[source].clone(),
+            line: source_line + 1,
+            column: source_column + 1,
+            name: segment.name.map(|name| self.names[name].clone()),
+        })
+    }
+}
+
+fn strings(map: &HashMap<String, StoredValue>, key: &str) -> Option<Vec<String>> {
+    match map.get(key) {
+        Some(StoredValue::List(list)) => {
+            let list: Vec<StoredValue> = list.clone().into();
+            Some(
+                list.into_iter()
+                    .map(|value| match value {
+                        StoredValue::String(value) => value,
+                        _ => String::new(),
+                    })
+                    .collect(),
+            )
+        }
+        _ => None,
+    }
+}
+
+fn join_source_root(root: &str, source: String) -> String {
+    if root.is_empty() || source.starts_with('/') || source.contains("://") {
+        source
+    } else if root.ends_with('/') {
+        format!("{}{}", root, source)
+    } else {
+        format!("{}/{}", root, source)
+    }
+}
+
+fn decode_mappings(mappings: &str, source_count: usize, name_count: usize) -> Option<Vec<Vec<Segment>>> {
+    let mut lines = Vec::new();
+    // All fields but the generated column are relative to the previous
+    // segment across lines.
+    let (mut source, mut source_line, mut source_column, mut name) = (0i64, 0i64, 0i64, 0i64);
+    for line in mappings.split(';') {
+        let mut generated_column = 0i64;
+        let mut segments = Vec::new();
+        for segment in line.split(',').filter(|segment| !segment.is_empty()) {
+            let fields = decode_vlq(segment)?;
+            generated_column += fields[0];
+            let mut decoded = Segment {
+                generated_column: u32::try_from(generated_column).ok()?,
+                source: None,
+                name: None,
+            };
+            match fields.len() {
+                1 => (),
+                4 | 5 => {
+                    source += fields[1];
+                    source_line += fields[2];
+                    source_column += fields[3];
+                    let index = usize::try_from(source).ok().filter(|&index| index < source_count)?;
+                    decoded.source = Some((index, u32::try_from(source_line).ok()?, u32::try_from(source_column).ok()?));
+                    if fields.len() == 5 {
+                        name += fields[4];
+                        decoded.name = Some(usize::try_from(name).ok().filter(|&index| index < name_count)?);
+                    }
+                }
+                _ => return None,
+            }
+            segments.push(decoded);
+        }
+        segments.sort_by_key(|segment: &Segment| segment.generated_column);
+        lines.push(segments);
+    }
+    Some(lines)
+}
+
+fn decode_vlq(segment: &str) -> Option<Vec<i64>> {
+    let mut fields = Vec::new();
+    let (mut value, mut shift) = (0i64, 0u32);
+    for byte in segment.bytes() {
+        let digit = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        } as i64;
+        if shift > 32 {
+            return None;
+        }
+        value += (digit & 0b11111) << shift;
+        if digit & 0b100000 != 0 {
+            shift += 5;
+        } else {
+            fields.push(if value & 1 != 0 { -(value >> 1) } else { value >> 1 });
+            value = 0;
+            shift = 0;
+        }
+    }
+    if shift != 0 {
+        return None;
+    }
+    Some(fields)
+}
+
+/// Loads and caches the source maps of scripts and resolves positions in
+/// them.
+#[derive(Clone)]
+pub struct SourceMapResolver {
+    loader: Arc<dyn SourceMapLoader>,
+    maps: Arc<Mutex<HashMap<String, Option<Arc<SourceMap>>>>>,
+}
+
+impl SourceMapResolver {
+    pub fn new(loader: impl SourceMapLoader) -> SourceMapResolver {
+        SourceMapResolver {
+            loader: Arc::new(loader),
+            maps: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+    /// Returns the source map of `script_url`, loading it on first use.
+    /// Maps that fail to parse are logged once and treated as missing.
+    pub fn source_map(&self, script_url: &str) -> Option<Arc<SourceMap>> {
+        if let Some(map) = self.maps.lock().get(script_url) {
+            return map.clone();
+        }
+        let map = (self.loader)(script_url).and_then(|json| match SourceMap::parse(&json) {
+            Ok(map) => Some(Arc::new(map)),
+            Err(error) => {
+                log::warn!("Cannot use the source map of {}: {}", script_url, error);
+                None
+            }
+        });
+        self.maps.lock().insert(script_url.to_owned(), map.clone());
+        map
+    }
+    /// Resolve a 1-based `line` and 1-based `column`, as reported by CEF, in
+    /// `script_url`.
+    pub fn resolve(&self, script_url: &str, line: u32, column: Option<u32>) -> Option<OriginalPosition> {
+        let line = line.checked_sub(1)?;
+        let column = match column {
+            Some(column) => Some(column.checked_sub(1)?),
+            None => None,
+        };
+        self.source_map(script_url)?.lookup(line, column)
+    }
+    /// Returns the original position of `script_url:line:column` as a string,
+    /// or the position itself if it can't be resolved.
+    pub fn format_position(&self, script_url: &str, line: u32, column: Option<u32>) -> String {
+        match self.resolve(script_url, line, column) {
+            Some(position) => position.to_string(),
+            None => match column {
+                Some(column) => format!("{}:{}:{}", script_url, line, column),
+                None => format!("{}:{}", script_url, line),
+            },
+        }
+    }
+    pub fn resolve_stack_frame(&self, frame: &V8StackFrame) -> Option<OriginalPosition> {
+        self.resolve(
+            &frame.get_script_name_or_source_url(),
+            frame.get_line_number() as u32,
+            Some(frame.get_column() as u32),
+        )
+    }
+    /// Log an exception received in
+    /// [RenderProcessHandlerCallbacks::on_uncaught_exception](crate::render_process_handler::RenderProcessHandlerCallbacks::on_uncaught_exception)
+    /// as an error, with positions in the original sources.
+    pub fn log_uncaught_exception(&self, exception: &V8Exception, stack_trace: &[V8StackFrame]) {
+        let mut report = format!(
+            "Uncaught {} at {}",
+            exception.get_message(),
+            self.format_position(
+                &exception.get_script_resource_name(),
+                exception.get_line_number() as u32,
+                // The exception's column is 0-based, unlike those of stack frames.
+                Some(exception.get_start_column() as u32 + 1),
+            ),
+        );
+        for frame in stack_trace {
+            let function = frame.get_function_name();
+            let position = self.format_position(
+                &frame.get_script_name_or_source_url(),
+                frame.get_line_number() as u32,
+                Some(frame.get_column() as u32),
+            );
+            if function.is_empty() {
+                report.push_str(&format!("\n    at {}", position));
+            } else {
+                report.push_str(&format!("\n    at {} ({})", function, position));
+            }
+        }
+        log::error!("{}", report);
+    }
+    /// Create a [DisplayHandler] that logs console messages with positions in
+    /// the original sources before calling into `callbacks`. CEF reports only
+    /// the line of console messages, so on minified lines the position is
+    /// that of the line's first mapping.
+    pub fn display_handler<C: DisplayHandlerCallbacks>(&self, callbacks: C) -> DisplayHandler {
+        DisplayHandler::new(SourceMappedDisplayHandler {
+            resolver: self.clone(),
+            inner: callbacks,
+        })
+    }
+}
+
+struct SourceMappedDisplayHandler<C: DisplayHandlerCallbacks> {
+    resolver: SourceMapResolver,
+    inner: C,
+}
+
+impl<C: DisplayHandlerCallbacks> DisplayHandlerDecorator for SourceMappedDisplayHandler<C> {
+    type Inner = C;
+    fn inner(&mut self) -> &mut C {
+        &mut self.inner
+    }
+    fn on_console_message(
+        &mut self,
+        browser: Browser,
+        level: LogSeverity,
+        message: &str,
+        source: &str,
+        line: usize,
+    ) -> bool {
+        let position = self.resolver.format_position(source, line as u32, None);
+        let log_level = level.to_log_level().unwrap_or(log::Level::Info);
+        log::log!(log_level, "Console: {} ({})", message, position);
+        self.inner.on_console_message(browser, level, message, source, line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(mappings: &str) -> SourceMap {
+        SourceMap {
+            sources: vec!["src/app.ts".to_owned()],
+            names: vec!["render".to_owned()],
+            lines: decode_mappings(mappings, 1, 1).unwrap(),
+        }
+    }
+
+    fn position(line: u32, column: u32, name: Option<&str>) -> Option<OriginalPosition> {
+        Some(OriginalPosition {
+            source: "src/app.ts".to_owned(),
+            line,
+            column,
+            name: name.map(str::to_owned),
+        })
+    }
+
+    #[test]
+    fn vlq_decodes_signed_values() {
+        assert_eq!(decode_vlq("AAAA"), Some(vec![0, 0, 0, 0]));
+        assert_eq!(decode_vlq("CDeF"), Some(vec![1, -1, 15, -2]));
+        assert_eq!(decode_vlq("gBhB"), Some(vec![16, -16]));
+        assert_eq!(decode_vlq("w+BhkxH"), Some(vec![1000, -123456]));
+        assert_eq!(decode_vlq(""), Some(vec![]));
+    }
+
+    #[test]
+    fn vlq_rejects_invalid_digits() {
+        assert_eq!(decode_vlq("A!"), None);
+        assert_eq!(decode_vlq("A="), None);
+        // The continuation bit is set on the last digit.
+        assert_eq!(decode_vlq("Ag"), None);
+        // More digits than fit into 32 bits.
+        assert_eq!(decode_vlq("ggggggggC"), None);
+    }
+
+    #[test]
+    fn mappings_are_relative_to_the_previous_segment() {
+        let map = map("AAAA,IAAIA;CACA");
+        assert_eq!(map.lookup(0, Some(0)), position(1, 1, None));
+        assert_eq!(map.lookup(0, Some(3)), position(1, 1, None));
+        assert_eq!(map.lookup(0, Some(5)), position(1, 5, Some("render")));
+        assert_eq!(map.lookup(1, Some(1)), position(2, 5, None));
+    }
+
+    #[test]
+    fn lookups_before_the_first_segment_fail() {
+        let map = map("AAAA;CACA;");
+        assert_eq!(map.lookup(1, Some(0)), None);
+        assert_eq!(map.lookup(2, Some(0)), None);
+        assert_eq!(map.lookup(3, Some(0)), None);
+    }
+
+    #[test]
+    fn lines_without_column_use_the_first_mapped_segment() {
+        let map = map("A,GAAE");
+        assert_eq!(map.lookup(0, None), position(1, 3, None));
+    }
+
+    #[test]
+    fn invalid_mappings_are_rejected() {
+        assert!(decode_mappings("AA", 1, 1).is_none());
+        assert!(decode_mappings("AAAA", 0, 0).is_none());
+        assert!(decode_mappings("AAAAA", 1, 0).is_none());
+        assert!(decode_mappings("AADA", 1, 0).is_none());
+        assert!(decode_mappings("D", 1, 0).is_none());
+    }
+
+    #[test]
+    fn source_root_is_prepended_to_relative_sources() {
+        assert_eq!(join_source_root("", "a.ts".to_owned()), "a.ts");
+        assert_eq!(join_source_root("src", "a.ts".to_owned()), "src/a.ts");
+        assert_eq!(join_source_root("src/", "a.ts".to_owned()), "src/a.ts");
+        assert_eq!(join_source_root("src", "/a.ts".to_owned()), "/a.ts");
+        assert_eq!(join_source_root("src", "webpack://app/a.ts".to_owned()), "webpack://app/a.ts");
+    }
+}
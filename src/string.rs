@@ -9,7 +9,7 @@ use cef_sys::cef_string_map_value;
 use cef_sys::cef_string_map_append;
 use cef_sys::{
     cef_string_list_alloc, cef_string_list_append, cef_string_list_free, cef_string_list_size,
-    cef_string_list_t, cef_string_list_value, cef_string_t, cef_string_utf8_to_utf16,
+    cef_string_list_t, cef_string_list_value, cef_string_t,
     cef_string_visitor_t, cef_string_userfree_t, cef_string_userfree_utf16_free,
     cef_string_userfree_utf16_alloc,
 };
@@ -35,26 +35,30 @@ impl CefString {
         })
     }
     pub fn new(source: &str) -> Self {
-        let mut instance = unsafe { std::mem::zeroed() };
-        let len = source.len();
-        panic_if_cef_not_loaded();
-        unsafe {
-            cef_string_utf8_to_utf16(
-                source.as_ptr() as *const std::os::raw::c_char,
-                len,
-                &mut instance,
-            );
-        }
-        CefString(instance)
+        let mut instance = CefString::null();
+        instance.set_string(source);
+        instance
     }
+    /// Converts `str` without calling into libcef, so strings can be created
+    /// before it is loaded. The buffer is null-terminated like the ones CEF
+    /// allocates, and freed with `dtor` wherever the string ends up.
     pub fn set_string(&mut self, str: &str) {
-        panic_if_cef_not_loaded();
+        unsafe extern "C" fn free_utf16(str: *mut u16) {
+            libc::free(str as *mut libc::c_void);
+        }
+        let utf16: Vec<u16> = str.encode_utf16().chain(std::iter::once(0)).collect();
         unsafe {
-            cef_string_utf8_to_utf16(
-                str.as_ptr() as *const std::os::raw::c_char,
-                str.len(),
-                &mut self.0,
-            );
+            let buffer = libc::malloc(utf16.len() * mem::size_of::<u16>()) as *mut u16;
+            assert!(!buffer.is_null(), "out of memory");
+            std::ptr::copy_nonoverlapping(utf16.as_ptr(), buffer, utf16.len());
+            if let Some(dtor) = self.0.dtor {
+                dtor(self.0.str_);
+            }
+            self.0 = cef_string_t {
+                str_: buffer,
+                length: utf16.len() - 1,
+                dtor: Some(free_utf16),
+            };
         }
     }
     pub fn into_raw(self) -> cef_string_t {
@@ -125,6 +129,12 @@ impl std::fmt::Debug for CefString {
 /// De-allocate the structure the userfree points to without freeing the underlying buffers.
 unsafe fn unwrap_userfree(raw: cef_string_userfree_t) -> cef_string_t {
     unsafe extern "C" fn null_dtor(str: *mut u16) {}
+    #[cfg(feature = "testing")]
+    {
+        if let Some(cef_string) = crate::testing::take_userfree(raw) {
+            return cef_string;
+        }
+    }
     let cef_string = cef_string_t{..*raw};
     (*raw).dtor = Some(null_dtor);
     cef_string_userfree_utf16_free(raw);
@@ -565,3 +575,77 @@ impl<'a> Extend<(&'a CefString, &'a CefString)> for CefStringMap {
         }
     }
 }
+
+/// A string already converted to the UTF-16 encoding CEF uses for its strings.
+///
+/// Functions taking `&str` convert their arguments on every call. For large
+/// payloads passed repeatedly, like a script executed in every frame or a big
+/// `data:` URL, convert once and use the `_utf16` variants of those functions,
+/// which pass the buffer to CEF without copying it.
+#[derive(Clone, PartialEq, Eq, Hash, Default)]
+pub struct Utf16String(Vec<u16>);
+
+impl Utf16String {
+    pub fn new(s: &str) -> Utf16String {
+        Utf16String(s.encode_utf16().collect())
+    }
+    /// Returns the length in UTF-16 code units.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn as_slice(&self) -> &[u16] {
+        &self.0
+    }
+    /// Returns a `cef_string_t` borrowing this string, for passing to CEF
+    /// functions that copy their arguments. It must not outlive `self`.
+    pub(crate) fn as_cef_string(&self) -> cef_string_t {
+        cef_string_t {
+            str_: self.0.as_ptr() as *mut u16,
+            length: self.0.len(),
+            dtor: None,
+        }
+    }
+}
+
+impl From<&str> for Utf16String {
+    fn from(s: &str) -> Utf16String {
+        Utf16String::new(s)
+    }
+}
+
+impl From<Vec<u16>> for Utf16String {
+    fn from(units: Vec<u16>) -> Utf16String {
+        Utf16String(units)
+    }
+}
+
+impl std::fmt::Debug for Utf16String {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        <String as std::fmt::Debug>::fmt(&String::from_utf16_lossy(&self.0), f)
+    }
+}
+
+/// Returns `s` as a double-quoted string literal that is valid both in JSON and
+/// in JavaScript source.
+pub(crate) fn quote_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            // Line terminators in JavaScript, but not in JSON.
+            '\u{2028}' | '\u{2029}' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
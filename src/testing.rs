@@ -0,0 +1,870 @@
+//! Fake browsers for unit testing code written against this crate. Only
+//! available with the `testing` feature.
+//!
+//! A [FakeBrowser] provides [Browser], [Frame] and [BrowserHost] objects that
+//! are implemented in Rust instead of by CEF. Queries like [Frame::get_url] or
+//! [Browser::get_identifier] are answered from the state of the fake, and calls
+//! that would make the browser do something, like [Frame::load_url] or
+//! [BrowserHost::close_browser], are recorded as [FakeCall]s. An
+//! [EventDispatcher] sends the events a real browser would send to the handlers
+//! of a [Client]:
+//!
+//! ```rust,ignore
+//! let browser = FakeBrowser::new(1);
+//! let events = EventDispatcher::new(Client::new(MyClient::default()), &browser);
+//! events.after_created();
+//! events.navigate("https://example.com/");
+//! events.set_title("Example");
+//!
+//! // MyClient's load handler injects a script once the page has loaded.
+//! assert_eq!(browser.take_calls(), vec![FakeCall::ExecuteJavaScript {
+//!     frame: browser.main_frame().get_identifier().unwrap(),
+//!     code: "init()".to_owned(),
+//! }]);
+//! ```
+//!
+//! [Browser::go_back] and [Browser::go_forward] move through the history of
+//! main frame navigations sent with [EventDispatcher::navigate_frame], changing
+//! the main frame's URL without sending any events.
+//!
+//! CEF is never initialized, and the fake objects don't call into libcef, so
+//! there's no need to load it (on macOS, with
+//! [load_framework](crate::load_framework)). The only exception is
+//! [Browser::get_frame_names], whose string list is allocated by libcef.
+//! Events are delivered synchronously on the calling thread. Functions of the
+//! fake objects that aren't mentioned in this module either do nothing and
+//! return a default value or panic, just like they would if CEF didn't
+//! implement them.
+
+use crate::{
+    browser::Browser,
+    browser_host::BrowserHost,
+    client::{
+        display_handler::DisplayHandler,
+        life_span_handler::LifeSpanHandler,
+        Client,
+    },
+    frame::Frame,
+    load_handler::{ErrorCode, LoadHandler},
+    refcounted::{RefCountedPtr, Wrapper},
+    string::{CefString, CefStringList},
+};
+use lazy_static::lazy_static;
+use cef_sys::{
+    cef_browser_host_t, cef_browser_t, cef_client_t, cef_errorcode_t, cef_frame_t,
+    cef_string_list_t, cef_string_t, cef_string_userfree_t, cef_transition_type_t,
+};
+use parking_lot::Mutex;
+use std::{
+    collections::HashSet,
+    mem::ManuallyDrop,
+    os::raw::c_int,
+    ptr::null_mut,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+};
+
+/// A call made on the objects of a [FakeBrowser]. Frames are referred to by
+/// their identifier.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FakeCall {
+    LoadUrl { frame: i64, url: String },
+    ExecuteJavaScript { frame: i64, code: String },
+    GoBack,
+    GoForward,
+    Reload { ignore_cache: bool },
+    StopLoad,
+    CloseBrowser { force_close: bool },
+    SetFocus(bool),
+    WasResized,
+    WasHidden(bool),
+    SetZoomLevel(f64),
+    SetAudioMuted(bool),
+}
+
+static NEXT_FRAME_ID: AtomicI64 = AtomicI64::new(1);
+
+lazy_static! {
+    /// Addresses of the strings the fake objects returned as
+    /// `cef_string_userfree_t`. They are allocated here rather than by libcef,
+    /// so they have to be freed here as well.
+    static ref USERFREE_STRINGS: Mutex<HashSet<usize>> = Mutex::new(HashSet::new());
+}
+
+fn userfree_string(s: &str) -> cef_string_userfree_t {
+    let raw = Box::into_raw(Box::new(CefString::new(s).into_raw()));
+    USERFREE_STRINGS.lock().insert(raw as usize);
+    raw
+}
+
+/// Frees `raw` and returns the string it holds if it was returned by a fake
+/// object.
+pub(crate) unsafe fn take_userfree(raw: cef_string_userfree_t) -> Option<cef_string_t> {
+    if USERFREE_STRINGS.lock().remove(&(raw as usize)) {
+        Some(*Box::from_raw(raw))
+    } else {
+        None
+    }
+}
+
+struct FakeFrameState {
+    identifier: i64,
+    name: Option<String>,
+    url: String,
+    parent: Option<i64>,
+    frame: Option<Frame>,
+}
+
+struct FakeState {
+    identifier: i32,
+    popup: bool,
+    loading: bool,
+    has_document: bool,
+    history: Vec<String>,
+    history_index: usize,
+    zoom_level: f64,
+    audio_muted: bool,
+    focused_frame: Option<i64>,
+    /// The main frame comes first.
+    frames: Vec<FakeFrameState>,
+    calls: Vec<FakeCall>,
+    // The fake objects are cached here so that every call returns the same
+    // object. The cache is cleared when the `FakeBrowser` is dropped, which
+    // breaks the reference cycle between the objects and this state.
+    browser: Option<Browser>,
+    host: Option<BrowserHost>,
+    client: Option<Client>,
+}
+
+type SharedState = Arc<Mutex<FakeState>>;
+
+impl FakeState {
+    fn browser(&self) -> Browser {
+        self.browser.clone().expect("FakeBrowser was dropped")
+    }
+    fn host(&self) -> BrowserHost {
+        self.host.clone().expect("FakeBrowser was dropped")
+    }
+    fn frame_state(&self, identifier: i64) -> Option<&FakeFrameState> {
+        self.frames.iter().find(|frame| frame.identifier == identifier)
+    }
+    fn frame_state_mut(&mut self, identifier: i64) -> Option<&mut FakeFrameState> {
+        self.frames.iter_mut().find(|frame| frame.identifier == identifier)
+    }
+    fn frame(&self, identifier: i64) -> Option<Frame> {
+        self.frame_state(identifier).and_then(|frame| frame.frame.clone())
+    }
+    fn main_frame(&self) -> Frame {
+        self.frames[0].frame.clone().expect("FakeBrowser was dropped")
+    }
+    /// Show the history entry at `history_index` in the main frame.
+    fn go_to_history_entry(&mut self, history_index: usize) {
+        self.history_index = history_index;
+        self.frames[0].url = self.history[history_index].clone();
+    }
+}
+
+/// A browser that exists without CEF. See the [module documentation](self).
+pub struct FakeBrowser {
+    state: SharedState,
+}
+
+impl FakeBrowser {
+    /// Create a browser with the given identifier and a main frame showing
+    /// `about:blank`.
+    pub fn new(identifier: i32) -> FakeBrowser {
+        let state = Arc::new(Mutex::new(FakeState {
+            identifier,
+            popup: false,
+            loading: false,
+            has_document: false,
+            history: Vec::new(),
+            history_index: 0,
+            zoom_level: 0.0,
+            audio_muted: false,
+            focused_frame: None,
+            frames: Vec::new(),
+            calls: Vec::new(),
+            browser: None,
+            host: None,
+            client: None,
+        }));
+        let browser = unsafe {
+            Browser::from_ptr_unchecked(FakeBrowserWrapper(state.clone()).wrap().into_raw())
+        };
+        let host = unsafe {
+            BrowserHost::from_ptr_unchecked(FakeBrowserHostWrapper(state.clone()).wrap().into_raw())
+        };
+        {
+            let mut state = state.lock();
+            state.browser = Some(browser);
+            state.host = Some(host);
+        }
+        let fake = FakeBrowser { state };
+        fake.insert_frame(None, None, "about:blank");
+        fake
+    }
+    fn insert_frame(&self, parent: Option<i64>, name: Option<&str>, url: &str) -> Frame {
+        let identifier = NEXT_FRAME_ID.fetch_add(1, Ordering::Relaxed);
+        let frame = unsafe {
+            Frame::from_ptr_unchecked(
+                FakeFrameWrapper {
+                    state: self.state.clone(),
+                    identifier,
+                }
+                .wrap()
+                .into_raw(),
+            )
+        };
+        self.state.lock().frames.push(FakeFrameState {
+            identifier,
+            name: name.map(str::to_owned),
+            url: url.to_owned(),
+            parent,
+            frame: Some(frame.clone()),
+        });
+        frame
+    }
+    pub fn browser(&self) -> Browser {
+        self.state.lock().browser()
+    }
+    pub fn host(&self) -> BrowserHost {
+        self.state.lock().host()
+    }
+    pub fn main_frame(&self) -> Frame {
+        self.state.lock().main_frame()
+    }
+    /// Add a frame with the given name and URL as a child of `parent`, which
+    /// has to be a frame of this browser.
+    pub fn add_frame(&self, parent: &Frame, name: &str, url: &str) -> Frame {
+        self.insert_frame(parent.get_identifier(), Some(name), url)
+    }
+    /// Change the URL of `frame` without sending any events. Use
+    /// [EventDispatcher::navigate_frame] to simulate a navigation instead.
+    pub fn set_url(&self, frame: &Frame, url: &str) {
+        if let Some(identifier) = frame.get_identifier() {
+            if let Some(frame) = self.state.lock().frame_state_mut(identifier) {
+                frame.url = url.to_owned();
+            }
+        }
+    }
+    /// Set the frame returned by [Browser::get_focused_frame].
+    pub fn set_focused_frame(&self, frame: Option<&Frame>) {
+        self.state.lock().focused_frame = frame.and_then(Frame::get_identifier);
+    }
+    /// Set the value returned by [Browser::is_popup].
+    pub fn set_popup(&self, popup: bool) {
+        self.state.lock().popup = popup;
+    }
+    /// Returns the calls made so far.
+    pub fn calls(&self) -> Vec<FakeCall> {
+        self.state.lock().calls.clone()
+    }
+    /// Returns the calls made so far and forgets them.
+    pub fn take_calls(&self) -> Vec<FakeCall> {
+        std::mem::replace(&mut self.state.lock().calls, Vec::new())
+    }
+}
+
+impl Drop for FakeBrowser {
+    fn drop(&mut self) {
+        // Drop the objects after unlocking, as their handlers may still call
+        // into the state.
+        let cached = {
+            let mut state = self.state.lock();
+            let frames: Vec<_> = state.frames.iter_mut().filter_map(|frame| frame.frame.take()).collect();
+            (state.browser.take(), state.host.take(), state.client.take(), frames)
+        };
+        drop(cached);
+    }
+}
+
+/// Sends simulated browser events to the handlers of a [Client], as CEF would
+/// for a [FakeBrowser]. Handlers the client doesn't provide are skipped.
+pub struct EventDispatcher {
+    client: Client,
+    state: SharedState,
+}
+
+impl EventDispatcher {
+    /// Dispatch events of `browser` to `client`. The client is also returned
+    /// by [BrowserHost::get_client] from then on.
+    pub fn new(client: Client, browser: &FakeBrowser) -> EventDispatcher {
+        browser.state.lock().client = Some(client.clone());
+        EventDispatcher {
+            client,
+            state: browser.state.clone(),
+        }
+    }
+    fn browser(&self) -> Browser {
+        self.state.lock().browser()
+    }
+    fn load_handler(&self) -> Option<LoadHandler> {
+        unsafe {
+            let get_load_handler = (*self.client.as_ptr()).get_load_handler?;
+            LoadHandler::from_ptr(get_load_handler(self.client.as_ptr()))
+        }
+    }
+    fn display_handler(&self) -> Option<DisplayHandler> {
+        unsafe {
+            let get_display_handler = (*self.client.as_ptr()).get_display_handler?;
+            DisplayHandler::from_ptr(get_display_handler(self.client.as_ptr()))
+        }
+    }
+    fn life_span_handler(&self) -> Option<LifeSpanHandler> {
+        unsafe {
+            let get_life_span_handler = (*self.client.as_ptr()).get_life_span_handler?;
+            LifeSpanHandler::from_ptr(get_life_span_handler(self.client.as_ptr()))
+        }
+    }
+    fn loading_state_change(&self, handler: &LoadHandler, is_loading: bool) {
+        let (can_go_back, can_go_forward) = {
+            let mut state = self.state.lock();
+            state.loading = is_loading;
+            (
+                state.history_index > 0,
+                state.history_index + 1 < state.history.len(),
+            )
+        };
+        let browser = self.browser();
+        unsafe {
+            if let Some(on_loading_state_change) = (*handler.as_ptr()).on_loading_state_change {
+                on_loading_state_change(
+                    handler.as_ptr(),
+                    browser.into_raw(),
+                    is_loading as c_int,
+                    can_go_back as c_int,
+                    can_go_forward as c_int,
+                );
+            }
+        }
+    }
+    /// Call [LifeSpanHandlerCallbacks::on_after_created](crate::client::life_span_handler::LifeSpanHandlerCallbacks::on_after_created).
+    pub fn after_created(&self) {
+        if let Some(handler) = self.life_span_handler() {
+            let browser = self.browser();
+            unsafe {
+                if let Some(on_after_created) = (*handler.as_ptr()).on_after_created {
+                    on_after_created(handler.as_ptr(), browser.into_raw());
+                }
+            }
+        }
+    }
+    /// Call [LifeSpanHandlerCallbacks::on_before_close](crate::client::life_span_handler::LifeSpanHandlerCallbacks::on_before_close).
+    /// As with a real browser, the browser object must not be used afterwards.
+    pub fn before_close(&self) {
+        if let Some(handler) = self.life_span_handler() {
+            let browser = self.browser();
+            unsafe {
+                if let Some(on_before_close) = (*handler.as_ptr()).on_before_close {
+                    on_before_close(handler.as_ptr(), browser.into_raw());
+                }
+            }
+        }
+    }
+    /// Simulate a successful navigation of the main frame to `url`.
+    pub fn navigate(&self, url: &str) {
+        let main_frame = self.state.lock().main_frame();
+        self.navigate_frame(&main_frame, url, 200);
+    }
+    /// Simulate a navigation of `frame` to `url` that finished with the given
+    /// HTTP status code. Sends the loading state, address and load start and end
+    /// events in the order CEF does.
+    pub fn navigate_frame(&self, frame: &Frame, url: &str, http_status_code: i32) {
+        let is_main = frame.is_main();
+        if let Some(identifier) = frame.get_identifier() {
+            let mut state = self.state.lock();
+            if let Some(frame) = state.frame_state_mut(identifier) {
+                frame.url = url.to_owned();
+            }
+            if is_main {
+                let index = if state.history.is_empty() { 0 } else { state.history_index + 1 };
+                state.history.truncate(index);
+                state.history.push(url.to_owned());
+                state.history_index = index;
+            }
+        }
+        let load_handler = self.load_handler();
+        if is_main {
+            if let Some(handler) = &load_handler {
+                self.loading_state_change(handler, true);
+            }
+            if let Some(handler) = self.display_handler() {
+                let browser = self.browser();
+                let url = CefString::new(url);
+                unsafe {
+                    if let Some(on_address_change) = (*handler.as_ptr()).on_address_change {
+                        on_address_change(handler.as_ptr(), browser.into_raw(), frame.clone().into_raw(), url.as_ptr());
+                    }
+                }
+            }
+        }
+        if let Some(handler) = &load_handler {
+            let source = if is_main {
+                cef_transition_type_t::TT_EXPLICIT.0
+            } else {
+                cef_transition_type_t::TT_AUTO_SUBFRAME.0
+            };
+            let transition_type = cef_transition_type_t(source | cef_transition_type_t::TT_DIRECT_LOAD_FLAG.0);
+            let browser = self.browser();
+            unsafe {
+                if let Some(on_load_start) = (*handler.as_ptr()).on_load_start {
+                    on_load_start(handler.as_ptr(), browser.into_raw(), frame.clone().into_raw(), transition_type);
+                }
+            }
+            if is_main {
+                self.state.lock().has_document = true;
+            }
+            let browser = self.browser();
+            unsafe {
+                if let Some(on_load_end) = (*handler.as_ptr()).on_load_end {
+                    on_load_end(handler.as_ptr(), browser.into_raw(), frame.clone().into_raw(), http_status_code);
+                }
+            }
+            if is_main {
+                self.loading_state_change(handler, false);
+            }
+        } else if is_main {
+            self.state.lock().has_document = true;
+        }
+    }
+    /// Simulate a navigation of `frame` to `url` that failed with `error_code`.
+    pub fn fail_navigation(&self, frame: &Frame, url: &str, error_code: ErrorCode, error_text: &str) {
+        if let Some(handler) = self.load_handler() {
+            let browser = self.browser();
+            let error_text = CefString::new(error_text);
+            let url = CefString::new(url);
+            unsafe {
+                if let Some(on_load_error) = (*handler.as_ptr()).on_load_error {
+                    on_load_error(
+                        handler.as_ptr(),
+                        browser.into_raw(),
+                        frame.clone().into_raw(),
+                        error_code as cef_errorcode_t::Type,
+                        error_text.as_ptr(),
+                        url.as_ptr(),
+                    );
+                }
+            }
+        }
+    }
+    /// Simulate the page changing its title.
+    pub fn set_title(&self, title: &str) {
+        if let Some(handler) = self.display_handler() {
+            let browser = self.browser();
+            let title = CefString::new(title);
+            unsafe {
+                if let Some(on_title_change) = (*handler.as_ptr()).on_title_change {
+                    on_title_change(handler.as_ptr(), browser.into_raw(), title.as_ptr());
+                }
+            }
+        }
+    }
+}
+
+struct FakeBrowserWrapper(SharedState);
+
+impl Wrapper for FakeBrowserWrapper {
+    type Cef = cef_browser_t;
+    fn wrap(self) -> RefCountedPtr<Self::Cef> {
+        RefCountedPtr::wrap(
+            cef_browser_t {
+                base: unsafe { std::mem::zeroed() },
+                get_host: Some(Self::get_host),
+                can_go_back: Some(Self::can_go_back),
+                go_back: Some(Self::go_back),
+                can_go_forward: Some(Self::can_go_forward),
+                go_forward: Some(Self::go_forward),
+                is_loading: Some(Self::is_loading),
+                reload: Some(Self::reload),
+                reload_ignore_cache: Some(Self::reload_ignore_cache),
+                stop_load: Some(Self::stop_load),
+                get_identifier: Some(Self::get_identifier),
+                is_same: Some(Self::is_same),
+                is_popup: Some(Self::is_popup),
+                has_document: Some(Self::has_document),
+                get_main_frame: Some(Self::get_main_frame),
+                get_focused_frame: Some(Self::get_focused_frame),
+                get_frame_byident: Some(Self::get_frame_byident),
+                get_frame: Some(Self::get_frame),
+                get_frame_count: Some(Self::get_frame_count),
+                get_frame_identifiers: Some(Self::get_frame_identifiers),
+                get_frame_names: Some(Self::get_frame_names),
+                ..unsafe { std::mem::zeroed() }
+            },
+            self,
+        )
+    }
+}
+
+cef_callback_impl! {
+    impl for FakeBrowserWrapper: cef_browser_t {
+        fn get_host(&self) -> *mut cef_browser_host_t {
+            self.0.lock().host().into_raw()
+        }
+        fn can_go_back(&self) -> c_int {
+            (self.0.lock().history_index > 0) as c_int
+        }
+        fn go_back(&self) {
+            let mut state = self.0.lock();
+            state.calls.push(FakeCall::GoBack);
+            if state.history_index > 0 {
+                let index = state.history_index - 1;
+                state.go_to_history_entry(index);
+            }
+        }
+        fn can_go_forward(&self) -> c_int {
+            let state = self.0.lock();
+            (state.history_index + 1 < state.history.len()) as c_int
+        }
+        fn go_forward(&self) {
+            let mut state = self.0.lock();
+            state.calls.push(FakeCall::GoForward);
+            if state.history_index + 1 < state.history.len() {
+                let index = state.history_index + 1;
+                state.go_to_history_entry(index);
+            }
+        }
+        fn is_loading(&self) -> c_int {
+            self.0.lock().loading as c_int
+        }
+        fn reload(&self) {
+            self.0.lock().calls.push(FakeCall::Reload { ignore_cache: false });
+        }
+        fn reload_ignore_cache(&self) {
+            self.0.lock().calls.push(FakeCall::Reload { ignore_cache: true });
+        }
+        fn stop_load(&self) {
+            self.0.lock().calls.push(FakeCall::StopLoad);
+        }
+        fn get_identifier(&self) -> c_int {
+            self.0.lock().identifier
+        }
+        fn is_same(&self, that: Browser: *mut cef_browser_t) -> c_int {
+            let this = self.0.lock().browser();
+            (this.as_ptr() == that.as_ptr()) as c_int
+        }
+        fn is_popup(&self) -> c_int {
+            self.0.lock().popup as c_int
+        }
+        fn has_document(&self) -> c_int {
+            self.0.lock().has_document as c_int
+        }
+        fn get_main_frame(&self) -> *mut cef_frame_t {
+            self.0.lock().main_frame().into_raw()
+        }
+        fn get_focused_frame(&self) -> *mut cef_frame_t {
+            let state = self.0.lock();
+            state
+                .focused_frame
+                .and_then(|identifier| state.frame(identifier))
+                .map(|frame| frame.into_raw())
+                .unwrap_or(null_mut())
+        }
+        fn get_frame_byident(&self, identifier: i64: i64) -> *mut cef_frame_t {
+            self.0.lock().frame(identifier).map(|frame| frame.into_raw()).unwrap_or(null_mut())
+        }
+        fn get_frame(&self, name: &CefString: *const cef_string_t) -> *mut cef_frame_t {
+            let name = String::from(name);
+            let state = self.0.lock();
+            state
+                .frames
+                .iter()
+                .find(|frame| frame.name.as_ref() == Some(&name))
+                .and_then(|frame| frame.frame.clone())
+                .map(|frame| frame.into_raw())
+                .unwrap_or(null_mut())
+        }
+        fn get_frame_count(&self) -> usize {
+            self.0.lock().frames.len()
+        }
+        fn get_frame_identifiers(
+            &self,
+            identifiers_count: *mut usize: *mut usize,
+            identifiers: *mut i64: *mut i64,
+        ) {
+            let state = self.0.lock();
+            unsafe {
+                let count = state.frames.len().min(*identifiers_count);
+                for (i, frame) in state.frames.iter().take(count).enumerate() {
+                    *identifiers.add(i) = frame.identifier;
+                }
+                *identifiers_count = count;
+            }
+        }
+        fn get_frame_names(&self, names: ManuallyDrop<CefStringList>: cef_string_list_t) {
+            let mut names = names;
+            for frame in &self.0.lock().frames {
+                names.push(&CefString::new(frame.name.as_deref().unwrap_or("")));
+            }
+        }
+    }
+}
+
+struct FakeBrowserHostWrapper(SharedState);
+
+impl Wrapper for FakeBrowserHostWrapper {
+    type Cef = cef_browser_host_t;
+    fn wrap(self) -> RefCountedPtr<Self::Cef> {
+        RefCountedPtr::wrap(
+            cef_browser_host_t {
+                base: unsafe { std::mem::zeroed() },
+                get_browser: Some(Self::get_browser),
+                close_browser: Some(Self::close_browser),
+                set_focus: Some(Self::set_focus),
+                get_client: Some(Self::get_client),
+                get_zoom_level: Some(Self::get_zoom_level),
+                set_zoom_level: Some(Self::set_zoom_level),
+                was_resized: Some(Self::was_resized),
+                was_hidden: Some(Self::was_hidden),
+                set_audio_muted: Some(Self::set_audio_muted),
+                is_audio_muted: Some(Self::is_audio_muted),
+                ..unsafe { std::mem::zeroed() }
+            },
+            self,
+        )
+    }
+}
+
+cef_callback_impl! {
+    impl for FakeBrowserHostWrapper: cef_browser_host_t {
+        fn get_browser(&self) -> *mut cef_browser_t {
+            self.0.lock().browser().into_raw()
+        }
+        fn close_browser(&self, force_close: bool: c_int) {
+            self.0.lock().calls.push(FakeCall::CloseBrowser { force_close });
+        }
+        fn set_focus(&self, focus: bool: c_int) {
+            self.0.lock().calls.push(FakeCall::SetFocus(focus));
+        }
+        fn get_client(&self) -> *mut cef_client_t {
+            self.0.lock().client.clone().map(|client| client.into_raw()).unwrap_or(null_mut())
+        }
+        fn get_zoom_level(&self) -> f64 {
+            self.0.lock().zoom_level
+        }
+        fn set_zoom_level(&self, zoom_level: f64: f64) {
+            let mut state = self.0.lock();
+            state.zoom_level = zoom_level;
+            state.calls.push(FakeCall::SetZoomLevel(zoom_level));
+        }
+        fn was_resized(&self) {
+            self.0.lock().calls.push(FakeCall::WasResized);
+        }
+        fn was_hidden(&self, hidden: bool: c_int) {
+            self.0.lock().calls.push(FakeCall::WasHidden(hidden));
+        }
+        fn set_audio_muted(&self, mute: bool: c_int) {
+            let mut state = self.0.lock();
+            state.audio_muted = mute;
+            state.calls.push(FakeCall::SetAudioMuted(mute));
+        }
+        fn is_audio_muted(&self) -> c_int {
+            self.0.lock().audio_muted as c_int
+        }
+    }
+}
+
+struct FakeFrameWrapper {
+    state: SharedState,
+    identifier: i64,
+}
+
+impl FakeFrameWrapper {
+    fn with_frame<R: Default>(&self, f: impl FnOnce(&FakeFrameState) -> R) -> R {
+        self.state.lock().frame_state(self.identifier).map(f).unwrap_or_default()
+    }
+}
+
+impl Wrapper for FakeFrameWrapper {
+    type Cef = cef_frame_t;
+    fn wrap(self) -> RefCountedPtr<Self::Cef> {
+        RefCountedPtr::wrap(
+            cef_frame_t {
+                base: unsafe { std::mem::zeroed() },
+                is_valid: Some(Self::is_valid),
+                load_url: Some(Self::load_url),
+                execute_java_script: Some(Self::execute_java_script),
+                is_main: Some(Self::is_main),
+                is_focused: Some(Self::is_focused),
+                get_name: Some(Self::get_name),
+                get_identifier: Some(Self::get_identifier),
+                get_parent: Some(Self::get_parent),
+                get_url: Some(Self::get_url),
+                get_browser: Some(Self::get_browser),
+                ..unsafe { std::mem::zeroed() }
+            },
+            self,
+        )
+    }
+}
+
+cef_callback_impl! {
+    impl for FakeFrameWrapper: cef_frame_t {
+        fn is_valid(&self) -> c_int {
+            self.with_frame(|frame| frame.frame.is_some()) as c_int
+        }
+        fn load_url(&self, url: &CefString: *const cef_string_t) {
+            self.state.lock().calls.push(FakeCall::LoadUrl {
+                frame: self.identifier,
+                url: String::from(url),
+            });
+        }
+        fn execute_java_script(
+            &self,
+            code: &CefString: *const cef_string_t,
+            script_url: &CefString: *const cef_string_t,
+            start_line: c_int: c_int,
+        ) {
+            self.state.lock().calls.push(FakeCall::ExecuteJavaScript {
+                frame: self.identifier,
+                code: String::from(code),
+            });
+        }
+        fn is_main(&self) -> c_int {
+            self.with_frame(|frame| frame.parent.is_none()) as c_int
+        }
+        fn is_focused(&self) -> c_int {
+            (self.state.lock().focused_frame == Some(self.identifier)) as c_int
+        }
+        fn get_name(&self) -> cef_string_userfree_t {
+            self.with_frame(|frame| frame.name.clone())
+                .map(|name| userfree_string(&name))
+                .unwrap_or(null_mut())
+        }
+        fn get_identifier(&self) -> i64 {
+            self.identifier
+        }
+        fn get_parent(&self) -> *mut cef_frame_t {
+            let state = self.state.lock();
+            state
+                .frame_state(self.identifier)
+                .and_then(|frame| frame.parent)
+                .and_then(|parent| state.frame(parent))
+                .map(|frame| frame.into_raw())
+                .unwrap_or(null_mut())
+        }
+        fn get_url(&self) -> cef_string_userfree_t {
+            userfree_string(&self.with_frame(|frame| frame.url.clone()))
+        }
+        fn get_browser(&self) -> *mut cef_browser_t {
+            self.state.lock().browser().into_raw()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ClientCallbacks;
+
+    #[derive(Default)]
+    struct LoadRecorder(Arc<Mutex<Vec<(String, i32)>>>);
+
+    impl crate::load_handler::LoadHandlerCallbacks for LoadRecorder {
+        fn on_load_end(&self, _browser: Browser, frame: Frame, http_status_code: i32) {
+            self.0.lock().push((frame.get_url(), http_status_code));
+        }
+    }
+
+    struct TestClient(LoadHandler);
+
+    impl ClientCallbacks for TestClient {
+        fn get_load_handler(&self) -> Option<LoadHandler> {
+            Some(self.0.clone())
+        }
+    }
+
+    fn dispatcher(browser: &FakeBrowser) -> (EventDispatcher, Arc<Mutex<Vec<(String, i32)>>>) {
+        let recorder = LoadRecorder::default();
+        let loads = recorder.0.clone();
+        let client = Client::new(TestClient(LoadHandler::new(recorder)));
+        (EventDispatcher::new(client, browser), loads)
+    }
+
+    #[test]
+    fn frames_are_answered_from_the_fake() {
+        let fake = FakeBrowser::new(7);
+        let browser = fake.browser();
+        let main_frame = fake.main_frame();
+        assert_eq!(browser.get_identifier(), 7);
+        assert!(main_frame.is_main());
+        assert_eq!(main_frame.get_url(), "about:blank");
+        assert_eq!(main_frame.get_name(), None);
+
+        let child = fake.add_frame(&main_frame, "child", "https://example.com/child");
+        assert!(!child.is_main());
+        assert_eq!(child.get_name().as_deref(), Some("child"));
+        assert_eq!(child.get_url(), "https://example.com/child");
+        assert_eq!(child.get_parent().and_then(|parent| parent.get_identifier()), main_frame.get_identifier());
+        assert_eq!(browser.get_frame("child").and_then(|frame| frame.get_identifier()), child.get_identifier());
+        assert_eq!(browser.get_frame_count(), 2);
+    }
+
+    #[test]
+    fn calls_are_recorded() {
+        let fake = FakeBrowser::new(1);
+        let main_frame = fake.main_frame();
+        main_frame.load_url("https://example.com/");
+        fake.host().set_zoom_level(1.5);
+        assert_eq!(
+            fake.take_calls(),
+            vec![
+                FakeCall::LoadUrl {
+                    frame: main_frame.get_identifier().unwrap(),
+                    url: "https://example.com/".to_owned(),
+                },
+                FakeCall::SetZoomLevel(1.5),
+            ],
+        );
+        assert!(fake.calls().is_empty());
+        assert_eq!(fake.host().get_zoom_level(), 1.5);
+    }
+
+    #[test]
+    fn navigation_sends_load_events() {
+        let fake = FakeBrowser::new(1);
+        let (events, loads) = dispatcher(&fake);
+        events.navigate("https://example.com/");
+        let child = fake.add_frame(&fake.main_frame(), "child", "about:blank");
+        events.navigate_frame(&child, "https://example.com/missing", 404);
+        assert_eq!(
+            *loads.lock(),
+            vec![
+                ("https://example.com/".to_owned(), 200),
+                ("https://example.com/missing".to_owned(), 404),
+            ],
+        );
+        assert!(fake.browser().has_document());
+    }
+
+    #[test]
+    fn history_moves_with_back_and_forward() {
+        let fake = FakeBrowser::new(1);
+        let (events, _) = dispatcher(&fake);
+        let browser = fake.browser();
+        events.navigate("https://example.com/a");
+        events.navigate("https://example.com/b");
+        assert!(browser.can_go_back());
+        assert!(!browser.can_go_forward());
+
+        browser.go_back();
+        assert_eq!(fake.main_frame().get_url(), "https://example.com/a");
+        assert!(!browser.can_go_back());
+        assert!(browser.can_go_forward());
+        browser.go_back();
+        assert_eq!(fake.main_frame().get_url(), "https://example.com/a");
+
+        browser.go_forward();
+        assert_eq!(fake.main_frame().get_url(), "https://example.com/b");
+        browser.go_back();
+        events.navigate("https://example.com/c");
+        assert!(!browser.can_go_forward());
+        assert_eq!(
+            fake.take_calls(),
+            vec![FakeCall::GoBack, FakeCall::GoBack, FakeCall::GoForward, FakeCall::GoBack],
+        );
+    }
+}
@@ -28,7 +28,7 @@ use crate::{
 
 /// Flags that represent [URLRequest] status.
 #[repr(C)]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum URLRequestStatus {
     /// Unknown status.
     Unknown = cef_urlrequest_status_t::UR_UNKNOWN as isize,
@@ -822,4 +822,20 @@ impl ResourceReadHandler {
     pub fn set_error(&mut self, error_code: ErrorCode) {
         self.bytes_read = -(error_code as i32);
     }
+
+    /// A read into `buffer`, for testing resource handlers without CEF.
+    /// `buffer` must outlive the handler.
+    #[cfg(test)]
+    pub(crate) fn for_buffer(buffer: &mut [u8], callback: ResourceReadCallback) -> ResourceReadHandler {
+        ResourceReadHandler {
+            callback,
+            data_out: buffer.as_mut_ptr() as *mut c_void,
+            bytes_to_read: buffer.len(),
+            bytes_read: 0,
+        }
+    }
+    #[cfg(test)]
+    pub(crate) fn bytes_read(&self) -> i32 {
+        self.bytes_read
+    }
 }
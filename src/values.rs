@@ -4,7 +4,7 @@ use cef_sys::{
     cef_binary_value_create, cef_binary_value_t, cef_dictionary_value_create,
     cef_dictionary_value_t, cef_list_value_create, cef_list_value_t, cef_point_t, cef_range_t,
     cef_size_t, cef_string_userfree_utf16_free, cef_value_create, cef_value_t, cef_value_type_t,
-    cef_rect_t,
+    cef_rect_t, cef_parse_json, cef_write_json, cef_json_parser_options_t, cef_json_writer_options_t,
 };
 use std::{
     collections::HashMap,
@@ -42,6 +42,33 @@ pub enum StoredValue {
     List(ListValue),
 }
 
+impl StoredValue {
+    /// Parses the specified `json` string and returns a dictionary or list
+    /// representation. If JSON parsing fails this function returns None.
+    pub fn from_json(json: &str) -> Option<StoredValue> {
+        let value = unsafe {
+            Value::from_ptr(cef_parse_json(
+                CefString::new(json).as_ptr(),
+                cef_json_parser_options_t::JSON_PARSER_RFC,
+            ))
+        };
+        value.map(StoredValue::from)
+    }
+    /// Generates a JSON string from the specified root value. Binary values
+    /// cannot be represented in JSON, so this function returns None if the
+    /// value or one of its children is binary.
+    pub fn to_json(&self) -> Option<String> {
+        let value = Value::try_from(self.clone()).ok()?;
+        unsafe {
+            CefString::from_userfree(cef_write_json(
+                value.into_raw(),
+                cef_json_writer_options_t::JSON_WRITER_DEFAULT,
+            ))
+        }
+        .map(String::from)
+    }
+}
+
 ref_counted_ptr! {
     pub(crate) struct Value(*mut cef_value_t);
 }
@@ -1279,6 +1306,9 @@ pub struct Range {
 }
 
 impl Range {
+    /// The range CEF uses for "no range", e.g. when there is no selection.
+    pub const INVALID: Range = Range { from: -1, to: -1 };
+
     pub fn new() -> Self {
         Self {
             from: 0,
@@ -1348,3 +1378,15 @@ pub fn date_time_to_cef_time(date_time: DateTime<Utc>) -> cef_sys::cef_time_t {
         millisecond: (date_time.nanosecond() / 1_000_000) as i32,
     }
 }
+
+/// Like [cef_time_to_date_time], but returns `None` for invalid times such as
+/// the zeroed times CEF uses for unset fields.
+pub fn cef_time_to_system_time(cef_time: cef_sys::cef_time_t) -> Option<std::time::SystemTime> {
+    let date = NaiveDate::from_ymd_opt(cef_time.year as i32, cef_time.month as u32, cef_time.day_of_month as u32)?;
+    let time = NaiveTime::from_hms_milli_opt(cef_time.hour as u32, cef_time.minute as u32, cef_time.second as u32, cef_time.millisecond as u32)?;
+    Some(DateTime::<Utc>::from_utc(NaiveDateTime::new(date, time), Utc).into())
+}
+
+pub fn system_time_to_cef_time(system_time: std::time::SystemTime) -> cef_sys::cef_time_t {
+    date_time_to_cef_time(system_time.into())
+}
@@ -0,0 +1,397 @@
+//! Showing video frames produced by the application in the page.
+//!
+//! A [VideoFeed] serves the frames pushed to it, e.g. from a camera or a
+//! native decoder, as a Motion JPEG stream that Chromium shows in an `<img>`
+//! without any script. Pages that need a `MediaStream`, e.g. to show the feed
+//! in a `<video>` or send it over WebRTC, can turn the stream into one with
+//! the script returned by [VideoFeed::media_stream_expression]:
+//!
+//! ```rust,ignore
+//! let feed = VideoFeed::new();
+//! feed.register(&browser.get_host().get_request_context());
+//! feed.allow_origin("https://app.example.com");
+//! frame.execute_java_script(
+//!     &format!("{}.then(function(stream) {{ video.srcObject = stream; }});", feed.media_stream_expression(30)),
+//!     "",
+//!     0,
+//! );
+//! // For every frame the camera delivers:
+//! feed.push_frame(&jpeg);
+//! ```
+//!
+//! Frames are JPEG encoded by the application. Clients that can't keep up
+//! skip frames instead of falling behind.
+//!
+//! Feed URLs contain a random id, so pages can't guess the URLs of feeds
+//! they weren't given. Reading the frames from a script, as the `MediaStream`
+//! does, is only allowed to the origins passed to [VideoFeed::allow_origin].
+
+use crate::{
+    browser::Browser,
+    callback::Callback,
+    frame::Frame,
+    load_handler::ErrorCode,
+    request::Request,
+    request_context::RequestContext,
+    response::Response,
+    scheme::{SchemeHandlerFactory, SchemeHandlerFactoryCallbacks},
+    url_request::{ResourceHandler, ResourceHandlerCallbacks, ResourceReadHandler, ResourceSkipCallback},
+};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak},
+};
+use uuid::Uuid;
+
+/// Domain the feeds are served from, in the reserved `.invalid` domain that
+/// nothing else answers.
+const VIDEO_FEED_DOMAIN: &str = "video-feed.invalid";
+
+/// Separates the frames of the multipart response.
+const BOUNDARY: &str = "cef-video-frame";
+
+lazy_static! {
+    static ref FEEDS: Mutex<HashMap<Uuid, Arc<FeedState>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Default)]
+struct FeedState {
+    /// The last frame, as a part of the multipart response, sent first to
+    /// new clients.
+    last_part: Mutex<Option<Arc<Vec<u8>>>>,
+    streams: Mutex<Vec<Weak<Mutex<Stream>>>>,
+    /// Origins allowed to read the frames from scripts.
+    allowed_origins: Mutex<Vec<String>>,
+}
+
+/// A stream of frames served under its own URL. The feed stops when it's
+/// dropped, which ends the responses of all clients.
+pub struct VideoFeed {
+    id: Uuid,
+    state: Arc<FeedState>,
+}
+
+impl VideoFeed {
+    pub fn new() -> VideoFeed {
+        let id = Uuid::new_v4();
+        let state = Arc::new(FeedState::default());
+        FEEDS.lock().insert(id, state.clone());
+        VideoFeed { id, state }
+    }
+    /// Returns the URL the feed is served under, for use as the `src` of an
+    /// `<img>`.
+    pub fn url(&self) -> String {
+        format!("http://{}/{}", VIDEO_FEED_DOMAIN, self.id)
+    }
+    /// Serve the feeds in `context`, which must be done for the request
+    /// context of every browser showing a feed. Returns false if the scheme
+    /// handler can't be registered.
+    pub fn register(&self, context: &RequestContext) -> bool {
+        context.register_scheme_handler_factory("http", Some(VIDEO_FEED_DOMAIN), SchemeHandlerFactory::new(VideoFeedFactory))
+    }
+    /// Let pages of `origin`, like `https://app.example.com`, read the frames
+    /// from scripts, which [VideoFeed::media_stream_expression] needs. Other
+    /// pages can still show the feed in an `<img>`.
+    pub fn allow_origin(&self, origin: &str) {
+        let mut allowed_origins = self.state.allowed_origins.lock();
+        if !allowed_origins.iter().any(|allowed| allowed == origin) {
+            allowed_origins.push(origin.to_owned());
+        }
+    }
+    /// Send a JPEG encoded frame to all clients. Can be called on any thread.
+    pub fn push_frame(&self, jpeg: &[u8]) {
+        let part = Arc::new(multipart_part(jpeg));
+        *self.state.last_part.lock() = Some(part.clone());
+        let mut ready = Vec::new();
+        self.state.streams.lock().retain(|stream| match stream.upgrade() {
+            Some(stream) => {
+                ready.extend(stream.lock().push(&part));
+                true
+            }
+            None => false,
+        });
+        for handler in ready {
+            handler.cont();
+        }
+    }
+    /// Returns a JavaScript expression that evaluates to a promise resolving
+    /// to a `MediaStream` of this feed, captured at up to `frame_rate` frames
+    /// per second. The promise is rejected if the feed can't be loaded, or
+    /// if the page's origin wasn't passed to [VideoFeed::allow_origin]. Like
+    /// all animations, the stream pauses while the page is hidden.
+    pub fn media_stream_expression(&self, frame_rate: u32) -> String {
+        format!(
+            "new Promise(function(resolve, reject) {{\n\
+             var image = new Image();\n\
+             var canvas = document.createElement('canvas');\n\
+             var context = canvas.getContext('2d');\n\
+             image.crossOrigin = 'anonymous';\n\
+             image.onerror = function() {{ reject(new Error('cannot load the video feed')); }};\n\
+             image.onload = function() {{\n\
+             if (canvas.width === image.naturalWidth && canvas.height === image.naturalHeight) return;\n\
+             canvas.width = image.naturalWidth;\n\
+             canvas.height = image.naturalHeight;\n\
+             (function draw() {{\n\
+             context.drawImage(image, 0, 0);\n\
+             requestAnimationFrame(draw);\n\
+             }})();\n\
+             resolve(canvas.captureStream({}));\n\
+             }};\n\
+             image.src = '{}';\n\
+             }})",
+            frame_rate.max(1),
+            self.url()
+        )
+    }
+}
+
+impl Default for VideoFeed {
+    fn default() -> VideoFeed {
+        VideoFeed::new()
+    }
+}
+
+impl Drop for VideoFeed {
+    fn drop(&mut self) {
+        FEEDS.lock().remove(&self.id);
+        let mut finished = Vec::new();
+        for stream in self.state.streams.lock().drain(..) {
+            if let Some(stream) = stream.upgrade() {
+                finished.extend(stream.lock().finish());
+            }
+        }
+        for handler in finished {
+            handler.cont();
+        }
+    }
+}
+
+fn multipart_part(jpeg: &[u8]) -> Vec<u8> {
+    let header = format!(
+        "--{}\r\nContent-Type: image/jpeg\r\nContent-Length: {}\r\n\r\n",
+        BOUNDARY,
+        jpeg.len()
+    );
+    let mut part = Vec::with_capacity(header.len() + jpeg.len() + 2);
+    part.extend_from_slice(header.as_bytes());
+    part.extend_from_slice(jpeg);
+    part.extend_from_slice(b"\r\n");
+    part
+}
+
+/// The response to one client.
+#[derive(Default)]
+struct Stream {
+    part: Option<Arc<Vec<u8>>>,
+    offset: usize,
+    /// A read waiting for the next frame.
+    waiting: Option<ResourceReadHandler>,
+    finished: bool,
+}
+
+impl Stream {
+    /// Copy as much of the current part as fits into `handler`. Returns
+    /// false if there's nothing to copy.
+    fn fill(&mut self, handler: &mut ResourceReadHandler) -> bool {
+        let part = match &self.part {
+            Some(part) if self.offset < part.len() => part,
+            _ => return false,
+        };
+        let buffer = handler.as_buffer_ref();
+        let len = (part.len() - self.offset).min(buffer.len());
+        buffer[..len].copy_from_slice(&part[self.offset..self.offset + len]);
+        self.offset += len;
+        handler.set_bytes_read(len as i32);
+        true
+    }
+    /// Queue `part`, unless the client is still reading the previous one.
+    /// Returns the waiting read if it can continue.
+    fn push(&mut self, part: &Arc<Vec<u8>>) -> Option<ResourceReadHandler> {
+        if self.finished {
+            return None;
+        }
+        let reading = matches!(&self.part, Some(current) if self.offset > 0 && self.offset < current.len());
+        if reading {
+            return None;
+        }
+        self.part = Some(part.clone());
+        self.offset = 0;
+        let mut handler = self.waiting.take()?;
+        self.fill(&mut handler);
+        Some(handler)
+    }
+    /// End the response. Returns the waiting read, which completes it.
+    fn finish(&mut self) -> Option<ResourceReadHandler> {
+        self.finished = true;
+        let mut handler = self.waiting.take()?;
+        handler.set_bytes_read(0);
+        Some(handler)
+    }
+}
+
+struct VideoFeedFactory;
+
+impl SchemeHandlerFactoryCallbacks for VideoFeedFactory {
+    fn create(
+        &self,
+        browser: Browser,
+        frame: Frame,
+        scheme_name: &str,
+        request: Request,
+    ) -> Option<ResourceHandler> {
+        let url = request.get_url();
+        let id = Uuid::parse_str(url.rsplit('/').next()?.split(|c| c == '?' || c == '#').next()?).ok()?;
+        let feed = FEEDS.lock().get(&id)?.clone();
+        let stream = Arc::new(Mutex::new(Stream {
+            part: feed.last_part.lock().clone(),
+            ..Stream::default()
+        }));
+        feed.streams.lock().push(Arc::downgrade(&stream));
+        // Only the origin of an allowed page is echoed, others get no CORS
+        // headers and can't read the frames.
+        let allowed_origin = request
+            .get_header_by_name("Origin")
+            .filter(|origin| feed.allowed_origins.lock().contains(origin));
+        Some(ResourceHandler::new(VideoFeedHandler { stream, allowed_origin }))
+    }
+}
+
+struct VideoFeedHandler {
+    stream: Arc<Mutex<Stream>>,
+    allowed_origin: Option<String>,
+}
+
+impl ResourceHandlerCallbacks for VideoFeedHandler {
+    fn open(&mut self, request: Request, handle_request: &mut bool, callback: Callback) -> bool {
+        *handle_request = true;
+        true
+    }
+    fn get_response_headers(
+        &self,
+        response: Response,
+        response_length: &mut Option<u64>,
+        redirect_url: &mut String,
+    ) {
+        response.set_status(200);
+        response.set_status_text("OK");
+        // CEF writes the MIME type into the Content-Type header verbatim.
+        response.set_mime_type(&format!("multipart/x-mixed-replace; boundary={}", BOUNDARY));
+        response.set_header_by_name("Cache-Control", "no-store", true);
+        // Lets allowed pages capture the frames from a canvas without
+        // tainting it.
+        if let Some(origin) = &self.allowed_origin {
+            response.set_header_by_name("Access-Control-Allow-Origin", origin, true);
+        }
+        response.set_header_by_name("Vary", "Origin", true);
+        *response_length = None;
+    }
+    fn skip(&mut self, bytes_to_skip: u64, bytes_skipped: &mut u64, callback: ResourceSkipCallback) -> Result<(), ErrorCode> {
+        Err(ErrorCode::RequestRangeNotSatisfiable)
+    }
+    fn read(&mut self, mut handler: ResourceReadHandler) -> Option<ResourceReadHandler> {
+        let mut stream = self.stream.lock();
+        if stream.fill(&mut handler) {
+            Some(handler)
+        } else if stream.finished {
+            handler.set_bytes_read(0);
+            Some(handler)
+        } else {
+            stream.waiting = Some(handler);
+            None
+        }
+    }
+    fn cancel(&mut self) {
+        let mut stream = self.stream.lock();
+        stream.finished = true;
+        stream.waiting = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::url_request::ResourceReadCallback;
+
+    fn part(data: &[u8]) -> Arc<Vec<u8>> {
+        Arc::new(data.to_vec())
+    }
+
+    /// Read from `stream` into a buffer of `len` bytes and return what was
+    /// read.
+    fn read(stream: &mut Stream, len: usize) -> Option<Vec<u8>> {
+        let mut buffer = vec![0; len];
+        let mut handler = ResourceReadHandler::for_buffer(&mut buffer, ResourceReadCallback::new(|_| ()));
+        if !stream.fill(&mut handler) {
+            return None;
+        }
+        let bytes_read = handler.bytes_read() as usize;
+        drop(handler);
+        buffer.truncate(bytes_read);
+        Some(buffer)
+    }
+
+    #[test]
+    fn reads_continue_across_buffer_boundaries() {
+        let mut stream = Stream::default();
+        assert!(stream.push(&part(b"0123456789")).is_none());
+        assert_eq!(read(&mut stream, 4).unwrap(), b"0123");
+        assert_eq!(read(&mut stream, 4).unwrap(), b"4567");
+        assert_eq!(read(&mut stream, 4).unwrap(), b"89");
+        assert_eq!(read(&mut stream, 4), None);
+        stream.push(&part(b"abc"));
+        assert_eq!(read(&mut stream, 4).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn frames_pushed_mid_part_are_skipped() {
+        let mut stream = Stream::default();
+        stream.push(&part(b"first"));
+        assert_eq!(read(&mut stream, 2).unwrap(), b"fi");
+        stream.push(&part(b"second"));
+        assert_eq!(read(&mut stream, 16).unwrap(), b"rst");
+        assert_eq!(read(&mut stream, 16), None);
+        stream.push(&part(b"third"));
+        assert_eq!(read(&mut stream, 16).unwrap(), b"third");
+    }
+
+    #[test]
+    fn unread_frames_are_replaced() {
+        let mut stream = Stream::default();
+        stream.push(&part(b"old"));
+        stream.push(&part(b"new"));
+        assert_eq!(read(&mut stream, 16).unwrap(), b"new");
+    }
+
+    #[test]
+    fn pushing_fills_a_waiting_read() {
+        let mut buffer = vec![0; 16];
+        let mut stream = Stream::default();
+        stream.waiting = Some(ResourceReadHandler::for_buffer(&mut buffer, ResourceReadCallback::new(|_| ())));
+        let handler = stream.push(&part(b"frame")).unwrap();
+        assert_eq!(handler.bytes_read(), 5);
+        assert!(stream.waiting.is_none());
+        drop(handler);
+        assert_eq!(&buffer[..5], b"frame");
+    }
+
+    #[test]
+    fn finish_wakes_the_waiting_read_and_ends_the_stream() {
+        let woken = Arc::new(Mutex::new(None));
+        let callback_woken = woken.clone();
+        let mut buffer = vec![0; 16];
+        let mut stream = Stream::default();
+        stream.waiting = Some(ResourceReadHandler::for_buffer(
+            &mut buffer,
+            ResourceReadCallback::new(move |bytes_read| *callback_woken.lock() = Some(bytes_read)),
+        ));
+        let handler = stream.finish().unwrap();
+        assert_eq!(handler.bytes_read(), 0);
+        handler.cont();
+        assert_eq!(*woken.lock(), Some(0));
+        assert!(stream.finished);
+        assert!(stream.push(&part(b"late")).is_none());
+        assert_eq!(read(&mut stream, 16), None);
+    }
+}
@@ -0,0 +1,131 @@
+//! Enabling experimental web platform features for prototyping.
+//!
+//! Chromium ships many features (WebTransport, WebGPU, ...) disabled or behind
+//! origin trials. They can only be turned on for the whole application, through
+//! switches of the browser process, so [WebFeatures::apply] is meant to be
+//! called from [AppCallbacks::on_before_command_line_processing](crate::app::AppCallbacks::on_before_command_line_processing)
+//! with every feature any browser may need. Individual browsers can then be
+//! restricted to a subset with [BrowserHost::restrict_web_features].
+//!
+//! Experimental features change between Chromium versions and may not be
+//! available in the version CEF is built on. Unknown features are ignored by
+//! Chromium.
+
+use crate::{
+    browser_host::BrowserHost,
+    command_line::CommandLine,
+    devtools::{DevToolsError, page::{ScriptInjectionTime, UserScriptId}},
+};
+use bitflags::bitflags;
+
+bitflags! {
+    /// Set of experimental web platform features.
+    pub struct WebFeatures: u32 {
+        /// The `WebTransport` API (`QuicTransport` in older Chromium versions).
+        const WEB_TRANSPORT = 1 << 0;
+        /// The `navigator.gpu` API. Also disables the GPU blocklist for WebGPU,
+        /// which is only safe for trusted content.
+        const WEB_GPU = 1 << 1;
+        /// Raw TCP and UDP sockets (Direct Sockets API).
+        const DIRECT_SOCKETS = 1 << 2;
+        /// Everything Chromium has marked as experimental, as with the
+        /// "Experimental Web Platform features" flag in chrome://flags.
+        /// Can't be restricted per browser.
+        const EXPERIMENTAL_WEB_PLATFORM = 1 << 3;
+    }
+}
+
+impl WebFeatures {
+    /// Names of the Blink runtime features enabling each feature.
+    fn blink_features(self) -> Vec<&'static str> {
+        let mut features = Vec::new();
+        if self.contains(WebFeatures::WEB_TRANSPORT) {
+            features.extend_from_slice(&["QuicTransport", "WebTransport"]);
+        }
+        if self.contains(WebFeatures::WEB_GPU) {
+            features.push("WebGPU");
+        }
+        if self.contains(WebFeatures::DIRECT_SOCKETS) {
+            features.push("DirectSockets");
+        }
+        features
+    }
+    /// Script expressions removing the JavaScript API of each feature.
+    fn hide_scripts(self) -> Vec<&'static str> {
+        let mut scripts = Vec::new();
+        if self.contains(WebFeatures::WEB_TRANSPORT) {
+            scripts.extend_from_slice(&["delete self.WebTransport", "delete self.QuicTransport"]);
+        }
+        if self.contains(WebFeatures::WEB_GPU) {
+            scripts.push("delete Navigator.prototype.gpu");
+        }
+        if self.contains(WebFeatures::DIRECT_SOCKETS) {
+            scripts.extend_from_slice(&[
+                "delete self.TCPSocket",
+                "delete self.UDPSocket",
+                "delete Navigator.prototype.openTCPSocket",
+                "delete Navigator.prototype.openUDPSocket",
+            ]);
+        }
+        scripts
+    }
+
+    /// Add the switches enabling these features to `command_line`, which has to
+    /// be the command line of the browser process. Blink features already
+    /// enabled on the command line are kept.
+    pub fn apply(self, command_line: &CommandLine) {
+        let mut blink_features: Vec<String> = command_line
+            .get_switch_value("enable-blink-features")
+            .map(|value| value.split(',').filter(|f| !f.is_empty()).map(str::to_owned).collect())
+            .unwrap_or_default();
+        let added = self.blink_features();
+        if !added.is_empty() {
+            for feature in added {
+                if !blink_features.iter().any(|f| f == feature) {
+                    blink_features.push(feature.to_owned());
+                }
+            }
+            command_line.append_switch_with_value("enable-blink-features", &blink_features.join(","));
+        }
+        if self.contains(WebFeatures::WEB_GPU) && !command_line.has_switch("enable-unsafe-webgpu") {
+            command_line.append_switch("enable-unsafe-webgpu");
+        }
+        if self.contains(WebFeatures::EXPERIMENTAL_WEB_PLATFORM)
+            && !command_line.has_switch("enable-experimental-web-platform-features")
+        {
+            command_line.append_switch("enable-experimental-web-platform-features");
+        }
+    }
+}
+
+impl BrowserHost {
+    /// Hide the JavaScript APIs of the features enabled with [WebFeatures::apply]
+    /// that aren't in `allowed` from documents subsequently loaded in this
+    /// browser, in all frames. This gates features for pages that shouldn't use
+    /// them; it's not a security boundary, and workers still see the APIs.
+    /// [WebFeatures::EXPERIMENTAL_WEB_PLATFORM] can't be restricted.
+    ///
+    /// `callback` will be called on the browser process UI thread with the
+    /// identifier of the injected script, which can be passed to
+    /// [BrowserHost::remove_user_script] to lift the restriction for new
+    /// documents. It's called with `Ok(None)` if nothing needs to be hidden.
+    pub fn restrict_web_features(
+        &self,
+        allowed: WebFeatures,
+        callback: impl 'static + Send + FnOnce(Result<Option<UserScriptId>, DevToolsError>),
+    ) {
+        let scripts = (!allowed).hide_scripts();
+        if scripts.is_empty() {
+            callback(Ok(None));
+            return;
+        }
+        let code = scripts
+            .iter()
+            .map(|script| format!("try {{ {}; }} catch (e) {{}}", script))
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.add_user_script(&code, ScriptInjectionTime::DocumentStart, move |result| {
+            callback(result.map(Some))
+        });
+    }
+}
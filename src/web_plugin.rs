@@ -1,6 +1,15 @@
 use cef_sys::{cef_string_userfree_utf16_free, cef_web_plugin_info_t};
 use std::path::PathBuf;
-use crate::string::CefString;
+use crate::{
+    permission_store::origin_of,
+    request_context::{PluginPolicy, RequestContextHandler, RequestContextHandlerCallbacks},
+    string::CefString,
+};
+
+/// The mime type handled by the built-in PDF viewer.
+pub const PDF_MIME_TYPE: &str = "application/pdf";
+/// The mime type handled by Flash, if it's installed.
+pub const FLASH_MIME_TYPE: &str = "application/x-shockwave-flash";
 
 /// Information about a specific web plugin.
 pub struct WebPluginInfo {
@@ -35,3 +44,107 @@ impl WebPluginInfo {
         result
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PluginRule {
+    mime_type: Option<String>,
+    name: Option<String>,
+    origin: Option<String>,
+    policy: PluginPolicy,
+}
+
+impl PluginRule {
+    fn matches(&self, mime_type: &str, origin: Option<&str>, info: &WebPluginInfo) -> bool {
+        self.mime_type.as_ref().map_or(true, |m| m.eq_ignore_ascii_case(mime_type))
+            && self.name.as_ref().map_or(true, |n| *n == info.name)
+            && self.origin.as_ref().map_or(true, |o| Some(&**o) == origin)
+    }
+}
+
+/// Decides which plugins may run, by mime type or plugin name and optionally
+/// by the origin of the top-level page. Rules for an origin take precedence
+/// over rules for all origins; otherwise the rule added last wins. Plugins no
+/// rule applies to get the policy CEF recommends.
+///
+/// Use [PluginPolicyRules::handler] as the handler of a request context, or
+/// call [PluginPolicyRules::decide] from
+/// [RequestContextHandlerCallbacks::on_before_plugin_load]. Builds without
+/// plugins never ask, so the rules have no effect there.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginPolicyRules {
+    rules: Vec<PluginRule>,
+}
+
+impl PluginPolicyRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Apply `policy` to plugins handling `mime_type` on all pages.
+    pub fn mime_type(self, mime_type: &str, policy: PluginPolicy) -> Self {
+        self.rule(Some(mime_type), None, None, policy)
+    }
+    /// Apply `policy` to plugins handling `mime_type` on pages from the origin
+    /// of `url`.
+    ///
+    /// Origin rules only apply when a plugin instance is loaded. Use
+    /// [PluginPolicy::Block] rather than [PluginPolicy::Disable] here: CEF
+    /// caches the decision for `navigator.plugins`, which is made without an
+    /// origin.
+    pub fn mime_type_for_origin(self, mime_type: &str, url: &str, policy: PluginPolicy) -> Self {
+        self.rule(Some(mime_type), None, Some(origin_of(url)), policy)
+    }
+    /// Apply `policy` to the plugin named `name`, like `Chrome PDF Viewer`.
+    pub fn plugin_name(self, name: &str, policy: PluginPolicy) -> Self {
+        self.rule(None, Some(name), None, policy)
+    }
+    /// Block Flash on all pages.
+    pub fn block_flash(self) -> Self {
+        self.mime_type(FLASH_MIME_TYPE, PluginPolicy::Block)
+    }
+    /// Disable the built-in PDF viewer, so PDF files are downloaded instead.
+    pub fn disable_pdf_viewer(self) -> Self {
+        self.mime_type(PDF_MIME_TYPE, PluginPolicy::Disable)
+    }
+    fn rule(mut self, mime_type: Option<&str>, name: Option<&str>, origin: Option<String>, policy: PluginPolicy) -> Self {
+        self.rules.push(PluginRule {
+            mime_type: mime_type.map(str::to_owned),
+            name: name.map(str::to_owned),
+            origin,
+            policy,
+        });
+        self
+    }
+    /// Returns the policy for a plugin, or `None` to use the recommended one.
+    /// The arguments are those of
+    /// [RequestContextHandlerCallbacks::on_before_plugin_load].
+    pub fn decide(&self, mime_type: &str, top_origin_url: Option<&str>, plugin_info: &WebPluginInfo) -> Option<PluginPolicy> {
+        let origin = top_origin_url.map(origin_of);
+        let matching = |for_origin: bool| {
+            self.rules
+                .iter()
+                .rev()
+                .filter(|rule| rule.origin.is_some() == for_origin)
+                .find(|rule| rule.matches(mime_type, origin.as_deref(), plugin_info))
+                .map(|rule| rule.policy)
+        };
+        matching(true).or_else(|| matching(false))
+    }
+    /// Create a request context handler that only applies these rules.
+    pub fn handler(self) -> RequestContextHandler {
+        RequestContextHandler::new(self)
+    }
+}
+
+impl RequestContextHandlerCallbacks for PluginPolicyRules {
+    fn on_before_plugin_load(
+        &self,
+        mime_type: &str,
+        plugin_url: Option<&str>,
+        is_main_frame: bool,
+        top_origin_url: Option<&str>,
+        plugin_info: &WebPluginInfo,
+        plugin_policy: PluginPolicy,
+    ) -> Option<PluginPolicy> {
+        self.decide(mime_type, top_origin_url, plugin_info)
+    }
+}
@@ -2,6 +2,7 @@ use crate::string::CefString;
 use cef_sys::{cef_window_info_t, cef_window_handle_t};
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
 
+#[derive(Clone)]
 pub struct RawWindow(RawWindowHandle);
 
 impl RawWindow {
@@ -75,6 +76,7 @@ unsafe impl HasRawWindowHandle for RawWindow {
 }
 
 /// Structure representing window information.
+#[derive(Clone)]
 pub struct WindowInfo {
     pub window_name: String,
     pub x: i32,
@@ -83,8 +85,17 @@ pub struct WindowInfo {
     pub height: i32,
     pub parent_window: Option<RawWindow>,
     pub window: Option<RawWindow>,
+    /// Render into buffers passed to
+    /// [RenderHandlerCallbacks::on_paint](crate::client::render_handler::RenderHandlerCallbacks::on_paint)
+    /// instead of a window.
     pub windowless_rendering_enabled: bool,
+    /// Pass frames of a windowless browser as GPU textures to
+    /// [RenderHandlerCallbacks::on_accelerated_paint](crate::client::render_handler::RenderHandlerCallbacks::on_accelerated_paint).
     pub shared_texture_enabled: bool,
+    /// Only produce frames of a windowless browser when the application calls
+    /// [BrowserHost::send_external_begin_frame](crate::browser_host::BrowserHost::send_external_begin_frame),
+    /// e.g. once per frame of a game it is drawn into, instead of on CEF's own
+    /// timer. Ignored unless `windowless_rendering_enabled` is set.
     pub external_begin_frame_enabled: bool,
     pub platform_specific: PlatformSpecificWindowInfo,
 }
@@ -98,6 +109,7 @@ pub mod windows {
         windef::HMENU,
         minwindef::DWORD,
     };
+    #[derive(Clone)]
     pub struct PlatformSpecificWindowInfo {
         pub style: DWORD,
         pub ex_style: DWORD,
@@ -113,13 +125,193 @@ pub mod windows {
             }
         }
     }
+
+    use super::{RawWindow, WindowInfo};
+    use crate::browser::Browser;
+    use parking_lot::Mutex;
+    use winapi::{
+        shared::{
+            minwindef::{LPARAM, UINT, WPARAM},
+            windef::{HWND, RECT},
+        },
+        um::winuser::{
+            GetClientRect, GetParent, IsWindow, SetParent, SetWindowPos, SIZE_MINIMIZED,
+            SWP_NOACTIVATE, SWP_NOZORDER, WM_ENTERSIZEMOVE, WM_ERASEBKGND, WM_MOVE, WM_MOVING,
+            WM_SETFOCUS, WM_SIZE, WS_CHILD, WS_CLIPCHILDREN, WS_CLIPSIBLINGS, WS_TABSTOP,
+            WS_VISIBLE,
+        },
+    };
+
+    /// Space between the edges of the parent's client area and the browser,
+    /// e.g. for a toolbar above the browser.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct Insets {
+        pub left: i32,
+        pub top: i32,
+        pub right: i32,
+        pub bottom: i32,
+    }
+
+    /// Embeds a windowed browser as child window of an application window.
+    ///
+    /// Create the browser with [WindowsEmbedder::window_info], pass it to
+    /// [WindowsEmbedder::attach] from
+    /// [LifeSpanHandlerCallbacks::on_after_created](crate::client::life_span_handler::LifeSpanHandlerCallbacks::on_after_created),
+    /// and forward the parent's window messages to
+    /// [WindowsEmbedder::handle_message]. The browser then fills the parent's
+    /// client area, receives the focus when the parent does, and closes
+    /// popups like `<select>` dropdowns when the parent is moved.
+    ///
+    /// All functions must be called on the thread that owns the parent window,
+    /// which is the browser process UI thread unless multi-threaded message
+    /// loop is enabled.
+    pub struct WindowsEmbedder {
+        parent: Mutex<HWND>,
+        insets: Mutex<Insets>,
+        browser: Mutex<Option<Browser>>,
+    }
+
+    unsafe impl Send for WindowsEmbedder {}
+    unsafe impl Sync for WindowsEmbedder {}
+
+    impl WindowsEmbedder {
+        /// Embed into `parent`, which must be a valid window handle.
+        pub unsafe fn new(parent: HWND) -> WindowsEmbedder {
+            WindowsEmbedder {
+                parent: Mutex::new(parent),
+                insets: Mutex::new(Insets::default()),
+                browser: Mutex::new(None),
+            }
+        }
+        pub fn parent(&self) -> HWND {
+            *self.parent.lock()
+        }
+        /// Returns the window info for creating the browser as visible child
+        /// window covering the parent's client area.
+        pub fn window_info(&self) -> WindowInfo {
+            let rect = self.browser_rect();
+            WindowInfo {
+                x: rect.left,
+                y: rect.top,
+                width: rect.right - rect.left,
+                height: rect.bottom - rect.top,
+                parent_window: unsafe { RawWindow::from_cef_handle(self.parent() as _) },
+                platform_specific: PlatformSpecificWindowInfo {
+                    style: WS_CHILD | WS_CLIPCHILDREN | WS_CLIPSIBLINGS | WS_TABSTOP | WS_VISIBLE,
+                    ..PlatformSpecificWindowInfo::default()
+                },
+                ..WindowInfo::default()
+            }
+        }
+        /// Start managing the window of `browser`, and resize it to fit.
+        pub fn attach(&self, browser: Browser) {
+            *self.browser.lock() = Some(browser);
+            self.resize();
+        }
+        /// Stop managing the browser, e.g. from
+        /// [LifeSpanHandlerCallbacks::on_before_close](crate::client::life_span_handler::LifeSpanHandlerCallbacks::on_before_close).
+        pub fn detach(&self) -> Option<Browser> {
+            self.browser.lock().take()
+        }
+        pub fn browser(&self) -> Option<Browser> {
+            self.browser.lock().clone()
+        }
+        /// Returns the browser's window, or `None` before [WindowsEmbedder::attach]
+        /// or for windowless browsers.
+        pub fn browser_window(&self) -> Option<HWND> {
+            let browser = self.browser.lock().clone()?;
+            let hwnd = browser.get_host().get_window_handle()?.to_cef_handle() as HWND;
+            if unsafe { IsWindow(hwnd) } != 0 {
+                Some(hwnd)
+            } else {
+                None
+            }
+        }
+        /// Leave space around the browser.
+        pub fn set_insets(&self, insets: Insets) {
+            *self.insets.lock() = insets;
+            self.resize();
+        }
+        /// Move the browser into another window, e.g. when a tab is dragged
+        /// into a new window. `new_parent` must be a valid window handle.
+        pub unsafe fn reparent(&self, new_parent: HWND) {
+            *self.parent.lock() = new_parent;
+            if let Some(hwnd) = self.browser_window() {
+                if GetParent(hwnd) != new_parent {
+                    SetParent(hwnd, new_parent);
+                }
+            }
+            self.resize();
+        }
+        /// Resize the browser to fill the parent's client area, minus the
+        /// insets.
+        pub fn resize(&self) {
+            let hwnd = match self.browser_window() {
+                Some(hwnd) => hwnd,
+                None => return,
+            };
+            let rect = self.browser_rect();
+            unsafe {
+                SetWindowPos(
+                    hwnd,
+                    std::ptr::null_mut(),
+                    rect.left,
+                    rect.top,
+                    rect.right - rect.left,
+                    rect.bottom - rect.top,
+                    SWP_NOZORDER | SWP_NOACTIVATE,
+                );
+            }
+        }
+        /// Handle a message received by the parent window. Returns true if the
+        /// message was handled and shouldn't be passed to `DefWindowProc`.
+        pub fn handle_message(&self, message: UINT, wparam: WPARAM, lparam: LPARAM) -> bool {
+            let browser = match self.browser() {
+                Some(browser) => browser,
+                None => return false,
+            };
+            match message {
+                // Resizing a minimized window to its tiny size would make the
+                // page reflow for nothing.
+                WM_SIZE if wparam != SIZE_MINIMIZED as WPARAM => {
+                    self.resize();
+                    false
+                }
+                WM_SETFOCUS => {
+                    browser.get_host().set_focus(true);
+                    true
+                }
+                WM_ENTERSIZEMOVE | WM_MOVE | WM_MOVING => {
+                    browser.get_host().notify_move_or_resize_started();
+                    false
+                }
+                // The browser covers the background, so don't erase it.
+                WM_ERASEBKGND if self.browser_window().is_some() => true,
+                _ => false,
+            }
+        }
+
+        fn browser_rect(&self) -> RECT {
+            let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+            unsafe {
+                GetClientRect(self.parent(), &mut rect);
+            }
+            let insets = *self.insets.lock();
+            RECT {
+                left: rect.left + insets.left,
+                top: rect.top + insets.top,
+                right: (rect.right - insets.right).max(rect.left + insets.left),
+                bottom: (rect.bottom - insets.bottom).max(rect.top + insets.top),
+            }
+        }
+    }
 }
 
 #[cfg(target_os = "linux")]
 use linux::PlatformSpecificWindowInfo;
 #[cfg(target_os = "linux")]
 pub mod linux {
-    #[derive(Default)]
+    #[derive(Default, Clone)]
     pub struct PlatformSpecificWindowInfo;
 }
 
@@ -129,6 +321,7 @@ use macos::PlatformSpecificWindowInfo;
 pub mod macos {
     use std::os::raw;
 
+    #[derive(Clone)]
     pub struct PlatformSpecificWindowInfo {
         /// Whether to create the view initially hidden.
         ///
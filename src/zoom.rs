@@ -0,0 +1,196 @@
+//! Zooming in percent, in Chrome's steps, with change notifications.
+//!
+//! CEF zooms by zoom level, where each step of 1.0 scales the page by 20%,
+//! and like Chrome stores the zoom level per host: zooming a page on one host
+//! also zooms the other browsers showing a page on that host, and pages
+//! return to that zoom level when navigating back to the host. The functions
+//! here convert between zoom levels and the percentages shown to users, and
+//! [display_handler] reports zoom changes, including those of other browsers
+//! caused by the per-host zoom and those caused by navigating to another
+//! host.
+//!
+//! CEF has no notification for zoom changes, so only changes made through
+//! this module, [BrowserHost::execute_chrome_command] and
+//! [ScaleFactorTracker](crate::dpi::ScaleFactorTracker), and changes of the
+//! host, are reported. Zoom levels set with [BrowserHost::set_zoom_level]
+//! are reported with the next reported change.
+
+use crate::{
+    browser::Browser,
+    browser_host::BrowserHost,
+    client::display_handler::{DisplayHandler, DisplayHandlerCallbacks, DisplayHandlerDecorator},
+    frame::Frame,
+    task::{TaskRunner, ThreadId},
+};
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{collections::HashMap, sync::Arc};
+
+/// Chrome's preset zoom factors, which [BrowserHost::zoom_in] and
+/// [BrowserHost::zoom_out] step through.
+pub const PRESET_ZOOM_FACTORS: &[f64] = &[
+    0.25, 1.0 / 3.0, 0.5, 2.0 / 3.0, 0.75, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0, 4.0,
+    5.0,
+];
+
+/// The smallest zoom Chrome allows, in percent.
+pub const MIN_ZOOM_PERCENT: u32 = 25;
+/// The largest zoom Chrome allows, in percent.
+pub const MAX_ZOOM_PERCENT: u32 = 500;
+
+/// Each zoom level step scales by 20%.
+pub(crate) const ZOOM_LEVEL_BASE: f64 = 1.2;
+
+/// Returns the zoom level that scales pages by `percent`, e.g. 0.0 for 100.
+pub fn percent_to_zoom_level(percent: f64) -> f64 {
+    (percent / 100.0).ln() / ZOOM_LEVEL_BASE.ln()
+}
+
+/// Returns the percentage pages are scaled by at `zoom_level`.
+pub fn zoom_level_to_percent(zoom_level: f64) -> f64 {
+    ZOOM_LEVEL_BASE.powf(zoom_level) * 100.0
+}
+
+/// Returns the zoom level of the next preset zoom factor in the given
+/// direction, or the smallest or largest one if there is none.
+fn next_zoom_level(level: f64, zoom_in: bool) -> f64 {
+    const EPSILON: f64 = 0.001;
+    let factor = ZOOM_LEVEL_BASE.powf(level);
+    let next = if zoom_in {
+        PRESET_ZOOM_FACTORS
+            .iter()
+            .find(|&&f| f > factor + EPSILON)
+            .unwrap_or(&PRESET_ZOOM_FACTORS[PRESET_ZOOM_FACTORS.len() - 1])
+    } else {
+        PRESET_ZOOM_FACTORS
+            .iter()
+            .rev()
+            .find(|&&f| f < factor - EPSILON)
+            .unwrap_or(&PRESET_ZOOM_FACTORS[0])
+    };
+    next.ln() / ZOOM_LEVEL_BASE.ln()
+}
+
+impl BrowserHost {
+    /// Zoom in to the next of Chrome's preset zoom factors.
+    pub fn zoom_in(&self) {
+        self.change_zoom_level(|level| next_zoom_level(level, true));
+    }
+    /// Zoom out to the previous of Chrome's preset zoom factors.
+    pub fn zoom_out(&self) {
+        self.change_zoom_level(|level| next_zoom_level(level, false));
+    }
+    pub fn reset_zoom(&self) {
+        self.change_zoom_level(|_| 0.0);
+    }
+    /// Zoom to `percent`, clamped to the range Chrome allows.
+    pub fn set_zoom_percent(&self, percent: u32) {
+        let percent = percent.max(MIN_ZOOM_PERCENT).min(MAX_ZOOM_PERCENT);
+        self.change_zoom_level(move |_| percent_to_zoom_level(f64::from(percent)));
+    }
+    /// Returns the current zoom in percent, rounded. This function can only
+    /// be called on the UI thread.
+    pub fn get_zoom_percent(&self) -> u32 {
+        zoom_level_to_percent(self.get_zoom_level()).round() as u32
+    }
+    /// Set the zoom level to the one returned by `change` for the current
+    /// one, on the browser process UI thread, and report the change.
+    pub(crate) fn change_zoom_level(&self, change: impl 'static + Send + FnOnce(f64) -> f64) {
+        let host = self.clone();
+        let zoom = move || {
+            host.set_zoom_level(change(host.get_zoom_level()));
+            check_zoom_levels();
+        };
+        if TaskRunner::currently_on(ThreadId::UI) {
+            zoom();
+        } else {
+            TaskRunner::post_task_on(ThreadId::UI, zoom);
+        }
+    }
+}
+
+/// Called on the browser process UI thread with a browser and its new zoom in
+/// percent.
+pub trait ZoomChangeCallback = 'static + Send + Sync + Fn(&Browser, u32);
+
+struct WatchedBrowser {
+    browser: Browser,
+    zoom_level: f64,
+    on_zoom_change: Arc<dyn ZoomChangeCallback>,
+}
+
+lazy_static! {
+    static ref WATCHED: Mutex<HashMap<i32, WatchedBrowser>> = Mutex::new(HashMap::new());
+}
+
+/// Compare the zoom levels of all watched browsers with the last reported
+/// ones and report those that changed. Must be called on the UI thread.
+pub(crate) fn check_zoom_levels() {
+    let mut changed = Vec::new();
+    WATCHED.lock().retain(|_, watched| {
+        if !watched.browser.is_valid() {
+            return false;
+        }
+        let zoom_level = watched.browser.get_host().get_zoom_level();
+        if (zoom_level - watched.zoom_level).abs() > f64::EPSILON {
+            watched.zoom_level = zoom_level;
+            changed.push((watched.browser.clone(), zoom_level, watched.on_zoom_change.clone()));
+        }
+        true
+    });
+    // Called without holding the lock, so callbacks can change the zoom.
+    for (browser, zoom_level, on_zoom_change) in changed {
+        on_zoom_change(&browser, zoom_level_to_percent(zoom_level).round() as u32);
+    }
+}
+
+/// Create a [DisplayHandler] that calls `on_zoom_change` when the zoom of a
+/// browser changes, before calling into `callbacks`. The browsers are
+/// watched from their first address change on.
+pub fn display_handler<C: DisplayHandlerCallbacks>(
+    on_zoom_change: impl ZoomChangeCallback,
+    callbacks: C,
+) -> DisplayHandler {
+    DisplayHandler::new(ZoomDisplayHandler {
+        on_zoom_change: Arc::new(on_zoom_change),
+        inner: callbacks,
+    })
+}
+
+struct ZoomDisplayHandler<C: DisplayHandlerCallbacks> {
+    on_zoom_change: Arc<dyn ZoomChangeCallback>,
+    inner: C,
+}
+
+impl<C: DisplayHandlerCallbacks> DisplayHandlerDecorator for ZoomDisplayHandler<C> {
+    type Inner = C;
+    fn inner(&mut self) -> &mut C {
+        &mut self.inner
+    }
+    fn on_address_change(&mut self, browser: Browser, frame: Frame, url: &str) {
+        if frame.is_main() {
+            let on_zoom_change = self.on_zoom_change.clone();
+            WATCHED
+                .lock()
+                .entry(browser.get_identifier())
+                .or_insert_with(|| WatchedBrowser {
+                    browser: browser.clone(),
+                    // The default zoom isn't reported, only changes from it.
+                    zoom_level: 0.0,
+                    on_zoom_change,
+                });
+            // Navigating to another host changes the zoom level.
+            check_zoom_levels();
+        }
+        self.inner.on_address_change(browser, frame, url)
+    }
+
+    fn on_loading_progress_change(&mut self, browser: Browser, progress: f64) {
+        // The zoom level of the new host may only be applied after the
+        // address changed.
+        if progress >= 1.0 {
+            check_zoom_levels();
+        }
+        self.inner.on_loading_progress_change(browser, progress)
+    }
+}
@@ -0,0 +1,21 @@
+//! Headless end-to-end check of the crate against a real libcef.
+//!
+//! Creates a windowless browser, loads a page, evaluates JavaScript, checks
+//! the painted frame and shuts down again. CEF can only be initialized once
+//! per process and starts its subprocesses from the running executable, so
+//! this test has its own `main` instead of the standard test harness. The
+//! harness itself lives in `examples/smoke/main.rs`, so it can also be run
+//! with `cargo run -p cef-examples --bin smoke`.
+//!
+//! `CEF_PATH` must be set to the directory holding CEF's resources;
+//! `scripts/fetch-cef.sh` downloads the CEF distribution and prints it:
+//!
+//! ```text
+//! eval "$(scripts/fetch-cef.sh)"
+//! cargo test --test smoke
+//! ```
+//!
+//! Without `CEF_PATH` the test reports itself as ignored, except when `CI` is
+//! set, where it fails. On Linux without a display, run it under `xvfb-run`.
+
+include!("../examples/smoke/main.rs");